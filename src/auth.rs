@@ -0,0 +1,277 @@
+//! JWT bearer authentication with per-collection `read:`/`write:` scopes.
+
+use actix_web::{
+    Error, HttpRequest, ResponseError,
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::{Method, header},
+    middleware::Next,
+    web,
+};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode};
+use serde::Deserialize;
+
+use crate::error::AppError;
+use crate::shared::AppState;
+
+/// Claims mongor looks at; `exp` is checked by the validation itself
+#[derive(Debug, Deserialize)]
+struct Claims {
+    /// Space-separated scopes, e.g. "read:orders write:orders read:*"
+    #[serde(default)]
+    scope: String,
+    /// Who the token was issued to, which rate limiting keys on
+    #[serde(default)]
+    sub: Option<String>,
+    /// The tenant the token may act for when `TENANT_HEADER` is set
+    #[serde(default)]
+    tenant: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum AuthError {
+    /// Missing, malformed, badly signed or expired token (401)
+    InvalidToken,
+    /// Valid token without the scope the request needs (403)
+    MissingScope(String),
+    /// Valid token issued for another tenant than the request names (403)
+    WrongTenant(String),
+    /// Administrative request while authentication is off (403)
+    Disabled,
+}
+
+/// Paths served without a token: the `/api/` greeting, `/api/ping` and the Swagger UI
+fn is_public_path(path: &str) -> bool {
+    path == "/api/" || path == "/api/ping" || path.starts_with("/swagger-ui/")
+}
+
+/// Whether a request only reads: GET and HEAD, and `POST /{coll}/find`, whose body is a filter
+pub fn is_read_request(method: &Method, path: &str) -> bool {
+    method == Method::GET
+        || method == Method::HEAD
+        || (method == Method::POST
+            && matches!(
+                path.trim_start_matches('/').split('/').collect::<Vec<_>>()[..],
+                [_, "find"]
+            ))
+}
+
+/// The scope a request needs, `read:{coll}` for reads and `write:{coll}` otherwise;
+/// paths outside the collections (like `/openapi.json` or `/api/stats`) only need a valid token,
+/// except switching maintenance mode, which needs `admin:maintenance`
+pub fn required_scope(method: &Method, path: &str) -> Option<String> {
+    let mut segments = path.trim_start_matches('/').split('/');
+    let collection_name = match segments.next()? {
+        // Collection administration lives under `/api/collections/{coll}`
+        "api" => match (segments.next(), segments.next()) {
+            (Some("collections"), Some(collection_name)) => collection_name,
+            // Switching maintenance mode affects every client, unlike reading it
+            (Some("maintenance"), None) if method != Method::GET && method != Method::HEAD => {
+                return Some("admin:maintenance".to_string());
+            }
+            _ => return None,
+        },
+        "" | "openapi.json" => return None,
+        collection_name => collection_name,
+    };
+    let action = if is_read_request(method, path) {
+        "read"
+    } else {
+        "write"
+    };
+    Some(format!("{}:{}", action, collection_name))
+}
+
+/// Verifies an HS256 token, returning its claims
+fn verify(token: &str, secret: &str) -> Result<Claims, AuthError> {
+    decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(secret.as_bytes()),
+        &Validation::new(Algorithm::HS256),
+    )
+    .map(|data| data.claims)
+    .map_err(|_| AuthError::InvalidToken)
+}
+
+/// Verifies an HS256 token and checks that it grants `scope` (or the `{action}:*` wildcard)
+pub fn authorize(token: &str, secret: &str, scope: Option<&str>) -> Result<(), AuthError> {
+    let claims = verify(token, secret)?;
+
+    let Some(scope) = scope else {
+        return Ok(());
+    };
+    let wildcard = scope
+        .split_once(':')
+        .map(|(action, _)| format!("{}:*", action));
+    let granted = claims
+        .scope
+        .split_whitespace()
+        .any(|granted| granted == scope || Some(granted) == wildcard.as_deref());
+    if granted {
+        Ok(())
+    } else {
+        Err(AuthError::MissingScope(scope.to_string()))
+    }
+}
+
+/// Reads the bearer token of a request, empty when there is none
+fn bearer_token(req: &HttpRequest) -> &str {
+    req.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default()
+}
+
+/// Checks a scope the middleware cannot derive from the path, like the collections
+/// touched by a transaction; always passes when authentication is off
+pub fn authorize_scope(req: &HttpRequest, data: &AppState, scope: &str) -> Result<(), AuthError> {
+    match &data.config.jwt_secret {
+        Some(secret) => authorize(bearer_token(req), secret, Some(scope)),
+        None => Ok(()),
+    }
+}
+
+/// Refuses administrative endpoints, like dropping a collection or switching maintenance
+/// mode, when authentication is off, as anyone who can reach the server could call them
+pub fn require_authentication(data: &AppState) -> Result<(), AuthError> {
+    match data.config.jwt_secret {
+        Some(_) => Ok(()),
+        None => Err(AuthError::Disabled),
+    }
+}
+
+/// Checks that the request's token was issued for `tenant`, so the tenant header cannot
+/// be switched to read another tenant's collections; always passes when authentication is off
+pub fn authorize_tenant(req: &HttpRequest, data: &AppState, tenant: &str) -> Result<(), AuthError> {
+    let Some(secret) = &data.config.jwt_secret else {
+        return Ok(());
+    };
+    let claims = verify(bearer_token(req), secret)?;
+    if claims.tenant.as_deref() == Some(tenant) {
+        Ok(())
+    } else {
+        Err(AuthError::WrongTenant(tenant.to_string()))
+    }
+}
+
+/// Who a request comes from as far as its bearer token proves: the token's `sub`, or the
+/// token itself when it names no subject. `None` without a valid token or with
+/// authentication off, as anything else a client sends it could make up.
+pub fn authenticated_subject(req: &HttpRequest, data: &AppState) -> Option<String> {
+    let secret = data.config.jwt_secret.as_ref()?;
+    let token = bearer_token(req);
+    let claims = verify(token, secret).ok()?;
+    Some(claims.sub.unwrap_or_else(|| token.to_string()))
+}
+
+/// Middleware rejecting requests without a valid bearer token (401) or scope (403)
+pub async fn authenticate(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let secret = req
+        .app_data::<web::Data<AppState>>()
+        .and_then(|data| data.config.jwt_secret.clone())
+        .filter(|_| !is_public_path(req.path()));
+
+    if let Some(secret) = secret {
+        let scope = required_scope(req.method(), req.path());
+
+        if let Err(e) = authorize(bearer_token(req.request()), &secret, scope.as_deref()) {
+            let response = AppError::from(e).error_response();
+            return Ok(req.into_response(response).map_into_right_body());
+        }
+    }
+
+    next.call(req)
+        .await
+        .map(ServiceResponse::map_into_left_body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{EncodingKey, Header, encode};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    const SECRET: &str = "test-secret";
+
+    fn token(scope: &str, expires_in_secs: i64) -> String {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        encode(
+            &Header::default(),
+            &serde_json::json!({ "scope": scope, "exp": now + expires_in_secs }),
+            &EncodingKey::from_secret(SECRET.as_bytes()),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_required_scope() {
+        assert_eq!(
+            required_scope(&Method::GET, "/orders/explain"),
+            Some("read:orders".to_string())
+        );
+        assert_eq!(
+            required_scope(&Method::PATCH, "/orders/42"),
+            Some("write:orders".to_string())
+        );
+        assert_eq!(
+            required_scope(&Method::POST, "/orders/find"),
+            Some("read:orders".to_string())
+        );
+        assert_eq!(
+            required_scope(&Method::POST, "/orders/42/increment"),
+            Some("write:orders".to_string())
+        );
+        assert_eq!(required_scope(&Method::GET, "/openapi.json"), None);
+        assert_eq!(required_scope(&Method::GET, "/api/stats"), None);
+        assert_eq!(
+            required_scope(&Method::DELETE, "/api/collections/orders"),
+            Some("write:orders".to_string())
+        );
+        assert_eq!(required_scope(&Method::GET, "/api/maintenance"), None);
+        assert_eq!(
+            required_scope(&Method::POST, "/api/maintenance"),
+            Some("admin:maintenance".to_string())
+        );
+        assert_eq!(required_scope(&Method::GET, "/"), None);
+        assert!(is_public_path("/api/ping"));
+        assert!(!is_public_path("/api/stats"));
+        assert!(!is_public_path("/apis"));
+    }
+
+    #[test]
+    fn test_authorize_scopes() {
+        let reader = token("read:orders read:users", 60);
+        assert_eq!(authorize(&reader, SECRET, Some("read:orders")), Ok(()));
+        assert_eq!(authorize(&reader, SECRET, None), Ok(()));
+        assert_eq!(
+            authorize(&reader, SECRET, Some("write:orders")),
+            Err(AuthError::MissingScope("write:orders".to_string()))
+        );
+
+        let writer = token("write:*", 60);
+        assert_eq!(authorize(&writer, SECRET, Some("write:orders")), Ok(()));
+        assert!(authorize(&writer, SECRET, Some("read:orders")).is_err());
+    }
+
+    #[test]
+    fn test_authorize_rejects_invalid_tokens() {
+        let expired = token("read:orders", -3600);
+        assert_eq!(
+            authorize(&expired, SECRET, Some("read:orders")),
+            Err(AuthError::InvalidToken)
+        );
+        let valid = token("read:orders", 60);
+        assert_eq!(
+            authorize(&valid, "other-secret", None),
+            Err(AuthError::InvalidToken)
+        );
+        assert_eq!(authorize("", SECRET, None), Err(AuthError::InvalidToken));
+    }
+}
@@ -0,0 +1,83 @@
+//! `Range: bytes=...` handling for binary field downloads, so they can be resumed.
+//!
+//! Only single ranges are served. Other units and multi-range requests get the whole body,
+//! which RFC 9110 allows servers to do instead of a partial response.
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ByteRange {
+    /// No usable `Range`, send everything (200)
+    Full,
+    /// The inclusive byte range to send (206)
+    Partial { start: u64, end: u64 },
+    /// The range is malformed or lies outside the body (416)
+    NotSatisfiable,
+}
+
+/// The part of a `len`-byte body a `Range` header asks for
+pub fn parse_range(header: Option<&str>, len: u64) -> ByteRange {
+    let Some(spec) = header.and_then(|header| header.trim().strip_prefix("bytes=")) else {
+        return ByteRange::Full;
+    };
+    if spec.contains(',') {
+        return ByteRange::Full;
+    }
+    let Some((start, end)) = spec.split_once('-') else {
+        return ByteRange::NotSatisfiable;
+    };
+    let (start, end) = (start.trim(), end.trim());
+    let range = match (start.parse::<u64>(), end.parse::<u64>()) {
+        // `bytes=-500` is the last 500 bytes
+        (Err(_), Ok(suffix)) if start.is_empty() && suffix > 0 => {
+            Some((len.saturating_sub(suffix), len.saturating_sub(1)))
+        }
+        // `bytes=500-` is everything from byte 500
+        (Ok(start), Err(_)) if end.is_empty() => Some((start, len.saturating_sub(1))),
+        (Ok(start), Ok(end)) if start <= end => Some((start, end.min(len.saturating_sub(1)))),
+        _ => None,
+    };
+    match range {
+        Some((start, end)) if start < len => ByteRange::Partial { start, end },
+        _ => ByteRange::NotSatisfiable,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range() {
+        let partial = |start, end| ByteRange::Partial { start, end };
+        assert_eq!(parse_range(None, 10), ByteRange::Full);
+        assert_eq!(parse_range(Some("bytes=2-5"), 10), partial(2, 5));
+        assert_eq!(parse_range(Some("bytes=2-"), 10), partial(2, 9));
+        assert_eq!(parse_range(Some("bytes=-3"), 10), partial(7, 9));
+        assert_eq!(parse_range(Some("bytes=-30"), 10), partial(0, 9));
+        // Ends past the body are clamped
+        assert_eq!(parse_range(Some("bytes=8-100"), 10), partial(8, 9));
+        // Unsupported units and multiple ranges fall back to the whole body
+        assert_eq!(parse_range(Some("items=0-1"), 10), ByteRange::Full);
+        assert_eq!(parse_range(Some("bytes=0-1,4-5"), 10), ByteRange::Full);
+    }
+
+    #[test]
+    fn test_parse_range_not_satisfiable() {
+        for header in [
+            "bytes=10-",
+            "bytes=10-20",
+            "bytes=5-2",
+            "bytes=-0",
+            "bytes=abc",
+            "bytes=a-b",
+            "bytes=-",
+        ] {
+            assert_eq!(
+                parse_range(Some(header), 10),
+                ByteRange::NotSatisfiable,
+                "{}",
+                header
+            );
+        }
+        assert_eq!(parse_range(Some("bytes=0-"), 0), ByteRange::NotSatisfiable);
+    }
+}
@@ -0,0 +1,84 @@
+//! `ETag`/`Cache-Control` headers and `If-None-Match` handling for collection queries.
+//!
+//! The ETag is a hash of the serialized response body, so it changes whenever the documents,
+//! their order or the negotiated Extended JSON mode do. Results are sorted with an `_id`
+//! tiebreaker, which keeps the body (and the tag) stable between identical queries.
+
+use actix_web::{HttpRequest, http::header};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use crate::config::AppConfig;
+
+/// A strong ETag for a response body
+pub fn etag(body: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    body.hash(&mut hasher);
+    format!("\"{:016x}\"", hasher.finish())
+}
+
+/// Whether the request's `If-None-Match` already names this ETag (or `*`)
+pub fn is_not_modified(req: &HttpRequest, etag: &str) -> bool {
+    req.headers()
+        .get_all(header::IF_NONE_MATCH)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == etag || tag == "*")
+}
+
+/// The `Cache-Control` value for query responses. Responses that depend on the caller's
+/// token or tenant are kept out of shared caches.
+pub fn cache_control(config: &AppConfig) -> String {
+    let visibility = if config.jwt_secret.is_some() || config.tenant_header.is_some() {
+        "private, "
+    } else {
+        ""
+    };
+    match config.cache_max_age_secs {
+        0 => format!("{}no-cache", visibility),
+        max_age => format!("{}max-age={}", visibility, max_age),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn test_etag_depends_on_body() {
+        assert_eq!(etag("[1,2]"), etag("[1,2]"));
+        assert_ne!(etag("[1,2]"), etag("[2,1]"));
+        assert!(etag("[]").starts_with('"') && etag("[]").ends_with('"'));
+    }
+
+    #[test]
+    fn test_is_not_modified() {
+        let tag = etag("[]");
+        let req = TestRequest::default()
+            .insert_header((header::IF_NONE_MATCH, format!("\"other\", W/{}", tag)))
+            .to_http_request();
+        assert!(is_not_modified(&req, &tag));
+
+        let req = TestRequest::default()
+            .insert_header((header::IF_NONE_MATCH, "\"other\""))
+            .to_http_request();
+        assert!(!is_not_modified(&req, &tag));
+        assert!(!is_not_modified(
+            &TestRequest::default().to_http_request(),
+            &tag
+        ));
+    }
+
+    #[test]
+    fn test_cache_control() {
+        assert_eq!(cache_control(&AppConfig::default()), "no-cache");
+        let config = AppConfig {
+            cache_max_age_secs: 30,
+            jwt_secret: Some("secret".to_string()),
+            ..AppConfig::default()
+        };
+        assert_eq!(cache_control(&config), "private, max-age=30");
+    }
+}
@@ -1,45 +1,132 @@
 use actix_web::web;
 use futures::TryStreamExt;
-use mongodb::results::CollectionSpecification;
+use mongodb::IndexModel;
+use mongodb::bson::Document;
+use mongodb::results::{CollectionSpecification, CollectionType};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 
+use crate::config::AppConfig;
 use crate::shared::AppState;
 
 #[derive(Debug, Clone)]
 pub struct Catalog {
     pub collection_specs: Vec<CollectionSpecification>,
+    /// Index specs keyed by collection name (views have none)
+    pub indexes: HashMap<String, Vec<IndexModel>>,
+    /// Hash of the collections and indexes, the same for equal catalogs across restarts
+    pub fingerprint: u64,
+    /// Starts at 0 and is bumped whenever a refresh publishes different contents
+    pub version: u64,
 }
 
-/// Fetches all collections from the MongoDB database and their contents
+impl Catalog {
+    pub fn new(
+        collection_specs: Vec<CollectionSpecification>,
+        indexes: HashMap<String, Vec<IndexModel>>,
+    ) -> Self {
+        let fingerprint = fingerprint(&collection_specs, &indexes);
+        Catalog {
+            collection_specs,
+            indexes,
+            fingerprint,
+            version: 0,
+        }
+    }
+
+    /// The catalog as a tenant sees it: only the collections with its `{tenant}_` prefix,
+    /// under their logical names, minus those the allow/deny lists hide
+    pub fn for_tenant(&self, tenant_prefix: &str, config: &AppConfig) -> Catalog {
+        let logical_name = |name: &str| {
+            name.strip_prefix(tenant_prefix)
+                .filter(|name| config.is_collection_exposed(name))
+                .map(str::to_string)
+        };
+        let collection_specs = self
+            .collection_specs
+            .iter()
+            .filter_map(|spec| {
+                let mut spec = spec.clone();
+                spec.name = logical_name(&spec.name)?;
+                Some(spec)
+            })
+            .collect();
+        let indexes = self
+            .indexes
+            .iter()
+            .filter_map(|(name, models)| Some((logical_name(name)?, models.clone())))
+            .collect();
+        Catalog {
+            version: self.version,
+            ..Catalog::new(collection_specs, indexes)
+        }
+    }
+}
+
+/// Hashes the catalog contents independently of the order the server listed them in
+fn fingerprint(
+    collection_specs: &[CollectionSpecification],
+    indexes: &HashMap<String, Vec<IndexModel>>,
+) -> u64 {
+    let mut specs: Vec<String> = collection_specs
+        .iter()
+        .map(|spec| serde_json::to_string(spec).unwrap_or_default())
+        .collect();
+    specs.sort();
+    let indexes: BTreeMap<&String, String> = indexes
+        .iter()
+        .map(|(name, models)| (name, serde_json::to_string(models).unwrap_or_default()))
+        .collect();
+    let mut hasher = DefaultHasher::new();
+    (specs, indexes).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Fetches all exposed collections from the MongoDB database and their contents
 pub async fn fetch_all_collections(
     database: &mongodb::Database,
+    config: &AppConfig,
 ) -> Result<Catalog, mongodb::error::Error> {
     let cursor = database.list_collections().await?;
-    // Consume cursor
-    let collections: Vec<_> = cursor.try_collect().await?;
+    // Consume cursor, dropping collections the allow/deny lists hide
+    let collections: Vec<_> = cursor
+        .try_filter(|spec| std::future::ready(config.is_physical_collection_exposed(&spec.name)))
+        .try_collect()
+        .await?;
 
-    Ok(Catalog {
-        collection_specs: collections,
-    })
+    let mut indexes = HashMap::new();
+    for spec in &collections {
+        if spec.collection_type == CollectionType::View {
+            continue;
+        }
+        match fetch_indexes(&database.collection::<Document>(&spec.name)).await {
+            Ok(models) => {
+                indexes.insert(spec.name.clone(), models);
+            }
+            Err(e) => {
+                eprintln!("Error fetching indexes for {}: {}", spec.name, e);
+            }
+        }
+    }
+
+    Ok(Catalog::new(collections, indexes))
+}
+
+/// Lists the indexes defined on a collection
+pub async fn fetch_indexes(
+    collection: &mongodb::Collection<Document>,
+) -> Result<Vec<IndexModel>, mongodb::error::Error> {
+    collection.list_indexes().await?.try_collect().await
 }
 
 /// Runs in the background and periodically fetches MongoDB collections
 pub async fn fetch_collections_periodically(state: web::Data<AppState>, interval: Duration) {
-    let db = state.db_client.database(&state.config.database_name);
     loop {
-        match fetch_all_collections(&db).await {
-            Ok(catalog) => {
-                // Update the shared state with the new collections
-                if let Ok(mut locked_catalog) = state.collections.lock() {
-                    *locked_catalog = catalog;
-                    println!("Successfully updated catalog: {:?}.", locked_catalog);
-                } else {
-                    eprintln!("Failed to acquire lock on collections");
-                }
-            }
-            Err(e) => {
-                eprintln!("Error fetching collections: {}", e);
-            }
+        if let Err(e) = refresh_catalog(&state).await {
+            eprintln!("Error fetching collections: {}", e);
         }
 
         // Sleep for the specified interval
@@ -47,7 +134,153 @@ pub async fn fetch_collections_periodically(state: web::Data<AppState>, interval
     }
 }
 
-/// Returns the current cached collections
-pub fn get_cached_collections(state: &web::Data<AppState>) -> Option<Catalog> {
-    state.collections.lock().ok().map(|guard| guard.clone())
+/// Fetches the catalog now and publishes it, e.g. right after dropping a collection
+pub async fn refresh_catalog(state: &web::Data<AppState>) -> Result<(), mongodb::error::Error> {
+    let db = state.db_client.database(&state.config.database_name);
+    let catalog = fetch_all_collections(&db, &state.config).await?;
+    // Update the shared state with the new collections
+    println!("Successfully updated catalog: {:?}.", catalog);
+    if !publish_catalog(&state.collections, catalog) {
+        eprintln!("Failed to acquire lock on collections");
+    }
+    Ok(())
+}
+
+/// Returns a shared snapshot of the current cached collections
+pub fn get_cached_collections(state: &web::Data<AppState>) -> Option<Arc<Catalog>> {
+    read_catalog(&state.collections)
+}
+
+/// Checks whether the cached catalog knows a collection, without copying it
+pub fn catalog_contains(state: &web::Data<AppState>, collection_name: &str) -> Option<bool> {
+    state.collections.read().ok().map(|catalog| {
+        catalog
+            .collection_specs
+            .iter()
+            .any(|spec| spec.name == collection_name)
+    })
+}
+
+/// Readers share the lock, so concurrent requests never wait on each other,
+/// only briefly on the refresh task swapping in a new catalog
+fn read_catalog(catalog: &RwLock<Arc<Catalog>>) -> Option<Arc<Catalog>> {
+    catalog.read().ok().map(|guard| Arc::clone(&guard))
+}
+
+fn publish_catalog(catalog: &RwLock<Arc<Catalog>>, mut new_catalog: Catalog) -> bool {
+    match catalog.write() {
+        Ok(mut guard) => {
+            new_catalog.version = if new_catalog.fingerprint == guard.fingerprint {
+                guard.version
+            } else {
+                guard.version + 1
+            };
+            *guard = Arc::new(new_catalog);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    // Builds a catalog of plain collections without validators or indexes
+    pub fn test_catalog(names: &[&str]) -> Catalog {
+        Catalog::new(
+            names
+                .iter()
+                .map(|name| {
+                    serde_json::from_value(serde_json::json!({
+                        "name": name,
+                        "type": "collection",
+                        "options": {},
+                        "info": { "readOnly": false }
+                    }))
+                    .unwrap()
+                })
+                .collect(),
+            HashMap::new(),
+        )
+    }
+
+    #[test]
+    fn test_for_tenant() {
+        let config = AppConfig {
+            tenant_header: Some("X-Tenant-ID".to_string()),
+            collections_denylist: vec!["secrets".to_string()],
+            ..AppConfig::default()
+        };
+        let catalog = test_catalog(&["acme_orders", "acme_secrets", "globex_orders"]);
+
+        let names: Vec<String> = catalog
+            .for_tenant("acme_", &config)
+            .collection_specs
+            .into_iter()
+            .map(|spec| spec.name)
+            .collect();
+        assert_eq!(names, vec!["orders"]);
+    }
+
+    #[test]
+    fn test_concurrent_reads_during_refreshes() {
+        let small = test_catalog(&["a"]);
+        let large = test_catalog(&["a", "b", "c"]);
+        let catalog = Arc::new(RwLock::new(Arc::new(small.clone())));
+
+        let readers: Vec<_> = (0..8)
+            .map(|_| {
+                let catalog = Arc::clone(&catalog);
+                std::thread::spawn(move || {
+                    for _ in 0..2_000 {
+                        // Every read sees one complete catalog, never a partial update
+                        let snapshot = read_catalog(&catalog).expect("Catalog lock poisoned");
+                        assert!(matches!(snapshot.collection_specs.len(), 1 | 3));
+                    }
+                })
+            })
+            .collect();
+
+        for i in 0..500 {
+            let next = if i % 2 == 0 { &large } else { &small };
+            assert!(publish_catalog(&catalog, next.clone()));
+        }
+        for reader in readers {
+            reader.join().expect("Reader thread panicked");
+        }
+    }
+
+    #[test]
+    fn test_reads_share_one_snapshot() {
+        let catalog = RwLock::new(Arc::new(test_catalog(&["a", "b"])));
+
+        // Reads hand out the same allocation instead of copying the specs
+        let first = read_catalog(&catalog).unwrap();
+        let second = read_catalog(&catalog).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+
+        // Publishing swaps the snapshot without touching the ones already handed out
+        assert!(publish_catalog(&catalog, test_catalog(&["c"])));
+        let third = read_catalog(&catalog).unwrap();
+        assert!(!Arc::ptr_eq(&first, &third));
+        assert_eq!(first.collection_specs.len(), 2);
+        assert_eq!(third.collection_specs.len(), 1);
+    }
+
+    #[test]
+    fn test_version_changes_with_contents() {
+        let catalog = RwLock::new(Arc::new(test_catalog(&["a", "b"])));
+
+        // The same collections listed in another order are not a new version
+        assert!(publish_catalog(&catalog, test_catalog(&["b", "a"])));
+        let unchanged = read_catalog(&catalog).unwrap();
+        assert_eq!(unchanged.version, 0);
+        assert_eq!(unchanged.fingerprint, test_catalog(&["a", "b"]).fingerprint);
+
+        assert!(publish_catalog(&catalog, test_catalog(&["a", "b", "c"])));
+        let added = read_catalog(&catalog).unwrap();
+        assert_eq!(added.version, 1);
+        assert_ne!(added.fingerprint, unchanged.fingerprint);
+    }
 }
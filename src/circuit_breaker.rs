@@ -0,0 +1,250 @@
+//! Circuit breaker around MongoDB operations.
+//!
+//! After `CIRCUIT_BREAKER_THRESHOLD` consecutive connectivity failures (network
+//! errors, server selection timeouts, cleared connection pools) the breaker opens
+//! and requests fail fast with 503 for `CIRCUIT_BREAKER_COOLDOWN_SECS`. The first
+//! request after the cooldown is let through as a probe: if it succeeds the breaker
+//! closes again, otherwise it re-opens for another cooldown. A probe dropped before it
+//! finishes, say by a client disconnecting, tells nothing about the server, so the next
+//! request becomes the probe instead.
+//!
+//! Lower thresholds fail fast sooner but may trip on isolated blips; the cooldown
+//! should roughly match how long a failover or restart takes in your deployment.
+//! Query errors (bad filters, duplicate keys) never count as failures.
+
+use mongodb::error::{Error, ErrorKind};
+use std::future::IntoFuture;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    Closed { failures: u32 },
+    Open { until: Instant },
+    HalfOpen,
+}
+
+#[derive(Debug)]
+pub enum BreakerError {
+    /// The breaker is open and the operation was not attempted
+    Open,
+    /// The operation ran and failed
+    Inner(Error),
+}
+
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        CircuitBreaker {
+            threshold,
+            cooldown,
+            state: Mutex::new(BreakerState::Closed { failures: 0 }),
+        }
+    }
+
+    pub fn state(&self) -> BreakerState {
+        *self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Whether an operation may run now; moves an expired open breaker to half-open
+    pub fn allow(&self, now: Instant) -> bool {
+        self.admit(now).is_some()
+    }
+
+    /// Like [`CircuitBreaker::allow`], telling whether the admitted operation is the probe
+    fn admit(&self, now: Instant) -> Option<bool> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        match *state {
+            BreakerState::Closed { .. } => Some(false),
+            BreakerState::Open { until } if now >= until => {
+                *state = BreakerState::HalfOpen;
+                Some(true)
+            }
+            // Only a single probe is in flight while half-open
+            BreakerState::Open { .. } | BreakerState::HalfOpen => None,
+        }
+    }
+
+    /// Lets the next operation probe again after the probe was dropped unfinished
+    pub fn record_abandoned_probe(&self, now: Instant) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if *state == BreakerState::HalfOpen {
+            *state = BreakerState::Open { until: now };
+        }
+    }
+
+    pub fn record_success(&self) {
+        *self.state.lock().unwrap_or_else(|e| e.into_inner()) =
+            BreakerState::Closed { failures: 0 };
+    }
+
+    pub fn record_failure(&self, now: Instant) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        *state = match *state {
+            BreakerState::Closed { failures } if failures + 1 < self.threshold => {
+                BreakerState::Closed {
+                    failures: failures + 1,
+                }
+            }
+            _ => BreakerState::Open {
+                until: now + self.cooldown,
+            },
+        };
+    }
+
+    /// Runs a MongoDB operation through the breaker
+    pub async fn call<T, F>(&self, operation: F) -> Result<T, BreakerError>
+    where
+        F: IntoFuture<Output = Result<T, Error>>,
+    {
+        let Some(is_probe) = self.admit(Instant::now()) else {
+            return Err(BreakerError::Open);
+        };
+        let mut probe = ProbeGuard {
+            breaker: self,
+            armed: is_probe,
+        };
+        let result = operation.await;
+        probe.armed = false;
+        match result {
+            Ok(value) => {
+                self.record_success();
+                Ok(value)
+            }
+            Err(e) => {
+                if is_connectivity_error(&e) {
+                    self.record_failure(Instant::now());
+                } else {
+                    // The server answered, so it is reachable
+                    self.record_success();
+                }
+                Err(BreakerError::Inner(e))
+            }
+        }
+    }
+}
+
+/// Hands the probe on to the next operation when the probing future is dropped
+struct ProbeGuard<'a> {
+    breaker: &'a CircuitBreaker,
+    armed: bool,
+}
+
+impl Drop for ProbeGuard<'_> {
+    fn drop(&mut self) {
+        if self.armed {
+            self.breaker.record_abandoned_probe(Instant::now());
+        }
+    }
+}
+
+/// Errors indicating MongoDB is unreachable rather than rejecting the operation
+pub fn is_connectivity_error(error: &Error) -> bool {
+    matches!(
+        *error.kind,
+        ErrorKind::Io(_)
+            | ErrorKind::ServerSelection { .. }
+            | ErrorKind::ConnectionPoolCleared { .. }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breaker_state_machine() {
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(10));
+        let now = Instant::now();
+
+        // Closed: failures below the threshold keep it closed
+        assert!(breaker.allow(now));
+        breaker.record_failure(now);
+        assert_eq!(breaker.state(), BreakerState::Closed { failures: 1 });
+
+        // Closed -> Open once the threshold is reached
+        breaker.record_failure(now);
+        let until = now + Duration::from_secs(10);
+        assert_eq!(breaker.state(), BreakerState::Open { until });
+        assert!(!breaker.allow(now + Duration::from_secs(5)));
+
+        // Open -> Half-open after the cooldown; only one probe is allowed
+        assert!(breaker.allow(until));
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+        assert!(!breaker.allow(until));
+
+        // Half-open -> Closed when the probe succeeds
+        breaker.record_success();
+        assert_eq!(breaker.state(), BreakerState::Closed { failures: 0 });
+    }
+
+    #[test]
+    fn test_failed_probe_reopens_breaker() {
+        let breaker = CircuitBreaker::new(1, Duration::from_secs(10));
+        let now = Instant::now();
+
+        breaker.record_failure(now);
+        let later = now + Duration::from_secs(10);
+        assert!(breaker.allow(later));
+        breaker.record_failure(later);
+        assert_eq!(
+            breaker.state(),
+            BreakerState::Open {
+                until: later + Duration::from_secs(10)
+            }
+        );
+    }
+
+    #[test]
+    fn test_call_counts_only_connectivity_errors() {
+        let breaker = CircuitBreaker::new(1, Duration::from_secs(10));
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        let result: Result<(), _> =
+            runtime.block_on(breaker.call(async { Err(Error::custom("duplicate key")) }));
+        assert!(matches!(result, Err(BreakerError::Inner(_))));
+        assert_eq!(breaker.state(), BreakerState::Closed { failures: 0 });
+
+        let result: Result<(), _> = runtime.block_on(breaker.call(async {
+            Err(std::io::Error::from(std::io::ErrorKind::ConnectionRefused).into())
+        }));
+        assert!(matches!(result, Err(BreakerError::Inner(_))));
+        let result = runtime.block_on(breaker.call(async { Ok(()) }));
+        assert!(matches!(result, Err(BreakerError::Open)));
+    }
+
+    #[test]
+    fn test_dropped_probe_lets_the_next_one_through() {
+        use futures_util::FutureExt;
+
+        let breaker = CircuitBreaker::new(1, Duration::ZERO);
+        breaker.record_failure(Instant::now());
+
+        // The probe starts, then its future is dropped before the server answers
+        let probe = breaker.call(std::future::pending::<Result<(), Error>>());
+        assert!(probe.now_or_never().is_none());
+        assert!(matches!(breaker.state(), BreakerState::Open { .. }));
+
+        // The next operation probes instead of being refused forever
+        let result = breaker.call(async { Ok(()) }).now_or_never();
+        assert!(matches!(result, Some(Ok(()))));
+        assert_eq!(breaker.state(), BreakerState::Closed { failures: 0 });
+
+        // Dropping an operation of a closed breaker changes nothing
+        assert!(
+            breaker
+                .call(std::future::pending::<Result<(), Error>>())
+                .now_or_never()
+                .is_none()
+        );
+        assert_eq!(breaker.state(), BreakerState::Closed { failures: 0 });
+    }
+}
@@ -0,0 +1,350 @@
+//! A typed async client for a running mongor server, behind the `client` feature.
+//!
+//! Filters use the same query grammar as the HTTP API, as `(field, value)` pairs like
+//! `("age", "gte.30")` or `("or", "(status.eq.active,score.gt.80)")`. Documents travel as
+//! canonical Extended JSON, so BSON types survive the round trip.
+
+use mongodb::bson::{Bson, Document};
+use serde::Deserialize;
+use std::fmt;
+
+use crate::query_param_parser::RESERVED_PARAMS;
+
+const EJSON_CONTENT_TYPE: &str = "application/ejson";
+
+#[derive(Debug)]
+pub enum ClientError {
+    /// The request was refused before sending it
+    InvalidRequest(String),
+    /// The request could not be sent or its response not read
+    Http(reqwest::Error),
+    /// The server answered with an error status
+    Status { status: u16, message: String },
+    /// The response body was not what the endpoint returns
+    InvalidResponse(String),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::InvalidRequest(message) => write!(f, "Invalid request: {}", message),
+            ClientError::Http(error) => write!(f, "Request failed: {}", error),
+            ClientError::Status { status, message } => {
+                write!(f, "Server answered {}: {}", status, message)
+            }
+            ClientError::InvalidResponse(message) => write!(f, "Invalid response: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<reqwest::Error> for ClientError {
+    fn from(error: reqwest::Error) -> Self {
+        ClientError::Http(error)
+    }
+}
+
+/// What an update touched, as returned by PATCH
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateSummary {
+    pub matched_count: u64,
+    pub modified_count: u64,
+    #[serde(default)]
+    pub upserted_id: Option<Bson>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DeleteSummary {
+    deleted_count: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct MongorClient {
+    base_url: String,
+    token: Option<String>,
+    http: reqwest::Client,
+}
+
+impl MongorClient {
+    /// A client for the server at `base_url`, e.g. `http://localhost:8080`
+    pub fn new(base_url: impl Into<String>) -> Self {
+        MongorClient {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            token: None,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Sends `token` as the bearer token of every request
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// The documents of `collection` matching `filter`
+    pub async fn find(
+        &self,
+        collection: &str,
+        filter: &[(&str, &str)],
+    ) -> Result<Vec<Document>, ClientError> {
+        let response = self
+            .request(reqwest::Method::GET, collection, filter)?
+            .send()
+            .await?;
+        match parse_ejson(check_status(response).await?).await? {
+            Bson::Array(values) => values
+                .into_iter()
+                .map(|value| match value {
+                    Bson::Document(document) => Ok(document),
+                    other => Err(ClientError::InvalidResponse(format!(
+                        "expected documents, got {}",
+                        other
+                    ))),
+                })
+                .collect(),
+            other => Err(ClientError::InvalidResponse(format!(
+                "expected an array, got {}",
+                other
+            ))),
+        }
+    }
+
+    /// Inserts `document`, returning its `_id`
+    pub async fn insert(&self, collection: &str, document: &Document) -> Result<Bson, ClientError> {
+        let response = self
+            .request(reqwest::Method::POST, collection, &[])?
+            .json(&Bson::Document(document.clone()).into_canonical_extjson())
+            .send()
+            .await?;
+        parse_ejson(check_status(response).await?).await
+    }
+
+    /// Sets the fields of `update` on every document matching `filter`
+    pub async fn update(
+        &self,
+        collection: &str,
+        filter: &[(&str, &str)],
+        update: &Document,
+    ) -> Result<UpdateSummary, ClientError> {
+        let response = self
+            .request(reqwest::Method::PATCH, collection, filter)?
+            .json(&Bson::Document(update.clone()).into_canonical_extjson())
+            .send()
+            .await?;
+        parse_json(check_status(response).await?).await
+    }
+
+    /// Deletes every document matching `filter`, returning how many were removed
+    pub async fn delete(
+        &self,
+        collection: &str,
+        filter: &[(&str, &str)],
+    ) -> Result<u64, ClientError> {
+        let response = self
+            .request(reqwest::Method::DELETE, collection, filter)?
+            .send()
+            .await?;
+        let summary: DeleteSummary = parse_json(check_status(response).await?).await?;
+        Ok(summary.deleted_count)
+    }
+
+    fn request(
+        &self,
+        method: reqwest::Method,
+        collection: &str,
+        filter: &[(&str, &str)],
+    ) -> Result<reqwest::RequestBuilder, ClientError> {
+        // Reserved names would change what the request does rather than filter on a field
+        if let Some((key, _)) = filter.iter().find(|(key, _)| RESERVED_PARAMS.contains(key)) {
+            return Err(ClientError::InvalidRequest(format!(
+                "{} is a request option, not a filter field",
+                key
+            )));
+        }
+        let mut request = self
+            .http
+            .request(method, format!("{}/{}", self.base_url, collection))
+            .header(reqwest::header::ACCEPT, EJSON_CONTENT_TYPE)
+            .query(filter);
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        Ok(request)
+    }
+}
+
+/// Turns error statuses into [`ClientError::Status`] with the server's message
+async fn check_status(response: reqwest::Response) -> Result<reqwest::Response, ClientError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    Err(ClientError::Status {
+        status: status.as_u16(),
+        message: response.text().await.unwrap_or_default(),
+    })
+}
+
+async fn parse_json<T: serde::de::DeserializeOwned>(
+    response: reqwest::Response,
+) -> Result<T, ClientError> {
+    let value: serde_json::Value = response.json().await?;
+    serde_json::from_value(value).map_err(|e| ClientError::InvalidResponse(e.to_string()))
+}
+
+async fn parse_ejson(response: reqwest::Response) -> Result<Bson, ClientError> {
+    let value: serde_json::Value = response.json().await?;
+    Bson::try_from(value).map_err(|e| ClientError::InvalidResponse(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mongodb::bson::doc;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    /// Serves one canned response on a local port, handing back the raw request it got
+    fn serve_once(status: &str, body: &str) -> (String, std::thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0; 4096];
+            // Read the head, then as much body as Content-Length announces
+            loop {
+                let n = stream.read(&mut buffer).unwrap();
+                request.extend_from_slice(&buffer[..n]);
+                let text = String::from_utf8_lossy(&request);
+                if let Some(head_end) = text.find("\r\n\r\n") {
+                    let length = text[..head_end]
+                        .lines()
+                        .find_map(|line| {
+                            line.to_lowercase()
+                                .strip_prefix("content-length: ")
+                                .map(|n| n.trim().parse::<usize>().unwrap())
+                        })
+                        .unwrap_or(0);
+                    if request.len() >= head_end + 4 + length {
+                        break;
+                    }
+                }
+                if n == 0 {
+                    break;
+                }
+            }
+            stream.write_all(response.as_bytes()).unwrap();
+            String::from_utf8(request).unwrap()
+        });
+        (url, handle)
+    }
+
+    #[tokio::test]
+    async fn test_find_builds_the_query_and_parses_documents() {
+        let (url, server) = serve_once(
+            "200 OK",
+            r#"[{"_id": {"$numberInt": "1"}, "age": {"$numberLong": "31"}}]"#,
+        );
+        let client = MongorClient::new(format!("{}/", url)).with_token("secret");
+        let documents = client
+            .find("users", &[("age", "gte.30"), ("name", "John Doe")])
+            .await
+            .unwrap();
+        assert_eq!(documents, vec![doc! { "_id": 1, "age": 31_i64 }]);
+
+        let request = server.join().unwrap();
+        assert!(
+            request.starts_with("GET /users?age=gte.30&name=John+Doe HTTP/1.1"),
+            "{}",
+            request
+        );
+        assert!(request.contains("accept: application/ejson"), "{}", request);
+        assert!(
+            request.contains("authorization: Bearer secret"),
+            "{}",
+            request
+        );
+    }
+
+    #[tokio::test]
+    async fn test_insert_sends_canonical_extjson() {
+        let (url, server) = serve_once("201 Created", r#"{"$oid": "65a000000000000000000001"}"#);
+        let id = MongorClient::new(url)
+            .insert("users", &doc! { "count": 1_i64 })
+            .await
+            .unwrap();
+        assert_eq!(
+            id,
+            Bson::ObjectId("65a000000000000000000001".parse().unwrap())
+        );
+
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /users HTTP/1.1"), "{}", request);
+        assert!(
+            request.ends_with(r#"{"count":{"$numberLong":"1"}}"#),
+            "{}",
+            request
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_and_delete_parse_summaries() {
+        let (url, server) = serve_once(
+            "200 OK",
+            r#"{"matchedCount": 2, "modifiedCount": 1, "upsertedId": null}"#,
+        );
+        let summary = MongorClient::new(url)
+            .update("users", &[("status", "active")], &doc! { "seen": true })
+            .await
+            .unwrap();
+        assert_eq!(
+            summary,
+            UpdateSummary {
+                matched_count: 2,
+                modified_count: 1,
+                upserted_id: None,
+            }
+        );
+        let request = server.join().unwrap();
+        assert!(request.starts_with("PATCH /users?status=active HTTP/1.1"));
+
+        let (url, _server) = serve_once("200 OK", r#"{"deletedCount": 3}"#);
+        let deleted = MongorClient::new(url)
+            .delete("users", &[("status", "gone")])
+            .await
+            .unwrap();
+        assert_eq!(deleted, 3);
+    }
+
+    #[tokio::test]
+    async fn test_errors_keep_the_status_and_message() {
+        let (url, _server) = serve_once("404 Not Found", "Collection users not found");
+        let error = MongorClient::new(url).find("users", &[]).await.unwrap_err();
+        assert!(
+            matches!(&error, ClientError::Status { status: 404, message } if message == "Collection users not found"),
+            "{:?}",
+            error
+        );
+
+        // Request options are not filters and never reach the server
+        let error = MongorClient::new("http://127.0.0.1:9")
+            .delete("users", &[("limit", "1")])
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(error, ClientError::InvalidRequest(_)),
+            "{:?}",
+            error
+        );
+    }
+}
@@ -0,0 +1,63 @@
+//! Opt-in CORS for browser apps calling the API from other origins.
+//!
+//! Preflights are answered before authentication, since browsers never send credentials with
+//! them. Credentialed requests need the origins spelled out: browsers reject a wildcard
+//! `Access-Control-Allow-Origin` on them, so that combination is refused at startup.
+
+use actix_cors::Cors;
+use actix_web::middleware::Condition;
+
+use crate::config::AppConfig;
+
+/// Rejects settings browsers would refuse to honor
+pub fn validate(config: &AppConfig) -> Result<(), String> {
+    if config.cors_allow_credentials && config.cors_allowed_origins.iter().any(|o| o == "*") {
+        return Err(
+            "CORS_ALLOW_CREDENTIALS requires explicit CORS_ALLOWED_ORIGINS, not *".to_string(),
+        );
+    }
+    Ok(())
+}
+
+/// The CORS middleware, enabled once any origin is allowed
+pub fn middleware(config: &AppConfig) -> Condition<Cors> {
+    let mut cors = Cors::default()
+        .allow_any_method()
+        .allow_any_header()
+        .max_age(config.cors_max_age_secs);
+    for origin in &config.cors_allowed_origins {
+        cors = match origin.as_str() {
+            "*" => cors.allow_any_origin().send_wildcard(),
+            origin => cors.allowed_origin(origin),
+        };
+    }
+    if !config.cors_expose_headers.is_empty() {
+        cors = cors.expose_headers(config.cors_expose_headers.iter().map(String::as_str));
+    }
+    if config.cors_allow_credentials {
+        cors = cors.supports_credentials();
+    }
+    Condition::new(!config.cors_allowed_origins.is_empty(), cors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_credentials_need_explicit_origins() {
+        let config = AppConfig {
+            cors_allowed_origins: vec!["*".to_string()],
+            cors_allow_credentials: true,
+            ..AppConfig::default()
+        };
+        assert!(validate(&config).is_err());
+
+        let config = AppConfig {
+            cors_allowed_origins: vec!["https://app.example.com".to_string()],
+            ..config
+        };
+        assert!(validate(&config).is_ok());
+        assert!(validate(&AppConfig::default()).is_ok());
+    }
+}
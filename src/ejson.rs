@@ -0,0 +1,224 @@
+//! Extended JSON (EJSON) conversion between HTTP bodies and BSON.
+//!
+//! Read endpoints serialize documents as relaxed Extended JSON by default, so an
+//! ObjectId is `{"$oid": "..."}` and a DateTime is `{"$date": "2023-01-01T00:00:00Z"}`,
+//! while plain numbers, strings and booleans stay plain JSON. Clients sending
+//! `Accept: application/ejson` get canonical Extended JSON instead, which also keeps
+//! numeric types (`{"$numberInt": "1"}`). Write endpoints parse bodies as Extended JSON,
+//! so anything a read endpoint returns can be written back unchanged.
+//!
+//! Plain JSON numbers in write bodies follow the configured [`NumberPolicy`]; explicit
+//! wrappers like `{"$numberLong": "42"}` always keep the type they spell out.
+//!
+//! Responses keep each document's field order unless `SORT_RESPONSE_KEYS` is set, in which
+//! case every object's keys are sorted alphabetically. Field names are cased as `FIELD_CASE`
+//! asks (see [`crate::field_case`]).
+
+use actix_web::{HttpRequest, HttpResponse, http::header, web};
+use mongodb::bson::{Bson, Document};
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::field_case::to_response;
+use crate::shared::AppState;
+
+pub const EJSON_CONTENT_TYPE: &str = "application/ejson";
+
+/// How plain whole numbers in write bodies are stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NumberPolicy {
+    /// As written: `42` becomes an int32 (int64 if it does not fit) and `42.0` a double
+    #[default]
+    Preserve,
+    /// Every whole number, `42.0` included, becomes an int64
+    Int64,
+    /// Every number becomes a double
+    Double,
+}
+
+impl NumberPolicy {
+    pub fn parse(value: &str) -> Option<NumberPolicy> {
+        match value {
+            "preserve" => Some(NumberPolicy::Preserve),
+            "int64" => Some(NumberPolicy::Int64),
+            "double" => Some(NumberPolicy::Double),
+            _ => None,
+        }
+    }
+}
+
+/// Extended JSON flavour used when serializing documents
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtJsonMode {
+    Relaxed,
+    Canonical,
+}
+
+impl ExtJsonMode {
+    /// Returns the mode requested via `Accept: application/ejson` (canonical by default,
+    /// relaxed with `application/ejson; mode=relaxed`)
+    pub fn from_request(req: &HttpRequest) -> Option<ExtJsonMode> {
+        req.headers()
+            .get(header::ACCEPT)
+            .and_then(|accept| accept.to_str().ok())
+            .filter(|accept| accept.contains(EJSON_CONTENT_TYPE))
+            .map(|accept| {
+                if accept.contains("mode=relaxed") {
+                    ExtJsonMode::Relaxed
+                } else {
+                    ExtJsonMode::Canonical
+                }
+            })
+    }
+}
+
+/// Converts a single BSON value into Extended JSON
+pub fn bson_to_extjson(value: Bson, mode: ExtJsonMode) -> serde_json::Value {
+    match mode {
+        ExtJsonMode::Relaxed => value.into_relaxed_extjson(),
+        ExtJsonMode::Canonical => value.into_canonical_extjson(),
+    }
+}
+
+/// Serializes any BSON value as relaxed Extended JSON, or in the negotiated mode,
+/// returning the content type along with the body
+pub fn serialize_bson(req: &HttpRequest, value: Bson) -> (&'static str, String) {
+    let data = req.app_data::<web::Data<AppState>>();
+    let sort_keys = data.is_some_and(|data| data.config.sort_response_keys);
+    let value = match data {
+        Some(data) => to_response(value, data.config.field_case),
+        None => value,
+    };
+    match ExtJsonMode::from_request(req) {
+        Some(mode) => (EJSON_CONTENT_TYPE, extjson_string(value, mode, sort_keys)),
+        None => (
+            "application/json",
+            extjson_string(value, ExtJsonMode::Relaxed, sort_keys),
+        ),
+    }
+}
+
+/// Writes a BSON value as Extended JSON text, optionally with every object's keys sorted
+fn extjson_string(value: Bson, mode: ExtJsonMode, sort_keys: bool) -> String {
+    let mut json = bson_to_extjson(value, mode);
+    if sort_keys {
+        json.sort_all_objects();
+    }
+    json.to_string()
+}
+
+/// Responds with any BSON value as relaxed Extended JSON, or in the negotiated mode
+pub fn bson_response(req: &HttpRequest, value: Bson) -> HttpResponse {
+    let (content_type, body) = serialize_bson(req, value);
+    HttpResponse::Ok().content_type(content_type).body(body)
+}
+
+/// Parses a write body (relaxed or canonical Extended JSON) into a document
+pub fn extjson_to_document(value: Value, policy: NumberPolicy) -> Result<Document, String> {
+    match Bson::try_from(apply_number_policy(value, policy)).map_err(|e| e.to_string())? {
+        Bson::Document(doc) => Ok(doc),
+        other => Err(format!("expected a JSON object, got {}", other)),
+    }
+}
+
+/// Rewrites plain numbers as the explicit Extended JSON wrapper the policy asks for.
+/// Objects with `$` keys are Extended JSON values themselves and are left alone.
+fn apply_number_policy(value: Value, policy: NumberPolicy) -> Value {
+    match value {
+        Value::Number(n) => match policy {
+            NumberPolicy::Preserve => Value::Number(n),
+            NumberPolicy::Int64 => match (n.as_i64(), n.as_f64()) {
+                (Some(i), _) => json!({ "$numberLong": i.to_string() }),
+                (None, Some(f)) if f.fract() == 0.0 && f.abs() < i64::MAX as f64 => {
+                    json!({ "$numberLong": (f as i64).to_string() })
+                }
+                _ => Value::Number(n),
+            },
+            NumberPolicy::Double => match n.as_f64() {
+                Some(f) => json!({ "$numberDouble": format!("{:?}", f) }),
+                None => Value::Number(n),
+            },
+        },
+        Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .map(|item| apply_number_policy(item, policy))
+                .collect(),
+        ),
+        Value::Object(fields) if !fields.keys().any(|key| key.starts_with('$')) => Value::Object(
+            fields
+                .into_iter()
+                .map(|(key, field)| (key, apply_number_policy(field, policy)))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mongodb::bson::doc;
+
+    fn parse(body: Value, policy: NumberPolicy) -> Document {
+        extjson_to_document(body, policy).unwrap()
+    }
+
+    #[test]
+    fn test_preserve_policy_keeps_numbers_as_written() {
+        let body = json!({ "small": 42, "large": 3_000_000_000_i64, "float": 42.0 });
+        assert_eq!(
+            parse(body, NumberPolicy::Preserve),
+            doc! { "small": 42, "large": 3_000_000_000_i64, "float": 42.0 }
+        );
+    }
+
+    #[test]
+    fn test_int64_policy_stores_whole_numbers_as_int64() {
+        let body = json!({ "count": 42, "whole": 7.0, "ratio": 0.5, "nested": [{ "n": 1 }] });
+        assert_eq!(
+            parse(body, NumberPolicy::Int64),
+            doc! { "count": 42_i64, "whole": 7_i64, "ratio": 0.5, "nested": [{ "n": 1_i64 }] }
+        );
+    }
+
+    #[test]
+    fn test_double_policy_stores_numbers_as_double() {
+        let body = json!({ "count": 42, "ratio": 0.5 });
+        assert_eq!(
+            parse(body, NumberPolicy::Double),
+            doc! { "count": 42.0, "ratio": 0.5 }
+        );
+    }
+
+    #[test]
+    fn test_sorted_keys_are_byte_identical() {
+        let first = doc! { "b": 1, "a": { "z": true, "y": [{ "d": 1, "c": 2 }] } };
+        let second = doc! { "a": { "y": [{ "c": 2, "d": 1 }], "z": true }, "b": 1 };
+
+        for mode in [ExtJsonMode::Relaxed, ExtJsonMode::Canonical] {
+            let sorted = extjson_string(first.clone().into(), mode, true);
+            assert_eq!(sorted, extjson_string(second.clone().into(), mode, true));
+            assert_eq!(sorted, extjson_string(first.clone().into(), mode, true));
+        }
+        assert_eq!(
+            extjson_string(first.clone().into(), ExtJsonMode::Relaxed, true),
+            r#"{"a":{"y":[{"c":2,"d":1}],"z":true},"b":1}"#
+        );
+        // Without sorting the document's own order is kept
+        assert_eq!(
+            extjson_string(first.into(), ExtJsonMode::Relaxed, false),
+            r#"{"b":1,"a":{"z":true,"y":[{"d":1,"c":2}]}}"#
+        );
+    }
+
+    #[test]
+    fn test_explicit_wrappers_override_the_policy() {
+        let body =
+            json!({ "count": { "$numberInt": "42" }, "at": { "$date": "2024-01-01T00:00:00Z" } });
+        let document = parse(body, NumberPolicy::Double);
+        assert_eq!(document.get("count"), Some(&Bson::Int32(42)));
+        assert!(matches!(document.get("at"), Some(Bson::DateTime(_))));
+    }
+}
@@ -0,0 +1,282 @@
+//! The error type handlers return, and the one place deciding which status each error gets.
+
+use actix_web::{
+    HttpResponse, ResponseError,
+    http::{StatusCode, header},
+};
+use mongodb::error::{ErrorKind, WriteFailure};
+use std::fmt;
+
+use crate::auth::AuthError;
+use crate::circuit_breaker::{BreakerError, is_connectivity_error};
+
+const DUPLICATE_KEY: i32 = 11000;
+const MAX_TIME_MS_EXPIRED: i32 = 50;
+const NAMESPACE_NOT_FOUND: i32 = 26;
+/// BadValue, FailedToParse, TypeMismatch, ImmutableField and DocumentValidationFailure
+const INVALID_INPUT: [i32; 5] = [2, 9, 14, 66, 121];
+/// HostUnreachable, HostNotFound, NetworkTimeout, ShutdownInProgress, PrimarySteppedDown,
+/// NotWritablePrimary, InterruptedDueToReplStateChange, NotPrimaryNoSecondaryOk and
+/// NotPrimaryOrSecondary: the replica set is failing over or reconnecting
+const TRANSIENT: [i32; 9] = [6, 7, 89, 91, 189, 10107, 11602, 13435, 13436];
+/// Seconds clients are told to wait before retrying a 503
+const RETRY_AFTER_SECS: &str = "1";
+
+#[derive(Debug)]
+pub enum AppError {
+    /// Malformed query parameters, headers or bodies (400)
+    BadRequest(String),
+    /// Bodies not matching the collection schema, one message per violation (400)
+    Validation(Vec<String>),
+    /// Missing, malformed, badly signed or expired bearer token (401)
+    Unauthorized,
+    /// Valid token without the scope the request needs, or a database outside the allowlist (403)
+    Forbidden(String),
+    /// Missing or hidden collections and documents (404)
+    NotFound(String),
+    /// Responses over `MAX_RESPONSE_BYTES` (413)
+    PayloadTooLarge(String),
+    /// Well-formed requests asking for something unsupported, like some JSON Patch ops (422)
+    Unprocessable(String),
+    /// The circuit breaker is open (503, with `Retry-After`)
+    Unavailable,
+    /// Maintenance mode turned the request away (503, with `Retry-After`)
+    Maintenance(String),
+    /// A driver error, whose status depends on what the server said (400/404/409/500/503/504)
+    Database(mongodb::error::Error),
+    /// Anything else (500)
+    Internal(String),
+}
+
+impl AppError {
+    /// The server error code carried by a driver error, if any
+    fn database_error_code(error: &mongodb::error::Error) -> Option<i32> {
+        match error.kind.as_ref() {
+            ErrorKind::Command(command_error) => Some(command_error.code),
+            ErrorKind::Write(WriteFailure::WriteError(write_error)) => Some(write_error.code),
+            ErrorKind::Write(WriteFailure::WriteConcernError(concern_error)) => {
+                Some(concern_error.code)
+            }
+            ErrorKind::InsertMany(insert_error) => insert_error
+                .write_errors
+                .as_ref()
+                .and_then(|errors| errors.first())
+                .map(|write_error| write_error.code),
+            _ => None,
+        }
+    }
+
+    /// Whether a driver error is likely to go away on retry, like a dropped connection
+    /// or an election in progress, rather than a problem with the request
+    pub fn is_transient(error: &mongodb::error::Error) -> bool {
+        is_connectivity_error(error)
+            || error.contains_label(mongodb::error::RETRYABLE_WRITE_ERROR)
+            || AppError::database_error_code(error).is_some_and(|code| TRANSIENT.contains(&code))
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::BadRequest(message)
+            | AppError::Forbidden(message)
+            | AppError::NotFound(message)
+            | AppError::PayloadTooLarge(message)
+            | AppError::Unprocessable(message)
+            | AppError::Maintenance(message)
+            | AppError::Internal(message) => write!(f, "{}", message),
+            AppError::Validation(errors) => write!(f, "{}", errors.join(", ")),
+            AppError::Unauthorized => write!(f, "Invalid or missing bearer token"),
+            AppError::Unavailable => write!(f, "MongoDB is unavailable, circuit breaker is open"),
+            AppError::Database(error) => write!(f, "Database error: {}", error),
+        }
+    }
+}
+
+impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::BadRequest(_) | AppError::Validation(_) => StatusCode::BAD_REQUEST,
+            AppError::Unauthorized => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            AppError::Unprocessable(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::Unavailable | AppError::Maintenance(_) => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::Database(error) => {
+                if AppError::is_transient(error) {
+                    return StatusCode::SERVICE_UNAVAILABLE;
+                }
+                if matches!(error.kind.as_ref(), ErrorKind::InvalidArgument { .. }) {
+                    return StatusCode::BAD_REQUEST;
+                }
+                match AppError::database_error_code(error) {
+                    Some(DUPLICATE_KEY) => StatusCode::CONFLICT,
+                    Some(MAX_TIME_MS_EXPIRED) => StatusCode::GATEWAY_TIMEOUT,
+                    Some(NAMESPACE_NOT_FOUND) => StatusCode::NOT_FOUND,
+                    Some(code) if INVALID_INPUT.contains(&code) => StatusCode::BAD_REQUEST,
+                    _ => StatusCode::INTERNAL_SERVER_ERROR,
+                }
+            }
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let status = self.status_code();
+        match self {
+            AppError::Validation(errors) => {
+                HttpResponse::build(status).json(serde_json::json!({ "errors": errors }))
+            }
+            AppError::Unauthorized => HttpResponse::build(status)
+                .insert_header((header::WWW_AUTHENTICATE, "Bearer"))
+                .body(self.to_string()),
+            _ if status == StatusCode::SERVICE_UNAVAILABLE => HttpResponse::build(status)
+                .insert_header((header::RETRY_AFTER, RETRY_AFTER_SECS))
+                .body(self.to_string()),
+            _ => HttpResponse::build(status).body(self.to_string()),
+        }
+    }
+}
+
+impl From<mongodb::error::Error> for AppError {
+    fn from(error: mongodb::error::Error) -> Self {
+        AppError::Database(error)
+    }
+}
+
+impl From<AuthError> for AppError {
+    fn from(error: AuthError) -> Self {
+        match error {
+            AuthError::InvalidToken => AppError::Unauthorized,
+            AuthError::MissingScope(scope) => {
+                AppError::Forbidden(format!("Token lacks the {} scope", scope))
+            }
+            AuthError::WrongTenant(tenant) => {
+                AppError::Forbidden(format!("Token was not issued for tenant {}", tenant))
+            }
+            AuthError::Disabled => {
+                AppError::Forbidden("This endpoint requires JWT_SECRET to be set".to_string())
+            }
+        }
+    }
+}
+
+impl From<BreakerError> for AppError {
+    fn from(error: BreakerError) -> Self {
+        match error {
+            BreakerError::Open => AppError::Unavailable,
+            BreakerError::Inner(error) => AppError::Database(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mongodb::bson::doc;
+    use mongodb::error::{CommandError, WriteError};
+
+    fn command_error(code: i32) -> AppError {
+        let command_error: CommandError = mongodb::bson::from_document(doc! {
+            "code": code,
+            "codeName": "",
+            "errmsg": "failed",
+        })
+        .unwrap();
+        AppError::Database(ErrorKind::Command(command_error).into())
+    }
+
+    #[test]
+    fn test_variants_map_to_status() {
+        let cases = [
+            (AppError::BadRequest("bad".to_string()), 400),
+            (
+                AppError::Validation(vec!["/name: is required".to_string()]),
+                400,
+            ),
+            (AppError::from(AuthError::InvalidToken), 401),
+            (
+                AppError::from(AuthError::MissingScope("read:orders".to_string())),
+                403,
+            ),
+            (
+                AppError::from(AuthError::WrongTenant("acme".to_string())),
+                403,
+            ),
+            (AppError::from(AuthError::Disabled), 403),
+            (AppError::NotFound("missing".to_string()), 404),
+            (AppError::PayloadTooLarge("large".to_string()), 413),
+            (AppError::Unprocessable("unsupported".to_string()), 422),
+            (AppError::Unavailable, 503),
+            (AppError::Maintenance("read-only".to_string()), 503),
+            (AppError::Internal("broken".to_string()), 500),
+            (AppError::from(BreakerError::Open), 503),
+        ];
+        for (error, status) in cases {
+            assert_eq!(error.status_code().as_u16(), status, "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn test_database_errors_map_to_status() {
+        assert_eq!(command_error(11000).status_code(), StatusCode::CONFLICT);
+        assert_eq!(command_error(50).status_code(), StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(command_error(26).status_code(), StatusCode::NOT_FOUND);
+        assert_eq!(command_error(14).status_code(), StatusCode::BAD_REQUEST);
+        assert_eq!(command_error(66).status_code(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            command_error(8000).status_code(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+
+        let write_error: WriteError = mongodb::bson::from_document(doc! {
+            "code": 11000,
+            "errmsg": "E11000 duplicate key error",
+        })
+        .unwrap();
+        let duplicate = AppError::from(BreakerError::Inner(
+            ErrorKind::Write(WriteFailure::WriteError(write_error)).into(),
+        ));
+        assert_eq!(duplicate.status_code(), StatusCode::CONFLICT);
+    }
+
+    #[test]
+    fn test_transient_errors_map_to_retryable_503() {
+        let transient = [
+            AppError::from(mongodb::error::Error::from(std::io::Error::from(
+                std::io::ErrorKind::ConnectionReset,
+            ))),
+            command_error(10107),
+            command_error(189),
+            command_error(91),
+            AppError::Unavailable,
+        ];
+        for error in transient {
+            let response = error.error_response();
+            assert_eq!(
+                response.status(),
+                StatusCode::SERVICE_UNAVAILABLE,
+                "{:?}",
+                error
+            );
+            assert_eq!(response.headers().get(header::RETRY_AFTER).unwrap(), "1");
+        }
+
+        // Permanent failures keep their status and get no Retry-After
+        let response = command_error(11000).error_response();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert!(response.headers().get(header::RETRY_AFTER).is_none());
+    }
+
+    #[test]
+    fn test_validation_errors_keep_their_json_shape() {
+        let response =
+            AppError::Validation(vec!["/age: expected integer".to_string()]).error_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+    }
+}
@@ -0,0 +1,276 @@
+//! Field-name casing between storage and the API, set with `FIELD_CASE`.
+//!
+//! With `camel`, stored snake_case names such as `first_name` are returned as `firstName`,
+//! and write bodies are translated back before they are stored, so storage keeps its
+//! snake_case names. Names without an underscore between words, like `_id` or `name`, read
+//! the same either way. When two names of one document map to the same one, as `first_name`
+//! and `firstName` would, the later keeps its own name instead of overwriting the other.
+//!
+//! Field paths clients send elsewhere, in filters, `select`, `mask`, `sort`, joins and JSON
+//! Patch paths, are translated the same way. Validators and required fields name the stored
+//! fields, as they see bodies once renamed.
+
+use mongodb::bson::{Bson, Document};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashSet;
+
+/// How field names are cased in request and response bodies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldCase {
+    /// As stored
+    #[default]
+    Preserve,
+    /// camelCase in bodies, snake_case in storage
+    Camel,
+}
+
+impl FieldCase {
+    pub fn parse(value: &str) -> Option<FieldCase> {
+        match value {
+            "preserve" => Some(FieldCase::Preserve),
+            "camel" => Some(FieldCase::Camel),
+            _ => None,
+        }
+    }
+}
+
+/// Renames the fields of every document within `value` as responses name them
+pub fn to_response(value: Bson, case: FieldCase) -> Bson {
+    match (case, value) {
+        (FieldCase::Preserve, value) => value,
+        (FieldCase::Camel, Bson::Document(document)) => {
+            let originals: HashSet<String> = document.keys().cloned().collect();
+            let mut renamed = Document::new();
+            for (key, value) in document {
+                let name = available_name(key, snake_to_camel, &originals, |name| {
+                    renamed.contains_key(name)
+                });
+                renamed.insert(name, to_response(value, case));
+            }
+            Bson::Document(renamed)
+        }
+        (FieldCase::Camel, Bson::Array(items)) => Bson::Array(
+            items
+                .into_iter()
+                .map(|item| to_response(item, case))
+                .collect(),
+        ),
+        (FieldCase::Camel, value) => value,
+    }
+}
+
+/// Renames the fields of a write body, or of every document of a bulk one, as they are
+/// stored. Dotted paths are renamed segment by segment. Objects with `$` keys are Extended
+/// JSON values such as `{"$date": ...}` and are left alone.
+pub fn to_stored(value: Value, case: FieldCase) -> Value {
+    match (case, value) {
+        (FieldCase::Preserve, value) => value,
+        (FieldCase::Camel, Value::Object(fields))
+            if !fields.keys().any(|key| key.starts_with('$')) =>
+        {
+            let originals: HashSet<String> = fields.keys().cloned().collect();
+            let mut renamed = serde_json::Map::new();
+            for (key, value) in fields {
+                let name = available_name(
+                    key,
+                    |path| to_stored_path(path, case),
+                    &originals,
+                    |name| renamed.contains_key(name),
+                );
+                renamed.insert(name, to_stored(value, case));
+            }
+            Value::Object(renamed)
+        }
+        (FieldCase::Camel, Value::Array(items)) => Value::Array(
+            items
+                .into_iter()
+                .map(|item| to_stored(item, case))
+                .collect(),
+        ),
+        (FieldCase::Camel, value) => value,
+    }
+}
+
+/// The stored name of a dotted field path as a body names it
+pub fn to_stored_path(path: &str, case: FieldCase) -> String {
+    match case {
+        FieldCase::Preserve => path.to_string(),
+        FieldCase::Camel => path
+            .split('.')
+            .map(camel_to_snake)
+            .collect::<Vec<_>>()
+            .join("."),
+    }
+}
+
+/// Renames the field paths of a filter, sort or projection document, at any depth, as they
+/// are stored; operators like `$and` keep their names
+pub fn to_stored_fields(document: Document, case: FieldCase) -> Document {
+    match case {
+        FieldCase::Preserve => document,
+        FieldCase::Camel => document
+            .into_iter()
+            .map(|(key, value)| (to_stored_path(&key, case), to_stored_bson(value, case)))
+            .collect(),
+    }
+}
+
+fn to_stored_bson(value: Bson, case: FieldCase) -> Bson {
+    match value {
+        Bson::Document(document) => Bson::Document(to_stored_fields(document, case)),
+        Bson::Array(items) => Bson::Array(
+            items
+                .into_iter()
+                .map(|item| to_stored_bson(item, case))
+                .collect(),
+        ),
+        value => value,
+    }
+}
+
+/// `key` renamed, unless another field of the document already has or takes that name
+fn available_name(
+    key: String,
+    rename: impl Fn(&str) -> String,
+    originals: &HashSet<String>,
+    is_taken: impl Fn(&str) -> bool,
+) -> String {
+    let name = rename(&key);
+    if name != key && (originals.contains(&name) || is_taken(&name)) {
+        key
+    } else {
+        name
+    }
+}
+
+/// `first_name` to `firstName`. Leading underscores, operators and an underscore not followed
+/// by a lowercase letter (`line_1`) are kept.
+fn snake_to_camel(name: &str) -> String {
+    if name.starts_with('$') {
+        return name.to_string();
+    }
+    let body = name.trim_start_matches('_');
+    let mut camel = name[..name.len() - body.len()].to_string();
+    let mut chars = body.chars().peekable();
+    while let Some(c) = chars.next() {
+        match chars.peek() {
+            Some(next) if c == '_' && next.is_ascii_lowercase() => {
+                camel.push(next.to_ascii_uppercase());
+                chars.next();
+            }
+            _ => camel.push(c),
+        }
+    }
+    camel
+}
+
+/// `firstName` to `first_name`, the inverse of [`snake_to_camel`] for snake_case names
+fn camel_to_snake(name: &str) -> String {
+    if name.starts_with('$') {
+        return name.to_string();
+    }
+    let mut snake = String::with_capacity(name.len());
+    for (i, c) in name.char_indices() {
+        if c.is_ascii_uppercase() && i > 0 {
+            snake.push('_');
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mongodb::bson::doc;
+    use serde_json::json;
+
+    #[test]
+    fn test_names_round_trip() {
+        for (snake, camel) in [
+            ("first_name", "firstName"),
+            ("_id", "_id"),
+            ("name", "name"),
+            ("home_address_line", "homeAddressLine"),
+            ("line_1", "line_1"),
+            ("__private_key", "__privateKey"),
+            ("$set", "$set"),
+        ] {
+            assert_eq!(snake_to_camel(snake), camel);
+            assert_eq!(camel_to_snake(camel), snake);
+        }
+    }
+
+    #[test]
+    fn test_documents_are_renamed_recursively() {
+        let stored = doc! {
+            "_id": 1,
+            "first_name": "Ann",
+            "home_address": { "zip_code": "0150" },
+            "past_jobs": [{ "job_title": "clerk" }],
+        };
+        assert_eq!(
+            to_response(stored.clone().into(), FieldCase::Camel),
+            Bson::Document(doc! {
+                "_id": 1,
+                "firstName": "Ann",
+                "homeAddress": { "zipCode": "0150" },
+                "pastJobs": [{ "jobTitle": "clerk" }],
+            })
+        );
+        assert_eq!(
+            to_response(stored.clone().into(), FieldCase::Preserve),
+            Bson::Document(stored)
+        );
+
+        let body = json!([{
+            "firstName": "Ann",
+            "homeAddress.zipCode": "0150",
+            "createdAt": { "$date": "2024-01-01T00:00:00Z" },
+            "photo": { "$binary": { "base64": "", "subType": "00" } },
+        }]);
+        assert_eq!(
+            to_stored(body, FieldCase::Camel),
+            json!([{
+                "first_name": "Ann",
+                "home_address.zip_code": "0150",
+                "created_at": { "$date": "2024-01-01T00:00:00Z" },
+                "photo": { "$binary": { "base64": "", "subType": "00" } },
+            }])
+        );
+    }
+
+    #[test]
+    fn test_filter_paths_are_renamed() {
+        let filter = doc! {
+            "firstName": "Ann",
+            "$or": [{ "homeAddress.zipCode": { "$in": ["0150"] } }, { "_id": 1 }],
+        };
+        assert_eq!(
+            to_stored_fields(filter.clone(), FieldCase::Camel),
+            doc! {
+                "first_name": "Ann",
+                "$or": [{ "home_address.zip_code": { "$in": ["0150"] } }, { "_id": 1 }],
+            }
+        );
+        assert_eq!(
+            to_stored_fields(filter.clone(), FieldCase::Preserve),
+            filter
+        );
+    }
+
+    #[test]
+    fn test_colliding_names_keep_both_fields() {
+        let stored = doc! { "first_name": "Ann", "firstName": "Bo" };
+        assert_eq!(
+            to_response(stored.clone().into(), FieldCase::Camel),
+            Bson::Document(stored)
+        );
+        let body = json!({ "firstName": "Ann", "first_name": "Bo" });
+        assert_eq!(to_stored(body.clone(), FieldCase::Camel), body);
+    }
+}
@@ -0,0 +1,63 @@
+//! `?flatten=true` responses for tabular consumers.
+//!
+//! Embedded documents become dotted keys (`address.city`) and array elements are
+//! addressed by index (`tags.0`), so every value ends up at the top level. Empty
+//! documents and arrays are kept as-is, since there is no key to flatten them into.
+
+use mongodb::bson::{Bson, Document};
+
+/// Flattens every embedded document and array of `doc` into dotted top-level keys
+pub fn flatten_document(doc: Document) -> Document {
+    let mut flat = Document::new();
+    for (key, value) in doc {
+        flatten_into(&mut flat, key, value);
+    }
+    flat
+}
+
+fn flatten_into(flat: &mut Document, prefix: String, value: Bson) {
+    match value {
+        Bson::Document(embedded) if !embedded.is_empty() => {
+            for (key, value) in embedded {
+                flatten_into(flat, format!("{}.{}", prefix, key), value);
+            }
+        }
+        Bson::Array(items) if !items.is_empty() => {
+            for (i, value) in items.into_iter().enumerate() {
+                flatten_into(flat, format!("{}.{}", prefix, i), value);
+            }
+        }
+        value => {
+            flat.insert(prefix, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mongodb::bson::doc;
+
+    #[test]
+    fn test_flatten_documents_and_arrays() {
+        let document = doc! {
+            "_id": 1,
+            "address": { "city": "Bucharest", "geo": { "lat": 44.4 } },
+            "tags": ["a", { "b": true }],
+            "empty": {},
+            "none": [],
+        };
+        assert_eq!(
+            flatten_document(document),
+            doc! {
+                "_id": 1,
+                "address.city": "Bucharest",
+                "address.geo.lat": 44.4,
+                "tags.0": "a",
+                "tags.1.b": true,
+                "empty": {},
+                "none": [],
+            }
+        );
+    }
+}
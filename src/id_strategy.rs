@@ -0,0 +1,118 @@
+//! `_id` generation for POSTed documents that do not carry one.
+//!
+//! Sequences are kept in [`COUNTERS_COLLECTION`], one `{ _id: collection, seq }` document per
+//! collection, and advanced with a single `$inc` per request so concurrent inserts never share
+//! an id. UUIDs are stored as their hyphenated string so they can be used in paths and filters.
+
+use mongodb::bson::{Bson, Document, Uuid, doc};
+use mongodb::options::ReturnDocument;
+use serde::Deserialize;
+
+use crate::circuit_breaker::BreakerError;
+use crate::shared::AppState;
+
+/// Holds the sequence counters; hidden from the catalog like a denylisted collection
+pub const COUNTERS_COLLECTION: &str = "mongor_counters";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IdStrategy {
+    /// Left to the driver, which generates an ObjectId
+    #[default]
+    ObjectId,
+    /// A random UUID string
+    Uuid,
+    /// 1, 2, 3, ... per collection
+    Sequence,
+}
+
+impl IdStrategy {
+    pub fn parse(value: &str) -> Option<IdStrategy> {
+        match value {
+            "objectid" => Some(IdStrategy::ObjectId),
+            "uuid" => Some(IdStrategy::Uuid),
+            "sequence" => Some(IdStrategy::Sequence),
+            _ => None,
+        }
+    }
+}
+
+/// Gives every document without an `_id` one generated with `strategy`
+pub async fn assign_ids(
+    data: &AppState,
+    database: &mongodb::Database,
+    collection_name: &str,
+    strategy: IdStrategy,
+    documents: &mut [Document],
+    comment: Bson,
+) -> Result<(), BreakerError> {
+    let missing: Vec<&mut Document> = documents
+        .iter_mut()
+        .filter(|document| !document.contains_key("_id"))
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    let ids: Vec<Bson> = match strategy {
+        IdStrategy::ObjectId => return Ok(()),
+        IdStrategy::Uuid => (0..missing.len())
+            .map(|_| Bson::String(Uuid::new().to_string()))
+            .collect(),
+        IdStrategy::Sequence => {
+            let count = missing.len() as i64;
+            let last = reserve_sequence(data, database, collection_name, count, comment).await?;
+            (last - missing.len() as i64 + 1..=last)
+                .map(Bson::Int64)
+                .collect()
+        }
+    };
+
+    for (document, id) in missing.into_iter().zip(ids) {
+        // Keep `_id` first, as the server would
+        let mut with_id = doc! { "_id": id };
+        with_id.extend(std::mem::take(document));
+        *document = with_id;
+    }
+    Ok(())
+}
+
+/// Advances the collection's counter, kept in the collection's own database, by `count` and returns its new value
+async fn reserve_sequence(
+    data: &AppState,
+    database: &mongodb::Database,
+    collection_name: &str,
+    count: i64,
+    comment: Bson,
+) -> Result<i64, BreakerError> {
+    let counter = data
+        .circuit_breaker
+        .call(
+            database
+                .collection::<Document>(COUNTERS_COLLECTION)
+                .find_one_and_update(
+                    doc! { "_id": collection_name },
+                    doc! { "$inc": { "seq": count } },
+                )
+                .upsert(true)
+                .return_document(ReturnDocument::After)
+                .comment(comment),
+        )
+        .await?;
+    Ok(counter
+        .and_then(|counter| counter.get_i64("seq").ok())
+        .unwrap_or(count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(IdStrategy::parse("uuid"), Some(IdStrategy::Uuid));
+        assert_eq!(IdStrategy::parse("sequence"), Some(IdStrategy::Sequence));
+        assert_eq!(IdStrategy::parse("objectid"), Some(IdStrategy::ObjectId));
+        assert_eq!(IdStrategy::parse("increment"), None);
+    }
+}
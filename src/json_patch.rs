@@ -0,0 +1,299 @@
+//! Translation of JSON Patch (RFC 6902) documents into MongoDB update operators.
+//!
+//! `add` and `replace` become `$set`, `remove` becomes `$unset`, and `add` to the
+//! end of an array (a path ending in `/-`) becomes `$push`. Operations that cannot
+//! be expressed as a single update (`move`, `copy`, `test`) are rejected, and so are
+//! other array indices, which MongoDB would overwrite or null out instead of shifting
+//! the elements, patches touching the same field twice, and field names with a `.` or a
+//! leading `$`, which MongoDB would read as a nested path or an operator.
+
+use mongodb::bson::{Bson, Document, doc};
+use serde_json::Value;
+
+use crate::field_case::{FieldCase, to_stored, to_stored_path};
+
+pub const JSON_PATCH_CONTENT_TYPE: &str = "application/json-patch+json";
+
+#[derive(Debug, PartialEq)]
+pub enum JsonPatchError {
+    /// The body is not a well-formed JSON Patch document
+    Invalid(String),
+    /// The patch is well-formed but uses an operation we cannot translate
+    Unsupported(String),
+}
+
+impl std::fmt::Display for JsonPatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JsonPatchError::Invalid(msg) => write!(f, "Invalid JSON Patch: {}", msg),
+            JsonPatchError::Unsupported(msg) => write!(f, "Unsupported JSON Patch: {}", msg),
+        }
+    }
+}
+
+/// Builds the update document for a JSON Patch array of operations, with paths and values
+/// named as `case` stores them.
+pub fn to_update_document(patch: Value, case: FieldCase) -> Result<Document, JsonPatchError> {
+    let operations = match patch {
+        Value::Array(operations) => operations,
+        _ => {
+            return Err(JsonPatchError::Invalid(
+                "expected an array of operations".to_string(),
+            ));
+        }
+    };
+
+    let mut set = Document::new();
+    let mut unset = Document::new();
+    let mut push = Document::new();
+    let mut fields: Vec<String> = Vec::new();
+
+    for operation in operations {
+        let op = operation.get("op").and_then(Value::as_str).ok_or_else(|| {
+            JsonPatchError::Invalid("every operation needs an \"op\"".to_string())
+        })?;
+        let path = operation
+            .get("path")
+            .and_then(Value::as_str)
+            .ok_or_else(|| {
+                JsonPatchError::Invalid("every operation needs a \"path\"".to_string())
+            })?;
+        let field = to_stored_path(&pointer_to_field(path)?, case);
+        let target = match field.strip_suffix(".-") {
+            Some(array) if op == "add" => array,
+            _ => field.as_str(),
+        };
+        if target.split('.').any(is_array_index) {
+            return Err(JsonPatchError::Unsupported(format!(
+                "path \"{}\" addresses an array element",
+                path
+            )));
+        }
+        if fields.iter().any(|other| overlaps(other, target)) {
+            return Err(JsonPatchError::Unsupported(format!(
+                "path \"{}\" overlaps an earlier operation",
+                path
+            )));
+        }
+        fields.push(target.to_string());
+
+        match op {
+            "add" | "replace" => {
+                let value = operation.get("value").cloned().ok_or_else(|| {
+                    JsonPatchError::Invalid(format!("\"{}\" needs a \"value\"", op))
+                })?;
+                let value = Bson::try_from(to_stored(value, case))
+                    .map_err(|e| JsonPatchError::Invalid(format!("invalid value: {}", e)))?;
+                if target != field {
+                    push.insert(target, value);
+                } else {
+                    set.insert(target, value);
+                }
+            }
+            "remove" => {
+                unset.insert(field, "");
+            }
+            "move" | "copy" | "test" => {
+                return Err(JsonPatchError::Unsupported(format!(
+                    "the \"{}\" operation is not supported",
+                    op
+                )));
+            }
+            other => {
+                return Err(JsonPatchError::Invalid(format!(
+                    "unknown operation \"{}\"",
+                    other
+                )));
+            }
+        }
+    }
+
+    let mut update = doc! {};
+    for (operator, fields) in [("$set", set), ("$unset", unset), ("$push", push)] {
+        if !fields.is_empty() {
+            update.insert(operator, fields);
+        }
+    }
+    if update.is_empty() {
+        return Err(JsonPatchError::Invalid("no operations given".to_string()));
+    }
+    Ok(update)
+}
+
+/// Whether a path segment is an array index (or `-`, the end of an array)
+fn is_array_index(segment: &str) -> bool {
+    segment == "-" || (!segment.is_empty() && segment.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Whether two dotted paths are the same field or one holds the other
+fn overlaps(a: &str, b: &str) -> bool {
+    let (shorter, longer) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    longer
+        .strip_prefix(shorter)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+/// Converts a JSON pointer like `/address/city` into a dotted field path. Tokens holding a
+/// `.` or starting with `$` cannot be told apart from other paths or operators once dotted,
+/// so they are rejected rather than rewritten.
+fn pointer_to_field(pointer: &str) -> Result<String, JsonPatchError> {
+    let tokens = match pointer.strip_prefix('/') {
+        Some(tokens) if !tokens.is_empty() => tokens,
+        _ => {
+            return Err(JsonPatchError::Unsupported(format!(
+                "path \"{}\" does not name a field",
+                pointer
+            )));
+        }
+    };
+
+    let tokens: Vec<String> = tokens
+        .split('/')
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect();
+    if let Some(token) = tokens
+        .iter()
+        .find(|token| token.contains('.') || token.starts_with('$'))
+    {
+        return Err(JsonPatchError::Unsupported(format!(
+            "path \"{}\" has a field name \"{}\" MongoDB cannot address",
+            pointer, token
+        )));
+    }
+    Ok(tokens.join("."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_to_update_document() {
+        let patch = json!([
+            { "op": "replace", "path": "/name", "value": "new name" },
+            { "op": "add", "path": "/address/city", "value": "Bucharest" },
+            { "op": "add", "path": "/tags/-", "value": "rust" },
+            { "op": "remove", "path": "/legacy~1field" }
+        ]);
+
+        assert_eq!(
+            to_update_document(patch, FieldCase::Preserve).unwrap(),
+            doc! {
+                "$set": { "name": "new name", "address.city": "Bucharest" },
+                "$unset": { "legacy/field": "" },
+                "$push": { "tags": "rust" }
+            }
+        );
+    }
+
+    #[test]
+    fn test_to_update_document_stores_camel_case_paths() {
+        let patch = json!([
+            { "op": "replace", "path": "/homeAddress/zipCode", "value": "0150" },
+            { "op": "add", "path": "/pastJobs/-", "value": { "jobTitle": "clerk" } },
+            { "op": "remove", "path": "/firstName" }
+        ]);
+
+        assert_eq!(
+            to_update_document(patch, FieldCase::Camel).unwrap(),
+            doc! {
+                "$set": { "home_address.zip_code": "0150" },
+                "$unset": { "first_name": "" },
+                "$push": { "past_jobs": { "job_title": "clerk" } }
+            }
+        );
+    }
+
+    #[test]
+    fn test_to_update_document_rejects_unsupported_operations() {
+        let patch = json!([{ "op": "move", "from": "/tags/0", "path": "/tags/1" }]);
+        assert!(matches!(
+            to_update_document(patch, FieldCase::Preserve),
+            Err(JsonPatchError::Unsupported(_))
+        ));
+
+        let patch = json!([{ "op": "replace", "path": "", "value": {} }]);
+        assert!(matches!(
+            to_update_document(patch, FieldCase::Preserve),
+            Err(JsonPatchError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_to_update_document_rejects_array_indices() {
+        for patch in [
+            json!([{ "op": "remove", "path": "/tags/0" }]),
+            json!([{ "op": "add", "path": "/tags/1", "value": "rust" }]),
+            json!([{ "op": "replace", "path": "/items/2/name", "value": "x" }]),
+            json!([{ "op": "remove", "path": "/tags/-" }]),
+            json!([{ "op": "add", "path": "/items/-/name", "value": "x" }]),
+        ] {
+            assert!(matches!(
+                to_update_document(patch, FieldCase::Preserve),
+                Err(JsonPatchError::Unsupported(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_to_update_document_rejects_dotted_and_operator_tokens() {
+        for path in ["/a.b", "/address/$set", "/$where", "/a~1b.c"] {
+            let patch = json!([{ "op": "replace", "path": path, "value": 1 }]);
+            assert!(
+                matches!(
+                    to_update_document(patch, FieldCase::Preserve),
+                    Err(JsonPatchError::Unsupported(_))
+                ),
+                "{} should be rejected",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn test_to_update_document_rejects_overlapping_paths() {
+        for patch in [
+            json!([
+                { "op": "add", "path": "/name", "value": "a" },
+                { "op": "remove", "path": "/name" }
+            ]),
+            json!([
+                { "op": "replace", "path": "/address", "value": {} },
+                { "op": "add", "path": "/address/city", "value": "Bucharest" }
+            ]),
+            json!([
+                { "op": "add", "path": "/tags/-", "value": "a" },
+                { "op": "add", "path": "/tags/-", "value": "b" }
+            ]),
+        ] {
+            assert!(matches!(
+                to_update_document(patch, FieldCase::Preserve),
+                Err(JsonPatchError::Unsupported(_))
+            ));
+        }
+
+        // Fields sharing a name prefix do not overlap
+        let patch = json!([
+            { "op": "add", "path": "/name", "value": "a" },
+            { "op": "add", "path": "/names", "value": "b" }
+        ]);
+        assert!(to_update_document(patch, FieldCase::Preserve).is_ok());
+    }
+
+    #[test]
+    fn test_to_update_document_rejects_malformed_patches() {
+        for patch in [
+            json!({ "op": "add", "path": "/name", "value": 1 }),
+            json!([{ "path": "/name" }]),
+            json!([{ "op": "add", "path": "/name" }]),
+            json!([{ "op": "rename", "path": "/name" }]),
+            json!([]),
+        ] {
+            assert!(matches!(
+                to_update_document(patch, FieldCase::Preserve),
+                Err(JsonPatchError::Invalid(_))
+            ));
+        }
+    }
+}
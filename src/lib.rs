@@ -1,4 +1,15 @@
+#[cfg(feature = "client")]
+pub mod client;
+pub mod field_case;
 pub mod query_param_parser;
 
-// Re-export the parse_match_query_params function at the top level
-pub use query_param_parser::parse_match_query_params;
+#[cfg(feature = "client")]
+pub use client::MongorClient;
+
+// Re-export the filter parsing functions at the top level
+pub use field_case::FieldCase;
+pub use query_param_parser::{
+    FilterBuilder, MAX_PATTERN_LEN, OPERATORS, ParseOptions, RepeatedParams, UnknownParams,
+    check_regex, parse_match_query_pairs, parse_match_query_pairs_with, parse_match_query_params,
+    parse_match_query_params_bounded, parse_match_query_params_with,
+};
@@ -1,25 +1,54 @@
-use actix_web::{App, HttpServer, web};
+use actix_web::{App, HttpServer, middleware, web};
 use mongodb::{Client, options::ClientOptions};
 use std::env;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
+mod auth;
+mod byte_range;
+mod caching;
 mod catalog;
+mod circuit_breaker;
 mod config;
+mod cors;
+mod ejson;
+mod error;
+mod flatten;
+mod id_strategy;
+mod json_patch;
+mod maintenance;
 mod openapi_docs;
+mod problem;
+mod query_cache;
 mod query_param_parser;
+mod rate_limit;
+mod redaction;
+mod request_id;
+mod retry;
 mod routes;
+mod schema_inference;
+mod schema_validation;
+mod stats;
+mod transaction;
+
+// Shared with the filter parsing of the library, whose options name a `FieldCase`
+use mongor::field_case;
 
 pub mod shared {
-    use std::sync::{Arc, Mutex};
+    use std::sync::{Arc, RwLock};
 
     #[derive(Clone)]
     pub struct AppState {
         pub config: crate::config::AppConfig,
         pub db_client: mongodb::Client,
-        pub collections: Arc<Mutex<crate::catalog::Catalog>>,
+        pub collections: Arc<RwLock<Arc<crate::catalog::Catalog>>>,
+        pub rate_limiter: Option<Arc<crate::rate_limit::RateLimiter>>,
+        pub query_cache: Option<Arc<crate::query_cache::QueryCache>>,
+        pub circuit_breaker: Arc<crate::circuit_breaker::CircuitBreaker>,
+        pub stats_cache: Arc<crate::stats::StatsCache>,
+        pub maintenance: Arc<crate::maintenance::Maintenance>,
     }
 }
 
@@ -34,36 +63,82 @@ async fn main() -> std::io::Result<()> {
         .and_then(|port_str| port_str.parse::<u16>().ok())
         .unwrap_or(8080);
 
-    let config = config::load_config();
+    let config_path = args
+        .iter()
+        .position(|arg| arg == "--config")
+        .and_then(|i| args.get(i + 1));
+
+    let config = config::load_config(config_path.map(String::as_str));
+    config::validate(&config).unwrap_or_else(|e| panic!("{}", e));
+    cors::validate(&config).unwrap_or_else(|e| panic!("{}", e));
 
-    let options = ClientOptions::parse(&config.database_conn_url)
-        .await
-        .expect("failed to parse config");
+    let redacted_conn_url = config::redact_conn_url(&config.database_conn_url);
+    let mut options = retry::with_backoff(
+        "parse the connection string",
+        config.startup_retry_attempts,
+        || ClientOptions::parse(&config.database_conn_url),
+    )
+    .await
+    .unwrap_or_else(|e| panic!("failed to parse {}: {}", redacted_conn_url, e));
+    config::apply_client_options(&mut options, &config).expect("invalid client options");
     let db_client = Client::with_options(options).expect("failed to create client");
 
-    println!("Successfully connected to MongoDB!");
+    println!(
+        "Successfully connected to MongoDB at {}!",
+        redacted_conn_url
+    );
     println!("Starting server on port {}", port);
 
-    let init_catalog = catalog::fetch_all_collections(&db_client.database(&config.database_name))
-        .await
-        .expect("Error fetching initial catalog");
+    // MongoDB may still be starting up, e.g. when both come up in the same deployment
+    let database = db_client.database(&config.database_name);
+    let init_catalog = retry::with_backoff(
+        "fetch the initial catalog",
+        config.startup_retry_attempts,
+        || catalog::fetch_all_collections(&database, &config),
+    )
+    .await
+    .expect("Error fetching initial catalog");
+    openapi_docs::check_schemas(&init_catalog, config.schema_check)
+        .unwrap_or_else(|e| panic!("{}", e));
 
     // Create the shared state
     let app_state = web::Data::new(crate::shared::AppState {
         config: config.clone(),
         db_client: db_client.clone(),
-        collections: Arc::new(Mutex::new(init_catalog)),
+        collections: Arc::new(RwLock::new(Arc::new(init_catalog))),
+        rate_limiter: config
+            .rate_limit_per_min
+            .map(|per_minute| Arc::new(rate_limit::RateLimiter::new(per_minute))),
+        query_cache: config.query_cache_size.map(|size| {
+            Arc::new(query_cache::QueryCache::new(
+                size,
+                Duration::from_millis(config.query_cache_ttl_ms),
+            ))
+        }),
+        circuit_breaker: Arc::new(circuit_breaker::CircuitBreaker::new(
+            config.circuit_breaker_threshold,
+            Duration::from_secs(config.circuit_breaker_cooldown_secs),
+        )),
+        stats_cache: Arc::new(stats::StatsCache::default()),
+        maintenance: Arc::new(maintenance::Maintenance::default()),
     });
 
     // Spawn a background task to periodically fetch catalog
     actix_web::rt::spawn(catalog::fetch_collections_periodically(
         app_state.clone(),
-        Duration::from_secs(60),
+        Duration::from_secs(config.catalog_refresh_secs),
     ));
 
     HttpServer::new(move || {
         App::new()
             .app_data(app_state.clone())
+            .wrap(middleware::from_fn(maintenance::enforce))
+            .wrap(middleware::from_fn(auth::authenticate))
+            .wrap(middleware::from_fn(rate_limit::rate_limit))
+            .wrap(middleware::from_fn(problem::problem_errors))
+            .wrap(middleware::from_fn(request_id::assign))
+            // Outermost, so preflights are answered before authentication
+            .wrap(cors::middleware(&config))
             // Add Swagger UI with a dynamic path to the OpenAPI JSON
             .service(crate::openapi_docs::get_openapi_json)
             .service(
@@ -0,0 +1,148 @@
+//! Runtime maintenance mode, toggled through `/api/maintenance` without a restart; switching
+//! it needs a token with the `admin:maintenance` scope, so it is refused without `JWT_SECRET`.
+//!
+//! `readonly` answers writes with 503 while reads go on, e.g. during a migration; `full`
+//! answers everything with 503 so load balancers drain the instance. The maintenance
+//! endpoint itself always stays reachable, so the mode can be switched back off.
+
+use actix_web::{
+    Error, ResponseError,
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::Method,
+    middleware::Next,
+    web,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::auth::is_read_request;
+use crate::error::AppError;
+use crate::shared::AppState;
+
+/// Path of the endpoint reading and switching the mode
+pub const MAINTENANCE_PATH: &str = "/api/maintenance";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MaintenanceMode {
+    /// Serving reads and writes
+    #[default]
+    Off,
+    /// Writes are rejected, reads go on
+    ReadOnly,
+    /// Every request is rejected
+    Full,
+}
+
+/// The current mode, shared by every worker
+#[derive(Debug, Default)]
+pub struct Maintenance {
+    mode: AtomicU8,
+}
+
+impl Maintenance {
+    pub fn mode(&self) -> MaintenanceMode {
+        match self.mode.load(Ordering::Relaxed) {
+            1 => MaintenanceMode::ReadOnly,
+            2 => MaintenanceMode::Full,
+            _ => MaintenanceMode::Off,
+        }
+    }
+
+    pub fn set_mode(&self, mode: MaintenanceMode) {
+        let value = match mode {
+            MaintenanceMode::Off => 0,
+            MaintenanceMode::ReadOnly => 1,
+            MaintenanceMode::Full => 2,
+        };
+        self.mode.store(value, Ordering::Relaxed);
+    }
+}
+
+/// The error a request gets in this mode, if it is turned away
+fn rejection(mode: MaintenanceMode, method: &Method, path: &str) -> Option<AppError> {
+    if path == MAINTENANCE_PATH {
+        return None;
+    }
+    let is_read = is_read_request(method, path) || method == Method::OPTIONS;
+    match mode {
+        MaintenanceMode::Off => None,
+        MaintenanceMode::ReadOnly if is_read => None,
+        MaintenanceMode::ReadOnly => Some(AppError::Maintenance(
+            "The server is read-only for maintenance".to_string(),
+        )),
+        MaintenanceMode::Full => Some(AppError::Maintenance(
+            "The server is down for maintenance".to_string(),
+        )),
+    }
+}
+
+/// Middleware answering requests the maintenance mode turns away with 503 and `Retry-After`
+pub async fn enforce(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let rejection = req
+        .app_data::<web::Data<AppState>>()
+        .and_then(|data| rejection(data.maintenance.mode(), req.method(), req.path()));
+
+    if let Some(error) = rejection {
+        let response = error.error_response();
+        return Ok(req.into_response(response).map_into_right_body());
+    }
+
+    next.call(req)
+        .await
+        .map(ServiceResponse::map_into_left_body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mode_round_trips() {
+        let maintenance = Maintenance::default();
+        assert_eq!(maintenance.mode(), MaintenanceMode::Off);
+        for mode in [
+            MaintenanceMode::ReadOnly,
+            MaintenanceMode::Full,
+            MaintenanceMode::Off,
+        ] {
+            maintenance.set_mode(mode);
+            assert_eq!(maintenance.mode(), mode);
+        }
+    }
+
+    #[test]
+    fn test_rejections_by_mode() {
+        let cases = [
+            (MaintenanceMode::Off, Method::POST, "/orders", false),
+            (MaintenanceMode::ReadOnly, Method::GET, "/orders", false),
+            (MaintenanceMode::ReadOnly, Method::POST, "/orders", true),
+            (
+                MaintenanceMode::ReadOnly,
+                Method::POST,
+                "/orders/find",
+                false,
+            ),
+            (MaintenanceMode::ReadOnly, Method::DELETE, "/orders", true),
+            (MaintenanceMode::Full, Method::GET, "/orders", true),
+            (MaintenanceMode::Full, Method::GET, "/api/ping", true),
+            // The mode can always be read and switched back off
+            (MaintenanceMode::Full, Method::GET, MAINTENANCE_PATH, false),
+            (MaintenanceMode::Full, Method::POST, MAINTENANCE_PATH, false),
+        ];
+        for (mode, method, path, rejected) in cases {
+            assert_eq!(
+                rejection(mode, &method, path).is_some(),
+                rejected,
+                "{:?} {} {}",
+                mode,
+                method,
+                path
+            );
+        }
+    }
+}
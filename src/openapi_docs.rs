@@ -1,14 +1,20 @@
 #![allow(dead_code)]
 
-use actix_web::{HttpResponse, Responder, web};
+use actix_web::{
+    HttpRequest, HttpResponse, Responder, ResponseError,
+    http::{Method, header},
+    web,
+};
 use mongodb::bson::{Bson, Document};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
 use utoipa::{
     OpenApi, ToSchema,
     openapi::{
         ContentBuilder, ObjectBuilder, RefOr, Required, Schema, SchemaFormat,
-        path::{OperationBuilder, ParameterBuilder, ParameterIn},
+        example::ExampleBuilder,
+        path::{OperationBuilder, Parameter, ParameterBuilder, ParameterIn, ParameterStyle},
         request_body::{RequestBody, RequestBodyBuilder},
     },
 };
@@ -27,10 +33,10 @@ pub struct CollectionInfo {
 #[derive(OpenApi)]
 #[openapi(
     paths(
-        ping
+        crate::routes::ping
     ),
     components(
-        schemas(CollectionInfo)
+        schemas(CollectionInfo, crate::routes::PingResponse)
     ),
     tags(
         (name = "collections", description = "MongoDB Collections API"),
@@ -43,6 +49,8 @@ pub struct ApiDoc;
 pub fn get_dynamic_openapi(catalog: &Catalog) -> utoipa::openapi::OpenApi {
     // Start with the base OpenAPI document
     let mut openapi = ApiDoc::openapi();
+    // Lets codegen pipelines tell documents of different catalogs apart
+    openapi.info.version = format!("{}+catalog.{}", openapi.info.version, catalog.version);
 
     // Add collection-specific paths
     for collection in &catalog.collection_specs {
@@ -50,9 +58,32 @@ pub fn get_dynamic_openapi(catalog: &Catalog) -> utoipa::openapi::OpenApi {
 
         // 1. GET path for retrieving documents
         let path = format!("/collections/{}", collection_name);
+        // Note which fields are indexed so users know which filters are efficient
+        let mut indexed_fields: Vec<String> = Vec::new();
+        for model in catalog.indexes.get(&collection_name).into_iter().flatten() {
+            for field in model.keys.keys() {
+                if !indexed_fields.contains(field) {
+                    indexed_fields.push(field.clone());
+                }
+            }
+        }
+        let mut get_description = if indexed_fields.is_empty() {
+            "Test description".to_string()
+        } else {
+            format!("Indexed fields: {}", indexed_fields.join(", "))
+        };
+        // Generated clients should not assume every field is present
+        get_description.push_str(
+            ". The shape of the returned documents depends on `select`: \
+             without it they carry every field but the collection's default exclusions, \
+             with it only `_id` and the selected fields.",
+        );
+        let (full_example, selected_example) =
+            get_response_examples(collection.options.validator.as_ref());
+
         let get_path_item = OperationBuilder::new()
             .summary(Some("Retrieve documents matching the query"))
-            .description(Some("Test description"))
+            .description(Some(get_description))
             .tag(format!(
                 "MongoDB Collections {}",
                 if collection.options.validator.is_some() {
@@ -85,6 +116,9 @@ pub fn get_dynamic_openapi(catalog: &Catalog) -> utoipa::openapi::OpenApi {
                     ))
                     .build(),
             )
+            .parameter(sort_parameter())
+            .parameter(select_parameter())
+            .parameter(filter_parameter())
             .response(
                 "200",
                 utoipa::openapi::ResponseBuilder::new()
@@ -98,10 +132,22 @@ pub fn get_dynamic_openapi(catalog: &Catalog) -> utoipa::openapi::OpenApi {
                             //         "items": {"type": "object"}
                             //     }),
                             // ))
-                            .example(Some(serde_json::json!([
-                                {"_id": "example_id_1", "field1": "value1"},
-                                {"_id": "example_id_2", "field2": 42}
-                            ])))
+                            .examples_from_iter([
+                                (
+                                    "all_fields",
+                                    ExampleBuilder::new()
+                                        .summary("Without `select`")
+                                        .value(Some(full_example))
+                                        .build(),
+                                ),
+                                (
+                                    "selected_fields",
+                                    ExampleBuilder::new()
+                                        .summary(selected_example.0)
+                                        .value(Some(selected_example.1))
+                                        .build(),
+                                ),
+                            ])
                             .build(),
                     )
                     .build(),
@@ -192,7 +238,9 @@ pub fn get_dynamic_openapi(catalog: &Catalog) -> utoipa::openapi::OpenApi {
                 ParameterBuilder::new()
                     .parameter_in(ParameterIn::Query)
                     .name("limit")
-                    .description(Some("Maximum number of documents to return | default: 100"))
+                    .description(Some(
+                        "Maximum number of documents to delete, in `sort` order | default: all",
+                    ))
                     .schema(Some(
                         ObjectBuilder::new()
                             .schema_type(utoipa::openapi::Type::Integer)
@@ -204,7 +252,9 @@ pub fn get_dynamic_openapi(catalog: &Catalog) -> utoipa::openapi::OpenApi {
                 ParameterBuilder::new()
                     .parameter_in(ParameterIn::Query)
                     .name("skip")
-                    .description(Some("Number of documents to skip | default: 0"))
+                    .description(Some(
+                        "Number of matching documents to keep before deleting | default: 0",
+                    ))
                     .schema(Some(
                         ObjectBuilder::new()
                             .schema_type(utoipa::openapi::Type::Integer)
@@ -212,6 +262,7 @@ pub fn get_dynamic_openapi(catalog: &Catalog) -> utoipa::openapi::OpenApi {
                     ))
                     .build(),
             )
+            .parameter(sort_parameter())
             .response(
                 "200",
                 utoipa::openapi::ResponseBuilder::new()
@@ -253,36 +304,239 @@ pub fn get_dynamic_openapi(catalog: &Catalog) -> utoipa::openapi::OpenApi {
     openapi
 }
 
-/// Endpoint to serve the dynamically generated OpenAPI document
-#[actix_web::get("/openapi.json")]
-pub async fn get_openapi_json(data: web::Data<AppState>) -> impl Responder {
-    if let Some(catalog) = crate::catalog::get_cached_collections(&data) {
+/// GET response examples: documents with every field, and with only the selected first one.
+/// The fields come from the collection validator when it has one.
+fn get_response_examples(
+    validator: Option<&Document>,
+) -> (serde_json::Value, (String, serde_json::Value)) {
+    let properties = validator
+        .map(|validator| validator.get_document("$jsonSchema").unwrap_or(validator))
+        .and_then(|schema| schema.get_document("properties").ok());
+    let fields: Vec<(String, serde_json::Value)> = match properties {
+        Some(properties) => properties
+            .iter()
+            .filter(|(name, _)| name.as_str() != "_id")
+            .map(|(name, property)| {
+                let bson_type = property
+                    .as_document()
+                    .and_then(|property| property.get_str("bsonType").ok());
+                (name.clone(), example_value(bson_type))
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+    let fields = if fields.is_empty() {
+        vec![
+            ("field1".to_string(), serde_json::json!("value1")),
+            ("field2".to_string(), serde_json::json!(42)),
+        ]
+    } else {
+        fields
+    };
+
+    let document = |fields: &[(String, serde_json::Value)]| {
+        let mut document = serde_json::Map::new();
+        document.insert("_id".to_string(), serde_json::json!("example_id_1"));
+        document.extend(fields.iter().cloned());
+        serde_json::Value::Array(vec![document.into()])
+    };
+    let selected = format!("With `select={}`", fields[0].0);
+    (document(&fields), (selected, document(&fields[..1])))
+}
+
+/// A placeholder value of a validator `bsonType`, as it appears in relaxed Extended JSON
+fn example_value(bson_type: Option<&str>) -> serde_json::Value {
+    match bson_type {
+        Some("int" | "long" | "double" | "decimal" | "number") => serde_json::json!(42),
+        Some("bool") => serde_json::json!(true),
+        Some("date") => serde_json::json!({ "$date": "2024-01-01T00:00:00Z" }),
+        Some("objectId") => serde_json::json!({ "$oid": "64b0c0ffee0000000000000a" }),
+        Some("array") => serde_json::json!([]),
+        Some("object") => serde_json::json!({}),
+        _ => serde_json::json!("text"),
+    }
+}
+
+/// Documents the `sort` query parameter
+fn sort_parameter() -> Parameter {
+    ParameterBuilder::new()
+        .parameter_in(ParameterIn::Query)
+        .name("sort")
+        .description(Some(
+            "Comma-separated sort keys, ascending unless suffixed with `.desc` (`.asc` is optional)",
+        ))
+        .schema(Some(
+            ObjectBuilder::new()
+                .schema_type(utoipa::openapi::Type::String)
+                .build(),
+        ))
+        .example(Some(serde_json::json!("age.desc,name")))
+        .build()
+}
+
+/// Documents the `select` query parameter
+fn select_parameter() -> Parameter {
+    ParameterBuilder::new()
+        .parameter_in(ParameterIn::Query)
+        .name("select")
+        .description(Some(
+            "Comma-separated fields to return; overrides the collection's default exclusions",
+        ))
+        .schema(Some(
+            ObjectBuilder::new()
+                .schema_type(utoipa::openapi::Type::String)
+                .build(),
+        ))
+        .example(Some(serde_json::json!("name,age")))
+        .build()
+}
+
+/// Documents the free-form filter query parameters and their grammar
+fn filter_parameter() -> Parameter {
+    ParameterBuilder::new()
+        .parameter_in(ParameterIn::Query)
+        .name("filter")
+        .description(Some(
+            "Any other query parameter filters on a field. Supported forms: \
+             `field=value`, `field=op.value` with op one of eq, ne, lt, gt, lte, gte, \
+             `field=type.alias` to match a BSON type such as string or number, \
+             `field=mod.(divisor,remainder)`, `field=all.(value,...)` for arrays \
+             containing every listed value, and logical groups \
+             `and=(field.op.value,...)` / `or=(field.value,...)` which may nest. \
+             Quote string values containing special characters, e.g. `name=\"a.b\"`.",
+        ))
+        .style(Some(ParameterStyle::Form))
+        .explode(Some(true))
+        .schema(Some(
+            ObjectBuilder::new()
+                .schema_type(utoipa::openapi::Type::Object)
+                .build(),
+        ))
+        .example(Some(serde_json::json!({
+            "age": "gte.18",
+            "or": "(status.active,score.gt.90)"
+        })))
+        .build()
+}
+
+/// The document's ETag, which only changes when the catalog does
+fn openapi_etag(catalog: &Catalog) -> String {
+    format!("\"{:016x}\"", catalog.fingerprint)
+}
+
+/// Endpoint to serve the dynamically generated OpenAPI document. `HEAD` returns just the
+/// ETag, so clients can check for changes without building or downloading the document.
+/// With tenancy it only documents the caller's tenant's collections, by their logical names.
+#[actix_web::route("/openapi.json", method = "GET", method = "HEAD")]
+pub async fn get_openapi_json(req: HttpRequest, data: web::Data<AppState>) -> impl Responder {
+    if let Some(mut catalog) = crate::catalog::get_cached_collections(&data) {
+        if data.config.tenant_header.is_some() {
+            match crate::routes::tenant_prefix(&req, &data) {
+                Ok(prefix) => catalog = Arc::new(catalog.for_tenant(&prefix, &data.config)),
+                Err(e) => return e.error_response(),
+            }
+        }
+        let etag = openapi_etag(&catalog);
+        if crate::caching::is_not_modified(&req, &etag) {
+            return HttpResponse::NotModified()
+                .insert_header((header::ETAG, etag))
+                .finish();
+        }
+        if req.method() == Method::HEAD {
+            return HttpResponse::Ok()
+                .insert_header((header::ETAG, etag))
+                .finish();
+        }
         let openapi = get_dynamic_openapi(&catalog);
-        HttpResponse::Ok().json(openapi)
+        HttpResponse::Ok()
+            .insert_header((header::ETAG, etag))
+            .json(openapi)
     } else {
         HttpResponse::InternalServerError().body("Could not access collections catalog")
     }
 }
 
-/// Ping the database
-#[utoipa::path(
-    get,
-    path = "/api/ping",
-    tag = "system",
-    responses(
-        (status = 200, description = "Database ping successful"),
-        (status = 500, description = "Database ping failed")
-    )
-)]
-async fn ping() {}
+/// What startup does about collection validators the generated docs cannot represent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SchemaCheck {
+    /// No check; such validators are only reported when the docs are generated
+    #[default]
+    Off,
+    /// Log every problem and start anyway
+    Warn,
+    /// Refuse to start
+    Fail,
+}
+
+impl SchemaCheck {
+    pub fn parse(value: &str) -> Option<SchemaCheck> {
+        match value {
+            "off" => Some(SchemaCheck::Off),
+            "warn" => Some(SchemaCheck::Warn),
+            "fail" => Some(SchemaCheck::Fail),
+            _ => None,
+        }
+    }
+}
+
+/// Every part of the catalog's validators that `bson_schema_to_openapi_schema` cannot convert,
+/// and so leaves out of the request bodies and examples of the generated docs
+pub fn schema_diagnostics(catalog: &Catalog) -> Vec<String> {
+    let mut problems = Vec::new();
+    for collection in &catalog.collection_specs {
+        let Some(validator) = collection.options.validator.as_ref() else {
+            continue;
+        };
+        let validator = validator.get_document("$jsonSchema").unwrap_or(validator);
+        let mut skipped = Vec::new();
+        if let Err(e) = convert_bson_schema(&collection.name, validator, &mut skipped) {
+            problems.push(e);
+        }
+        problems.extend(skipped);
+    }
+    problems
+}
+
+/// Runs the startup schema check, logging the problems found and, with
+/// [`SchemaCheck::Fail`], turning them into an error
+pub fn check_schemas(catalog: &Catalog, check: SchemaCheck) -> Result<(), String> {
+    if check == SchemaCheck::Off {
+        return Ok(());
+    }
+    let problems = schema_diagnostics(catalog);
+    for problem in &problems {
+        eprintln!("Warning: {}", problem);
+    }
+    match check {
+        SchemaCheck::Fail if !problems.is_empty() => Err(format!(
+            "{} collection validator problem(s) cannot be represented in the OpenAPI docs",
+            problems.len()
+        )),
+        _ => Ok(()),
+    }
+}
 
-// Helper function to convert a BSON schema for a single property
-// into an OpenAPI Schema object.
 // Helper function to convert a BSON schema for a single property or an entire document
-// into an OpenAPI Schema object. This function is largely the same as in the previous context.
+// into an OpenAPI Schema object. Sub-properties that cannot be converted are left out
+// with a warning.
 fn bson_schema_to_openapi_schema(
     schema_name_or_property: &str, // For context in error messages or logging
     bson_schema_doc: &Document,
+) -> Result<RefOr<Schema>, String> {
+    let mut skipped = Vec::new();
+    let schema = convert_bson_schema(schema_name_or_property, bson_schema_doc, &mut skipped);
+    for problem in skipped {
+        eprintln!("Warning: {}", problem);
+    }
+    schema
+}
+
+// Converts a BSON schema, collecting why any sub-property got skipped into `skipped`
+fn convert_bson_schema(
+    schema_name_or_property: &str,
+    bson_schema_doc: &Document,
+    skipped: &mut Vec<String>,
 ) -> Result<RefOr<Schema>, String> {
     // Get the BSON type (e.g., "string", "int", "object")
     let bson_type_str = bson_schema_doc.get_str("bsonType").map_err(|e| {
@@ -412,10 +666,10 @@ fn bson_schema_to_openapi_schema(
             }
             if let Ok(max) = bson_schema_doc.get_f64("maximum") {
                 schema_builder = schema_builder.maximum(Some(max));
-            } else if let Ok(max_dec_str) = bson_schema_doc.get_str("maximum") {
-                if let Ok(max_val) = max_dec_str.parse::<f64>() {
-                    schema_builder = schema_builder.maximum(Some(max_val));
-                }
+            } else if let Ok(max_dec_str) = bson_schema_doc.get_str("maximum")
+                && let Ok(max_val) = max_dec_str.parse::<f64>()
+            {
+                schema_builder = schema_builder.maximum(Some(max_val));
             }
         }
         "bool" => {
@@ -439,6 +693,7 @@ fn bson_schema_to_openapi_schema(
                 ));
         }
         "array" => {
+            schema_builder = schema_builder.schema_type(utoipa::openapi::Type::Array);
             // schema_builder = schema_builder.schema_type(utoipa::openapi::Type::Array);
             // if let Ok(items_schema_doc) = bson_schema_doc.get_document("items") {
             // Recursively convert the schema for array items
@@ -452,24 +707,30 @@ fn bson_schema_to_openapi_schema(
         }
         "object" => {
             schema_builder = schema_builder.schema_type(utoipa::openapi::Type::Object);
+            if let Ok(required) = bson_schema_doc.get_array("required") {
+                for field in required.iter().filter_map(Bson::as_str) {
+                    schema_builder = schema_builder.required(field);
+                }
+            }
             // Process nested properties for the object
             if let Ok(object_properties) = bson_schema_doc.get_document("properties") {
                 for (key, value_doc) in object_properties.iter() {
                     if let Bson::Document(prop_doc) = value_doc {
                         // Recursively convert schema for each property
-                        match bson_schema_to_openapi_schema(
+                        match convert_bson_schema(
                             &format!("{}.{}", schema_name_or_property, key),
                             prop_doc,
+                            skipped,
                         ) {
                             Ok(prop_schema) => {
                                 schema_builder = schema_builder.property(key, prop_schema);
                             }
                             Err(e) => {
-                                // Log error for sub-property conversion
-                                eprintln!(
-                                    "Warning: Skipping sub-property {} for object {}: {}",
+                                // Record the error for sub-property conversion
+                                skipped.push(format!(
+                                    "Skipping sub-property {} for object {}: {}",
                                     key, schema_name_or_property, e
-                                );
+                                ));
                             }
                         }
                     }
@@ -512,7 +773,10 @@ pub fn mongo_validator_to_openapi_request_body(
     validator_doc: &Document,
 ) -> Result<RequestBody, String> {
     // Convert the entire MongoDB validator document into an OpenAPI Schema.
-    // The validator_doc is treated as the root schema for the request body.
+    // Collection validators wrap the root schema in `$jsonSchema`.
+    let validator_doc = validator_doc
+        .get_document("$jsonSchema")
+        .unwrap_or(validator_doc);
     let openapi_schema = bson_schema_to_openapi_schema("RequestBodyRootSchema", validator_doc).ok();
 
     let content = ContentBuilder::new().schema(openapi_schema).build(); // Wrap the map in the Content struct.
@@ -532,3 +796,113 @@ pub fn mongo_validator_to_openapi_request_body(
 
     Ok(request_body_builder.build())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::tests::test_catalog;
+    use utoipa::openapi::HttpMethod;
+
+    #[test]
+    fn test_get_operation_documents_query_parameters() {
+        let openapi = get_dynamic_openapi(&test_catalog(&["items"]));
+
+        let get = openapi
+            .paths
+            .get_path_operation("/collections/items", HttpMethod::Get)
+            .expect("Expected a GET operation for the collection");
+        let names: Vec<&str> = get
+            .parameters
+            .iter()
+            .flatten()
+            .map(|parameter| parameter.name.as_str())
+            .collect();
+        assert!(names.contains(&"sort"));
+        assert!(names.contains(&"select"));
+        assert!(names.contains(&"filter"));
+    }
+
+    #[test]
+    fn test_get_operation_has_examples_with_and_without_select() {
+        let mut catalog = test_catalog(&["items"]);
+        catalog.collection_specs[0].options.validator = Some(mongodb::bson::doc! {
+            "$jsonSchema": {
+                "bsonType": "object",
+                "properties": {
+                    "name": { "bsonType": "string" },
+                    "price": { "bsonType": "double" },
+                },
+            },
+        });
+        let openapi = get_dynamic_openapi(&catalog);
+
+        let get = openapi
+            .paths
+            .get_path_operation("/collections/items", HttpMethod::Get)
+            .expect("Expected a GET operation for the collection");
+        assert!(get.description.as_ref().unwrap().contains("`select`"));
+        let ok = match get.responses.responses.get("200") {
+            Some(RefOr::T(response)) => response,
+            _ => panic!("Expected an inline 200 response"),
+        };
+        let examples = &ok.content["application/json"].examples;
+        let value = |name: &str| match examples.get(name) {
+            Some(RefOr::T(example)) => example.value.clone().unwrap(),
+            _ => panic!("Expected an inline {} example", name),
+        };
+        assert_eq!(
+            value("all_fields"),
+            serde_json::json!([{ "_id": "example_id_1", "name": "text", "price": 42 }])
+        );
+        assert_eq!(
+            value("selected_fields"),
+            serde_json::json!([{ "_id": "example_id_1", "name": "text" }])
+        );
+    }
+
+    #[test]
+    fn test_openapi_documents_real_ping_route() {
+        let openapi = get_dynamic_openapi(&test_catalog(&[]));
+
+        let ping = openapi
+            .paths
+            .get_path_operation("/api/ping", HttpMethod::Get)
+            .expect("Expected a GET /api/ping operation");
+        assert_eq!(ping.tags, Some(vec!["system".to_string()]));
+
+        let ok = match ping.responses.responses.get("200") {
+            Some(RefOr::T(response)) => response,
+            _ => panic!("Expected an inline 200 response"),
+        };
+        assert!(ok.content.contains_key("application/json"));
+        assert!(ok.content.contains_key("text/plain"));
+        assert!(ping.responses.responses.contains_key("500"));
+    }
+
+    #[test]
+    fn test_schema_diagnostics_report_unsupported_validators() {
+        let mut catalog = test_catalog(&["items", "files", "plain"]);
+        catalog.collection_specs[0].options.validator = Some(mongodb::bson::doc! {
+            "$jsonSchema": {
+                "bsonType": "object",
+                "properties": {
+                    "name": { "bsonType": "string" },
+                    "photo": { "bsonType": "binData" },
+                },
+            },
+        });
+        catalog.collection_specs[1].options.validator = Some(mongodb::bson::doc! {
+            "$jsonSchema": { "bsonType": "binData" },
+        });
+
+        let problems = schema_diagnostics(&catalog);
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].contains("sub-property photo for object items"));
+        assert!(problems[0].contains("'binData'"));
+        assert!(problems[1].contains("'files'"));
+
+        assert!(check_schemas(&catalog, SchemaCheck::Warn).is_ok());
+        assert!(check_schemas(&catalog, SchemaCheck::Fail).is_err());
+        assert!(check_schemas(&test_catalog(&["plain"]), SchemaCheck::Fail).is_ok());
+    }
+}
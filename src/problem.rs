@@ -0,0 +1,138 @@
+//! RFC 7807 `application/problem+json` error bodies.
+//!
+//! Handlers keep building plain-text errors; when `ERROR_FORMAT=problem` the middleware
+//! below rewrites every plain-text (or empty) 4xx/5xx response into a problem document,
+//! keeping the status and headers. Clients sending `Accept: text/plain` still get the text.
+
+use actix_web::{
+    Error, HttpResponse,
+    body::{self, BoxBody, EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::{StatusCode, header},
+    middleware::Next,
+    web,
+};
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::shared::AppState;
+
+pub const PROBLEM_CONTENT_TYPE: &str = "application/problem+json";
+
+/// How error responses are rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorFormat {
+    /// The handler's message as `text/plain`
+    #[default]
+    Plain,
+    /// An `application/problem+json` document
+    Problem,
+}
+
+impl ErrorFormat {
+    pub fn parse(value: &str) -> Option<ErrorFormat> {
+        match value {
+            "plain" => Some(ErrorFormat::Plain),
+            "problem" => Some(ErrorFormat::Problem),
+            _ => None,
+        }
+    }
+}
+
+/// The problem document for a status; `detail` is left out when there is nothing to add
+pub fn problem_details(status: StatusCode, detail: &str) -> Value {
+    let mut problem = json!({
+        "type": "about:blank",
+        "title": status.canonical_reason().unwrap_or("Error"),
+        "status": status.as_u16(),
+    });
+    if !detail.is_empty() {
+        problem["detail"] = Value::String(detail.to_string());
+    }
+    problem
+}
+
+/// Whether a response is an error whose body is plain text (or empty)
+fn is_plain_error(response: &HttpResponse<impl MessageBody>) -> bool {
+    if !(response.status().is_client_error() || response.status().is_server_error()) {
+        return false;
+    }
+    match response.headers().get(header::CONTENT_TYPE) {
+        Some(content_type) => content_type
+            .to_str()
+            .is_ok_and(|content_type| content_type.starts_with("text/plain")),
+        None => true,
+    }
+}
+
+/// Whether the client asked for plain-text errors
+fn accepts_plain_text(req: &ServiceRequest) -> bool {
+    req.headers()
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/plain"))
+}
+
+/// Middleware rewriting plain-text error responses as problem documents
+pub async fn problem_errors(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let enabled = req
+        .app_data::<web::Data<AppState>>()
+        .is_some_and(|data| data.config.error_format == ErrorFormat::Problem)
+        && !accepts_plain_text(&req);
+
+    let res = next.call(req).await?;
+    if !enabled || !is_plain_error(res.response()) {
+        return Ok(res.map_into_left_body());
+    }
+
+    let (req, response) = res.into_parts();
+    let (mut head, body) = response.into_parts();
+    let detail = body::to_bytes(body)
+        .await
+        .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+        .unwrap_or_default();
+    let problem = problem_details(head.status(), &detail);
+
+    head.headers_mut().insert(
+        header::CONTENT_TYPE,
+        header::HeaderValue::from_static(PROBLEM_CONTENT_TYPE),
+    );
+    let response = head.set_body(BoxBody::new(problem.to_string()));
+    Ok(ServiceResponse::new(req, response).map_into_right_body())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_problem_details() {
+        assert_eq!(
+            problem_details(StatusCode::NOT_FOUND, "Collection orders not found"),
+            json!({
+                "type": "about:blank",
+                "title": "Not Found",
+                "status": 404,
+                "detail": "Collection orders not found",
+            })
+        );
+        assert_eq!(
+            problem_details(StatusCode::METHOD_NOT_ALLOWED, ""),
+            json!({ "type": "about:blank", "title": "Method Not Allowed", "status": 405 })
+        );
+    }
+
+    #[test]
+    fn test_only_plain_errors_are_rewritten() {
+        assert!(is_plain_error(&HttpResponse::BadRequest().body("bad")));
+        assert!(is_plain_error(&HttpResponse::NotFound().finish()));
+        assert!(!is_plain_error(&HttpResponse::Ok().body("fine")));
+        assert!(!is_plain_error(
+            &HttpResponse::Conflict().json(json!({ "failedOperation": 0 }))
+        ));
+    }
+}
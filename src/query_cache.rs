@@ -0,0 +1,231 @@
+//! Opt-in LRU cache of serialized GET results, for dashboards polling the same queries.
+//!
+//! Entries are keyed by namespace (`database.collection`) and the request's query parameters
+//! and live for `QUERY_CACHE_TTL_MS`. Every write through the API invalidates its namespace;
+//! a per-namespace generation keeps a query that started before the write from caching its
+//! then stale result. Writes made outside the API are only picked up once entries expire.
+
+use actix_web::{HttpRequest, http::header, web};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A GET result as sent to the client
+#[derive(Debug)]
+pub struct CachedQuery {
+    pub content_type: &'static str,
+    pub body: String,
+    pub missing_ids: Option<String>,
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    result: Arc<CachedQuery>,
+    stored_at: Instant,
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    entries: HashMap<(String, String), Entry>,
+    generations: HashMap<String, u64>,
+    /// Bumped on every access, ordering entries by recency
+    clock: u64,
+}
+
+#[derive(Debug)]
+pub struct QueryCache {
+    capacity: usize,
+    ttl: Duration,
+    state: Mutex<CacheState>,
+}
+
+impl QueryCache {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        QueryCache {
+            capacity,
+            ttl,
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    /// The cached result for this query, unless missing or expired
+    pub fn get(&self, namespace: &str, key: &str, now: Instant) -> Option<Arc<CachedQuery>> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.clock += 1;
+        let clock = state.clock;
+        let entry_key = (namespace.to_string(), key.to_string());
+        match state.entries.get_mut(&entry_key) {
+            Some(entry) if now.saturating_duration_since(entry.stored_at) < self.ttl => {
+                entry.last_used = clock;
+                Some(Arc::clone(&entry.result))
+            }
+            Some(_) => {
+                state.entries.remove(&entry_key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// The namespace's generation, to pass to [`QueryCache::insert`] once the query ran
+    pub fn generation(&self, namespace: &str) -> u64 {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.generations.get(namespace).copied().unwrap_or(0)
+    }
+
+    /// Caches a result, evicting the least recently used entry when full. Results of
+    /// queries that raced with a write (the generation moved on) are not cached.
+    pub fn insert(
+        &self,
+        namespace: &str,
+        key: &str,
+        result: Arc<CachedQuery>,
+        generation: u64,
+        now: Instant,
+    ) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.generations.get(namespace).copied().unwrap_or(0) != generation {
+            return;
+        }
+        let entry_key = (namespace.to_string(), key.to_string());
+        if !state.entries.contains_key(&entry_key) && state.entries.len() >= self.capacity {
+            let ttl = self.ttl;
+            state
+                .entries
+                .retain(|_, entry| now.saturating_duration_since(entry.stored_at) < ttl);
+            if state.entries.len() >= self.capacity
+                && let Some(oldest) = state
+                    .entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.last_used)
+                    .map(|(key, _)| key.clone())
+            {
+                state.entries.remove(&oldest);
+            }
+        }
+        state.clock += 1;
+        let last_used = state.clock;
+        state.entries.insert(
+            entry_key,
+            Entry {
+                result,
+                stored_at: now,
+                last_used,
+            },
+        );
+    }
+
+    /// Drops every cached query of a namespace after a write to it
+    pub fn invalidate(&self, namespace: &str) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state
+            .entries
+            .retain(|(entry_namespace, _), _| entry_namespace != namespace);
+        *state.generations.entry(namespace.to_string()).or_insert(0) += 1;
+    }
+}
+
+/// The cache key of a request: its decoded query parameters, sorted by name while repeated
+/// ones keep their order, plus the `Accept` header, which picks the Extended JSON mode of
+/// the body. Serialized as JSON so no parameter value can pass for another parameter.
+pub fn cache_key(req: &HttpRequest) -> String {
+    let mut params = web::Query::<Vec<(String, String)>>::from_query(req.query_string())
+        .map(web::Query::into_inner)
+        .unwrap_or_default();
+    params.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let accept = req
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .unwrap_or_default();
+    serde_json::json!({ "accept": accept, "params": params }).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(body: &str) -> Arc<CachedQuery> {
+        Arc::new(CachedQuery {
+            content_type: "application/json",
+            body: body.to_string(),
+            missing_ids: None,
+            next_cursor: None,
+        })
+    }
+
+    #[test]
+    fn test_hits_expire_after_ttl() {
+        let cache = QueryCache::new(10, Duration::from_secs(5));
+        let now = Instant::now();
+        cache.insert("db.orders", "limit=1", result("[1]"), 0, now);
+
+        let hit = cache.get("db.orders", "limit=1", now + Duration::from_secs(4));
+        assert_eq!(hit.unwrap().body, "[1]");
+        assert!(cache.get("db.orders", "limit=2", now).is_none());
+        assert!(
+            cache
+                .get("db.orders", "limit=1", now + Duration::from_secs(5))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_least_recently_used_is_evicted() {
+        let cache = QueryCache::new(2, Duration::from_secs(60));
+        let now = Instant::now();
+        cache.insert("db.orders", "a", result("a"), 0, now);
+        cache.insert("db.orders", "b", result("b"), 0, now);
+        // Reading `a` makes `b` the least recently used
+        assert!(cache.get("db.orders", "a", now).is_some());
+        cache.insert("db.orders", "c", result("c"), 0, now);
+
+        assert!(cache.get("db.orders", "a", now).is_some());
+        assert!(cache.get("db.orders", "b", now).is_none());
+        assert!(cache.get("db.orders", "c", now).is_some());
+    }
+
+    #[test]
+    fn test_writes_invalidate_their_namespace() {
+        let cache = QueryCache::new(10, Duration::from_secs(60));
+        let now = Instant::now();
+        cache.insert("db.orders", "a", result("orders"), 0, now);
+        cache.insert("db.users", "a", result("users"), 0, now);
+
+        let generation = cache.generation("db.orders");
+        cache.invalidate("db.orders");
+        assert!(cache.get("db.orders", "a", now).is_none());
+        assert!(cache.get("db.users", "a", now).is_some());
+
+        // A query that started before the write does not cache its result
+        cache.insert("db.orders", "a", result("stale"), generation, now);
+        assert!(cache.get("db.orders", "a", now).is_none());
+        cache.insert(
+            "db.orders",
+            "a",
+            result("fresh"),
+            cache.generation("db.orders"),
+            now,
+        );
+        assert_eq!(cache.get("db.orders", "a", now).unwrap().body, "fresh");
+    }
+
+    #[test]
+    fn test_cache_key_tells_queries_apart() {
+        let key =
+            |uri: &str| cache_key(&actix_web::test::TestRequest::with_uri(uri).to_http_request());
+
+        assert_ne!(
+            key("/orders?status=a%26role%3Dadmin"),
+            key("/orders?status=a&role=admin")
+        );
+        assert_ne!(key("/orders?tag=a&tag=b"), key("/orders?tag=b"));
+        assert_ne!(key("/orders?sort=a&sort=b"), key("/orders?sort=b&sort=a"));
+        assert_eq!(
+            key("/orders?status=a&role=admin"),
+            key("/orders?role=admin&status=a")
+        );
+    }
+}
@@ -1,11 +1,148 @@
 use mongodb::{
     bson::{Bson, Document, bson, doc},
-    options::FindOptions,
+    options::{Collation, CollationStrength, FindOptions, Hint},
 };
+use serde::Deserialize;
 use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::field_case::{FieldCase, to_stored_fields, to_stored_path};
 
 type Number = f64;
 
+/// Query parameters that control the request rather than filter on a field
+pub const RESERVED_PARAMS: &[&str] = &[
+    "limit",
+    "skip",
+    "select",
+    "sort",
+    "timeoutMs",
+    "dryRun",
+    "after",
+    "ids",
+    "meta",
+    "flatten",
+    "mask",
+    "since",
+    "until",
+    "comment",
+    "upsert",
+    "confirm",
+    "collation",
+    "hint",
+    "require",
+    "multi",
+    "withCount",
+    "writeConcern",
+    "ordered",
+    "idsOnly",
+];
+
+/// Operators of the filter grammar, the names an operator allowlist may hold
+#[allow(dead_code)]
+pub const OPERATORS: &[&str] = &[
+    "eq", "ne", "lt", "gt", "lte", "gte", "type", "ilike", "and", "or", "mod", "all", "within",
+];
+
+/// Longest regex, or `ilike` pattern, a filter may match with, in characters
+pub const MAX_PATTERN_LEN: usize = 256;
+
+/// Most `%` wildcards an `ilike` pattern may have; each becomes a `.*` the server can
+/// backtrack over, so matching time grows with the power of their number
+pub const MAX_LIKE_WILDCARDS: usize = 4;
+
+/// Longest `comment` passed on to MongoDB, in characters
+pub const MAX_COMMENT_LEN: usize = 256;
+
+/// Type names accepted by the `type` operator, as understood by MongoDB's `$type`
+const BSON_TYPE_ALIASES: &[&str] = &[
+    "double",
+    "string",
+    "object",
+    "array",
+    "binData",
+    "undefined",
+    "objectId",
+    "bool",
+    "date",
+    "null",
+    "regex",
+    "dbPointer",
+    "javascript",
+    "symbol",
+    "javascriptWithScope",
+    "int",
+    "timestamp",
+    "long",
+    "decimal",
+    "minKey",
+    "maxKey",
+    "number",
+];
+
+/// How a filter parameter repeated in the query string, like `?tag=a&tag=b`, combines
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RepeatedParams {
+    /// Every condition has to hold: `tag=a&tag=b` matches arrays holding both
+    #[default]
+    All,
+    /// Any condition may hold: `tag=a&tag=b` is `{ "tag": { "$in": ["a", "b"] } }`, and
+    /// repeated operators become alternatives of an `$or`
+    Any,
+}
+
+#[allow(dead_code)]
+impl RepeatedParams {
+    pub fn parse(value: &str) -> Option<RepeatedParams> {
+        match value {
+            "all" => Some(RepeatedParams::All),
+            "any" => Some(RepeatedParams::Any),
+            _ => None,
+        }
+    }
+}
+
+/// What happens to a query parameter that is neither reserved nor a known field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnknownParams {
+    /// It filters on a field of that name, so a typo like `?limt=10` matches nothing
+    #[default]
+    Lenient,
+    /// It is rejected, where the fields of the collection are known
+    Strict,
+}
+
+#[allow(dead_code)]
+impl UnknownParams {
+    pub fn parse(value: &str) -> Option<UnknownParams> {
+        match value {
+            "lenient" => Some(UnknownParams::Lenient),
+            "strict" => Some(UnknownParams::Strict),
+            _ => None,
+        }
+    }
+}
+
+/// How a deployment restricts and combines filter parameters
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParseOptions<'a> {
+    /// Longest the filter parameters (keys and values) may be in total, in bytes
+    pub max_len: Option<usize>,
+    /// Most filter parameters one request may have, a repeated parameter counting each time
+    pub max_params: Option<usize>,
+    pub repeated: RepeatedParams,
+    /// Operators a filter may use, out of [`OPERATORS`]; plain `field=value` equality is
+    /// always allowed
+    pub allowed_operators: Option<&'a [String]>,
+    /// Top-level fields filter parameters may name, for [`UnknownParams::Strict`]; a dotted
+    /// parameter like `address.city` names its first segment, and `and`/`or` are always allowed
+    pub known_fields: Option<&'a [String]>,
+    /// How parameters name fields; they are matched against `known_fields` once stored
+    pub field_case: FieldCase,
+}
+
 #[derive(Debug, Clone)]
 pub enum Value {
     Str(String),
@@ -28,12 +165,18 @@ pub enum LexItem {
     ComparisonOperator(String), // 'eq', 'ne', 'lt', 'gt', 'lte', 'gte'
     SpecialChar(char),          // Specoal characters like `(` `)` `,` `.`
     ArrayOp(String),            // "and", "or"
+    ListOperator(String),       // "mod", "all"
+    GeoOperator(String),        // "within"
     Symbol(Value),              // Number, String
 }
 
 pub struct Lexer {
     input: Vec<char>,
     position: usize,
+    // How many parentheses are open, as a raw pattern ends at `,` or `)` inside them
+    depth: usize,
+    // Set after `ilike`, whose value is read raw as it may hold `%`, `_` and `.`
+    pattern_ahead: bool,
 }
 
 impl PartialEq for LexItem {
@@ -41,6 +184,8 @@ impl PartialEq for LexItem {
         match (self, other) {
             (LexItem::ComparisonOperator(a), LexItem::ComparisonOperator(b)) => a == b,
             (LexItem::SpecialChar(a), LexItem::SpecialChar(b)) => a == b,
+            (LexItem::ListOperator(a), LexItem::ListOperator(b)) => a == b,
+            (LexItem::GeoOperator(a), LexItem::GeoOperator(b)) => a == b,
             (LexItem::Symbol(a), LexItem::Symbol(b)) => a == b,
             _ => false,
         }
@@ -53,6 +198,8 @@ impl Lexer {
         Lexer {
             input: input.chars().collect(),
             position: 0,
+            depth: 0,
+            pattern_ahead: false,
         }
     }
 
@@ -74,7 +221,7 @@ impl Lexer {
         let mut result = String::new();
 
         while let Some(c) = self.peek() {
-            if c.is_alphanumeric() {
+            if is_symbol_char(c) {
                 result.push(self.next_char().unwrap());
             } else {
                 break;
@@ -84,6 +231,14 @@ impl Lexer {
         result
     }
 
+    /// Whether an ObjectId's 24 hex digits come next, which may start with a digit
+    fn is_object_id_ahead(&self) -> bool {
+        let rest = &self.input[self.position..];
+        rest.len() >= 24
+            && rest[..24].iter().all(char::is_ascii_hexdigit)
+            && !rest.get(24).is_some_and(|c| c.is_alphanumeric())
+    }
+
     fn read_string(&mut self) -> String {
         // Skip the opening quote
         self.next_char();
@@ -102,15 +257,30 @@ impl Lexer {
         result
     }
 
+    /// Reads an `ilike` pattern: the rest of the input, or up to the next unescaped `,` or
+    /// `)` inside parentheses. Backslash escapes are kept for [`like_to_regex`].
+    fn read_pattern(&mut self) -> String {
+        let mut result = String::new();
+        while let Some(c) = self.peek() {
+            if self.depth > 0 && (c == ',' || c == ')') {
+                break;
+            }
+            result.push(self.next_char().unwrap());
+            if c == '\\'
+                && let Some(escaped) = self.next_char()
+            {
+                result.push(escaped);
+            }
+        }
+        result
+    }
+
     pub fn read_number(&mut self) -> f64 {
         let mut result = String::new();
         let mut has_dot = false;
 
-        match self.peek() {
-            Some(c) if c == '-' => {
-                result.push(self.next_char().unwrap());
-            }
-            _ => {}
+        if let Some('-') = self.peek() {
+            result.push(self.next_char().unwrap());
         }
 
         while let Some(c) = self.peek() {
@@ -129,35 +299,74 @@ impl Lexer {
         result.parse().unwrap_or(0.0)
     }
 
-    fn next_token(&mut self) -> Option<LexItem> {
-        self.peek().map(|c| {
-            match c {
-                '(' | ')' | ',' | '.' | '=' => LexItem::SpecialChar(self.next_char().unwrap()),
-                '"' => LexItem::Symbol(Value::Str(self.read_string())),
-                '0'..='9' | '-' => LexItem::Symbol(Value::Num(self.read_number())),
-                ' ' => LexItem::SpecialChar(self.next_char().unwrap()),
-                _ => {
-                    let ident = self.read_symbol();
-                    match ident.as_str() {
-                        // You would add other operators here
-                        "eq" | "lt" | "gt" | "lte" | "gte" => LexItem::ComparisonOperator(ident),
-                        "and" | "or" => LexItem::ArrayOp(ident),
-                        _ => LexItem::Symbol(Value::Str(ident)),
+    /// The next token, or an error on a character no token can start with
+    fn next_token(&mut self) -> Option<Result<LexItem, String>> {
+        let c = self.peek()?;
+        if self.pattern_ahead && c != '.' {
+            self.pattern_ahead = false;
+            return Some(Ok(LexItem::Symbol(Value::Str(self.read_pattern()))));
+        }
+        let token = match c {
+            '(' => {
+                self.depth += 1;
+                LexItem::SpecialChar(self.next_char().unwrap())
+            }
+            ')' => {
+                self.depth = self.depth.saturating_sub(1);
+                LexItem::SpecialChar(self.next_char().unwrap())
+            }
+            ',' | '.' | '=' => LexItem::SpecialChar(self.next_char().unwrap()),
+            '"' => LexItem::Symbol(Value::Str(self.read_string())),
+            '0'..='9' if self.is_object_id_ahead() => {
+                LexItem::Symbol(Value::Str(self.read_symbol()))
+            }
+            '0'..='9' | '-' => LexItem::Symbol(Value::Num(self.read_number())),
+            ' ' => LexItem::SpecialChar(self.next_char().unwrap()),
+            _ => {
+                let ident = self.read_symbol();
+                match ident.as_str() {
+                    "" => {
+                        return Some(Err(format!(
+                            "Unexpected character {:?} at position {}",
+                            c, self.position
+                        )));
+                    }
+                    // You would add other operators here
+                    "eq" | "ne" | "lt" | "gt" | "lte" | "gte" | "type" => {
+                        LexItem::ComparisonOperator(ident)
+                    }
+                    "ilike" => {
+                        self.pattern_ahead = self.peek() == Some('.');
+                        LexItem::ComparisonOperator(ident)
                     }
+                    "and" | "or" => LexItem::ArrayOp(ident),
+                    "mod" | "all" => LexItem::ListOperator(ident),
+                    "within" => LexItem::GeoOperator(ident),
+                    _ => LexItem::Symbol(Value::Str(ident)),
                 }
             }
-        })
+        };
+        Some(Ok(token))
     }
 
-    pub fn tokenize(&mut self) -> Vec<LexItem> {
+    /// Splits the input into tokens. A character no token can consume is an error, and every
+    /// other token consumes at least one character, bar an empty `ilike` pattern which clears
+    /// `pattern_ahead`, so tokenizing always ends.
+    pub fn tokenize(&mut self) -> Result<Vec<LexItem>, String> {
         let mut tokens = Vec::new();
         while let Some(token) = self.next_token() {
-            tokens.push(token);
+            tokens.push(token?);
         }
-        tokens
+        Ok(tokens)
     }
 }
 
+/// Characters of field names and unquoted values, besides the grammar's own punctuation.
+/// `$` is left out so a field cannot name an operator.
+fn is_symbol_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '@' | ':' | '+')
+}
+
 pub struct Parser {
     tokens: Vec<LexItem>,
     position: usize,
@@ -195,14 +404,44 @@ impl Parser {
     fn comparison_op_to_bson_key(operator: &str) -> Result<String, String> {
         match operator {
             "eq" => Ok("$eq".to_string()),
+            "ne" => Ok("$ne".to_string()),
             "lt" => Ok("$lt".to_string()),
             "gt" => Ok("$gt".to_string()),
             "lte" => Ok("$lte".to_string()),
             "gte" => Ok("$gte".to_string()),
+            "type" => Ok("$type".to_string()),
+            "ilike" => Ok("$regex".to_string()),
             _ => Err(format!("Unknown operator: {}", operator)),
         }
     }
 
+    fn comparison_to_bson(operator: &str, value: Value) -> Result<Bson, String> {
+        let bson_key = Parser::comparison_op_to_bson_key(operator)?;
+        let bson_value = match value {
+            Value::Str(s) => Bson::String(s),
+            Value::Num(n) => Bson::Double(n),
+        };
+        if operator == "ilike"
+            && let Bson::String(pattern) = &bson_value
+        {
+            check_like_pattern(pattern)?;
+            return Ok(ilike_condition(pattern));
+        }
+        if operator == "type" {
+            match &bson_value {
+                Bson::String(alias) if BSON_TYPE_ALIASES.contains(&alias.as_str()) => {}
+                other => {
+                    return Err(format!(
+                        "Unknown BSON type: {}, expected one of {}",
+                        other,
+                        BSON_TYPE_ALIASES.join(", ")
+                    ));
+                }
+            }
+        }
+        Ok(operator_condition(&bson_key, bson_value))
+    }
+
     fn logical_op_to_bson_key(operator: &str) -> Result<String, String> {
         match operator {
             "and" => Ok("$and".to_string()),
@@ -211,6 +450,78 @@ impl Parser {
         }
     }
 
+    fn list_op_to_bson(&self, operator: &str, values: Vec<Value>) -> Result<Bson, String> {
+        match operator {
+            // `$mod` takes exactly [divisor, remainder], both integers
+            "mod" => match values.as_slice() {
+                [Value::Num(divisor), Value::Num(remainder)]
+                    if divisor.fract() == 0.0 && remainder.fract() == 0.0 =>
+                {
+                    Ok(bson!({ "$mod": [*divisor as i64, *remainder as i64] }))
+                }
+                _ => Err(self.return_error_msg()),
+            },
+            "all" => {
+                let values: Vec<Bson> = values
+                    .into_iter()
+                    .map(|value| match value {
+                        Value::Str(s) => Bson::String(s),
+                        Value::Num(n) => Bson::Double(n),
+                    })
+                    .collect();
+                Ok(operator_condition("$all", values.into()))
+            }
+            _ => Err(format!("Unknown list operator: {}", operator)),
+        }
+    }
+
+    /// `$geoWithin` for a shape given as a flat list of `lng,lat` coordinates
+    fn geo_op_to_bson(&self, shape: &str, values: Vec<Value>) -> Result<Bson, String> {
+        let coordinates = values
+            .into_iter()
+            .map(|value| match value {
+                Value::Num(n) => Ok(n),
+                Value::Str(_) => Err(self.return_error_msg()),
+            })
+            .collect::<Result<Vec<f64>, String>>()?;
+        let points: Vec<Bson> = coordinates
+            .chunks(2)
+            .map(|point| bson!(point.to_vec()))
+            .collect();
+        match shape {
+            // Bottom-left and top-right corners
+            "box" if coordinates.len() == 4 => Ok(bson!({ "$geoWithin": { "$box": points } })),
+            // At least three vertices; the server closes the ring
+            "polygon" if coordinates.len() >= 6 && coordinates.len() % 2 == 0 => {
+                Ok(bson!({ "$geoWithin": { "$polygon": points } }))
+            }
+            "box" | "polygon" => Err(format!(
+                "Invalid number of coordinates for {}: {}",
+                shape,
+                coordinates.len()
+            )),
+            _ => Err(format!("Unknown geo shape: {}", shape)),
+        }
+    }
+
+    fn parse_value_list(&mut self) -> Result<Vec<Value>, String> {
+        if self.advance() != Some(LexItem::SpecialChar('(')) {
+            return Err(self.return_error_msg());
+        }
+        let mut values = Vec::new();
+        loop {
+            match self.advance() {
+                Some(LexItem::Symbol(value)) => values.push(value),
+                _ => return Err(self.return_error_msg()),
+            }
+            match self.advance() {
+                Some(LexItem::SpecialChar(',')) => continue,
+                Some(LexItem::SpecialChar(')')) => return Ok(values),
+                _ => return Err(self.return_error_msg()),
+            }
+        }
+    }
+
     fn parse_inner_filter(&mut self) -> Result<Bson, String> {
         let first_token = self.advance();
         let second_token = self.advance();
@@ -219,14 +530,10 @@ impl Parser {
                 match self.advance() {
                     // Case Field.ComparisonOp.Value
                     Some(LexItem::ComparisonOperator(operator)) => {
-                        let bson_key = Parser::comparison_op_to_bson_key(operator.as_str())?;
                         match (self.advance(), self.advance()) {
                             (Some(LexItem::SpecialChar('.')), Some(LexItem::Symbol(value))) => {
-                                let bson_value = match value {
-                                    Value::Str(s) => Bson::String(s),
-                                    Value::Num(n) => Bson::Double(n),
-                                };
-                                Ok(bson!({ field_name: { bson_key: bson_value }}))
+                                let comparison = Parser::comparison_to_bson(&operator, value)?;
+                                Ok(bson!({ field_name: comparison }))
                             }
                             _ => Err(self.return_error_msg()),
                         }
@@ -275,7 +582,7 @@ impl Parser {
             }
             return Ok(bson!(filters));
         }
-        return Err(self.return_error_msg());
+        Err(self.return_error_msg())
     }
 
     fn parse_top_level_expr(&mut self, key: &str) -> Result<Bson, String> {
@@ -294,7 +601,7 @@ impl Parser {
                         Value::Str(s) => Bson::String(s.clone()),
                         Value::Num(n) => Bson::Double(n),
                     };
-                    return Ok(bson!({key: bson_value}));
+                    Ok(bson!({key: bson_value}))
                 }
                 // Case TopLevelExpr -> Field=ComparisonOp.Value
                 Some(LexItem::ComparisonOperator(_)) => {
@@ -304,12 +611,38 @@ impl Parser {
                             Some(LexItem::SpecialChar('.')),
                             Some(LexItem::Symbol(value)),
                         ) => {
-                            let mql_comparison_op = Parser::comparison_op_to_bson_key(op.as_str())?;
-                            let bson_value = match value {
-                                Value::Str(s) => Bson::String(s.clone()),
-                                Value::Num(n) => Bson::Double(n),
-                            };
-                            Ok(bson!({ key: {mql_comparison_op: bson_value} }))
+                            let comparison = Parser::comparison_to_bson(&op, value)?;
+                            Ok(bson!({ key: comparison }))
+                        }
+                        _ => Err(self.return_error_msg()),
+                    }
+                }
+                // Case TopLevelExpr -> Field=ListOp.(Values)
+                Some(LexItem::ListOperator(_)) => match (self.advance(), self.advance()) {
+                    (Some(LexItem::ListOperator(op)), Some(LexItem::SpecialChar('.'))) => {
+                        let values = self.parse_value_list()?;
+                        let bson_value = self.list_op_to_bson(op.as_str(), values)?;
+                        Ok(bson!({ key: bson_value }))
+                    }
+                    _ => Err(self.return_error_msg()),
+                },
+                // Case TopLevelExpr -> Field=GeoOp.Shape.(Coordinates)
+                Some(LexItem::GeoOperator(_)) => {
+                    match (
+                        self.advance(),
+                        self.advance(),
+                        self.advance(),
+                        self.advance(),
+                    ) {
+                        (
+                            Some(LexItem::GeoOperator(_)),
+                            Some(LexItem::SpecialChar('.')),
+                            Some(LexItem::Symbol(Value::Str(shape))),
+                            Some(LexItem::SpecialChar('.')),
+                        ) => {
+                            let values = self.parse_value_list()?;
+                            let bson_value = self.geo_op_to_bson(&shape, values)?;
+                            Ok(bson!({ key: bson_value }))
                         }
                         _ => Err(self.return_error_msg()),
                     }
@@ -328,13 +661,46 @@ impl Parser {
     }
 }
 
+#[allow(dead_code)]
 pub fn parse(key: &str, value: &str) -> Result<Bson, String> {
+    parse_allowing(key, value, None)
+}
+
+/// Like [`parse`], rejecting operators missing from `allowed_operators` before parsing
+fn parse_allowing(
+    key: &str,
+    value: &str,
+    allowed_operators: Option<&[String]>,
+) -> Result<Bson, String> {
     let mut lexer = Lexer::new(value);
-    let tokens = lexer.tokenize();
+    let tokens = lexer.tokenize()?;
+    if let Some(allowed) = allowed_operators {
+        check_operators(key, &tokens, allowed)?;
+    }
     let mut parser = Parser::new(tokens);
     parser.parse(key)
 }
 
+/// Fails on the first operator of a parameter, its `and`/`or` key included, not in `allowed`
+fn check_operators(key: &str, tokens: &[LexItem], allowed: &[String]) -> Result<(), String> {
+    let top_level = ["and", "or"].contains(&key).then_some(key);
+    let operators = tokens.iter().filter_map(|token| match token {
+        LexItem::ComparisonOperator(op)
+        | LexItem::ArrayOp(op)
+        | LexItem::ListOperator(op)
+        | LexItem::GeoOperator(op) => Some(op.as_str()),
+        LexItem::SpecialChar(_) | LexItem::Symbol(_) => None,
+    });
+    match top_level
+        .into_iter()
+        .chain(operators)
+        .find(|op| !allowed.iter().any(|allowed| allowed == op))
+    {
+        Some(op) => Err(format!("Operator {} is not allowed", op)),
+        None => Ok(()),
+    }
+}
+
 /// Parses query parameters from a URL query string into a MongoDB filter document.
 ///
 /// This function supports two modes of operation:
@@ -354,140 +720,1656 @@ pub fn parse(key: &str, value: &str) -> Result<Bson, String> {
 pub fn parse_match_query_params(
     query_params: &HashMap<String, String>,
 ) -> Result<Document, String> {
-    let mut filter = doc! {};
-
-    for (query_param, field_value) in query_params.iter() {
-        // Skip "reserved" key words.
-        match query_param.as_str() {
-            "limit" | "skip" => continue,
-            field_name => match parse(field_name, field_value) {
-                Ok(Bson::Document(doc)) => filter.extend(doc),
-                Ok(val) => return Err(format!("Unexpected bson: {}", val)),
-                Err(err) => return Err(err),
-            },
+    parse_match_query_params_bounded(query_params, None)
+}
+
+/// Like [`parse_match_query_params`], but rejects the query without lexing any of it when
+/// its filter parameters (keys and values) are longer than `max_len` bytes in total.
+#[allow(dead_code)]
+pub fn parse_match_query_params_bounded(
+    query_params: &HashMap<String, String>,
+    max_len: Option<usize>,
+) -> Result<Document, String> {
+    parse_match_query_params_with(
+        query_params,
+        ParseOptions {
+            max_len,
+            ..ParseOptions::default()
+        },
+    )
+}
+
+/// Like [`parse_match_query_params`], with the limits and semantics of `options`
+pub fn parse_match_query_params_with(
+    query_params: &HashMap<String, String>,
+    options: ParseOptions,
+) -> Result<Document, String> {
+    parse_match_query_pairs_with(
+        query_params
+            .iter()
+            .map(|(query_param, field_value)| (query_param.as_str(), field_value.as_str())),
+        options,
+    )
+}
+
+/// Like [`parse_match_query_params_bounded`], for query strings that may repeat a parameter.
+/// Every condition on a field applies, so `_id=gte.100&_id=lt.200` becomes the range
+/// `{ "_id": { "$gte": 100, "$lt": 200 } }`.
+#[allow(dead_code)]
+pub fn parse_match_query_pairs<'a>(
+    query_params: impl IntoIterator<Item = (&'a str, &'a str)>,
+    max_len: Option<usize>,
+) -> Result<Document, String> {
+    parse_match_query_pairs_with(
+        query_params,
+        ParseOptions {
+            max_len,
+            ..ParseOptions::default()
+        },
+    )
+}
+
+/// Like [`parse_match_query_pairs`], with the limits and semantics of `options`: how the
+/// conditions of a repeated parameter combine and which operators they may use.
+pub fn parse_match_query_pairs_with<'a>(
+    query_params: impl IntoIterator<Item = (&'a str, &'a str)>,
+    options: ParseOptions,
+) -> Result<Document, String> {
+    // Skip "reserved" key words.
+    let query_params: Vec<_> = query_params
+        .into_iter()
+        .filter(|(query_param, _)| !RESERVED_PARAMS.contains(query_param))
+        .collect();
+    if let Some(max_params) = options.max_params
+        && query_params.len() > max_params
+    {
+        return Err(format!(
+            "Query has {} filter parameters, the limit is {}",
+            query_params.len(),
+            max_params
+        ));
+    }
+    if let Some(known_fields) = options.known_fields
+        && let Some((query_param, _)) = query_params.iter().find(|(query_param, _)| {
+            !is_known_param(
+                &to_stored_path(query_param, options.field_case),
+                known_fields,
+            )
+        })
+    {
+        return Err(format!(
+            "{} is neither a reserved parameter nor a known field",
+            query_param
+        ));
+    }
+    if let Some(max_len) = options.max_len {
+        let len: usize = query_params
+            .iter()
+            .map(|(query_param, field_value)| query_param.len() + field_value.len())
+            .sum();
+        if len > max_len {
+            return Err(format!(
+                "Query is {} bytes long, the limit is {}",
+                len, max_len
+            ));
+        }
+    }
+
+    // Parameters keep the order they first appear in, with their values grouped for `Any`
+    let mut groups: Vec<(&str, Vec<Document>)> = Vec::new();
+    for (query_param, field_value) in query_params {
+        let condition = match parse_allowing(query_param, field_value, options.allowed_operators)? {
+            Bson::Document(doc) => doc,
+            val => return Err(format!("Unexpected bson: {}", val)),
+        };
+        match groups.iter_mut().find(|(key, _)| *key == query_param) {
+            Some((_, conditions)) if options.repeated == RepeatedParams::Any => {
+                conditions.push(condition)
+            }
+            _ => groups.push((query_param, vec![condition])),
+        }
+    }
+
+    let mut filter = FilterBuilder::new();
+    for (_, mut conditions) in groups {
+        let condition = match conditions.len() {
+            1 => conditions.remove(0),
+            _ => any_of(conditions),
+        };
+        for (field, condition) in condition {
+            filter = filter.condition(field, condition);
         }
     }
 
+    let filter = to_stored_fields(filter.build(), options.field_case);
+    // `and`/`or` groups name their fields inside the value, so the parsed filter is checked too
+    if let Some(known_fields) = options.known_fields
+        && let Some(field) = unknown_filter_field(&filter, known_fields)
+    {
+        return Err(format!(
+            "{} is neither a reserved parameter nor a known field",
+            field
+        ));
+    }
     Ok(filter)
 }
 
-pub fn parse_find_options(query_params: &HashMap<String, String>) -> FindOptions {
-    let limit_value = match query_params.get("limit") {
-        Some(limit) => limit.parse::<i64>().unwrap_or(100),
-        None => 100,
-    };
-    let skip_value = match query_params.get("skip") {
-        Some(skip) => skip.parse::<u64>().unwrap_or(0),
-        None => 0,
-    };
-    FindOptions::builder()
-        .limit(limit_value)
-        .skip(skip_value)
-        .build()
+/// Whether a filter parameter is `and`/`or` or names one of `known_fields`
+fn is_known_param(query_param: &str, known_fields: &[String]) -> bool {
+    ["and", "or"].contains(&query_param) || is_known_field(query_param, known_fields)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use mongodb::bson::doc;
+/// Whether a (dotted) field path starts with one of `known_fields`
+fn is_known_field(field: &str, known_fields: &[String]) -> bool {
+    let field = field.split('.').next().unwrap_or(field);
+    known_fields.iter().any(|known| known == field)
+}
 
-    #[test]
-    fn test_parse_query_params_empty() {
-        let query_params = HashMap::new();
-        let result = parse_match_query_params(&query_params);
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), doc! {});
+/// The first field of a filter, looking inside `$and`/`$or` groups, that is not known
+fn unknown_filter_field(filter: &Document, known_fields: &[String]) -> Option<String> {
+    filter.iter().find_map(|(key, value)| match value {
+        Bson::Array(groups) if key.starts_with('$') => {
+            groups.iter().find_map(|group| match group {
+                Bson::Document(group) => unknown_filter_field(group, known_fields),
+                _ => None,
+            })
+        }
+        _ if key.starts_with('$') || is_known_field(key, known_fields) => None,
+        _ => Some(key.clone()),
+    })
+}
+
+/// Combines the conditions of a repeated parameter as alternatives: plain values on one field
+/// into `$in`, anything else into `$or`
+fn any_of(conditions: Vec<Document>) -> Document {
+    let values: Option<Vec<(String, Bson)>> = conditions
+        .iter()
+        .map(|condition| match condition.iter().next() {
+            Some((field, value)) if condition.len() == 1 && !field.starts_with('$') => {
+                match value {
+                    Bson::Document(_) => None,
+                    value => Some((field.clone(), value.clone())),
+                }
+            }
+            _ => None,
+        })
+        .collect();
+    match values {
+        Some(values) => {
+            let field = values[0].0.clone();
+            let values: Vec<Bson> = values.into_iter().map(|(_, value)| value).collect();
+            doc! { field: { "$in": values } }
+        }
+        None => doc! { "$or": conditions },
     }
+}
 
-    #[test]
-    fn test_parse_query_params_single_string() {
-        let mut query_params = HashMap::new();
-        query_params.insert("title".to_owned(), "\"The Perils of Pauline\"".to_owned());
-        let result = parse_match_query_params(&query_params);
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), doc! {"title": "The Perils of Pauline"});
+/// Builds filter documents in code, producing the same BSON as the query grammar, so
+/// `FilterBuilder::new().eq("age", 30.0).gt("score", 80.0)` is `age=30&score=gt.80`.
+/// Conditions combine like repeated query parameters, and hex `_id`s become ObjectIds.
+#[derive(Debug, Clone, Default)]
+pub struct FilterBuilder {
+    filter: Document,
+}
+
+#[allow(dead_code)]
+impl FilterBuilder {
+    pub fn new() -> Self {
+        FilterBuilder::default()
     }
 
-    #[test]
-    fn test_parse_query_params_single_or() {
-        let mut query_params = HashMap::new();
-        query_params.insert(
-            "or".to_owned(),
-            "(title.\"The Perils of Pauline\",title.\"It\")".to_owned(),
-        );
-        let result = parse_match_query_params(&query_params);
-        assert!(result.is_ok());
-        assert_eq!(
-            result.unwrap(),
-            doc! {"$or": [{"title": "The Perils of Pauline"}, {"title": "It"}]}
-        );
+    /// Adds a condition (a value, or a document of operators) that `field` has to meet
+    pub fn condition(mut self, field: impl Into<String>, condition: impl Into<Bson>) -> Self {
+        self.filter = add_condition(self.filter, field.into(), condition.into());
+        self
     }
 
-    #[test]
-    fn test_parse_query_params_string_value() {
-        let mut query_params = HashMap::new();
-        query_params.insert("name".to_string(), "john".to_string());
+    /// `field=value`
+    pub fn eq(self, field: impl Into<String>, value: impl Into<Bson>) -> Self {
+        self.condition(field, value)
+    }
 
-        let result = parse_match_query_params(&query_params);
-        assert!(result.is_ok());
+    /// `field=ne.value`
+    pub fn ne(self, field: impl Into<String>, value: impl Into<Bson>) -> Self {
+        self.condition(field, operator_condition("$ne", value.into()))
+    }
 
-        let filter = result.unwrap();
-        assert_eq!(filter.get_str("name").unwrap(), "john");
+    /// `field=lt.value`
+    pub fn lt(self, field: impl Into<String>, value: impl Into<Bson>) -> Self {
+        self.condition(field, operator_condition("$lt", value.into()))
     }
 
-    #[test]
-    fn test_parse_query_params_numeric_value() {
-        let mut query_params = HashMap::new();
-        query_params.insert("age".to_string(), "30".to_string());
+    /// `field=lte.value`
+    pub fn lte(self, field: impl Into<String>, value: impl Into<Bson>) -> Self {
+        self.condition(field, operator_condition("$lte", value.into()))
+    }
 
-        let result = parse_match_query_params(&query_params);
-        assert!(result.is_ok());
+    /// `field=gt.value`
+    pub fn gt(self, field: impl Into<String>, value: impl Into<Bson>) -> Self {
+        self.condition(field, operator_condition("$gt", value.into()))
+    }
 
-        let filter = result.unwrap();
-        assert_eq!(filter, doc! {"age": 30.0});
+    /// `field=gte.value`
+    pub fn gte(self, field: impl Into<String>, value: impl Into<Bson>) -> Self {
+        self.condition(field, operator_condition("$gte", value.into()))
     }
 
-    #[test]
-    fn test_parse_query_params_multiple_fields() {
-        let mut query_params = HashMap::new();
-        query_params.insert("name".to_string(), "john".to_string());
-        query_params.insert("age".to_string(), "30".to_string());
+    /// `field=ilike.pattern`
+    pub fn ilike(self, field: impl Into<String>, pattern: &str) -> Self {
+        self.condition(field, ilike_condition(pattern))
+    }
 
-        let result = parse_match_query_params(&query_params);
-        assert!(
-            result.is_ok(),
-            "Failed to parse query params: {:?}",
-            result.err()
-        );
+    /// `field=all.(values)`
+    pub fn all(self, field: impl Into<String>, values: Vec<Bson>) -> Self {
+        self.condition(field, operator_condition("$all", values.into()))
+    }
 
-        let filter = result.unwrap();
-        assert_eq!(filter, doc! {"name": "john", "age": 30.0});
+    /// `or=(...)`, matching documents that meet any of the filters
+    pub fn or(self, filters: Vec<Document>) -> Self {
+        self.condition("$or", filters)
     }
 
-    #[test]
-    fn test_parse_query_params_advanced_comparison() {
-        let mut query_params = HashMap::new();
-        query_params.insert("age".to_string(), "gt.25".to_string());
+    /// `and=(...)`, matching documents that meet all of the filters
+    pub fn and(self, filters: Vec<Document>) -> Self {
+        self.condition("$and", filters)
+    }
 
-        let result = parse_match_query_params(&query_params);
-        assert!(result.is_ok());
+    pub fn build(self) -> Document {
+        self.filter
+    }
+}
 
-        let filter = result.unwrap();
-        assert!(filter.contains_key("age"));
-        let age_doc = filter.get_document("age").unwrap();
-        assert!(age_doc.contains_key("$gt"));
-        assert_eq!(age_doc.get_f64("$gt").unwrap(), 25.0);
+/// A case-insensitive `$regex` matching the whole value against a SQL `LIKE` pattern
+fn ilike_condition(pattern: &str) -> Bson {
+    bson!({ "$regex": like_to_regex(pattern), "$options": "i" })
+}
+
+/// Translates a SQL `LIKE` pattern into an anchored regex: `%` matches any run of characters,
+/// `_` any single one, and a backslash makes the next character literal (`50\%` is "50%").
+/// Everything else matches itself, so regex metacharacters are escaped.
+fn like_to_regex(pattern: &str) -> String {
+    let mut regex = String::from("^");
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            // A run of `%` matches what one does, without stacking `.*`s to backtrack over
+            '%' if regex.ends_with(".*") => {}
+            '%' => regex.push_str(".*"),
+            '_' => regex.push('.'),
+            '\\' => match chars.next() {
+                Some(escaped) => push_literal(&mut regex, escaped),
+                None => push_literal(&mut regex, '\\'),
+            },
+            c => push_literal(&mut regex, c),
+        }
     }
+    regex.push('$');
+    regex
+}
 
-    #[test]
-    fn test_parse_query_params_advanced_logical() {
-        let mut query_params = HashMap::new();
-        query_params.insert("or".to_string(), "(age.gt.25,name.eq.john)".to_string());
+/// Rejects `ilike` patterns that are too long or have more than [`MAX_LIKE_WILDCARDS`]
+/// wildcards; a run of `%` counts once, as it matches what a single one does
+fn check_like_pattern(pattern: &str) -> Result<(), String> {
+    check_pattern_len(pattern)?;
+    let mut wildcards = 0;
+    let mut previous = None;
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '%' if previous != Some('%') => wildcards += 1,
+            _ => {}
+        }
+        previous = Some(c);
+    }
+    if wildcards > MAX_LIKE_WILDCARDS {
+        return Err(format!(
+            "Pattern {} has {} wildcards, the limit is {}",
+            pattern, wildcards, MAX_LIKE_WILDCARDS
+        ));
+    }
+    Ok(())
+}
 
-        let result = parse_match_query_params(&query_params);
-        assert!(result.is_ok());
+fn check_pattern_len(pattern: &str) -> Result<(), String> {
+    let len = pattern.chars().count();
+    if len > MAX_PATTERN_LEN {
+        return Err(format!(
+            "Pattern is {} characters long, the limit is {}",
+            len, MAX_PATTERN_LEN
+        ));
+    }
+    Ok(())
+}
 
-        let filter = result.unwrap();
-        assert!(filter.contains_key("or") || filter.contains_key("$or"));
+/// Refuses regexes prone to catastrophic backtracking before they reach the server: patterns
+/// longer than [`MAX_PATTERN_LEN`] and repeated groups that repeat something themselves, like
+/// `(a+)+` or `(\w*){2,}`.
+pub fn check_regex(pattern: &str) -> Result<(), String> {
+    check_pattern_len(pattern)?;
+    let chars: Vec<char> = pattern.chars().collect();
+    let is_repeat = |i: usize| match chars.get(i) {
+        Some('*' | '+') => true,
+        Some('{') => chars.get(i + 1).is_some_and(char::is_ascii_digit),
+        _ => false,
+    };
+    // Whether each open group, and the pattern itself at the bottom, repeats anything yet
+    let mut groups = vec![false];
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            // A class is a single atom, whatever it holds; `]` may come first as a literal
+            '[' => {
+                i += 1;
+                if chars.get(i) == Some(&'^') {
+                    i += 1;
+                }
+                if chars.get(i) == Some(&']') {
+                    i += 1;
+                }
+                while i < chars.len() && chars[i] != ']' {
+                    if chars[i] == '\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+            }
+            '(' => groups.push(false),
+            ')' if groups.len() > 1 => {
+                let repeats_inside = groups.pop().unwrap_or(false);
+                let repeated = is_repeat(i + 1);
+                if repeats_inside && repeated {
+                    return Err(format!(
+                        "Pattern {} nests quantifiers, which can take exponential time to match",
+                        pattern
+                    ));
+                }
+                if let Some(outer) = groups.last_mut() {
+                    *outer |= repeats_inside || repeated;
+                }
+            }
+            _ if is_repeat(i) => {
+                if let Some(group) = groups.last_mut() {
+                    *group = true;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    Ok(())
+}
+
+fn push_literal(regex: &mut String, c: char) {
+    if "\\^$.|?*+()[]{}".contains(c) {
+        regex.push('\\');
+    }
+    regex.push(c);
+}
+
+/// A single-operator condition like `{ "$gt": 80 }`, as the grammar and the builder write it
+fn operator_condition(operator: &str, value: Bson) -> Bson {
+    bson!({ operator: value })
+}
+
+/// Adds a condition on `field` to the filter. Operators are merged into an earlier condition
+/// on the same field when they do not overlap, anything else has to hold as well.
+fn add_condition(mut filter: Document, field: String, condition: Bson) -> Document {
+    // Hex `_id`s are ObjectIds, as in paths and id lists
+    let condition = if field == "_id" {
+        object_ids(condition)
+    } else {
+        condition
+    };
+    let is_operators =
+        |condition: &Document| condition.keys().all(|operator| operator.starts_with('$'));
+    let mergeable = match (filter.get(&field), &condition) {
+        (None, _) => true,
+        (Some(Bson::Document(existing)), Bson::Document(operators)) => {
+            is_operators(existing)
+                && is_operators(operators)
+                && operators
+                    .keys()
+                    .all(|operator| !existing.contains_key(operator))
+        }
+        _ => false,
+    };
+    if !mergeable {
+        return and_filters(filter, doc! { field: condition });
+    }
+    match (filter.get_mut(&field), condition) {
+        (Some(Bson::Document(existing)), Bson::Document(operators)) => existing.extend(operators),
+        (_, condition) => {
+            filter.insert(field, condition);
+        }
+    }
+    filter
+}
+
+/// Turns ObjectId-shaped strings in an `_id` condition into ObjectIds
+fn object_ids(condition: Bson) -> Bson {
+    match condition {
+        Bson::String(id) => match mongodb::bson::oid::ObjectId::parse_str(&id) {
+            Ok(oid) => oid.into(),
+            Err(_) => id.into(),
+        },
+        Bson::Document(operators) => operators
+            .into_iter()
+            .map(|(operator, value)| match operator.as_str() {
+                "$type" => (operator, value),
+                _ => (operator, object_ids(value)),
+            })
+            .collect::<Document>()
+            .into(),
+        Bson::Array(values) => values.into_iter().map(object_ids).collect(),
+        other => other,
+    }
+}
+
+/// Parses a sort specification like `age.desc,name` into a sort document.
+/// Each key is sorted ascending unless suffixed with `.desc` (`.asc` is also accepted).
+pub fn parse_sort(sort: &str) -> Document {
+    let mut sort_doc = doc! {};
+    for key in sort.split(',').map(str::trim).filter(|k| !k.is_empty()) {
+        match key.rsplit_once('.') {
+            Some((field, "desc")) => sort_doc.insert(field, -1),
+            Some((field, "asc")) => sort_doc.insert(field, 1),
+            _ => sort_doc.insert(key, 1),
+        };
+    }
+    sort_doc
+}
+
+/// Parses a field mask like `name,address{city,zip}` into the dotted paths it selects
+/// (`name`, `address.city`, `address.zip`). Braces nest; a mask without them is a plain
+/// comma-separated list, like `select`.
+pub fn parse_mask(mask: &str) -> Result<Vec<String>, String> {
+    let mut paths = Vec::new();
+    let rest = parse_mask_fields(mask, "", &mut paths)?;
+    if !rest.is_empty() {
+        return Err("Unbalanced '}' in mask".to_string());
+    }
+    Ok(paths)
+}
+
+/// Parses comma-separated fields until the end of the mask or a closing brace,
+/// returning what is left (starting at that brace)
+fn parse_mask_fields<'a>(
+    mut mask: &'a str,
+    prefix: &str,
+    paths: &mut Vec<String>,
+) -> Result<&'a str, String> {
+    loop {
+        let end = mask.find([',', '{', '}']).unwrap_or(mask.len());
+        let name = mask[..end].trim();
+        let path = format!("{}{}", prefix, name);
+        mask = &mask[end..];
+
+        if let Some(nested) = mask.strip_prefix('{') {
+            if name.is_empty() {
+                return Err("Expected a field name before '{' in mask".to_string());
+            }
+            let rest = parse_mask_fields(nested, &format!("{}.", path), paths)?;
+            mask = rest
+                .strip_prefix('}')
+                .ok_or_else(|| format!("Unclosed '{{' after {} in mask", path))?;
+        } else if !name.is_empty() {
+            paths.push(path);
+        }
+
+        match mask.strip_prefix(',') {
+            Some(rest) => mask = rest,
+            None if mask.trim().is_empty() || mask.starts_with('}') => return Ok(mask),
+            None => return Err(format!("Unexpected {} in mask", mask)),
+        }
+    }
+}
+
+/// Builds the find options from the reserved query parameters.
+///
+/// `select=field1,field2` projects only the listed fields, and `mask` (see [`parse_mask`])
+/// projects nested subfields, winning over `select` (callers reject invalid masks first). When
+/// neither is given, `excluded_fields` (the collection's default exclusions) are projected out.
+/// `sort` is parsed by [`parse_sort`], with `_id` appended as a final ascending key (unless
+/// already sorted on) so ties come back in a stable order. The server-side time limit is
+/// parsed by [`parse_timeout`]. Keyset pagination with `after` always sorts by `_id`.
+pub fn parse_find_options(
+    query_params: &HashMap<String, String>,
+    excluded_fields: &[String],
+    max_timeout: Duration,
+) -> FindOptions {
+    // A batch of ids returns every requested document unless limited explicitly
+    let default_limit = match query_params.get("ids") {
+        Some(ids) => (split_ids(ids).len() as i64).max(100),
+        None => 100,
+    };
+    let limit_value = match query_params.get("limit") {
+        Some(limit) => limit.parse::<i64>().unwrap_or(default_limit),
+        None => default_limit,
+    };
+    let skip_value = match query_params.get("skip") {
+        Some(skip) => skip.parse::<u64>().unwrap_or(0),
+        None => 0,
+    };
+
+    let mut projection = doc! {};
+    let selected = match (query_params.get("mask"), query_params.get("select")) {
+        (Some(mask), _) => Some(parse_mask(mask).unwrap_or_default()),
+        (None, Some(select)) => Some(
+            select
+                .split(',')
+                .map(str::trim)
+                .filter(|f| !f.is_empty())
+                .map(str::to_string)
+                .collect(),
+        ),
+        (None, None) => None,
+    };
+    match selected {
+        Some(fields) => {
+            for field in fields {
+                projection.insert(field, 1);
+            }
+        }
+        None => {
+            for field in excluded_fields {
+                projection.insert(field.as_str(), 0);
+            }
+        }
+    }
+
+    FindOptions::builder()
+        .limit(limit_value)
+        .skip(skip_value)
+        .projection((!projection.is_empty()).then_some(projection))
+        .sort(match query_params.get("after") {
+            Some(_) => Some(doc! { "_id": 1 }),
+            None => query_params.get("sort").map(|sort| {
+                let mut sort_doc = parse_sort(sort);
+                if !sort_doc.contains_key("_id") {
+                    sort_doc.insert("_id", 1);
+                }
+                sort_doc
+            }),
+        })
+        .max_time(parse_timeout(query_params, max_timeout))
+        .comment(parse_comment(query_params).map(Bson::from))
+        .collation(parse_collation(query_params))
+        .hint(
+            query_params
+                .get("hint")
+                .map(|name| Hint::Name(name.clone())),
+        )
+        .build()
+}
+
+/// Interprets an id as an ObjectId or integer when it looks like one, otherwise a string.
+pub fn parse_id(id: &str) -> Bson {
+    if let Ok(oid) = mongodb::bson::oid::ObjectId::parse_str(id) {
+        return oid.into();
+    }
+    match id.parse::<i64>() {
+        Ok(n) => n.into(),
+        Err(_) => id.into(),
+    }
+}
+
+/// Restricts the filter to documents after the keyset pagination cursor.
+/// An empty cursor starts from the first page.
+pub fn apply_after_cursor(filter: Document, after: &str) -> Document {
+    if after.is_empty() {
+        return filter;
+    }
+    and_filters(filter, doc! { "_id": { "$gt": parse_id(after) } })
+}
+
+/// Parses an RFC 3339 timestamp, or a `YYYY-MM-DD` date meaning midnight UTC.
+pub fn parse_timestamp(value: &str) -> Result<mongodb::bson::DateTime, String> {
+    let is_date = value.len() == 10 && value.as_bytes()[4] == b'-' && value.as_bytes()[7] == b'-';
+    let timestamp = if is_date {
+        format!("{}T00:00:00Z", value)
+    } else {
+        value.to_string()
+    };
+    mongodb::bson::DateTime::parse_rfc3339_str(&timestamp)
+        .map_err(|_| format!("Invalid timestamp: {}", value))
+}
+
+/// Restricts the filter to documents whose `field` is at or after `since` and before `until`.
+pub fn apply_time_range(
+    filter: Document,
+    field: &str,
+    since: Option<&str>,
+    until: Option<&str>,
+) -> Result<Document, String> {
+    let mut range = doc! {};
+    if let Some(since) = since {
+        range.insert("$gte", parse_timestamp(since)?);
+    }
+    if let Some(until) = until {
+        range.insert("$lt", parse_timestamp(until)?);
+    }
+    if range.is_empty() {
+        return Ok(filter);
+    }
+    Ok(and_filters(filter, doc! { field: range }))
+}
+
+/// Rewrites a filter so its fields refer to `prefix.field`, descending into
+/// `$and`/`$or` groups. Used to match change events on their `fullDocument`.
+/// Fields listed in `unprefixed` are kept as they are.
+pub fn prefix_fields(filter: Document, prefix: &str, unprefixed: &[&str]) -> Document {
+    filter
+        .into_iter()
+        .map(|(key, value)| match value {
+            Bson::Array(groups) if key.starts_with('$') => {
+                let groups = groups
+                    .into_iter()
+                    .map(|group| match group {
+                        Bson::Document(group) => {
+                            Bson::Document(prefix_fields(group, prefix, unprefixed))
+                        }
+                        other => other,
+                    })
+                    .collect();
+                (key, Bson::Array(groups))
+            }
+            value if unprefixed.contains(&key.as_str()) => (key, value),
+            value => (format!("{}.{}", prefix, key), value),
+        })
+        .collect()
+}
+
+/// Splits a comma-separated `ids` list, skipping empty entries.
+pub fn split_ids(ids: &str) -> Vec<&str> {
+    ids.split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .collect()
+}
+
+/// Restricts the filter to documents whose `_id` is one of `ids`.
+pub fn apply_ids(filter: Document, ids: &[&str]) -> Document {
+    let ids: Vec<Bson> = ids.iter().map(|id| parse_id(id)).collect();
+    and_filters(filter, doc! { "_id": { "$in": ids } })
+}
+
+/// Combines two filters so documents have to match both.
+pub fn and_filters(filter: Document, extra: Document) -> Document {
+    if filter.is_empty() {
+        extra
+    } else {
+        doc! { "$and": [filter, extra] }
+    }
+}
+
+/// Returns the operation timeout requested with `timeoutMs`, capped at `max_timeout`.
+/// Missing, invalid or zero values fall back to `max_timeout`.
+pub fn parse_timeout(query_params: &HashMap<String, String>, max_timeout: Duration) -> Duration {
+    query_params
+        .get("timeoutMs")
+        .and_then(|timeout| timeout.parse::<u64>().ok())
+        .filter(|timeout| *timeout > 0)
+        .map(Duration::from_millis)
+        .map_or(max_timeout, |timeout| timeout.min(max_timeout))
+}
+
+/// Returns the collation asked for with `collation`: `ci` compares strings case-insensitively,
+/// a locale like `de` compares them by that language's rules and `de.ci` does both. Unknown
+/// locales are left for the server to reject.
+pub fn parse_collation(query_params: &HashMap<String, String>) -> Option<Collation> {
+    let collation = query_params.get("collation")?.trim();
+    let (locale, case_insensitive) = match collation {
+        "ci" => ("en", true),
+        collation => match collation.strip_suffix(".ci") {
+            Some(locale) => (locale, true),
+            None => (collation, false),
+        },
+    };
+    if locale.is_empty() {
+        return None;
+    }
+    Some(
+        Collation::builder()
+            .locale(locale)
+            .strength(case_insensitive.then_some(CollationStrength::Secondary))
+            .build(),
+    )
+}
+
+/// Returns the `comment` to tag the operation with in MongoDB's profiler and logs.
+/// Control characters are dropped and the text is cut at `MAX_COMMENT_LEN` characters.
+pub fn parse_comment(query_params: &HashMap<String, String>) -> Option<String> {
+    let comment: String = query_params
+        .get("comment")?
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_COMMENT_LEN)
+        .collect();
+    let comment = comment.trim();
+    (!comment.is_empty()).then(|| comment.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mongodb::bson::doc;
+
+    #[test]
+    fn test_parse_query_params_empty() {
+        let query_params = HashMap::new();
+        let result = parse_match_query_params(&query_params);
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), doc! {});
+    }
+
+    #[test]
+    fn test_parse_query_params_single_string() {
+        let mut query_params = HashMap::new();
+        query_params.insert("title".to_owned(), "\"The Perils of Pauline\"".to_owned());
+        let result = parse_match_query_params(&query_params);
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), doc! {"title": "The Perils of Pauline"});
+    }
+
+    #[test]
+    fn test_parse_query_params_single_or() {
+        let mut query_params = HashMap::new();
+        query_params.insert(
+            "or".to_owned(),
+            "(title.\"The Perils of Pauline\",title.\"It\")".to_owned(),
+        );
+        let result = parse_match_query_params(&query_params);
+        assert!(result.is_ok());
+        assert_eq!(
+            result.unwrap(),
+            doc! {"$or": [{"title": "The Perils of Pauline"}, {"title": "It"}]}
+        );
+    }
+
+    #[test]
+    fn test_parse_query_params_string_value() {
+        let mut query_params = HashMap::new();
+        query_params.insert("name".to_string(), "john".to_string());
+
+        let result = parse_match_query_params(&query_params);
+        assert!(result.is_ok());
+
+        let filter = result.unwrap();
+        assert_eq!(filter.get_str("name").unwrap(), "john");
+    }
+
+    #[test]
+    fn test_parse_query_params_numeric_value() {
+        let mut query_params = HashMap::new();
+        query_params.insert("age".to_string(), "30".to_string());
+
+        let result = parse_match_query_params(&query_params);
+        assert!(result.is_ok());
+
+        let filter = result.unwrap();
+        assert_eq!(filter, doc! {"age": 30.0});
+    }
+
+    #[test]
+    fn test_parse_query_params_multiple_fields() {
+        let mut query_params = HashMap::new();
+        query_params.insert("name".to_string(), "john".to_string());
+        query_params.insert("age".to_string(), "30".to_string());
+
+        let result = parse_match_query_params(&query_params);
+        assert!(
+            result.is_ok(),
+            "Failed to parse query params: {:?}",
+            result.err()
+        );
+
+        let filter = result.unwrap();
+        assert_eq!(filter, doc! {"name": "john", "age": 30.0});
+    }
+
+    #[test]
+    fn test_parse_query_params_advanced_comparison() {
+        let mut query_params = HashMap::new();
+        query_params.insert("age".to_string(), "gt.25".to_string());
+
+        let result = parse_match_query_params(&query_params);
+        assert!(result.is_ok());
+
+        let filter = result.unwrap();
+        assert!(filter.contains_key("age"));
+        let age_doc = filter.get_document("age").unwrap();
+        assert!(age_doc.contains_key("$gt"));
+        assert_eq!(age_doc.get_f64("$gt").unwrap(), 25.0);
+    }
+
+    #[test]
+    fn test_parse_query_params_mod() {
+        let mut query_params = HashMap::new();
+        query_params.insert("id".to_string(), "mod.(10,0)".to_string());
+
+        let result = parse_match_query_params(&query_params);
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), doc! {"id": {"$mod": [10_i64, 0_i64]}});
+    }
+
+    #[test]
+    fn test_parse_query_params_all() {
+        let mut query_params = HashMap::new();
+        query_params.insert("tags".to_string(), "all.(rust,mongodb)".to_string());
+
+        let result = parse_match_query_params(&query_params);
+        assert!(result.is_ok());
+        assert_eq!(
+            result.unwrap(),
+            doc! {"tags": {"$all": ["rust", "mongodb"]}}
+        );
+    }
+
+    #[test]
+    fn test_parse_query_params_within() {
+        let mut query_params = HashMap::new();
+        query_params.insert(
+            "location".to_string(),
+            "within.box.(-74.1,40.6,-73.7,40.9)".to_string(),
+        );
+        assert_eq!(
+            parse_match_query_params(&query_params).unwrap(),
+            doc! {"location": {"$geoWithin": {"$box": [[-74.1, 40.6], [-73.7, 40.9]]}}}
+        );
+
+        query_params.insert(
+            "location".to_string(),
+            "within.polygon.(0,0,3,6,6,0)".to_string(),
+        );
+        assert_eq!(
+            parse_match_query_params(&query_params).unwrap(),
+            doc! {"location": {"$geoWithin": {"$polygon": [[0.0, 0.0], [3.0, 6.0], [6.0, 0.0]]}}}
+        );
+
+        for value in [
+            "within.box.(0,0,1)",
+            "within.polygon.(0,0,1,1)",
+            "within.polygon.(0,0,1,1,2)",
+            "within.circle.(0,0,1)",
+            "within.box.(a,0,1,1)",
+            "within.(0,0,1,1)",
+        ] {
+            query_params.insert("location".to_string(), value.to_string());
+            assert!(
+                parse_match_query_params(&query_params).is_err(),
+                "Expected {} to be rejected",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_query_pairs_merges_repeated_fields() {
+        let range = parse_match_query_pairs([("_id", "gte.100"), ("_id", "lt.200")], None);
+        assert_eq!(range.unwrap(), doc! {"_id": {"$gte": 100.0, "$lt": 200.0}});
+
+        // Conditions that cannot share a document must all hold
+        let both = parse_match_query_pairs([("age", "gt.1"), ("age", "gt.5")], None);
+        assert_eq!(
+            both.unwrap(),
+            doc! {"$and": [{"age": {"$gt": 1.0}}, {"age": {"$gt": 5.0}}]}
+        );
+        let both = parse_match_query_pairs([("status", "open"), ("status", "ne.closed")], None);
+        assert_eq!(
+            both.unwrap(),
+            doc! {"$and": [{"status": "open"}, {"status": {"$ne": "closed"}}]}
+        );
+
+        let long = parse_match_query_pairs([("name", "a"), ("name", "b")], Some(3));
+        assert!(long.unwrap_err().contains("Query is 10 bytes long"));
+    }
+
+    #[test]
+    fn test_parse_query_pairs_any_repeated_params() {
+        let any = |pairs: &[(&str, &str)]| {
+            let options = ParseOptions {
+                repeated: RepeatedParams::Any,
+                ..ParseOptions::default()
+            };
+            parse_match_query_pairs_with(pairs.iter().copied(), options).unwrap()
+        };
+        assert_eq!(
+            any(&[("tag", "a"), ("tag", "b"), ("status", "open")]),
+            doc! {"tag": {"$in": ["a", "b"]}, "status": "open"}
+        );
+        assert_eq!(
+            any(&[("age", "lt.18"), ("age", "gte.65")]),
+            doc! {"$or": [{"age": {"$lt": 18.0}}, {"age": {"$gte": 65.0}}]}
+        );
+        // A single parameter is unaffected
+        assert_eq!(any(&[("age", "gt.1")]), doc! {"age": {"$gt": 1.0}});
+        // By default every repeated condition has to hold
+        assert_eq!(
+            parse_match_query_pairs([("tag", "a"), ("tag", "b")], None).unwrap(),
+            doc! {"$and": [{"tag": "a"}, {"tag": "b"}]}
+        );
+        assert_eq!(RepeatedParams::parse("any"), Some(RepeatedParams::Any));
+        assert_eq!(RepeatedParams::parse("some"), None);
+    }
+
+    #[test]
+    fn test_tokenize_always_makes_progress() {
+        let chars = (0..128u8)
+            .map(char::from)
+            .chain(['é', '€', '\u{200b}', '数']);
+        for c in chars {
+            for input in [
+                c.to_string(),
+                format!("a{}b", c),
+                format!("(a.eq.{})", c),
+                format!("({}.eq.1)", c),
+                format!("ilike.{}", c),
+                format!("(a.ilike.{},b.eq.1)", c),
+            ] {
+                // Ends, and never with more tokens than there are characters
+                if let Ok(tokens) = Lexer::new(&input).tokenize() {
+                    assert!(
+                        tokens.len() <= input.chars().count(),
+                        "{:?} gave {:?}",
+                        input,
+                        tokens
+                    );
+                }
+            }
+        }
+        assert_eq!(
+            Lexer::new("(a.eq.$where)").tokenize().unwrap_err(),
+            "Unexpected character '$' at position 6"
+        );
+    }
+
+    #[test]
+    fn test_parse_query_params_snake_case_fields_in_groups() {
+        let mut query_params = HashMap::new();
+        query_params.insert("or".to_string(), "(snake_case.eq.1,_id.eq.2)".to_string());
+        assert_eq!(
+            parse_match_query_params(&query_params).unwrap(),
+            doc! { "$or": [{ "snake_case": { "$eq": 1.0 } }, { "_id": { "$eq": 2.0 } }] }
+        );
+    }
+
+    #[test]
+    fn test_parse_query_pairs_known_fields() {
+        let known_fields = ["_id".to_string(), "address".to_string()];
+        let options = ParseOptions {
+            known_fields: Some(&known_fields),
+            ..ParseOptions::default()
+        };
+        assert_eq!(
+            parse_match_query_pairs_with([("limt", "10")], options).unwrap_err(),
+            "limt is neither a reserved parameter nor a known field"
+        );
+        assert!(
+            parse_match_query_pairs_with(
+                [
+                    ("limit", "10"),
+                    ("_id", "gt.1"),
+                    ("address.city", "Oslo"),
+                    ("or", "(_id.eq.1,_id.eq.2)"),
+                ],
+                options,
+            )
+            .is_ok()
+        );
+        assert!(parse_match_query_pairs_with([("limt", "10")], ParseOptions::default()).is_ok());
+
+        // Fields inside `and`/`or` groups are checked as well
+        for (group, conditions) in [("or", "(limt.eq.10)"), ("and", "(_id.eq.1,limt.eq.10)")] {
+            assert_eq!(
+                parse_match_query_pairs_with([(group, conditions)], options).unwrap_err(),
+                "limt is neither a reserved parameter nor a known field"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_query_pairs_camel_case_fields() {
+        let known = ["_id", "first_name"].map(String::from);
+        let options = ParseOptions {
+            known_fields: Some(&known),
+            field_case: FieldCase::Camel,
+            ..ParseOptions::default()
+        };
+        assert_eq!(
+            parse_match_query_pairs_with(
+                [("firstName", "Ann"), ("or", "(firstName.eq.Bo,_id.eq.1)")],
+                options
+            )
+            .unwrap(),
+            doc! {
+                "first_name": "Ann",
+                "$or": [{"first_name": {"$eq": "Bo"}}, {"_id": {"$eq": 1.0}}]
+            }
+        );
+        assert_eq!(
+            parse_match_query_pairs_with([("lastName", "Ann")], options).unwrap_err(),
+            "lastName is neither a reserved parameter nor a known field"
+        );
+    }
+
+    #[test]
+    fn test_parse_query_pairs_operator_allowlist() {
+        let allowed = ["eq", "gte", "or"].map(String::from);
+        let parse_allowed = |pairs: &[(&str, &str)]| {
+            let options = ParseOptions {
+                allowed_operators: Some(&allowed),
+                ..ParseOptions::default()
+            };
+            parse_match_query_pairs_with(pairs.iter().copied(), options)
+        };
+
+        // Allowed operators and plain equality still work
+        assert_eq!(
+            parse_allowed(&[("age", "gte.30"), ("name", "John")]).unwrap(),
+            doc! {"age": {"$gte": 30.0}, "name": "John"}
+        );
+        assert_eq!(
+            parse_allowed(&[("or", "(age.eq.1,name.eq.Jo)")]).unwrap(),
+            doc! {"$or": [{"age": {"$eq": 1.0}}, {"name": {"$eq": "Jo"}}]}
+        );
+
+        // Anything else is refused, nested or top level
+        assert_eq!(
+            parse_allowed(&[("name", "ilike.jo%")]).unwrap_err(),
+            "Operator ilike is not allowed"
+        );
+        assert_eq!(
+            parse_allowed(&[("or", "(age.lt.1,name.eq.Jo)")]).unwrap_err(),
+            "Operator lt is not allowed"
+        );
+        assert_eq!(
+            parse_allowed(&[("and", "(age.eq.1)")]).unwrap_err(),
+            "Operator and is not allowed"
+        );
+        assert_eq!(
+            parse_allowed(&[("tags", "all.(a,b)")]).unwrap_err(),
+            "Operator all is not allowed"
+        );
+    }
+
+    #[test]
+    fn test_check_regex() {
+        for safe in [
+            "^abc$",
+            "a+b*c",
+            "(ab)+",
+            "(a|b)*c",
+            "[(+*]+x",
+            "\\(a+\\)+",
+            "(a+)?",
+        ] {
+            assert!(check_regex(safe).is_ok(), "{} should be accepted", safe);
+        }
+        for nested in [
+            "(a+)+",
+            "(a*)*",
+            "(\\w+\\s?)*$",
+            "((ab)*c)+",
+            "(.*){2,}",
+            "(?:x+)+",
+        ] {
+            assert!(
+                check_regex(nested)
+                    .unwrap_err()
+                    .contains("nests quantifiers"),
+                "{} should be rejected",
+                nested
+            );
+        }
+        let overlong = "a".repeat(MAX_PATTERN_LEN + 1);
+        assert!(
+            check_regex(&overlong)
+                .unwrap_err()
+                .contains("the limit is 256")
+        );
+    }
+
+    #[test]
+    fn test_parse_query_params_ilike_pattern_limits() {
+        // Runs of `%` collapse into a single wildcard
+        assert_eq!(like_to_regex("a%%%b"), "^a.*b$");
+
+        let overlong = format!("ilike.{}", "%".repeat(MAX_PATTERN_LEN + 1));
+        let result = parse_match_query_pairs([("name", overlong.as_str())], None);
+        assert!(result.unwrap_err().contains("the limit is 256"));
+
+        // Backtracking grows with the number of wildcards, so it is capped
+        let wildcards = format!("ilike.{}b", "%a".repeat(MAX_LIKE_WILDCARDS + 1));
+        let result = parse_match_query_pairs([("name", wildcards.as_str())], None);
+        assert!(result.unwrap_err().contains("wildcards, the limit is 4"));
+        for allowed in ["ilike.%a%%b%c%", "ilike.50\\%\\%\\%\\%\\%%"] {
+            assert!(parse_match_query_pairs([("name", allowed)], None).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_parse_query_pairs_object_id_range() {
+        let (low, high) = (
+            mongodb::bson::oid::ObjectId::parse_str("64b0c0ffee0000000000000a").unwrap(),
+            mongodb::bson::oid::ObjectId::parse_str("64b0c0ffee0000000000000f").unwrap(),
+        );
+        let query = [
+            ("_id", "gte.64b0c0ffee0000000000000a".to_string()),
+            ("_id", format!("lt.\"{}\"", high.to_hex())),
+        ];
+        let filter = parse_match_query_pairs(
+            query.iter().map(|(key, value)| (*key, value.as_str())),
+            None,
+        );
+        assert_eq!(filter.unwrap(), doc! {"_id": {"$gte": low, "$lt": high}});
+
+        // Other fields keep hex strings as they are
+        let filter = parse_match_query_pairs([("ref", "eq.64b0c0ffee0000000000000a")], None);
+        assert_eq!(
+            filter.unwrap(),
+            doc! {"ref": {"$eq": "64b0c0ffee0000000000000a"}}
+        );
+    }
+
+    #[test]
+    fn test_parse_query_params_type() {
+        let mut query_params = HashMap::new();
+        query_params.insert("value".to_string(), "type.string".to_string());
+        assert_eq!(
+            parse_match_query_params(&query_params).unwrap(),
+            doc! {"value": {"$type": "string"}}
+        );
+
+        query_params.insert(
+            "or".to_string(),
+            "(value.type.number,value.type.null)".to_string(),
+        );
+        query_params.remove("value");
+        assert_eq!(
+            parse_match_query_params(&query_params).unwrap(),
+            doc! {"$or": [{"value": {"$type": "number"}}, {"value": {"$type": "null"}}]}
+        );
+
+        for value in ["type.text", "type.2"] {
+            let mut query_params = HashMap::new();
+            query_params.insert("value".to_string(), value.to_string());
+            let error = parse_match_query_params(&query_params).unwrap_err();
+            assert!(error.contains("Unknown BSON type"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_like_to_regex() {
+        assert_eq!(like_to_regex("jo%n"), "^jo.*n$");
+        assert_eq!(like_to_regex("j_hn"), "^j.hn$");
+        assert_eq!(like_to_regex("50\\%"), "^50%$");
+        assert_eq!(like_to_regex("a\\_b"), "^a_b$");
+        assert_eq!(like_to_regex("1.5+(x)"), "^1\\.5\\+\\(x\\)$");
+        assert_eq!(like_to_regex("end\\"), "^end\\\\$");
+    }
+
+    #[test]
+    fn test_parse_query_params_ilike() {
+        let mut query_params = HashMap::new();
+        query_params.insert("name".to_string(), "ilike.jo%n".to_string());
+        assert_eq!(
+            parse_match_query_params(&query_params).unwrap(),
+            doc! {"name": {"$regex": "^jo.*n$", "$options": "i"}}
+        );
+
+        // Patterns may hold the grammar's own separators
+        query_params.insert("name".to_string(), "ilike.j.%_".to_string());
+        assert_eq!(
+            parse_match_query_params(&query_params).unwrap(),
+            doc! {"name": {"$regex": "^j\\..*.$", "$options": "i"}}
+        );
+
+        // Inside groups the pattern ends at the next comma or parenthesis
+        query_params.remove("name");
+        query_params.insert(
+            "or".to_string(),
+            "(name.ilike.a%,name.ilike.50\\%)".to_string(),
+        );
+        assert_eq!(
+            parse_match_query_params(&query_params).unwrap(),
+            doc! {"$or": [
+                {"name": {"$regex": "^a.*$", "$options": "i"}},
+                {"name": {"$regex": "^50%$", "$options": "i"}},
+            ]}
+        );
+
+        assert_eq!(
+            FilterBuilder::new().ilike("name", "jo%n").build(),
+            doc! {"name": {"$regex": "^jo.*n$", "$options": "i"}}
+        );
+    }
+
+    #[test]
+    fn test_parse_query_params_mod_rejects_invalid_lists() {
+        for value in [
+            "mod.(10)",
+            "mod.(10,0,1)",
+            "mod.(2.5,1)",
+            "mod.(a,1)",
+            "mod.10",
+        ] {
+            let mut query_params = HashMap::new();
+            query_params.insert("id".to_string(), value.to_string());
+            assert!(
+                parse_match_query_params(&query_params).is_err(),
+                "Expected {} to be rejected",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_find_options_default_exclusions() {
+        let query_params = HashMap::new();
+        let excluded = vec!["rawPayload".to_string()];
+
+        let options = parse_find_options(&query_params, &excluded, Duration::from_secs(30));
+        assert_eq!(options.projection, Some(doc! {"rawPayload": 0}));
+
+        let options = parse_find_options(&query_params, &[], Duration::from_secs(30));
+        assert_eq!(options.projection, None);
+    }
+
+    #[test]
+    fn test_parse_find_options_select_overrides_exclusions() {
+        let mut query_params = HashMap::new();
+        query_params.insert("select".to_string(), "name, rawPayload".to_string());
+        let excluded = vec!["rawPayload".to_string()];
+
+        let options = parse_find_options(&query_params, &excluded, Duration::from_secs(30));
+        assert_eq!(options.projection, Some(doc! {"name": 1, "rawPayload": 1}));
+        assert_eq!(parse_match_query_params(&query_params).unwrap(), doc! {});
+    }
+
+    #[test]
+    fn test_parse_query_params_bounded_rejects_long_queries() {
+        let mut query_params = HashMap::new();
+        query_params.insert("or".to_string(), format!("({})", ["a.eq.1"; 100].join(",")));
+        // Reserved parameters are not lexed, so they do not count
+        query_params.insert("select".to_string(), "x".repeat(1000));
+
+        assert_eq!(
+            parse_match_query_params_bounded(&query_params, Some(100)).unwrap_err(),
+            "Query is 703 bytes long, the limit is 100"
+        );
+        assert!(parse_match_query_params_bounded(&query_params, Some(1000)).is_ok());
+        assert!(parse_match_query_params_bounded(&query_params, None).is_ok());
+    }
+
+    #[test]
+    fn test_parse_query_pairs_rejects_too_many_params() {
+        let options = ParseOptions {
+            max_params: Some(2),
+            ..ParseOptions::default()
+        };
+        // Every occurrence of a repeated parameter counts, reserved parameters do not
+        assert_eq!(
+            parse_match_query_pairs_with(
+                [
+                    ("age", "gt.1"),
+                    ("age", "lt.9"),
+                    ("name", "Ann"),
+                    ("limit", "5")
+                ],
+                options,
+            )
+            .unwrap_err(),
+            "Query has 3 filter parameters, the limit is 2"
+        );
+        assert_eq!(
+            parse_match_query_pairs_with([("age", "gt.1"), ("limit", "5")], options).unwrap(),
+            doc! { "age": { "$gt": 1.0 } }
+        );
+    }
+
+    #[test]
+    fn test_apply_time_range() {
+        let since = mongodb::bson::DateTime::parse_rfc3339_str("2023-01-01T00:00:00Z").unwrap();
+        let until = mongodb::bson::DateTime::parse_rfc3339_str("2023-02-01T12:30:00Z").unwrap();
+        assert_eq!(
+            apply_time_range(
+                doc! { "type": "click" },
+                "createdAt",
+                Some("2023-01-01"),
+                Some("2023-02-01T12:30:00Z")
+            )
+            .unwrap(),
+            doc! { "$and": [
+                { "type": "click" },
+                { "createdAt": { "$gte": since, "$lt": until } },
+            ] }
+        );
+        assert_eq!(
+            apply_time_range(doc! {}, "createdAt", None, None).unwrap(),
+            doc! {}
+        );
+        assert!(apply_time_range(doc! {}, "createdAt", Some("yesterday"), None).is_err());
+    }
+
+    #[test]
+    fn test_parse_mask() {
+        assert_eq!(
+            parse_mask("name, address{city,geo{lat}},tags").unwrap(),
+            vec!["name", "address.city", "address.geo.lat", "tags"]
+        );
+        assert_eq!(parse_mask("name,age").unwrap(), vec!["name", "age"]);
+        assert!(parse_mask("address{city").is_err());
+        assert!(parse_mask("address}city").is_err());
+        assert!(parse_mask("{city}").is_err());
+        assert!(parse_mask("address{city}zip").is_err());
+    }
+
+    #[test]
+    fn test_parse_find_options_mask_overrides_select() {
+        let mut query_params = HashMap::new();
+        query_params.insert("select".to_string(), "name".to_string());
+        query_params.insert("mask".to_string(), "address{city,zip}".to_string());
+        let options = parse_find_options(&query_params, &[], Duration::from_secs(30));
+        assert_eq!(
+            options.projection,
+            Some(doc! { "address.city": 1, "address.zip": 1 })
+        );
+    }
+
+    #[test]
+    fn test_parse_find_options_sort() {
+        let mut query_params = HashMap::new();
+        query_params.insert(
+            "sort".to_string(),
+            "age.desc,name,address.city.asc".to_string(),
+        );
+
+        let options = parse_find_options(&query_params, &[], Duration::from_secs(30));
+        assert_eq!(
+            options.sort,
+            Some(doc! {"age": -1, "name": 1, "address.city": 1, "_id": 1})
+        );
+        assert_eq!(parse_match_query_params(&query_params).unwrap(), doc! {});
+    }
+
+    #[test]
+    fn test_parse_find_options_sort_keeps_explicit_id() {
+        let mut query_params = HashMap::new();
+        query_params.insert("sort".to_string(), "_id.desc,age".to_string());
+
+        let options = parse_find_options(&query_params, &[], Duration::from_secs(30));
+        assert_eq!(options.sort, Some(doc! {"_id": -1, "age": 1}));
+    }
+
+    #[test]
+    fn test_parse_find_options_timeout() {
+        let max_timeout = Duration::from_secs(30);
+        let mut query_params = HashMap::new();
+
+        let options = parse_find_options(&query_params, &[], max_timeout);
+        assert_eq!(options.max_time, Some(max_timeout));
+
+        query_params.insert("timeoutMs".to_string(), "1500".to_string());
+        let options = parse_find_options(&query_params, &[], max_timeout);
+        assert_eq!(options.max_time, Some(Duration::from_millis(1500)));
+        assert_eq!(parse_match_query_params(&query_params).unwrap(), doc! {});
+
+        // Requests can only shorten the configured timeout
+        query_params.insert("timeoutMs".to_string(), "60000".to_string());
+        assert_eq!(parse_timeout(&query_params, max_timeout), max_timeout);
+
+        query_params.insert("timeoutMs".to_string(), "soon".to_string());
+        assert_eq!(parse_timeout(&query_params, max_timeout), max_timeout);
+    }
+
+    #[test]
+    fn test_filter_builder_matches_query_strings() {
+        let parsed =
+            |pairs: &[(&str, &str)]| parse_match_query_pairs(pairs.iter().copied(), None).unwrap();
+
+        let built = FilterBuilder::new()
+            .eq("age", 30.0)
+            .gt("score", 80.0)
+            .ne("status", "archived")
+            .build();
+        assert_eq!(
+            built,
+            parsed(&[("age", "30"), ("score", "gt.80"), ("status", "ne.archived")])
+        );
+
+        // Conditions on one field merge into a range, like repeated parameters
+        let oid = mongodb::bson::oid::ObjectId::new();
+        let built = FilterBuilder::new()
+            .gte("_id", oid.to_hex())
+            .lt("_id", 200.0)
+            .lte("age", 65.0)
+            .build();
+        assert_eq!(
+            built,
+            parsed(&[
+                ("_id", &format!("gte.{}", oid.to_hex())),
+                ("_id", "lt.200"),
+                ("age", "lte.65"),
+            ])
+        );
+
+        let built = FilterBuilder::new()
+            .or(vec![
+                doc! { "age": { "$lt": 18.0 } },
+                doc! { "name": "bob" },
+            ])
+            .all("tags", vec!["a".into(), "b".into()])
+            .build();
+        assert_eq!(
+            built,
+            parsed(&[("or", "(age.lt.18,name.bob)"), ("tags", "all.(a,b)")])
+        );
+
+        let built = FilterBuilder::new()
+            .and(vec![
+                doc! { "age": { "$gt": 35.0 } },
+                doc! { "status": "active" },
+            ])
+            .build();
+        assert_eq!(built, parsed(&[("and", "(age.gt.35,status.active)")]));
+
+        assert_eq!(FilterBuilder::new().build(), doc! {});
+    }
+
+    #[test]
+    fn test_parse_find_options_collation() {
+        let mut query_params = HashMap::new();
+        let options = parse_find_options(&query_params, &[], Duration::from_secs(30));
+        assert!(options.collation.is_none());
+
+        let strength = |query_params: &HashMap<String, String>| {
+            let collation = parse_collation(query_params).unwrap();
+            (collation.locale, collation.strength.map(u32::from))
+        };
+        query_params.insert("collation".to_string(), "ci".to_string());
+        let options = parse_find_options(&query_params, &[], Duration::from_secs(30));
+        assert_eq!(options.collation.unwrap().locale, "en");
+        assert_eq!(strength(&query_params), ("en".to_string(), Some(2)));
+        assert_eq!(parse_match_query_params(&query_params).unwrap(), doc! {});
+
+        query_params.insert("collation".to_string(), "de.ci".to_string());
+        assert_eq!(strength(&query_params), ("de".to_string(), Some(2)));
+        query_params.insert("collation".to_string(), "de".to_string());
+        assert_eq!(strength(&query_params), ("de".to_string(), None));
+
+        query_params.insert("collation".to_string(), ".ci".to_string());
+        assert!(parse_collation(&query_params).is_none());
+    }
+
+    #[test]
+    fn test_parse_find_options_hint() {
+        let mut query_params = HashMap::new();
+        let options = parse_find_options(&query_params, &[], Duration::from_secs(30));
+        assert!(options.hint.is_none());
+
+        query_params.insert("hint".to_string(), "status_1".to_string());
+        let options = parse_find_options(&query_params, &[], Duration::from_secs(30));
+        assert!(matches!(options.hint, Some(Hint::Name(name)) if name == "status_1"));
+        assert_eq!(parse_match_query_params(&query_params).unwrap(), doc! {});
+    }
+
+    #[test]
+    fn test_parse_find_options_comment() {
+        let mut query_params = HashMap::new();
+        let options = parse_find_options(&query_params, &[], Duration::from_secs(30));
+        assert_eq!(options.comment, None);
+
+        query_params.insert("comment".to_string(), " reports\n#42 ".to_string());
+        let options = parse_find_options(&query_params, &[], Duration::from_secs(30));
+        assert_eq!(options.comment, Some(Bson::from("reports#42")));
+        assert_eq!(parse_match_query_params(&query_params).unwrap(), doc! {});
+
+        query_params.insert("comment".to_string(), "x".repeat(1000));
+        let comment = parse_comment(&query_params).unwrap();
+        assert_eq!(comment.len(), MAX_COMMENT_LEN);
+
+        query_params.insert("comment".to_string(), "\t".to_string());
+        assert_eq!(parse_comment(&query_params), None);
+    }
+
+    #[test]
+    fn test_apply_after_cursor() {
+        let oid = mongodb::bson::oid::ObjectId::new();
+        assert_eq!(
+            apply_after_cursor(doc! {}, &oid.to_hex()),
+            doc! {"_id": {"$gt": oid}}
+        );
+        assert_eq!(
+            apply_after_cursor(doc! {"status": "active"}, "42"),
+            doc! {"$and": [{"status": "active"}, {"_id": {"$gt": 42_i64}}]}
+        );
+        assert_eq!(apply_after_cursor(doc! {}, ""), doc! {});
+
+        let mut query_params = HashMap::new();
+        query_params.insert("after".to_string(), "42".to_string());
+        query_params.insert("sort".to_string(), "name".to_string());
+        let options = parse_find_options(&query_params, &[], Duration::from_secs(30));
+        assert_eq!(options.sort, Some(doc! {"_id": 1}));
+        assert_eq!(parse_match_query_params(&query_params).unwrap(), doc! {});
+    }
+
+    #[test]
+    fn test_apply_ids() {
+        let oid = mongodb::bson::oid::ObjectId::new();
+        let ids = format!("1, {},missing,", oid.to_hex());
+        let ids = split_ids(&ids);
+        assert_eq!(ids.len(), 3);
+        assert_eq!(
+            apply_ids(doc! {"status": "active"}, &ids),
+            doc! {"$and": [
+                {"status": "active"},
+                {"_id": {"$in": [1_i64, oid, "missing"]}}
+            ]}
+        );
+
+        let mut query_params = HashMap::new();
+        query_params.insert("ids".to_string(), "1,2,3".to_string());
+        assert_eq!(parse_match_query_params(&query_params).unwrap(), doc! {});
+    }
+
+    #[test]
+    fn test_parse_query_params_inclusive_and_negated_comparisons() {
+        for (op, mql_op) in [("gte", "$gte"), ("lte", "$lte"), ("ne", "$ne")] {
+            let mut query_params = HashMap::new();
+            query_params.insert("age".to_string(), format!("{}.18", op));
+            assert_eq!(
+                parse_match_query_params(&query_params).unwrap(),
+                doc! {"age": {mql_op: 18.0}}
+            );
+
+            let mut query_params = HashMap::new();
+            query_params.insert("or".to_string(), format!("(age.{}.18,name.bob)", op));
+            assert_eq!(
+                parse_match_query_params(&query_params).unwrap(),
+                doc! {"$or": [{"age": {mql_op: 18.0}}, {"name": "bob"}]}
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_query_params_nested_logical_groups() {
+        let mut query_params = HashMap::new();
+        query_params.insert(
+            "or".to_string(),
+            "(and=(category.A,score.lt.80),and=(age.gt.35,status.active))".to_string(),
+        );
+        assert_eq!(
+            parse_match_query_params(&query_params).unwrap(),
+            doc! {"$or": [
+                {"$and": [{"category": "A"}, {"score": {"$lt": 80.0}}]},
+                {"$and": [{"age": {"$gt": 35.0}}, {"status": "active"}]}
+            ]}
+        );
+    }
+
+    #[test]
+    fn test_prefix_fields() {
+        let filter = doc! {
+            "operationType": "insert",
+            "status": "active",
+            "$or": [{"age": {"$gt": 30.0}}, {"name": "bob"}]
+        };
+        assert_eq!(
+            prefix_fields(filter, "fullDocument", &["operationType"]),
+            doc! {
+                "operationType": "insert",
+                "fullDocument.status": "active",
+                "$or": [{"fullDocument.age": {"$gt": 30.0}}, {"fullDocument.name": "bob"}]
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_query_params_advanced_logical() {
+        let mut query_params = HashMap::new();
+        query_params.insert("or".to_string(), "(age.gt.25,name.eq.john)".to_string());
+
+        let result = parse_match_query_params(&query_params);
+        assert!(result.is_ok());
+
+        let filter = result.unwrap();
+        assert_eq!(
+            filter,
+            doc! {"$or": [{"age": {"$gt": 25.0}}, {"name": {"$eq": "john"}}]}
+        );
+    }
+
+    #[test]
+    fn test_logical_groups_use_operator_keys() {
+        // The raw parser and the query-string path agree, at the top level and nested
+        for (key, operator) in [("and", "$and"), ("or", "$or")] {
+            let expected = doc! { operator: [{"a": 1.0}, {"b": {"$gt": 2.0}}] };
+            assert_eq!(
+                parse(key, "(a.1,b.gt.2)").unwrap(),
+                Bson::Document(expected.clone())
+            );
+
+            let mut query_params = HashMap::new();
+            query_params.insert(key.to_string(), "(a.1,b.gt.2)".to_string());
+            assert_eq!(parse_match_query_params(&query_params).unwrap(), expected);
+        }
+
+        let mut query_params = HashMap::new();
+        query_params.insert(
+            "and".to_string(),
+            "(or=(a.1,b.2),and=(c.3),d.4)".to_string(),
+        );
+        assert_eq!(
+            parse_match_query_params(&query_params).unwrap(),
+            doc! {"$and": [
+                {"$or": [{"a": 1.0}, {"b": 2.0}]},
+                {"$and": [{"c": 3.0}]},
+                {"d": 4.0},
+            ]}
+        );
     }
 }
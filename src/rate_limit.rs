@@ -0,0 +1,221 @@
+//! Token-bucket rate limiting keyed by the authenticated subject or the client IP.
+//!
+//! Only identities the server verified pick the bucket: with `JWT_SECRET` set, a valid
+//! bearer token's subject. Anything unverified, like an `X-API-Key` header, could be
+//! changed on every request to get a fresh bucket, so those requests share their IP's.
+//! That is the connecting peer's address; `X-Forwarded-For` is only read when the peer
+//! is one of the `TRUSTED_PROXIES`.
+
+use actix_web::{
+    Error, HttpRequest, HttpResponse,
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::header,
+    middleware::Next,
+    web,
+};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::auth::authenticated_subject;
+use crate::shared::AppState;
+
+/// Once this many clients are tracked, idle (fully refilled) buckets are dropped
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+#[derive(Debug)]
+pub struct RateLimiter {
+    capacity: f64,
+    refill_per_sec: f64,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    /// Allows bursts of up to `per_minute` requests, refilled evenly over a minute
+    pub fn new(per_minute: u32) -> Self {
+        RateLimiter {
+            capacity: per_minute as f64,
+            refill_per_sec: per_minute as f64 / 60.0,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Takes a token for `key`, or returns how long until the next token is available
+    pub fn check(&self, key: &str, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+
+        if buckets.len() >= MAX_TRACKED_CLIENTS {
+            let (capacity, refill_per_sec) = (self.capacity, self.refill_per_sec);
+            buckets.retain(|_, bucket| {
+                let elapsed = now.saturating_duration_since(bucket.last_refill);
+                bucket.tokens + elapsed.as_secs_f64() * refill_per_sec < capacity
+            });
+        }
+
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: self.capacity,
+            last_refill: now,
+        });
+
+        // Refill for the time elapsed since the last request
+        let elapsed = now.saturating_duration_since(bucket.last_refill);
+        bucket.tokens =
+            (bucket.tokens + elapsed.as_secs_f64() * self.refill_per_sec).min(self.capacity);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            let missing = 1.0 - bucket.tokens;
+            Err(Duration::from_secs_f64(missing / self.refill_per_sec))
+        }
+    }
+}
+
+/// The address a client's bucket is keyed on: the connecting peer, or when that is a trusted
+/// proxy, the nearest `X-Forwarded-For` hop no trusted proxy added. Hops further left are
+/// whatever the client sent, so they are never used.
+fn client_ip(req: &HttpRequest, trusted_proxies: &[IpAddr]) -> Option<IpAddr> {
+    let peer = req.peer_addr()?.ip();
+    if !trusted_proxies.contains(&peer) {
+        return Some(peer);
+    }
+    let hops: Vec<&str> = req
+        .headers()
+        .get_all("x-forwarded-for")
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .collect();
+    let mut client = peer;
+    for hop in hops.into_iter().rev() {
+        match hop.parse::<IpAddr>() {
+            Ok(ip) if trusted_proxies.contains(&ip) => client = ip,
+            Ok(ip) => return Some(ip),
+            Err(_) => break,
+        }
+    }
+    Some(client)
+}
+
+/// Middleware rejecting requests over the configured limit with 429 and `Retry-After`
+pub async fn rate_limit(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let data = req.app_data::<web::Data<AppState>>().cloned();
+    let limiter = data.as_ref().and_then(|data| data.rate_limiter.clone());
+
+    if let (Some(limiter), Some(data)) = (limiter, data) {
+        // Runs before authentication, so the token is verified here
+        let key = match authenticated_subject(req.request(), &data) {
+            Some(subject) => format!("sub:{}", subject),
+            None => {
+                let trusted_proxies: Vec<IpAddr> = data
+                    .config
+                    .trusted_proxies
+                    .iter()
+                    .filter_map(|proxy| proxy.parse().ok())
+                    .collect();
+                match client_ip(req.request(), &trusted_proxies) {
+                    Some(ip) => format!("ip:{}", ip),
+                    None => "ip:unknown".to_string(),
+                }
+            }
+        };
+
+        if let Err(retry_after) = limiter.check(&key, Instant::now()) {
+            let retry_secs = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+            let response = HttpResponse::TooManyRequests()
+                .insert_header((header::RETRY_AFTER, retry_secs.to_string()))
+                .body("Rate limit exceeded");
+            return Ok(req.into_response(response).map_into_right_body());
+        }
+    }
+
+    next.call(req)
+        .await
+        .map(ServiceResponse::map_into_left_body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_allows_burst_then_limits() {
+        let limiter = RateLimiter::new(3);
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.check("client", now).is_ok());
+        }
+        let retry_after = limiter.check("client", now).unwrap_err();
+        assert_eq!(retry_after, Duration::from_secs(20));
+
+        // Other clients have their own bucket
+        assert!(limiter.check("other", now).is_ok());
+    }
+
+    #[test]
+    fn test_bucket_refills_over_time() {
+        let limiter = RateLimiter::new(60);
+        let now = Instant::now();
+
+        for _ in 0..60 {
+            assert!(limiter.check("client", now).is_ok());
+        }
+        assert!(limiter.check("client", now).is_err());
+        assert!(
+            limiter
+                .check("client", now + Duration::from_secs(1))
+                .is_ok()
+        );
+        assert!(
+            limiter
+                .check("client", now + Duration::from_secs(1))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_client_ip_only_trusts_forwarding_proxies() {
+        let request = |peer: &str, forwarded_for: &str| {
+            actix_web::test::TestRequest::default()
+                .peer_addr(format!("{}:4000", peer).parse().unwrap())
+                .insert_header(("X-Forwarded-For", forwarded_for))
+                .to_http_request()
+        };
+        let proxy: IpAddr = "10.0.0.1".parse().unwrap();
+        let ip = |ip: &str| ip.parse::<IpAddr>().ok();
+
+        // Without trusted proxies the header is ignored
+        assert_eq!(
+            client_ip(&request("1.2.3.4", "5.6.7.8"), &[]),
+            ip("1.2.3.4")
+        );
+        assert_eq!(
+            client_ip(&request("1.2.3.4", "5.6.7.8"), &[proxy]),
+            ip("1.2.3.4")
+        );
+
+        // Behind a trusted proxy, the hop it added names the client, not what the client sent
+        assert_eq!(
+            client_ip(&request("10.0.0.1", "5.6.7.8, 1.2.3.4"), &[proxy]),
+            ip("1.2.3.4")
+        );
+        assert_eq!(
+            client_ip(&request("10.0.0.1", "not-an-ip"), &[proxy]),
+            ip("10.0.0.1")
+        );
+    }
+}
@@ -0,0 +1,119 @@
+//! Unconditional removal of sensitive fields from documents before they leave the server.
+//!
+//! Unlike excluded fields, which only shape the default projection, redacted fields are
+//! stripped after retrieval, so no `select` or projection can bring them back.
+
+use mongodb::bson::{Bson, Document};
+
+/// Removes every listed field from the document; dotted paths reach into
+/// embedded documents and into each document of an array.
+pub fn redact_fields(doc: &mut Document, fields: &[String]) {
+    for field in fields {
+        redact_path(doc, field);
+    }
+}
+
+fn redact_path(doc: &mut Document, path: &str) {
+    let Some((head, rest)) = path.split_once('.') else {
+        doc.remove(path);
+        return;
+    };
+    match doc.get_mut(head) {
+        Some(Bson::Document(embedded)) => redact_path(embedded, rest),
+        Some(Bson::Array(items)) => {
+            for item in items {
+                if let Bson::Document(embedded) = item {
+                    redact_path(embedded, rest);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Removes the listed fields from a document keyed by dotted paths, like the
+/// `updatedFields` of a change event: a key naming a redacted field (or a field inside it)
+/// goes, and a key holding a redacted field further down has it removed from its value.
+/// Array indices in the keys (`cards.0.number`) are skipped when matching.
+pub fn redact_dotted_keys(doc: &mut Document, fields: &[String]) {
+    let keys: Vec<String> = doc.keys().cloned().collect();
+    for key in keys {
+        let path = key
+            .split('.')
+            .filter(|segment| segment.is_empty() || !segment.bytes().all(|b| b.is_ascii_digit()))
+            .collect::<Vec<_>>()
+            .join(".");
+        for field in fields {
+            if path == *field || path.starts_with(&format!("{}.", field)) {
+                doc.remove(&key);
+                break;
+            }
+            if let Some(rest) = field.strip_prefix(&format!("{}.", path))
+                && let Some(value) = doc.get_mut(&key)
+            {
+                match value {
+                    Bson::Document(embedded) => redact_path(embedded, rest),
+                    Bson::Array(items) => {
+                        for item in items {
+                            if let Bson::Document(embedded) = item {
+                                redact_path(embedded, rest);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mongodb::bson::doc;
+
+    #[test]
+    fn test_redact_top_level_and_nested_fields() {
+        let mut document = doc! {
+            "_id": 1,
+            "password": "hunter2",
+            "profile": { "ssn": "123-45-6789", "name": "Ana" },
+            "cards": [{ "number": "4111", "brand": "visa" }, "not a document"],
+        };
+        let fields =
+            ["password", "profile.ssn", "cards.number", "missing.field"].map(str::to_string);
+        redact_fields(&mut document, &fields);
+
+        assert_eq!(
+            document,
+            doc! {
+                "_id": 1,
+                "profile": { "name": "Ana" },
+                "cards": [{ "brand": "visa" }, "not a document"],
+            }
+        );
+    }
+
+    #[test]
+    fn test_redact_dotted_keys() {
+        let mut updated_fields = doc! {
+            "profile.ssn": "123-45-6789",
+            "profile.name": "Ana",
+            "profile": { "ssn": "987-65-4321", "name": "Ana" },
+            "cards.0.number": "4111",
+            "cards.1": { "number": "5500", "brand": "visa" },
+            "password.hash": "abc",
+        };
+        let fields = ["password", "profile.ssn", "cards.number"].map(str::to_string);
+        redact_dotted_keys(&mut updated_fields, &fields);
+
+        assert_eq!(
+            updated_fields,
+            doc! {
+                "profile.name": "Ana",
+                "profile": { "name": "Ana" },
+                "cards.1": { "brand": "visa" },
+            }
+        );
+    }
+}
@@ -0,0 +1,101 @@
+//! Correlation ids tying a request's HTTP response, log lines and MongoDB operations together.
+//!
+//! A well-formed incoming `X-Request-ID` is kept, so ids from a proxy or the client carry
+//! through; otherwise a UUID is generated. The id is echoed in the response header, prefixes
+//! the request's log lines and is sent to MongoDB as the operations' `$comment`, where it
+//! shows up in the profiler and slow query logs.
+
+use actix_web::{
+    Error, HttpMessage, HttpRequest,
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::{HeaderName, HeaderValue},
+    middleware::Next,
+};
+use mongodb::bson::{Bson, Uuid};
+use std::collections::HashMap;
+
+use crate::query_param_parser::parse_comment;
+
+pub const REQUEST_ID_HEADER: &str = "X-Request-ID";
+
+/// Longest incoming id that is kept rather than replaced
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// The id of the request being handled, kept in its extensions
+#[derive(Debug, Clone)]
+struct RequestId(String);
+
+/// Keeps ids that are safe to echo and log, i.e. short and without spaces or control characters
+fn accept_request_id(value: &HeaderValue) -> Option<String> {
+    let id = value.to_str().ok()?;
+    let valid = !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id.chars().all(|c| c.is_ascii_graphic());
+    valid.then(|| id.to_string())
+}
+
+/// The id of this request, or `-` outside the middleware
+pub fn request_id(req: &HttpRequest) -> String {
+    req.extensions()
+        .get::<RequestId>()
+        .map_or_else(|| "-".to_string(), |id| id.0.clone())
+}
+
+/// The `$comment` for this request's operations: its id, followed by any `?comment=`
+pub fn operation_comment(req: &HttpRequest, query: &HashMap<String, String>) -> Bson {
+    let id = request_id(req);
+    match parse_comment(query) {
+        Some(comment) => Bson::String(format!("{}: {}", id, comment)),
+        None => Bson::String(id),
+    }
+}
+
+/// Middleware assigning the request id, echoing it and logging server errors under it
+pub async fn assign(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(accept_request_id)
+        .unwrap_or_else(|| Uuid::new().to_string());
+    req.extensions_mut().insert(RequestId(id.clone()));
+
+    let mut res = next.call(req).await?;
+    if res.status().is_server_error()
+        && let Some(error) = res.response().error()
+    {
+        println!("[{}] Error handling request: {:?}", id, error);
+    }
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        res.headers_mut()
+            .insert(HeaderName::from_static("x-request-id"), value);
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accept_request_id() {
+        let accept = |value: &str| accept_request_id(&HeaderValue::from_str(value).unwrap());
+        assert_eq!(accept("abc-123").as_deref(), Some("abc-123"));
+        assert_eq!(accept(""), None);
+        assert_eq!(accept("two words"), None);
+        assert_eq!(accept(&"x".repeat(MAX_REQUEST_ID_LEN + 1)), None);
+    }
+
+    #[test]
+    fn test_operation_comment() {
+        let req = actix_web::test::TestRequest::default().to_http_request();
+        req.extensions_mut().insert(RequestId("abc".to_string()));
+        let mut query = HashMap::new();
+        assert_eq!(operation_comment(&req, &query), Bson::from("abc"));
+        query.insert("comment".to_string(), "reports".to_string());
+        assert_eq!(operation_comment(&req, &query), Bson::from("abc: reports"));
+    }
+}
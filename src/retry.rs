@@ -0,0 +1,65 @@
+//! Exponential backoff for the startup steps that need MongoDB to be reachable.
+//!
+//! In container deployments the database often comes up a little after the API, so the
+//! connection string (which may need an SRV lookup) and the initial catalog are retried
+//! `STARTUP_RETRY_ATTEMPTS` times, waiting 0.5s, 1s, 2s, ... (capped at 30s) in between.
+
+use std::fmt::Display;
+use std::future::IntoFuture;
+use std::time::Duration;
+
+const INITIAL_DELAY: Duration = Duration::from_millis(500);
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// How long to wait after the given failed attempt (0-based)
+pub fn backoff_delay(attempt: u32) -> Duration {
+    INITIAL_DELAY
+        .checked_mul(2u32.saturating_pow(attempt))
+        .map_or(MAX_DELAY, |delay| delay.min(MAX_DELAY))
+}
+
+/// Runs `operation` until it succeeds or `attempts` tries have failed, returning the last error
+pub async fn with_backoff<T, E, F, Fut>(what: &str, attempts: u32, mut operation: F) -> Result<T, E>
+where
+    E: Display,
+    F: FnMut() -> Fut,
+    Fut: IntoFuture<Output = Result<T, E>>,
+{
+    let mut attempt = 0;
+    loop {
+        match operation().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt + 1 < attempts => {
+                let delay = backoff_delay(attempt);
+                eprintln!(
+                    "Failed to {} (attempt {}/{}): {}; retrying in {:?}",
+                    what,
+                    attempt + 1,
+                    attempts,
+                    e,
+                    delay
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_delay() {
+        let delays: Vec<u64> = (0..8)
+            .map(|attempt| backoff_delay(attempt).as_millis() as u64)
+            .collect();
+        assert_eq!(
+            delays,
+            vec![500, 1_000, 2_000, 4_000, 8_000, 16_000, 30_000, 30_000]
+        );
+        assert_eq!(backoff_delay(u32::MAX), MAX_DELAY);
+    }
+}
@@ -1,304 +1,3083 @@
-use actix_web::{HttpResponse, Responder, delete, get, patch, post, put, web};
+use actix_web::{
+    HttpMessage, HttpRequest, HttpResponse, Responder, ResponseError, delete, get, http::header,
+    patch, post, put, web,
+};
+use futures_util::StreamExt;
 use futures_util::TryStreamExt;
-use mongodb::bson::doc;
+use mongodb::bson::{Bson, doc};
+use mongodb::error::{ErrorKind, IndexedWriteError, InsertManyError};
+use mongodb::options::{
+    AggregateOptions, CountOptions, CursorType, DeleteOptions, FindOneAndUpdateOptions,
+    FullDocumentType, Hint, InsertManyOptions, InsertOneOptions, ReturnDocument, UpdateOptions,
+    WriteConcern,
+};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
 
-use crate::{query_param_parser::parse_find_options, shared::AppState};
-use mongor::parse_match_query_params;
+use crate::auth::{authorize_scope, require_authentication};
+use crate::byte_range::{ByteRange, parse_range};
+use crate::circuit_breaker::BreakerError;
+use crate::config::parse_write_concern;
+use crate::ejson::{
+    ExtJsonMode, NumberPolicy, bson_response, bson_to_extjson, extjson_to_document, serialize_bson,
+};
+use crate::error::AppError;
+use crate::field_case::{to_stored, to_stored_fields, to_stored_path};
+use crate::flatten::flatten_document;
+use crate::id_strategy::{IdStrategy, assign_ids};
+use crate::json_patch::{JSON_PATCH_CONTENT_TYPE, JsonPatchError};
+use crate::maintenance::MaintenanceMode;
+use crate::openapi_docs::mongo_validator_to_openapi_request_body;
+use crate::query_cache::CachedQuery;
+use crate::redaction::{redact_dotted_keys, redact_fields};
+use crate::request_id::{operation_comment, request_id};
+use crate::schema_inference::infer_schema;
+use crate::stats::CollectionStats;
+use crate::transaction::{
+    PreparedOperation, TransactionError, TransactionOperation, TransactionRequest,
+};
+use crate::{
+    query_param_parser::{
+        and_filters, apply_after_cursor, apply_ids, apply_time_range, check_regex, parse_collation,
+        parse_find_options, parse_id, parse_mask, parse_timeout, prefix_fields, split_ids,
+    },
+    shared::AppState,
+};
+use mongor::{
+    ParseOptions, UnknownParams, parse_match_query_pairs_with, parse_match_query_params_with,
+};
+
+/// Header pointing a single request at another, allowlisted, database
+const DATABASE_HEADER: &str = "X-Database";
 
 #[get("/")]
 async fn hello() -> impl Responder {
     HttpResponse::Ok().body("Hello world!")
 }
 
+/// A successful ping, for monitoring
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PingResponse {
+    ok: bool,
+    /// Round trip of the `ping` command to MongoDB, in milliseconds
+    latency_ms: f64,
+    /// The server's reply
+    #[schema(value_type = Object)]
+    ping: Value,
+}
+
+/// Ping the database. The answer is JSON with the round-trip latency, or the server's reply
+/// as text for `Accept: text/plain`.
+#[utoipa::path(
+    get,
+    path = "/api/ping",
+    tag = "system",
+    responses(
+        (status = 200, description = "Database ping successful", content(
+            (PingResponse = "application/json",
+                example = json!({ "ok": true, "latencyMs": 0.42, "ping": { "ok": 1.0 } })),
+            (String = "text/plain", example = json!("Pong! { \"ok\": 1 }"))
+        )),
+        (status = 500, description = "Database ping failed", body = String, content_type = "text/plain")
+    )
+)]
 #[get("/ping")]
-async fn ping(data: web::Data<AppState>) -> impl Responder {
+pub(crate) async fn ping(req: HttpRequest, data: web::Data<AppState>) -> impl Responder {
+    let database = match request_database(&req, &data) {
+        Ok(database) => database,
+        Err(e) => return e.error_response(),
+    };
     // Ping database and match on ping response
-    match data
-        .db_client
-        .database(&data.config.database_name)
-        .run_command(doc! {"ping": 1})
-        .await
-    {
-        Ok(doc) => HttpResponse::Ok().body(format!("Pong! {}", doc)),
+    let started = Instant::now();
+    let result = database.run_command(doc! {"ping": 1}).await;
+    let latency = started.elapsed();
+    let plain_text = req
+        .headers()
+        .get(actix_web::http::header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/plain"));
+    match result {
+        Ok(doc) if plain_text => HttpResponse::Ok().body(format!("Pong! {}", doc)),
+        Ok(doc) => HttpResponse::Ok().json(PingResponse {
+            ok: true,
+            latency_ms: latency.as_secs_f64() * 1000.0,
+            ping: bson_to_extjson(doc.into(), ExtJsonMode::Relaxed),
+        }),
         Err(e) => {
-            println!("Error pinging database: {:?}", e);
+            println!("[{}] Error pinging database: {:?}", request_id(&req), e);
             HttpResponse::InternalServerError().body("Error pinging database")
         }
     }
 }
 
+#[derive(Debug, Deserialize)]
+struct MaintenanceRequest {
+    mode: MaintenanceMode,
+}
+
+#[get("/maintenance")]
+async fn maintenance_status(data: web::Data<AppState>) -> HttpResponse {
+    HttpResponse::Ok().json(serde_json::json!({ "mode": data.maintenance.mode() }))
+}
+
+/// Switches maintenance mode, e.g. to `readonly` before a migration and back to `off` after
+#[post("/maintenance")]
+async fn set_maintenance(
+    req: HttpRequest,
+    payload: web::Json<MaintenanceRequest>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    require_authentication(&data)?;
+    let mode = payload.into_inner().mode;
+    println!(
+        "[{}] Switching maintenance mode to {:?}",
+        request_id(&req),
+        mode
+    );
+    data.maintenance.set_mode(mode);
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "mode": mode })))
+}
+
+#[get("/stats")]
+async fn stats(req: HttpRequest, data: web::Data<AppState>) -> Result<HttpResponse, AppError> {
+    let database = request_database(&req, &data)?;
+    let stats = if database.name() == data.config.database_name {
+        data.stats_cache.get(&data).await?
+    } else {
+        std::sync::Arc::new(crate::stats::fetch_database_stats(&data, &database).await?)
+    };
+
+    // Tenants only see their own collections, under their logical names
+    let tenant_prefix = tenant_prefix(&req, &data)?;
+    let collections: Vec<CollectionStats> = stats
+        .iter()
+        .filter_map(|collection| {
+            let name = collection.name.strip_prefix(tenant_prefix.as_str())?;
+            if !data.config.is_collection_exposed(name) {
+                return None;
+            }
+            Some(CollectionStats {
+                name: name.to_string(),
+                ..collection.clone()
+            })
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "collections": collections,
+        "totals": {
+            "count": collections.iter().map(|c| c.count).sum::<i64>(),
+            "size": collections.iter().map(|c| c.size).sum::<i64>(),
+            "storageSize": collections.iter().map(|c| c.storage_size).sum::<i64>(),
+            "totalIndexSize": collections.iter().map(|c| c.total_index_size).sum::<i64>(),
+        },
+    })))
+}
+
+#[delete("/collections/{coll_name}")]
+async fn drop_collection(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<std::collections::HashMap<String, String>>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    require_authentication(&data)?;
+    let coll_name = tenant_collection_name(&req, &data, path.into_inner())?;
+    let database = request_database(&req, &data)?;
+
+    // Dropping is irreversible, so it has to be asked for explicitly
+    if query.get("confirm").map(String::as_str) != Some("true") {
+        return Err(AppError::BadRequest(
+            "Dropping a collection requires the confirm=true query parameter".to_string(),
+        ));
+    }
+
+    ensure_collection_exists(&database, &coll_name, &data).await?;
+
+    data.circuit_breaker
+        .call(
+            database
+                .collection::<mongodb::bson::Document>(&coll_name)
+                .drop(),
+        )
+        .await?;
+    invalidate_cached_queries(&data, &database, &coll_name);
+
+    // Forget the collection right away instead of at the next periodic refresh
+    if let Err(e) = crate::catalog::refresh_catalog(&data).await {
+        println!(
+            "[{}] Error refreshing catalog after drop: {:?}",
+            request_id(&req),
+            e
+        );
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[post("/transaction")]
+async fn run_transaction(
+    req: HttpRequest,
+    payload: web::Json<TransactionRequest>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let database = request_database(&req, &data)?;
+
+    // Resolve and parse every operation before touching the database
+    let mut operations = Vec::new();
+    let mut collections = Vec::new();
+    for (i, operation) in payload.into_inner().operations.into_iter().enumerate() {
+        // The path does not name the collections, so their scopes are checked here
+        let scope = format!("write:{}", operation.collection());
+        authorize_scope(&req, &data, &scope)?;
+
+        let required = required_fields(&data, operation.collection());
+        let coll_name = tenant_collection_name(&req, &data, operation.collection().to_string())?;
+        ensure_collection_exists(&database, &coll_name, &data).await?;
+        collections.push(coll_name.clone());
+
+        let prepared = match operation {
+            TransactionOperation::Insert { document, .. } => {
+                let document = to_stored(document, data.config.field_case);
+                check_required_fields(required, &document)?;
+                check_schema(&data, &coll_name, &document, false)?;
+                extjson_to_document(document, data.config.number_policy).map(|document| {
+                    PreparedOperation::Insert {
+                        collection: coll_name,
+                        document,
+                    }
+                })
+            }
+            TransactionOperation::Update {
+                filter,
+                update,
+                confirm,
+                ..
+            } => {
+                let update = to_stored(update, data.config.field_case);
+                check_schema(&data, &coll_name, &update, true)?;
+                parse_match_query_params_with(&filter, parse_options(&data))
+                    .and_then(|filter| confirmed_filter(filter, confirm, "update"))
+                    .and_then(|filter| {
+                        extjson_to_document(update, data.config.number_policy).map(|update| {
+                            PreparedOperation::Update {
+                                collection: coll_name,
+                                filter,
+                                update,
+                            }
+                        })
+                    })
+            }
+            TransactionOperation::Delete {
+                filter, confirm, ..
+            } => parse_match_query_params_with(&filter, parse_options(&data))
+                .and_then(|filter| confirmed_filter(filter, confirm, "delete"))
+                .map(|filter| PreparedOperation::Delete {
+                    collection: coll_name,
+                    filter,
+                }),
+        };
+        let prepared = prepared
+            .map_err(|e| AppError::BadRequest(format!("Invalid operation {}: {}", i, e)))?;
+        operations.push(prepared);
+    }
+
+    let result = crate::transaction::run_transaction(
+        &data.db_client,
+        &data.circuit_breaker,
+        database.name(),
+        operations,
+        operation_comment(&req, &Default::default()),
+    )
+    .await;
+    if result.is_ok() {
+        for coll_name in &collections {
+            invalidate_cached_queries(&data, &database, coll_name);
+        }
+    }
+    match result {
+        Ok(results) => Ok(HttpResponse::Ok().json(bson_to_extjson(
+            doc! { "results": results }.into(),
+            ExtJsonMode::Relaxed,
+        ))),
+        Err(TransactionError::Open) => Err(AppError::Unavailable),
+        Err(TransactionError::Unsupported) => Err(AppError::BadRequest(
+            "Transactions require MongoDB to run as a replica set".to_string(),
+        )),
+        Err(TransactionError::Operation(i, e)) => {
+            // The failing operation is reported along with the rollback, not as a bare error
+            println!(
+                "[{}] Transaction operation {} failed: {:?}",
+                request_id(&req),
+                i,
+                e
+            );
+            Ok(HttpResponse::Conflict().json(serde_json::json!({
+                "failedOperation": i,
+                "error": e.to_string(),
+                "rolledBack": true,
+            })))
+        }
+        Err(TransactionError::Transaction(e)) => Err(AppError::Database(e)),
+    }
+}
+
 #[get("/{coll_name}")]
 async fn query_collection(
+    req: HttpRequest,
     path: web::Path<String>,
     query: web::Query<std::collections::HashMap<String, String>>,
     data: web::Data<AppState>,
-) -> impl Responder {
+) -> Result<HttpResponse, AppError> {
     let coll_name = path.into_inner();
+    let excluded = excluded_fields(&data, coll_name.as_str());
+    let redacted = redacted_fields(&data, coll_name.as_str());
+    let timestamp_field = timestamp_field(&data, coll_name.as_str());
+    let coll_name = tenant_collection_name(&req, &data, coll_name)?;
+    let database = request_database(&req, &data)?;
+
+    ensure_collection_exists(&database, &coll_name, &data).await?;
+
+    // Parse query parameters; `since`/`until` apply to the collection's timestamp field
+    let filter = parse_filter(&data, &req, &coll_name, &query)?;
+    let filter = apply_time_range_params(&query, timestamp_field, filter)?;
+    check_mask(&query)?;
+    check_hint(&database, &coll_name, &query, &data).await?;
+    let require = parse_require(&query)?;
 
-    if let Some(e) = get_exception_if_collection_absent(coll_name.as_str(), &data).await {
-        return e;
+    // Keyset pagination: `after` continues past the given `_id`, in `_id` order
+    let after = query.get("after");
+    let filter = match after {
+        Some(after) => apply_after_cursor(filter, after),
+        None => filter,
+    };
+    // Batch lookup: `ids` fetches several documents by `_id` in one query
+    let ids = query.get("ids").map(|ids| split_ids(ids));
+    let filter = match &ids {
+        Some(ids) => apply_ids(filter, ids),
+        None => filter,
+    };
+    let mut options = find_options(&query, excluded, &data);
+    options.comment = Some(operation_comment(&req, &query));
+    // `idsOnly=true` answers with a flat array of the matching `_id`s instead of documents
+    let ids_only = query.get("idsOnly").map(String::as_str) == Some("true");
+    if ids_only {
+        options.projection = Some(doc! { "_id": 1 });
     }
+    let limit = options.limit;
 
-    // Parse query parameters
-    let filter = match parse_match_query_params(&query) {
-        Ok(filter) => filter,
-        Err(e) => {
-            return HttpResponse::BadRequest().body(format!("Invalid query parameter: {}", e));
+    // `meta=true` wraps the results with the parsed filter and timing, for debugging queries
+    let meta_filter =
+        (query.get("meta").map(String::as_str) == Some("true")).then(|| filter.clone());
+    let started = std::time::Instant::now();
+
+    // Identical queries may be answered from the cache; `meta` timings never are
+    let namespace = format!("{}.{}", database.name(), coll_name);
+    let cache = data.query_cache.as_ref().filter(|_| meta_filter.is_none());
+    let cache_key = crate::query_cache::cache_key(&req);
+    if let Some(hit) = cache.and_then(|cache| cache.get(&namespace, &cache_key, started)) {
+        return Ok(query_response(&req, &data, &hit));
+    }
+    let generation = cache.map(|cache| cache.generation(&namespace));
+
+    // Execute the query; `withCount=true` also reports how many documents match in all
+    let (mut docs, total) = if query.get("withCount").map(String::as_str) == Some("true") {
+        let (docs, total) =
+            find_page_with_total(&data, &database, &coll_name, filter, options).await?;
+        (docs, Some(total))
+    } else {
+        let cursor = data
+            .circuit_breaker
+            .call(
+                database
+                    .collection::<mongodb::bson::Document>(&coll_name)
+                    .find(filter)
+                    .with_options(options),
+            )
+            .await?;
+        // Convert cursor to vector of documents
+        (
+            collect_documents(cursor, data.config.max_response_bytes).await?,
+            None,
+        )
+    };
+    for doc in &mut docs {
+        redact_fields(doc, redacted);
+    }
+    check_required_matches(require, docs.len(), &coll_name)?;
+    // A full page means there may be more, so point at its last document
+    let next_cursor = match docs.last() {
+        Some(last) if after.is_some() && Some(docs.len() as i64) == limit => {
+            last.get("_id").and_then(id_to_cursor)
         }
+        _ => None,
     };
-
-    // Execute the query
-    match data
-        .db_client
-        .database(&data.config.database_name)
-        .collection::<mongodb::bson::Document>(&coll_name)
-        .find(filter)
-        .with_options(parse_find_options(&query))
-        .await
-    {
-        Ok(cursor) => {
-            // Convert cursor to vector of documents
-            match cursor.try_collect::<Vec<mongodb::bson::Document>>().await {
-                Ok(docs) => HttpResponse::Ok().json(docs),
-                Err(e) => {
-                    println!("Error collecting documents: {:?}", e);
-                    HttpResponse::InternalServerError()
-                        .body(format!("Error collecting documents: {:?}", e))
-                }
+    // Report requested ids that matched no document
+    let missing_ids = ids
+        .map(|ids| {
+            let found: Vec<String> = docs
+                .iter()
+                .filter_map(|doc| doc.get("_id").and_then(id_to_cursor))
+                .collect();
+            ids.into_iter()
+                .filter(|id| !found.iter().any(|found| found == id))
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+        .filter(|ids| !ids.is_empty());
+    // `flatten=true` turns nested keys into dotted paths for tabular consumers
+    if query.get("flatten").map(String::as_str) == Some("true") {
+        docs = docs.into_iter().map(flatten_document).collect();
+    }
+    let items: Vec<Bson> = match ids_only {
+        true => docs
+            .into_iter()
+            .filter_map(|mut doc| doc.remove("_id"))
+            .collect(),
+        false => docs.into_iter().map(Bson::Document).collect(),
+    };
+    let value = match (meta_filter, total) {
+        (None, None) => Bson::Array(items),
+        (meta_filter, total) => {
+            let count = items.len() as i64;
+            let mut body = doc! { "data": items };
+            if let Some(total) = total {
+                body.insert("total", total as i64);
             }
+            if let Some(filter) = meta_filter {
+                body.insert(
+                    "meta",
+                    doc! {
+                        "took_ms": started.elapsed().as_millis() as i64,
+                        "filter": filter,
+                        "count": count,
+                    },
+                );
+            }
+            body.into()
         }
-        Err(e) => {
-            println!("Error executing query: {:?}", e);
-            HttpResponse::InternalServerError().body(format!("Error executing query: {:?}", e))
-        }
+    };
+
+    let (content_type, body) = serialize_bson(&req, value);
+    let result = std::sync::Arc::new(CachedQuery {
+        content_type,
+        body,
+        missing_ids,
+        next_cursor,
+    });
+    if let (Some(cache), Some(generation)) = (cache, generation) {
+        cache.insert(
+            &namespace,
+            &cache_key,
+            std::sync::Arc::clone(&result),
+            generation,
+            std::time::Instant::now(),
+        );
     }
+    Ok(query_response(&req, &data, &result))
 }
 
-#[post("/{coll_name}")]
-async fn insert_document(
+/// Finds documents with a MongoDB filter document as the body, for filters the query-string
+/// grammar cannot express. The query string still takes `limit`, `skip`, `sort`, `select` and
+/// the other find options, and any query-string filter has to match as well. Operators that
+/// run JavaScript are rejected.
+#[post("/{coll_name}/find")]
+async fn find_with_body(
+    req: HttpRequest,
     path: web::Path<String>,
+    query: web::Query<std::collections::HashMap<String, String>>,
     payload: web::Json<Value>,
     data: web::Data<AppState>,
-) -> impl Responder {
+) -> Result<HttpResponse, AppError> {
     let coll_name = path.into_inner();
+    let excluded = excluded_fields(&data, coll_name.as_str());
+    let redacted = redacted_fields(&data, coll_name.as_str());
+    let coll_name = tenant_collection_name(&req, &data, coll_name)?;
+    let database = request_database(&req, &data)?;
+
+    ensure_collection_exists(&database, &coll_name, &data).await?;
+
+    let body_filter = parse_body_filter(&data, payload.into_inner())?;
+    let filter = with_body_filter(
+        parse_filter(&data, &req, &coll_name, &query)?,
+        Some(body_filter),
+    );
+    check_mask(&query)?;
+    check_hint(&database, &coll_name, &query, &data).await?;
 
-    if let Some(e) = get_exception_if_collection_absent(coll_name.as_str(), &data).await {
-        return e;
+    let mut options = find_options(&query, excluded, &data);
+    options.comment = Some(operation_comment(&req, &query));
+    let cursor = data
+        .circuit_breaker
+        .call(
+            database
+                .collection::<mongodb::bson::Document>(&coll_name)
+                .find(filter)
+                .with_options(options),
+        )
+        .await?;
+
+    let mut docs = collect_documents(cursor, data.config.max_response_bytes).await?;
+    for doc in &mut docs {
+        redact_fields(doc, redacted);
     }
+    Ok(bson_response(
+        &req,
+        Bson::Array(docs.into_iter().map(Bson::Document).collect()),
+    ))
+}
 
-    // Convert the JSON payload to a MongoDB document
-    let document = match mongodb::bson::to_document(&payload) {
-        Ok(doc) => doc,
-        Err(e) => {
-            return HttpResponse::BadRequest().body(format!("Invalid document format: {}", e));
-        }
+/// Runs a find as a single `$facet` aggregation returning the page along with the total number
+/// of matches, so both come from the same snapshot rather than from a find and a racing count
+async fn find_page_with_total(
+    data: &web::Data<AppState>,
+    database: &mongodb::Database,
+    collection_name: &str,
+    filter: mongodb::bson::Document,
+    options: mongodb::options::FindOptions,
+) -> Result<(Vec<mongodb::bson::Document>, u64), AppError> {
+    let mut page = Vec::new();
+    if let Some(sort) = options.sort {
+        page.push(doc! { "$sort": sort });
+    }
+    if let Some(skip) = options.skip.filter(|skip| *skip > 0) {
+        page.push(doc! { "$skip": skip as i64 });
+    }
+    // As in a find, a limit of 0 means none and a negative one counts like a positive one
+    if let Some(limit) = options.limit.map(i64::abs).filter(|limit| *limit > 0) {
+        page.push(doc! { "$limit": limit });
+    }
+    if let Some(projection) = options.projection {
+        page.push(doc! { "$project": projection });
+    }
+    let pipeline = vec![
+        doc! { "$match": filter },
+        doc! { "$facet": { "data": page, "total": [{ "$count": "count" }] } },
+    ];
+    let cursor = data
+        .circuit_breaker
+        .call(
+            database
+                .collection::<mongodb::bson::Document>(collection_name)
+                .aggregate(pipeline)
+                .with_options(
+                    AggregateOptions::builder()
+                        .max_time(options.max_time)
+                        .comment(options.comment)
+                        .collation(options.collation)
+                        .hint(options.hint)
+                        .build(),
+                ),
+        )
+        .await?;
+
+    // `$facet` always yields exactly one document; `total` is empty when nothing matched
+    let mut result = collect_documents(cursor, data.config.max_response_bytes)
+        .await?
+        .pop()
+        .unwrap_or_default();
+    let docs = match result.remove("data") {
+        Some(Bson::Array(docs)) => docs
+            .into_iter()
+            .filter_map(|doc| match doc {
+                Bson::Document(doc) => Some(doc),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
     };
+    let total = result
+        .get_array("total")
+        .ok()
+        .and_then(|total| total.first()?.as_document()?.get("count").cloned())
+        .map_or(0, |count| match count {
+            Bson::Int32(count) => count as u64,
+            Bson::Int64(count) => count as u64,
+            _ => 0,
+        });
+    Ok((docs, total))
+}
 
-    // Insert the document
-    match data
-        .db_client
-        .database(&data.config.database_name)
-        .collection::<mongodb::bson::Document>(&coll_name)
-        .insert_one(document)
-        .await
-    {
-        Ok(result) => HttpResponse::Created().json(result.inserted_id),
-        Err(e) => {
-            println!("Error inserting document: {:?}", e);
-            HttpResponse::InternalServerError().body(format!("Error inserting document: {:?}", e))
+/// Sends a GET result with its caching and pagination headers. Polling clients
+/// revalidate with If-None-Match and get a bodiless 304 when nothing changed.
+fn query_response(
+    req: &HttpRequest,
+    data: &web::Data<AppState>,
+    result: &CachedQuery,
+) -> HttpResponse {
+    let etag = crate::caching::etag(&result.body);
+    let mut response = if crate::caching::is_not_modified(req, &etag) {
+        HttpResponse::NotModified().finish()
+    } else {
+        HttpResponse::Ok()
+            .content_type(result.content_type)
+            .body(result.body.clone())
+    };
+    let headers = response.headers_mut();
+    for (name, value) in [
+        (header::ETAG, Some(etag)),
+        (
+            header::CACHE_CONTROL,
+            Some(crate::caching::cache_control(&data.config)),
+        ),
+        (header::VARY, Some("Accept".to_string())),
+        (
+            header::HeaderName::from_static("x-missing-ids"),
+            result.missing_ids.clone(),
+        ),
+        (
+            header::HeaderName::from_static("x-next-cursor"),
+            result.next_cursor.clone(),
+        ),
+    ] {
+        if let Some(value) = value.and_then(|value| value.parse().ok()) {
+            headers.insert(name, value);
         }
     }
+    response
 }
 
-#[put("/{coll_name}")]
-async fn update_document(
+/// Forgets the cached GET results of a collection after a write to it
+fn invalidate_cached_queries(
+    data: &web::Data<AppState>,
+    database: &mongodb::Database,
+    collection_name: &str,
+) {
+    if let Some(cache) = &data.query_cache {
+        cache.invalidate(&format!("{}.{}", database.name(), collection_name));
+    }
+}
+
+#[get("/{coll_name}/indexes")]
+async fn list_indexes(
+    req: HttpRequest,
+    path: web::Path<String>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let coll_name = tenant_collection_name(&req, &data, path.into_inner())?;
+    let database = request_database(&req, &data)?;
+
+    ensure_collection_exists(&database, &coll_name, &data).await?;
+
+    let collection = database.collection::<mongodb::bson::Document>(&coll_name);
+
+    let indexes = data
+        .circuit_breaker
+        .call(crate::catalog::fetch_indexes(&collection))
+        .await?;
+    Ok(HttpResponse::Ok().json(indexes))
+}
+
+#[get("/{coll_name}/explain")]
+async fn explain_query(
+    req: HttpRequest,
     path: web::Path<String>,
     query: web::Query<std::collections::HashMap<String, String>>,
-    payload: web::Json<Value>,
     data: web::Data<AppState>,
-) -> impl Responder {
+) -> Result<HttpResponse, AppError> {
     let coll_name = path.into_inner();
+    let excluded = excluded_fields(&data, coll_name.as_str());
+    let timestamp_field = timestamp_field(&data, coll_name.as_str());
+    let coll_name = tenant_collection_name(&req, &data, coll_name)?;
+    let database = request_database(&req, &data)?;
 
-    if let Some(e) = get_exception_if_collection_absent(coll_name.as_str(), &data).await {
-        return e;
-    }
+    ensure_collection_exists(&database, &coll_name, &data).await?;
 
-    // Parse query parameters for filter
-    let filter = match parse_match_query_params(&query) {
-        Ok(filter) => filter,
-        Err(e) => {
-            return HttpResponse::BadRequest().body(format!("Invalid query parameter: {}", e));
-        }
+    // Parse query parameters; `since`/`until` apply to the collection's timestamp field
+    let filter = parse_filter(&data, &req, &coll_name, &query)?;
+    let filter = apply_time_range_params(&query, timestamp_field, filter)?;
+    check_mask(&query)?;
+    check_hint(&database, &coll_name, &query, &data).await?;
+
+    let filter = match query.get("after") {
+        Some(after) => apply_after_cursor(filter, after),
+        None => filter,
     };
 
-    // Convert the JSON payload to a MongoDB document
-    let update_doc = match mongodb::bson::to_document(&payload) {
-        Ok(doc) => doc,
-        Err(e) => {
-            return HttpResponse::BadRequest().body(format!("Invalid document format: {}", e));
+    // Wrap the same find the GET endpoint would run in an explain command
+    let mut options = find_options(&query, excluded, &data);
+    options.comment = Some(operation_comment(&req, &query));
+    let mut find_command = doc! { "find": coll_name.as_str(), "filter": filter };
+    if let Some(projection) = options.projection {
+        find_command.insert("projection", projection);
+    }
+    if let Some(sort) = options.sort {
+        find_command.insert("sort", sort);
+    }
+    if let Some(limit) = options.limit {
+        find_command.insert("limit", limit);
+    }
+    if let Some(skip) = options.skip {
+        find_command.insert("skip", skip as i64);
+    }
+    if let Some(max_time) = options.max_time {
+        find_command.insert("maxTimeMS", max_time.as_millis() as i64);
+    }
+    find_command.insert("comment", options.comment);
+    if let Some(collation) = options.collation {
+        let collation = mongodb::bson::to_bson(&collation)
+            .map_err(|e| AppError::Internal(format!("Invalid collation: {}", e)))?;
+        find_command.insert("collation", collation);
+    }
+    if let Some(Hint::Name(name)) = options.hint {
+        find_command.insert("hint", name);
+    }
+
+    let plan = data
+        .circuit_breaker
+        .call(database.run_command(doc! { "explain": find_command, "verbosity": "queryPlanner" }))
+        .await?;
+    Ok(HttpResponse::Ok().json(bson_to_extjson(plan.into(), ExtJsonMode::Relaxed)))
+}
+
+/// Counts the matching documents with `countDocuments`, which the server answers from an index
+/// alone when one covers the filtered fields, so indexing them keeps counts from reading
+/// documents. `estimate=true` reads the collection metadata instead, for unfiltered counts.
+#[get("/{coll_name}/count")]
+async fn count_documents(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<std::collections::HashMap<String, String>>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let coll_name = path.into_inner();
+    let timestamp_field = timestamp_field(&data, coll_name.as_str());
+    let coll_name = tenant_collection_name(&req, &data, coll_name)?;
+    let database = request_database(&req, &data)?;
+
+    ensure_collection_exists(&database, &coll_name, &data).await?;
+
+    let mut query = query.into_inner();
+    let estimate = query.remove("estimate").as_deref() == Some("true");
+    let filter = parse_filter(&data, &req, &coll_name, &query)?;
+    let filter = apply_time_range_params(&query, timestamp_field, filter)?;
+    let max_time = parse_timeout(&query, max_timeout(&data));
+    let comment = operation_comment(&req, &query);
+    let collection = database.collection::<mongodb::bson::Document>(&coll_name);
+
+    let method = count_method(estimate, &filter)?;
+    let count = match method {
+        CountMethod::Metadata => {
+            data.circuit_breaker
+                .call(
+                    collection
+                        .estimated_document_count()
+                        .max_time(max_time)
+                        .comment(comment),
+                )
+                .await?
+        }
+        CountMethod::Scan => {
+            data.circuit_breaker
+                .call(
+                    collection.count_documents(filter).with_options(
+                        CountOptions::builder()
+                            .max_time(max_time)
+                            .comment(comment)
+                            .collation(parse_collation(&query))
+                            .build(),
+                    ),
+                )
+                .await?
         }
     };
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "count": count,
+        "estimate": method == CountMethod::Metadata,
+    })))
+}
 
-    // Create the update document with $set operator
-    let update = doc! { "$set": update_doc };
+/// How the count endpoint counts
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CountMethod {
+    /// `estimatedDocumentCount`, read from the collection metadata without touching documents
+    Metadata,
+    /// `countDocuments`, which visits every match (in an index when one covers the filter)
+    Scan,
+}
 
-    // Update a single document with upsert
-    match data
-        .db_client
-        .database(&data.config.database_name)
-        .collection::<mongodb::bson::Document>(&coll_name)
-        .update_one(filter, update)
-        .upsert(true)
-        .await
+/// Picks the count method. Counts are exact unless an estimate is asked for, as the metadata
+/// can be off after an unclean shutdown and counts orphaned documents on sharded clusters;
+/// estimates read only the metadata and so cannot take a filter.
+fn count_method(estimate: bool, filter: &mongodb::bson::Document) -> Result<CountMethod, AppError> {
+    match (estimate, filter.is_empty()) {
+        (true, true) => Ok(CountMethod::Metadata),
+        (false, _) => Ok(CountMethod::Scan),
+        (true, false) => Err(AppError::BadRequest(
+            "estimate=true cannot be combined with a filter".to_string(),
+        )),
+    }
+}
+
+/// The distinct values of `field` among the matching documents. Arrays contribute their
+/// elements rather than themselves, and dotted paths reach into embedded documents.
+#[get("/{coll_name}/distinct")]
+async fn distinct_values(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<std::collections::HashMap<String, String>>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let coll_name = path.into_inner();
+    let redacted = redacted_fields(&data, coll_name.as_str());
+    let timestamp_field = timestamp_field(&data, coll_name.as_str());
+    let coll_name = tenant_collection_name(&req, &data, coll_name)?;
+    let database = request_database(&req, &data)?;
+
+    ensure_collection_exists(&database, &coll_name, &data).await?;
+
+    let mut query = query.into_inner();
+    let field = query
+        .remove("field")
+        .filter(|field| !field.is_empty())
+        .ok_or_else(|| AppError::BadRequest("Missing the field query parameter".to_string()))?;
+    if redacted
+        .iter()
+        .any(|path| field == *path || field.starts_with(&format!("{}.", path)))
     {
-        Ok(result) => {
-            // Return 201 Created if a new document was inserted, otherwise 200 OK
-            if result.upserted_id.is_some() {
-                HttpResponse::Created().json(result)
-            } else {
-                HttpResponse::Ok().json(result)
-            }
-        }
-        Err(e) => {
-            println!("Error updating document: {:?}", e);
-            HttpResponse::InternalServerError().body(format!("Error updating document: {:?}", e))
+        return Err(AppError::Forbidden(format!("Field {} is redacted", field)));
+    }
+    let filter = parse_filter(&data, &req, &coll_name, &query)?;
+    let filter = apply_time_range_params(&query, timestamp_field, filter)?;
+
+    let mut values = data
+        .circuit_breaker
+        .call(
+            database
+                .collection::<mongodb::bson::Document>(&coll_name)
+                .distinct(field.as_str(), filter)
+                .max_time(parse_timeout(&query, max_timeout(&data)))
+                .comment(operation_comment(&req, &query)),
+        )
+        .await?;
+
+    // Embedded documents lose the redacted fields below them
+    let prefix = format!("{}.", field);
+    let nested: Vec<String> = redacted
+        .iter()
+        .filter_map(|path| path.strip_prefix(prefix.as_str()))
+        .map(str::to_string)
+        .collect();
+    for value in &mut values {
+        if let Bson::Document(document) = value {
+            redact_fields(document, &nested);
         }
     }
+    Ok(bson_response(&req, doc! { "values": values }.into()))
 }
 
-#[patch("/{coll_name}")]
-async fn patch_document(
+/// A best-effort schema inferred from a `$sample` of the matching documents, for collections
+/// whose validator says nothing: per field path, the types seen and how often it is present.
+/// Redacted fields are left out.
+#[get("/{coll_name}/schema")]
+async fn infer_collection_schema(
+    req: HttpRequest,
     path: web::Path<String>,
     query: web::Query<std::collections::HashMap<String, String>>,
-    payload: web::Json<Value>,
     data: web::Data<AppState>,
-) -> impl Responder {
-    // PATCH is the same as PUT in this implementation
-    // We need to reimplement the logic here since we can't call the handler directly
+) -> Result<HttpResponse, AppError> {
+    let coll_name = path.into_inner();
+    let redacted = redacted_fields(&data, coll_name.as_str());
+    let timestamp_field = timestamp_field(&data, coll_name.as_str());
+    let coll_name = tenant_collection_name(&req, &data, coll_name)?;
+    let database = request_database(&req, &data)?;
+
+    ensure_collection_exists(&database, &coll_name, &data).await?;
+
+    let filter = parse_filter(&data, &req, &coll_name, &query)?;
+    let filter = apply_time_range_params(&query, timestamp_field, filter)?;
+    let options = AggregateOptions::builder()
+        .max_time(parse_timeout(&query, max_timeout(&data)))
+        .comment(operation_comment(&req, &query))
+        .build();
+
+    let mut docs = sample_documents(
+        &data,
+        &database,
+        &coll_name,
+        filter,
+        data.config.schema_sample_size,
+        None,
+        options,
+    )
+    .await?;
+    for doc in &mut docs {
+        redact_fields(doc, redacted);
+    }
+    Ok(HttpResponse::Ok().json(infer_schema(&docs)))
+}
+
+/// `size` random documents among the matching ones (10 by default, at most
+/// `MAX_SAMPLE_SIZE`), for previews and test data. Fewer come back when fewer match.
+#[get("/{coll_name}/sample")]
+async fn sample_collection(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<std::collections::HashMap<String, String>>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
     let coll_name = path.into_inner();
+    let excluded = excluded_fields(&data, coll_name.as_str());
+    let redacted = redacted_fields(&data, coll_name.as_str());
+    let timestamp_field = timestamp_field(&data, coll_name.as_str());
+    let coll_name = tenant_collection_name(&req, &data, coll_name)?;
+    let database = request_database(&req, &data)?;
+
+    ensure_collection_exists(&database, &coll_name, &data).await?;
 
-    if let Some(e) = get_exception_if_collection_absent(coll_name.as_str(), &data).await {
-        return e;
+    let mut query = query.into_inner();
+    let size = sample_size(query.remove("size").as_deref(), data.config.max_sample_size)?;
+    let filter = parse_filter(&data, &req, &coll_name, &query)?;
+    let filter = apply_time_range_params(&query, timestamp_field, filter)?;
+    check_mask(&query)?;
+    let options = find_options(&query, excluded, &data);
+
+    let mut docs = sample_documents(
+        &data,
+        &database,
+        &coll_name,
+        filter,
+        size,
+        options.projection,
+        AggregateOptions::builder()
+            .max_time(options.max_time)
+            .comment(operation_comment(&req, &query))
+            .collation(options.collation)
+            .build(),
+    )
+    .await?;
+    for doc in &mut docs {
+        redact_fields(doc, redacted);
     }
+    Ok(bson_response(
+        &req,
+        Bson::Array(docs.into_iter().map(Bson::Document).collect()),
+    ))
+}
 
-    // Parse query parameters for filter
-    let filter = match parse_match_query_params(&query) {
-        Ok(filter) => filter,
-        Err(e) => {
-            return HttpResponse::BadRequest().body(format!("Invalid query parameter: {}", e));
-        }
+/// Documents a sample holds when the request does not pick a `size`
+const DEFAULT_SAMPLE_SIZE: u32 = 10;
+
+/// The `size` of a sample, capped at `max`
+fn sample_size(size: Option<&str>, max: u32) -> Result<u32, AppError> {
+    let size = match size {
+        None => DEFAULT_SAMPLE_SIZE,
+        Some(size) => size
+            .parse::<u32>()
+            .ok()
+            .filter(|size| *size > 0)
+            .ok_or_else(|| {
+                AppError::BadRequest(format!("size must be a positive integer, got {}", size))
+            })?,
     };
+    Ok(size.min(max))
+}
 
-    // Convert the JSON payload to a MongoDB document
-    let update_doc = match mongodb::bson::to_document(&payload) {
-        Ok(doc) => doc,
-        Err(e) => {
-            return HttpResponse::BadRequest().body(format!("Invalid document format: {}", e));
+/// `size` random documents among those matching `filter`, through a `$sample` stage
+async fn sample_documents(
+    data: &web::Data<AppState>,
+    database: &mongodb::Database,
+    collection_name: &str,
+    filter: mongodb::bson::Document,
+    size: u32,
+    projection: Option<mongodb::bson::Document>,
+    options: AggregateOptions,
+) -> Result<Vec<mongodb::bson::Document>, AppError> {
+    let mut pipeline = Vec::new();
+    if !filter.is_empty() {
+        pipeline.push(doc! { "$match": filter });
+    }
+    pipeline.push(doc! { "$sample": { "size": size } });
+    if let Some(projection) = projection {
+        pipeline.push(doc! { "$project": projection });
+    }
+
+    let cursor = data
+        .circuit_breaker
+        .call(
+            database
+                .collection::<mongodb::bson::Document>(collection_name)
+                .aggregate(pipeline)
+                .with_options(options),
+        )
+        .await?;
+    collect_documents(cursor, data.config.max_response_bytes).await
+}
+
+/// Downloads a single binData field as raw bytes, honoring `Range` so large files can resume
+#[get("/{coll_name}/{id}/field/{field}")]
+async fn download_field(
+    req: HttpRequest,
+    path: web::Path<(String, String, String)>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let (coll_name, id, field) = path.into_inner();
+    let redacted = redacted_fields(&data, coll_name.as_str());
+    let coll_name = tenant_collection_name(&req, &data, coll_name)?;
+    let database = request_database(&req, &data)?;
+
+    ensure_collection_exists(&database, &coll_name, &data).await?;
+
+    let mut document = data
+        .circuit_breaker
+        .call(
+            database
+                .collection::<mongodb::bson::Document>(&coll_name)
+                .find_one(doc! { "_id": parse_id(&id) })
+                .projection(doc! { field.as_str(): 1 })
+                .comment(operation_comment(&req, &Default::default())),
+        )
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Document {} not found", id)))?;
+    // Redacted fields are never served, not even as bytes
+    redact_fields(&mut document, redacted);
+
+    let bytes = match lookup_path(&document, &field) {
+        Some(Bson::Binary(binary)) => &binary.bytes,
+        Some(_) => {
+            return Err(AppError::Unprocessable(format!(
+                "Field {} is not binary data",
+                field
+            )));
+        }
+        None => {
+            return Err(AppError::NotFound(format!(
+                "Field {} not found in document {}",
+                field, id
+            )));
         }
     };
+    let len = bytes.len() as u64;
+    let range = req
+        .headers()
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok());
+    Ok(match parse_range(range, len) {
+        ByteRange::Full => HttpResponse::Ok()
+            .insert_header((header::ACCEPT_RANGES, "bytes"))
+            .content_type("application/octet-stream")
+            .body(bytes.clone()),
+        ByteRange::Partial { start, end } => HttpResponse::PartialContent()
+            .insert_header((header::ACCEPT_RANGES, "bytes"))
+            .insert_header((
+                header::CONTENT_RANGE,
+                format!("bytes {}-{}/{}", start, end, len),
+            ))
+            .content_type("application/octet-stream")
+            .body(bytes[start as usize..=end as usize].to_vec()),
+        ByteRange::NotSatisfiable => HttpResponse::RangeNotSatisfiable()
+            .insert_header((header::CONTENT_RANGE, format!("bytes */{}", len)))
+            .finish(),
+    })
+}
 
-    // Create the update document with $set operator
-    let update = doc! { "$set": update_doc };
+/// Query parameters describing the join, kept out of the base collection's filter
+const JOIN_PARAMS: &[&str] = &["foreign", "localField", "foreignField", "as"];
 
-    // Update the document(s)
-    match data
-        .db_client
-        .database(&data.config.database_name)
-        .collection::<mongodb::bson::Document>(&coll_name)
-        .update_many(filter, update)
-        .await
-    {
-        Ok(result) => HttpResponse::Ok().json(result),
-        Err(e) => {
-            println!("Error updating document: {:?}", e);
-            HttpResponse::InternalServerError().body(format!("Error updating document: {:?}", e))
+#[get("/{coll_name}/join")]
+async fn join_collection(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<std::collections::HashMap<String, String>>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let required = |name: &str| {
+        query
+            .get(name)
+            .filter(|value| !value.is_empty())
+            .cloned()
+            .ok_or_else(|| AppError::BadRequest(format!("Missing {} query parameter", name)))
+    };
+    let foreign = required("foreign")?;
+    let case = data.config.field_case;
+    let local_field = to_stored_path(&required("localField")?, case);
+    let foreign_field = to_stored_path(&required("foreignField")?, case);
+    let as_field = to_stored_path(query.get("as").unwrap_or(&foreign), case);
+    if as_field.is_empty() || as_field.starts_with('$') {
+        return Err(AppError::BadRequest(format!(
+            "Invalid as field: {}",
+            as_field
+        )));
+    }
+
+    // The path only names the base collection, so reading the joined one is checked here
+    authorize_scope(&req, &data, &format!("read:{}", foreign))?;
+
+    let coll_name = path.into_inner();
+    let excluded = excluded_fields(&data, coll_name.as_str());
+    // Joined documents are redacted like the foreign collection's own responses
+    let redacted: Vec<String> = redacted_fields(&data, coll_name.as_str())
+        .iter()
+        .cloned()
+        .chain(
+            redacted_fields(&data, foreign.as_str())
+                .iter()
+                .map(|field| format!("{}.{}", as_field, field)),
+        )
+        .collect();
+    let coll_name = tenant_collection_name(&req, &data, coll_name)?;
+    let foreign = tenant_collection_name(&req, &data, foreign)?;
+    let database = request_database(&req, &data)?;
+
+    ensure_collection_exists(&database, &coll_name, &data).await?;
+    // Hidden collections cannot be joined in, and look missing like everywhere else
+    ensure_collection_exists(&database, &foreign, &data).await?;
+
+    // The usual filter, sort, skip and limit select the base documents
+    let mut base_query = query.into_inner();
+    base_query.retain(|key, _| !JOIN_PARAMS.contains(&key.as_str()));
+    let filter = parse_filter(&data, &req, &coll_name, &base_query)?;
+    check_mask(&base_query)?;
+    let options = find_options(&base_query, excluded, &data);
+
+    let mut pipeline = vec![doc! { "$match": filter }];
+    if let Some(sort) = options.sort {
+        pipeline.push(doc! { "$sort": sort });
+    }
+    if let Some(skip) = options.skip {
+        pipeline.push(doc! { "$skip": skip as i64 });
+    }
+    if let Some(limit) = options.limit {
+        pipeline.push(doc! { "$limit": limit });
+    }
+    pipeline.push(doc! {
+        "$lookup": {
+            "from": foreign.as_str(),
+            "localField": local_field.as_str(),
+            "foreignField": foreign_field.as_str(),
+            "as": as_field.as_str(),
         }
+    });
+    // Selected fields always keep the joined array
+    if let Some(mut projection) = options.projection {
+        if projection.values().any(|value| value == &Bson::Int32(1)) {
+            projection.insert(as_field.as_str(), 1);
+        }
+        pipeline.push(doc! { "$project": projection });
+    }
+
+    let cursor = data
+        .circuit_breaker
+        .call(
+            database
+                .collection::<mongodb::bson::Document>(&coll_name)
+                .aggregate(pipeline)
+                .with_options(
+                    AggregateOptions::builder()
+                        .max_time(options.max_time)
+                        .comment(operation_comment(&req, &base_query))
+                        .collation(options.collation)
+                        .build(),
+                ),
+        )
+        .await?;
+
+    let mut docs = collect_documents(cursor, data.config.max_response_bytes).await?;
+    for doc in &mut docs {
+        redact_fields(doc, &redacted);
     }
+    Ok(bson_response(
+        &req,
+        Bson::Array(docs.into_iter().map(Bson::Document).collect()),
+    ))
 }
 
-#[delete("/{coll_name}")]
-async fn delete_document(
+#[get("/{coll_name}/watch")]
+async fn watch_collection(
+    req: HttpRequest,
+    body: web::Payload,
     path: web::Path<String>,
     query: web::Query<std::collections::HashMap<String, String>>,
     data: web::Data<AppState>,
-) -> impl Responder {
+) -> Result<HttpResponse, AppError> {
     let coll_name = path.into_inner();
+    // Changed documents and updated fields are redacted like any other response
+    let redacted = redacted_fields(&data, coll_name.as_str()).to_vec();
+    let coll_name = tenant_collection_name(&req, &data, coll_name)?;
+    let database = request_database(&req, &data)?;
 
-    if let Some(e) = get_exception_if_collection_absent(coll_name.as_str(), &data).await {
-        return e;
-    }
+    ensure_collection_exists(&database, &coll_name, &data).await?;
 
-    // Parse query parameters for filter
-    let filter = match parse_match_query_params(&query) {
-        Ok(filter) => filter,
-        Err(e) => {
-            return HttpResponse::BadRequest().body(format!("Invalid query parameter: {}", e));
-        }
-    };
+    // Query params filter the changed document; `operationType` filters the event kind
+    let filter = prefix_fields(
+        parse_filter(&data, &req, &coll_name, &query)?,
+        "fullDocument",
+        &["operationType"],
+    );
 
-    // Delete the document(s)
-    match data
-        .db_client
-        .database(&data.config.database_name)
-        .collection::<mongodb::bson::Document>(&coll_name)
-        .delete_many(filter)
+    // Open the change stream before upgrading so failures can still be reported over HTTP
+    let mut change_stream = match data
+        .circuit_breaker
+        .call(
+            database
+                .collection::<mongodb::bson::Document>(&coll_name)
+                .watch()
+                .pipeline([doc! { "$match": filter }])
+                .full_document(FullDocumentType::UpdateLookup),
+        )
         .await
     {
-        Ok(result) => HttpResponse::Ok().json(result),
-        Err(e) => {
-            println!("Error deleting document: {:?}", e);
-            HttpResponse::InternalServerError().body(format!("Error deleting document: {:?}", e))
+        Ok(change_stream) => change_stream,
+        Err(BreakerError::Inner(e)) if is_replica_set_required_error(&e) => {
+            return Err(AppError::BadRequest(
+                "Change streams require MongoDB to run as a replica set".to_string(),
+            ));
         }
-    }
-}
+        Err(e) => return Err(e.into()),
+    };
 
-pub fn configure(cfg: &mut web::ServiceConfig) {
-    cfg.service(web::scope("/api").service(hello).service(ping))
-        .service(query_collection)
-        .service(insert_document)
-        .service(update_document)
-        .service(patch_document)
-        .service(delete_document);
-}
+    let request_id = request_id(&req);
+    let (response, mut session, mut messages) = actix_ws::handle(&req, body)
+        .map_err(|e| AppError::BadRequest(format!("WebSocket upgrade failed: {}", e)))?;
 
-async fn get_exception_if_collection_absent(
-    collection_name: &str,
-    data: &web::Data<AppState>,
-) -> Option<HttpResponse> {
-    match crate::catalog::get_cached_collections(&data) {
-        Some(catalog) => match catalog
-            .collection_specs
-            .iter()
-            .find(|c| c.name == collection_name)
-        {
-            Some(_) => None,
-            None => {
-                match data
-                    .db_client
-                    .database(&data.config.database_name)
-                    .list_collection_names()
-                    .await
-                {
-                    Ok(names) => {
-                        if names.contains(&collection_name.to_string()) {
-                            None
-                        } else {
-                            Some(HttpResponse::NotFound().body(format!(
-                                "Collection {} not found",
-                                collection_name.to_owned()
-                            )))
+    // Forward every change event as a relaxed Extended JSON text message until either side stops
+    actix_web::rt::spawn(async move {
+        loop {
+            tokio::select! {
+                event = change_stream.next() => match event {
+                    Some(Ok(event)) => {
+                        let event = match mongodb::bson::to_document(&event) {
+                            Ok(mut event) => {
+                                if let Ok(document) = event.get_document_mut("fullDocument") {
+                                    redact_fields(document, &redacted);
+                                }
+                                // Updated fields are keyed by dotted paths, like `profile.ssn`
+                                if let Ok(updated_fields) = event
+                                    .get_document_mut("updateDescription")
+                                    .and_then(|update| update.get_document_mut("updatedFields"))
+                                {
+                                    redact_dotted_keys(updated_fields, &redacted);
+                                }
+                                bson_to_extjson(event.into(), ExtJsonMode::Relaxed)
+                            }
+                            Err(e) => {
+                                println!(
+                                    "[{}] Error serializing change event: {:?}",
+                                    request_id, e
+                                );
+                                break;
+                            }
+                        };
+                        if session.text(event.to_string()).await.is_err() {
+                            return;
                         }
                     }
-                    Err(_) => Some(
-                        HttpResponse::InternalServerError()
-                            .body("Failed to check collection existence"),
-                    ),
-                }
+                    Some(Err(e)) => {
+                        println!("[{}] Error reading change stream: {:?}", request_id, e);
+                        break;
+                    }
+                    None => break,
+                },
+                message = messages.next() => match message {
+                    Some(Ok(actix_ws::Message::Ping(bytes))) => {
+                        if session.pong(&bytes).await.is_err() {
+                            return;
+                        }
+                    }
+                    Some(Ok(actix_ws::Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => {}
+                },
             }
-        },
-        None => {
-            Some(HttpResponse::InternalServerError().body("Could not access collections catalog"))
         }
+        let _ = session.close(None).await;
+    });
+
+    Ok(response)
+}
+
+#[get("/{coll_name}/tail")]
+async fn tail_collection(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<std::collections::HashMap<String, String>>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let coll_name = path.into_inner();
+    let redacted = redacted_fields(&data, coll_name.as_str()).to_vec();
+    let coll_name = tenant_collection_name(&req, &data, coll_name)?;
+    let database = request_database(&req, &data)?;
+
+    ensure_collection_exists(&database, &coll_name, &data).await?;
+
+    let filter = parse_filter(&data, &req, &coll_name, &query)?;
+
+    // Tailable cursors only exist on capped collections
+    let specs: Vec<_> = data
+        .circuit_breaker
+        .call(
+            database
+                .list_collections()
+                .filter(doc! { "name": coll_name.as_str() }),
+        )
+        .await?
+        .try_collect()
+        .await?;
+    if !specs.iter().any(|spec| spec.options.capped == Some(true)) {
+        return Err(AppError::BadRequest(format!(
+            "Collection {} is not capped",
+            coll_name
+        )));
+    }
+
+    let state = TailState {
+        collection: database.collection::<mongodb::bson::Document>(&coll_name),
+        filter,
+        redacted,
+        request_id: request_id(&req),
+        cursor: None,
+        last_id: None,
+    };
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(futures_util::stream::unfold(state, next_tail_event)))
+}
+
+#[post("/{coll_name}")]
+async fn insert_document(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<std::collections::HashMap<String, String>>,
+    payload: web::Json<Value>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let coll_name = path.into_inner();
+    let id_strategy = id_strategy(&data, coll_name.as_str());
+    let required = required_fields(&data, coll_name.as_str());
+    let coll_name = tenant_collection_name(&req, &data, coll_name)?;
+    let database = request_database(&req, &data)?;
+
+    ensure_collection_exists(&database, &coll_name, &data).await?;
+
+    let payload = to_stored(payload.into_inner(), data.config.field_case);
+    check_required_fields(required, &payload)?;
+    check_schema(&data, &coll_name, &payload, false)?;
+    let write_concern = write_concern(&query, &data.config.write_concern_allowlist)?;
+
+    // A JSON array is a bulk insert of its documents
+    let payload = match payload {
+        Value::Array(items) => {
+            let options = InsertManyOptions::builder()
+                .comment(operation_comment(&req, &query))
+                .write_concern(write_concern)
+                .build();
+            return insert_documents(
+                &data,
+                &database,
+                &coll_name,
+                id_strategy,
+                &query,
+                options,
+                items,
+            )
+            .await;
+        }
+        payload => payload,
+    };
+
+    // Convert the JSON payload to a MongoDB document
+    let mut document = parse_document(&data, payload)?;
+
+    if is_dry_run(&query) {
+        return Ok(dry_run_response(doc! { "document": document }));
+    }
+
+    assign_ids(
+        &data,
+        &database,
+        &coll_name,
+        id_strategy,
+        std::slice::from_mut(&mut document),
+        operation_comment(&req, &query),
+    )
+    .await?;
+
+    // Insert the document
+    let result = data
+        .circuit_breaker
+        .call(
+            database
+                .collection::<mongodb::bson::Document>(&coll_name)
+                .insert_one(document)
+                .with_options(
+                    InsertOneOptions::builder()
+                        .comment(operation_comment(&req, &query))
+                        .write_concern(write_concern)
+                        .build(),
+                ),
+        )
+        .await?;
+    invalidate_cached_queries(&data, &database, &coll_name);
+    Ok(HttpResponse::Created().json(result.inserted_id))
+}
+
+/// Inserts every document of a bulk POST in a single `insert_many`. With `ordered=false` one
+/// failing document does not stop the others, and the answer reports each document's outcome.
+async fn insert_documents(
+    data: &web::Data<AppState>,
+    database: &mongodb::Database,
+    coll_name: &str,
+    id_strategy: IdStrategy,
+    query: &web::Query<std::collections::HashMap<String, String>>,
+    options: InsertManyOptions,
+    items: Vec<Value>,
+) -> Result<HttpResponse, AppError> {
+    if items.is_empty() {
+        return Err(AppError::BadRequest(
+            "Expected at least one document to insert".to_string(),
+        ));
+    }
+
+    let mut documents = Vec::with_capacity(items.len());
+    for (i, item) in items.into_iter().enumerate() {
+        let document = extjson_to_document(item, data.config.number_policy).map_err(|e| {
+            AppError::BadRequest(format!("Invalid document format at index {}: {}", i, e))
+        })?;
+        documents.push(document);
+    }
+
+    if is_dry_run(query) {
+        return Ok(dry_run_response(doc! { "documents": documents }));
+    }
+    let comment = options.comment.clone().unwrap_or_default();
+    assign_ids(
+        data,
+        database,
+        coll_name,
+        id_strategy,
+        &mut documents,
+        comment,
+    )
+    .await?;
+
+    let ordered = query.get("ordered").map(String::as_str) != Some("false");
+    if !ordered {
+        return insert_unordered(data, database, coll_name, options, documents).await;
+    }
+
+    let result = data
+        .circuit_breaker
+        .call(
+            database
+                .collection::<mongodb::bson::Document>(coll_name)
+                .insert_many(documents)
+                .with_options(options),
+        )
+        .await?;
+    invalidate_cached_queries(data, database, coll_name);
+
+    // Report the ids in the order the documents were sent
+    let mut inserted_ids: Vec<_> = result.inserted_ids.into_iter().collect();
+    inserted_ids.sort_by_key(|(i, _)| *i);
+    let inserted_ids: Vec<_> = inserted_ids.into_iter().map(|(_, id)| id).collect();
+    Ok(HttpResponse::Created().json(serde_json::json!({ "insertedIds": inserted_ids })))
+}
+
+/// An unordered `insert_many`, answered with 201 when every document went in and 207 with
+/// each document's id or error otherwise
+async fn insert_unordered(
+    data: &web::Data<AppState>,
+    database: &mongodb::Database,
+    coll_name: &str,
+    mut options: InsertManyOptions,
+    mut documents: Vec<mongodb::bson::Document>,
+) -> Result<HttpResponse, AppError> {
+    options.ordered = Some(false);
+    // The driver does not report which ids a failed batch inserted, so every id is known upfront
+    for document in &mut documents {
+        if !document.contains_key("_id") {
+            document.insert("_id", mongodb::bson::oid::ObjectId::new());
+        }
+    }
+    let ids: Vec<Bson> = documents
+        .iter()
+        .map(|document| document.get("_id").cloned().unwrap_or(Bson::Null))
+        .collect();
+
+    let result = data
+        .circuit_breaker
+        .call(
+            database
+                .collection::<mongodb::bson::Document>(coll_name)
+                .insert_many(documents)
+                .with_options(options),
+        )
+        .await;
+    let write_errors = match result {
+        Ok(_) => Vec::new(),
+        Err(BreakerError::Inner(e)) => match e.kind.as_ref() {
+            ErrorKind::InsertMany(InsertManyError {
+                write_errors: Some(write_errors),
+                write_concern_error: None,
+                ..
+            }) => write_errors.clone(),
+            _ => return Err(BreakerError::Inner(e).into()),
+        },
+        Err(e) => return Err(e.into()),
+    };
+    if write_errors.len() < ids.len() {
+        invalidate_cached_queries(data, database, coll_name);
+    }
+
+    let results = unordered_insert_results(ids, &write_errors);
+    let inserted_ids: Vec<&Value> = results
+        .iter()
+        .filter_map(|result| result.get("insertedId"))
+        .collect();
+    let body = serde_json::json!({ "insertedIds": inserted_ids, "results": results });
+    if write_errors.is_empty() {
+        Ok(HttpResponse::Created().json(body))
+    } else {
+        Ok(HttpResponse::build(actix_web::http::StatusCode::MULTI_STATUS).json(body))
+    }
+}
+
+/// Each document's outcome, in the order they were sent: its id, or the error that kept it out
+fn unordered_insert_results(ids: Vec<Bson>, write_errors: &[IndexedWriteError]) -> Vec<Value> {
+    ids.into_iter()
+        .enumerate()
+        .map(
+            |(index, id)| match write_errors.iter().find(|error| error.index == index) {
+                Some(error) => serde_json::json!({
+                    "index": index,
+                    "error": { "code": error.code, "message": error.message },
+                }),
+                None => serde_json::json!({
+                    "index": index,
+                    "insertedId": bson_to_extjson(id, ExtJsonMode::Relaxed),
+                }),
+            },
+        )
+        .collect()
+}
+
+#[put("/{coll_name}")]
+async fn update_document(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<std::collections::HashMap<String, String>>,
+    payload: web::Json<Value>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let coll_name = path.into_inner();
+    let required = required_fields(&data, coll_name.as_str());
+    let coll_name = tenant_collection_name(&req, &data, coll_name)?;
+    let database = request_database(&req, &data)?;
+
+    ensure_collection_exists(&database, &coll_name, &data).await?;
+
+    // Parse query parameters for filter, narrowed by a filter in the body
+    let (payload, body_filter) = split_body_filter(&data, payload.into_inner())?;
+    let filter = with_body_filter(parse_filter(&data, &req, &coll_name, &query)?, body_filter);
+    require_filter(&filter, &query, "update")?;
+    let payload = to_stored(payload, data.config.field_case);
+
+    // PUT may create the document, so it carries the fields mongor requires, but the
+    // collection schema's required fields may be missing from the update
+    check_required_fields(required, &payload)?;
+    check_schema(&data, &coll_name, &payload, true)?;
+
+    // Convert the JSON payload to a MongoDB document
+    let update_doc = parse_document(&data, payload)?;
+
+    // Create the update document with $set operator
+    let update = doc! { "$set": update_doc };
+    check_id_unchanged(&update, &filter)?;
+    let write_concern = write_concern(&query, &data.config.write_concern_allowlist)?;
+
+    if is_dry_run(&query) {
+        // update_one touches at most one document and upserts when nothing matches
+        let matched = count_matches(
+            &data,
+            &database,
+            &coll_name,
+            filter,
+            operation_comment(&req, &query),
+        )
+        .await?;
+        return Ok(dry_run_response(doc! {
+            "matchedCount": matched.min(1) as i64,
+            "upsert": matched == 0,
+            "update": update,
+        }));
+    }
+
+    // Update a single document with upsert. Like the other updates and deletes it runs to
+    // completion: the driver sends no `maxTimeMS` for them, and dropping the request
+    // client-side would not stop the server applying the write.
+    let result = data
+        .circuit_breaker
+        .call(
+            database
+                .collection::<mongodb::bson::Document>(&coll_name)
+                .update_one(filter, update)
+                .with_options(
+                    UpdateOptions::builder()
+                        .upsert(true)
+                        .comment(operation_comment(&req, &query))
+                        .collation(parse_collation(&query))
+                        .write_concern(write_concern)
+                        .build(),
+                ),
+        )
+        .await?;
+    invalidate_cached_queries(&data, &database, &coll_name);
+
+    // Return 201 Created if a new document was inserted, otherwise 200 OK
+    if result.upserted_id.is_some() {
+        Ok(HttpResponse::Created().json(result))
+    } else {
+        Ok(HttpResponse::Ok().json(result))
+    }
+}
+
+#[patch("/{coll_name}")]
+async fn patch_document(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<std::collections::HashMap<String, String>>,
+    payload: web::Json<Value>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    // PATCH is the same as PUT in this implementation
+    // We need to reimplement the logic here since we can't call the handler directly
+    let coll_name = tenant_collection_name(&req, &data, path.into_inner())?;
+    let database = request_database(&req, &data)?;
+
+    ensure_collection_exists(&database, &coll_name, &data).await?;
+
+    // Parse query parameters for filter, narrowed by a filter in the body
+    let (payload, body_filter) = split_body_filter(&data, payload.into_inner())?;
+    let filter = with_body_filter(parse_filter(&data, &req, &coll_name, &query)?, body_filter);
+    require_filter(&filter, &query, "update")?;
+
+    let (payload, array_filters) = split_array_filters(&data, payload)?;
+    let payload = to_stored(payload, data.config.field_case);
+
+    // Updates only carry the fields they set, so required fields may be missing
+    check_schema(&data, &coll_name, &payload, true)?;
+
+    // Convert the JSON payload to a MongoDB document
+    let update_doc = parse_document(&data, payload)?;
+
+    // Create the update document with $set operator
+    let update = doc! { "$set": update_doc };
+    check_id_unchanged(&update, &filter)?;
+
+    // `upsert=true` creates a document from the filter and update when nothing matches
+    let upsert = query.get("upsert").map(String::as_str) == Some("true");
+    // `multi=false` updates only the first matching document
+    let multi = query.get("multi").map(String::as_str) != Some("false");
+    let write_concern = write_concern(&query, &data.config.write_concern_allowlist)?;
+
+    if is_dry_run(&query) {
+        let matched = count_matches(
+            &data,
+            &database,
+            &coll_name,
+            filter,
+            operation_comment(&req, &query),
+        )
+        .await?;
+        let matched = if multi { matched } else { matched.min(1) };
+        let mut summary = doc! {
+            "matchedCount": matched as i64,
+            "update": update,
+        };
+        if upsert {
+            summary.insert("upsert", matched == 0);
+        }
+        if let Some(array_filters) = array_filters {
+            summary.insert("arrayFilters", array_filters);
+        }
+        return Ok(dry_run_response(summary));
+    }
+
+    // Update the document(s)
+    let collection = database.collection::<mongodb::bson::Document>(&coll_name);
+    let action = if multi {
+        collection.update_many(filter, update)
+    } else {
+        collection.update_one(filter, update)
+    };
+    let result = data
+        .circuit_breaker
+        .call(
+            action.with_options(
+                UpdateOptions::builder()
+                    .array_filters(array_filters)
+                    .upsert(upsert)
+                    .comment(operation_comment(&req, &query))
+                    .collation(parse_collation(&query))
+                    .write_concern(write_concern)
+                    .build(),
+            ),
+        )
+        .await?;
+    invalidate_cached_queries(&data, &database, &coll_name);
+
+    // Like PUT, a created document is answered with 201
+    if result.upserted_id.is_some() {
+        Ok(HttpResponse::Created().json(result))
+    } else {
+        Ok(HttpResponse::Ok().json(result))
+    }
+}
+
+/// Operators that run JavaScript on the server, which body filters may not use
+const JAVASCRIPT_OPERATORS: &[&str] = &["$where", "$function", "$accumulator"];
+
+/// Unwraps `{ "filter": {...}, "update": {...} }` bodies, whose filter is a MongoDB filter
+/// document for targets too complex for the query string. `arrayFilters` may sit alongside,
+/// as in [`split_array_filters`]. Any other body is returned as it is, with no filter.
+fn split_body_filter(
+    data: &web::Data<AppState>,
+    body: Value,
+) -> Result<(Value, Option<mongodb::bson::Document>), AppError> {
+    match body {
+        Value::Object(mut wrapper)
+            if wrapper.contains_key("filter")
+                && wrapper.contains_key("update")
+                && wrapper
+                    .keys()
+                    .all(|key| ["filter", "update", "arrayFilters"].contains(&key.as_str())) =>
+        {
+            let filter = parse_body_filter(data, wrapper.remove("filter").unwrap_or_default())?;
+            let update = match wrapper.len() {
+                1 => wrapper.remove("update").unwrap_or_default(),
+                _ => Value::Object(wrapper),
+            };
+            Ok((update, Some(filter)))
+        }
+        body => Ok((body, None)),
+    }
+}
+
+/// Parses a filter given in a request body, refusing server-side JavaScript
+fn parse_body_filter(
+    data: &web::Data<AppState>,
+    filter: Value,
+) -> Result<mongodb::bson::Document, AppError> {
+    if !filter.is_object() {
+        return Err(AppError::BadRequest(
+            "`filter` must be a filter document".to_string(),
+        ));
+    }
+    let filter = to_stored_fields(parse_document(data, filter)?, data.config.field_case);
+    if let Some(operator) = find_javascript_operator(&filter) {
+        return Err(AppError::BadRequest(format!(
+            "{} is not allowed in filters",
+            operator
+        )));
+    }
+    check_regexes(&filter).map_err(AppError::BadRequest)?;
+    Ok(filter)
+}
+
+/// Runs [`check_regex`] on every `$regex` string and regular expression value of a filter
+fn check_regexes(document: &mongodb::bson::Document) -> Result<(), String> {
+    document.iter().try_for_each(|(key, value)| match value {
+        Bson::String(pattern) if key == "$regex" => check_regex(pattern),
+        value => check_regex_values(value),
+    })
+}
+
+fn check_regex_values(value: &Bson) -> Result<(), String> {
+    match value {
+        Bson::RegularExpression(regex) => check_regex(&regex.pattern),
+        Bson::Document(nested) => check_regexes(nested),
+        Bson::Array(values) => values.iter().try_for_each(check_regex_values),
+        _ => Ok(()),
+    }
+}
+
+fn find_javascript_operator(document: &mongodb::bson::Document) -> Option<&str> {
+    document.iter().find_map(|(key, value)| {
+        if JAVASCRIPT_OPERATORS.contains(&key.as_str()) {
+            return Some(key.as_str());
+        }
+        find_javascript_operator_in(value)
+    })
+}
+
+/// Looks for [`JAVASCRIPT_OPERATORS`] in documents at any depth of `value`, arrays of arrays
+/// included, as aggregation expressions like `$in` evaluate their nested elements
+fn find_javascript_operator_in(value: &Bson) -> Option<&str> {
+    match value {
+        Bson::Document(nested) => find_javascript_operator(nested),
+        Bson::Array(values) => values.iter().find_map(find_javascript_operator_in),
+        _ => None,
+    }
+}
+
+/// Narrows the query-string filter by the body's, when there is one
+fn with_body_filter(
+    filter: mongodb::bson::Document,
+    body_filter: Option<mongodb::bson::Document>,
+) -> mongodb::bson::Document {
+    match body_filter {
+        Some(body_filter) if !body_filter.is_empty() => and_filters(filter, body_filter),
+        _ => filter,
+    }
+}
+
+/// Unwraps `{ "update": {...}, "arrayFilters": [...] }` bodies, whose `$[identifier]` paths
+/// only update the array elements the filters match; any other body is the update itself
+fn split_array_filters(
+    data: &web::Data<AppState>,
+    body: Value,
+) -> Result<(Value, Option<Vec<mongodb::bson::Document>>), AppError> {
+    match body {
+        Value::Object(mut wrapper)
+            if wrapper.len() == 2
+                && wrapper.contains_key("update")
+                && wrapper.contains_key("arrayFilters") =>
+        {
+            let update = wrapper.remove("update").unwrap_or_default();
+            let Some(Value::Array(filters)) = wrapper.remove("arrayFilters") else {
+                return Err(AppError::BadRequest(
+                    "`arrayFilters` must be an array of filter documents".to_string(),
+                ));
+            };
+            let filters = filters
+                .into_iter()
+                .map(|filter| parse_document(data, filter))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok((update, Some(filters)))
+        }
+        body => Ok((body, None)),
+    }
+}
+
+#[patch("/{coll_name}/{id}")]
+async fn patch_document_by_id(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    query: web::Query<std::collections::HashMap<String, String>>,
+    payload: web::Json<Value>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let (coll_name, id) = path.into_inner();
+    let coll_name = tenant_collection_name(&req, &data, coll_name)?;
+    let database = request_database(&req, &data)?;
+
+    ensure_collection_exists(&database, &coll_name, &data).await?;
+
+    // JSON Patch bodies are translated into update operators, anything else is merged with $set
+    let update = if req.content_type() == JSON_PATCH_CONTENT_TYPE {
+        crate::json_patch::to_update_document(payload.into_inner(), data.config.field_case)
+            .map_err(|e| match e {
+                JsonPatchError::Unsupported(_) => AppError::Unprocessable(e.to_string()),
+                e => AppError::BadRequest(e.to_string()),
+            })?
+    } else {
+        let payload = to_stored(payload.into_inner(), data.config.field_case);
+        doc! { "$set": parse_document(&data, payload)? }
+    };
+
+    let filter = doc! { "_id": parse_id(&id) };
+    check_id_unchanged(&update, &filter)?;
+    let write_concern = write_concern(&query, &data.config.write_concern_allowlist)?;
+    let not_found = || AppError::NotFound(format!("Document {} not found", id));
+
+    if is_dry_run(&query) {
+        return match count_matches(
+            &data,
+            &database,
+            &coll_name,
+            filter,
+            operation_comment(&req, &query),
+        )
+        .await?
+        {
+            0 => Err(not_found()),
+            matched => Ok(dry_run_response(doc! {
+                "matchedCount": matched as i64,
+                "update": update,
+            })),
+        };
+    }
+
+    let result = data
+        .circuit_breaker
+        .call(
+            database
+                .collection::<mongodb::bson::Document>(&coll_name)
+                .update_one(filter, update)
+                .with_options(
+                    UpdateOptions::builder()
+                        .comment(operation_comment(&req, &query))
+                        .write_concern(write_concern)
+                        .build(),
+                ),
+        )
+        .await?;
+    invalidate_cached_queries(&data, &database, &coll_name);
+    if result.matched_count == 0 {
+        return Err(not_found());
+    }
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[derive(Debug, Deserialize)]
+struct IncrementRequest {
+    field: String,
+    by: Value,
+}
+
+#[post("/{coll_name}/{id}/increment")]
+async fn increment_field(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    query: web::Query<std::collections::HashMap<String, String>>,
+    payload: web::Json<IncrementRequest>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let (coll_name, id) = path.into_inner();
+    let redacted = redacted_fields(&data, coll_name.as_str());
+    let coll_name = tenant_collection_name(&req, &data, coll_name)?;
+    let database = request_database(&req, &data)?;
+
+    ensure_collection_exists(&database, &coll_name, &data).await?;
+
+    let IncrementRequest { field, by } = payload.into_inner();
+    if field.is_empty() || field == "_id" || field.starts_with('$') {
+        return Err(AppError::BadRequest(format!(
+            "Cannot increment field {:?}",
+            field
+        )));
+    }
+    // Parsed like any write body, so `{"$numberLong": "..."}` and the number policy apply
+    let by = match extjson_to_document(serde_json::json!({ "by": by }), data.config.number_policy) {
+        Ok(mut parsed) => parsed.remove("by"),
+        Err(_) => None,
+    };
+    let by = match by {
+        Some(by @ (Bson::Int32(_) | Bson::Int64(_) | Bson::Double(_) | Bson::Decimal128(_))) => by,
+        _ => return Err(AppError::BadRequest("`by` must be a number".to_string())),
+    };
+
+    let filter = doc! { "_id": parse_id(&id) };
+    let stored_field = to_stored_path(&field, data.config.field_case);
+    let update = doc! { "$inc": { stored_field.as_str(): by } };
+    let write_concern = write_concern(&query, &data.config.write_concern_allowlist)?;
+    let not_found = || AppError::NotFound(format!("Document {} not found", id));
+
+    if is_dry_run(&query) {
+        return match count_matches(
+            &data,
+            &database,
+            &coll_name,
+            filter,
+            operation_comment(&req, &query),
+        )
+        .await?
+        {
+            0 => Err(not_found()),
+            matched => Ok(dry_run_response(doc! {
+                "matchedCount": matched as i64,
+                "update": update,
+            })),
+        };
+    }
+
+    // A non-numeric field is a type mismatch, which maps to 400
+    let mut document = data
+        .circuit_breaker
+        .call(
+            database
+                .collection::<mongodb::bson::Document>(&coll_name)
+                .find_one_and_update(filter, update)
+                .with_options(
+                    FindOneAndUpdateOptions::builder()
+                        .return_document(ReturnDocument::After)
+                        .max_time(parse_timeout(&query, max_timeout(&data)))
+                        .comment(operation_comment(&req, &query))
+                        .write_concern(write_concern)
+                        .build(),
+                ),
+        )
+        .await?
+        .ok_or_else(not_found)?;
+    invalidate_cached_queries(&data, &database, &coll_name);
+
+    // A redacted field can still be incremented, but its value never leaves the server
+    redact_fields(&mut document, redacted);
+    let value = lookup_path(&document, &stored_field)
+        .cloned()
+        .unwrap_or(Bson::Null);
+    Ok(bson_response(
+        &req,
+        doc! { "field": field, "value": value }.into(),
+    ))
+}
+
+/// Follows a dotted path through nested documents
+fn lookup_path<'a>(document: &'a mongodb::bson::Document, path: &str) -> Option<&'a Bson> {
+    match path.split_once('.') {
+        Some((head, rest)) => lookup_path(document.get_document(head).ok()?, rest),
+        None => document.get(path),
+    }
+}
+
+#[delete("/{coll_name}")]
+async fn delete_document(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<std::collections::HashMap<String, String>>,
+    body: web::Bytes,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let coll_name = tenant_collection_name(&req, &data, path.into_inner())?;
+    let database = request_database(&req, &data)?;
+
+    ensure_collection_exists(&database, &coll_name, &data).await?;
+
+    // Parse query parameters for filter, narrowed by a `{ "filter": {...} }` body
+    let body_filter = if body.is_empty() {
+        None
+    } else {
+        match serde_json::from_slice::<Value>(&body) {
+            Ok(Value::Object(mut body)) if body.len() == 1 && body.contains_key("filter") => Some(
+                parse_body_filter(&data, body.remove("filter").unwrap_or_default())?,
+            ),
+            _ => {
+                return Err(AppError::BadRequest(
+                    "A DELETE body must be { \"filter\": {...} }".to_string(),
+                ));
+            }
+        }
+    };
+    let filter = with_body_filter(parse_filter(&data, &req, &coll_name, &query)?, body_filter);
+    // A positive `limit` already bounds what an unfiltered delete removes; MongoDB reads
+    // a limit of 0 as no limit at all
+    let bounded = match query.get("limit") {
+        None => false,
+        Some(limit) => match limit.parse::<i64>() {
+            Ok(limit) if limit > 0 => true,
+            _ => {
+                return Err(AppError::BadRequest(format!(
+                    "Invalid limit {}: a delete limit has to be a positive integer",
+                    limit
+                )));
+            }
+        },
+    };
+    if !bounded {
+        require_filter(&filter, &query, "delete")?;
+    }
+    let write_concern = write_concern(&query, &data.config.write_concern_allowlist)?;
+    // `delete_many` has no limit, so bounded deletes first find the ids to remove
+    let filter = if query.contains_key("limit") || query.contains_key("skip") {
+        bounded_delete_filter(&data, &database, &coll_name, &req, &query, filter).await?
+    } else {
+        filter
+    };
+
+    if is_dry_run(&query) {
+        let matched = count_matches(
+            &data,
+            &database,
+            &coll_name,
+            filter,
+            operation_comment(&req, &query),
+        )
+        .await?;
+        return Ok(dry_run_response(doc! { "deletedCount": matched as i64 }));
+    }
+
+    // Delete the document(s)
+    let result = data
+        .circuit_breaker
+        .call(
+            database
+                .collection::<mongodb::bson::Document>(&coll_name)
+                .delete_many(filter)
+                .with_options(
+                    DeleteOptions::builder()
+                        .comment(operation_comment(&req, &query))
+                        .collation(parse_collation(&query))
+                        .write_concern(write_concern)
+                        .build(),
+                ),
+        )
+        .await?;
+    invalidate_cached_queries(&data, &database, &coll_name);
+    Ok(HttpResponse::Ok().json(result))
+}
+
+/// Narrows a delete filter to the documents `sort`, `skip` and `limit` select. The original
+/// filter is kept so documents changed between the find and the delete are left alone.
+async fn bounded_delete_filter(
+    data: &web::Data<AppState>,
+    database: &mongodb::Database,
+    collection_name: &str,
+    req: &HttpRequest,
+    query: &web::Query<std::collections::HashMap<String, String>>,
+    filter: mongodb::bson::Document,
+) -> Result<mongodb::bson::Document, AppError> {
+    let mut options = find_options(query, &[], data);
+    options.projection = Some(doc! { "_id": 1 });
+    options.comment = Some(operation_comment(req, query));
+    let selected: Vec<mongodb::bson::Document> = data
+        .circuit_breaker
+        .call(
+            database
+                .collection::<mongodb::bson::Document>(collection_name)
+                .find(filter.clone())
+                .with_options(options),
+        )
+        .await?
+        .try_collect()
+        .await?;
+    let ids: Vec<Bson> = selected
+        .into_iter()
+        .filter_map(|mut doc| doc.remove("_id"))
+        .collect();
+    Ok(doc! { "$and": [filter, { "_id": { "$in": ids } }] })
+}
+
+#[derive(Debug, Deserialize)]
+struct BatchDeleteRequest {
+    ids: Vec<Value>,
+}
+
+/// Deletes the documents whose `_id` is listed in a `{ "ids": [...] }` body, in a single
+/// `delete_many`, and reports the listed ids no document had
+#[post("/{coll_name}/batch-delete")]
+async fn batch_delete(
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<std::collections::HashMap<String, String>>,
+    payload: web::Json<BatchDeleteRequest>,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, AppError> {
+    let coll_name = tenant_collection_name(&req, &data, path.into_inner())?;
+    let database = request_database(&req, &data)?;
+
+    ensure_collection_exists(&database, &coll_name, &data).await?;
+
+    let ids = payload
+        .into_inner()
+        .ids
+        .into_iter()
+        .map(|id| batch_delete_id(id, data.config.number_policy))
+        .collect::<Result<Vec<Bson>, AppError>>()?;
+    if ids.is_empty() {
+        return Err(AppError::BadRequest("`ids` must not be empty".to_string()));
+    }
+    let filter = doc! { "_id": { "$in": ids.clone() } };
+    let write_concern = write_concern(&query, &data.config.write_concern_allowlist)?;
+
+    // Ids are compared as `id_to_cursor` writes them, so `1` and `1.0` are one id as in `$in`
+    let id_key = |id: &Bson| id_to_cursor(id).unwrap_or_else(|| id.to_string());
+    let found: Vec<mongodb::bson::Document> = data
+        .circuit_breaker
+        .call(
+            database
+                .collection::<mongodb::bson::Document>(&coll_name)
+                .find(filter.clone())
+                .projection(doc! { "_id": 1 })
+                .comment(operation_comment(&req, &query)),
+        )
+        .await?
+        .try_collect()
+        .await?;
+    let found: Vec<String> = found
+        .iter()
+        .filter_map(|doc| doc.get("_id").map(id_key))
+        .collect();
+    let not_found: Vec<Bson> = ids
+        .into_iter()
+        .filter(|id| !found.contains(&id_key(id)))
+        .collect();
+
+    if is_dry_run(&query) {
+        return Ok(dry_run_response(doc! {
+            "deletedCount": found.len() as i64,
+            "notFound": not_found,
+        }));
+    }
+
+    let result = data
+        .circuit_breaker
+        .call(
+            database
+                .collection::<mongodb::bson::Document>(&coll_name)
+                .delete_many(filter)
+                .with_options(
+                    DeleteOptions::builder()
+                        .comment(operation_comment(&req, &query))
+                        .write_concern(write_concern)
+                        .build(),
+                ),
+        )
+        .await?;
+    invalidate_cached_queries(&data, &database, &coll_name);
+    Ok(HttpResponse::Ok().json(bson_to_extjson(
+        doc! {
+            "deletedCount": result.deleted_count as i64,
+            "notFound": not_found,
+        }
+        .into(),
+        ExtJsonMode::Relaxed,
+    )))
+}
+
+/// An id of a batch delete: ObjectId-shaped strings become ObjectIds, other values are read
+/// as Extended JSON, so `{"$oid": ...}` and the number policy apply
+fn batch_delete_id(id: Value, policy: NumberPolicy) -> Result<Bson, AppError> {
+    let id = match id {
+        Value::String(id) => match mongodb::bson::oid::ObjectId::parse_str(&id) {
+            Ok(oid) => oid.into(),
+            Err(_) => id.into(),
+        },
+        id => extjson_to_document(serde_json::json!({ "_id": id }), policy)
+            .ok()
+            .and_then(|mut parsed| parsed.remove("_id"))
+            .unwrap_or(Bson::Null),
+    };
+    match id {
+        Bson::Array(_) | Bson::Null => Err(AppError::BadRequest(format!(
+            "Invalid id {}, expected a string, number or Extended JSON value",
+            id
+        ))),
+        id => Ok(id),
+    }
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api")
+            .service(hello)
+            .service(ping)
+            .service(stats)
+            .service(maintenance_status)
+            .service(set_maintenance)
+            .service(drop_collection)
+            .service(run_transaction),
+    )
+    .service(list_indexes)
+    .service(explain_query)
+    .service(join_collection)
+    .service(count_documents)
+    .service(distinct_values)
+    .service(infer_collection_schema)
+    .service(sample_collection)
+    .service(download_field)
+    .service(watch_collection)
+    .service(tail_collection)
+    .service(query_collection)
+    .service(find_with_body)
+    .service(batch_delete)
+    .service(insert_document)
+    .service(update_document)
+    .service(patch_document)
+    .service(patch_document_by_id)
+    .service(increment_field)
+    .service(delete_document);
+}
+
+/// Drains the cursor, failing with 413 once the documents exceed `max_bytes`.
+/// The budget counts BSON bytes, which closely tracks the size of the JSON response.
+async fn collect_documents(
+    mut cursor: mongodb::Cursor<mongodb::bson::Document>,
+    max_bytes: Option<usize>,
+) -> Result<Vec<mongodb::bson::Document>, AppError> {
+    let mut docs = Vec::new();
+    let mut total_bytes = 0;
+    while cursor.advance().await? {
+        total_bytes += cursor.current().as_bytes().len();
+        if let Some(max_bytes) = max_bytes.filter(|max_bytes| total_bytes > *max_bytes) {
+            return Err(AppError::PayloadTooLarge(format!(
+                "Response exceeds the {} byte limit, narrow the query or lower the limit",
+                max_bytes
+            )));
+        }
+        docs.push(cursor.deserialize_current()?);
+    }
+    Ok(docs)
+}
+
+struct TailState {
+    collection: mongodb::Collection<mongodb::bson::Document>,
+    filter: mongodb::bson::Document,
+    redacted: Vec<String>,
+    request_id: String,
+    cursor: Option<mongodb::Cursor<mongodb::bson::Document>>,
+    last_id: Option<mongodb::bson::Bson>,
+}
+
+/// Produces the next SSE event of a tail. Tailable cursors die when the collection is
+/// empty or wraps past them, so the cursor is reopened after the last `_id` it returned.
+async fn next_tail_event(
+    mut state: TailState,
+) -> Option<(Result<web::Bytes, actix_web::Error>, TailState)> {
+    loop {
+        let cursor = match &mut state.cursor {
+            Some(cursor) => cursor,
+            None => {
+                let filter = match &state.last_id {
+                    Some(last_id) => doc! { "$and": [
+                        state.filter.clone(),
+                        { "_id": { "$gt": last_id.clone() } },
+                    ] },
+                    None => state.filter.clone(),
+                };
+                match state
+                    .collection
+                    .find(filter)
+                    .cursor_type(CursorType::TailableAwait)
+                    .comment(state.request_id.as_str())
+                    .await
+                {
+                    Ok(cursor) => state.cursor.insert(cursor),
+                    Err(e) => {
+                        println!(
+                            "[{}] Error opening tailable cursor: {:?}",
+                            state.request_id, e
+                        );
+                        return None;
+                    }
+                }
+            }
+        };
+
+        match cursor.next().await {
+            Some(Ok(mut doc)) => {
+                state.last_id = doc.get("_id").cloned();
+                redact_fields(&mut doc, &state.redacted);
+                let event = bson_to_extjson(doc.into(), ExtJsonMode::Relaxed);
+                let event = web::Bytes::from(format!("data: {}\n\n", event));
+                return Some((Ok(event), state));
+            }
+            Some(Err(e)) => {
+                println!("[{}] Error tailing collection: {:?}", state.request_id, e);
+                return None;
+            }
+            None => {
+                state.cursor = None;
+                actix_web::rt::time::sleep(Duration::from_secs(1)).await;
+            }
+        }
+    }
+}
+
+/// Whether the server rejected a change stream because it is not part of a replica set
+fn is_replica_set_required_error(e: &mongodb::error::Error) -> bool {
+    matches!(e.kind.as_ref(), ErrorKind::Command(command_error) if command_error.code == 40573)
+}
+
+/// The find options of the reserved query parameters, with the fields `sort`, `select` and
+/// `mask` name translated as `FIELD_CASE` stores them
+fn find_options(
+    query: &std::collections::HashMap<String, String>,
+    excluded: &[String],
+    data: &web::Data<AppState>,
+) -> mongodb::options::FindOptions {
+    let mut options = parse_find_options(query, excluded, max_timeout(data));
+    let case = data.config.field_case;
+    options.sort = options.sort.map(|sort| to_stored_fields(sort, case));
+    options.projection = options
+        .projection
+        .map(|projection| to_stored_fields(projection, case));
+    options
+}
+
+/// The configured upper bound for a single MongoDB operation
+fn max_timeout(data: &web::Data<AppState>) -> Duration {
+    Duration::from_millis(data.config.op_timeout_ms)
+}
+
+/// The database a request works on: the configured one, or the one its `X-Database` header
+/// names when that database is allowlisted
+fn request_database(
+    req: &HttpRequest,
+    data: &web::Data<AppState>,
+) -> Result<mongodb::Database, AppError> {
+    let Some(name) = req.headers().get(DATABASE_HEADER) else {
+        return Ok(data.db_client.database(&data.config.database_name));
+    };
+    let name = name
+        .to_str()
+        .map_err(|_| AppError::BadRequest(format!("Invalid {} header", DATABASE_HEADER)))?;
+    if !data.config.is_database_allowed(name) {
+        return Err(AppError::Forbidden(format!(
+            "Database {} is not allowlisted",
+            name
+        )));
+    }
+    Ok(data.db_client.database(name))
+}
+
+/// Prefixes the collection name with the request's tenant when tenancy is enabled,
+/// so each tenant only ever sees its own `{tenant}_{collection}` collections. Hidden
+/// collections are turned away here, by their logical name, and look like missing ones.
+fn tenant_collection_name(
+    req: &HttpRequest,
+    data: &web::Data<AppState>,
+    collection_name: String,
+) -> Result<String, AppError> {
+    if !data.config.is_collection_exposed(&collection_name) {
+        return Err(AppError::NotFound(format!(
+            "Collection {} not found",
+            collection_name
+        )));
+    }
+    Ok(format!("{}{}", tenant_prefix(req, data)?, collection_name))
+}
+
+/// The `{tenant}_` prefix of the request's collections, empty when tenancy is disabled
+pub(crate) fn tenant_prefix(
+    req: &HttpRequest,
+    data: &web::Data<AppState>,
+) -> Result<String, AppError> {
+    let Some(header) = &data.config.tenant_header else {
+        return Ok(String::new());
+    };
+    match req
+        .headers()
+        .get(header)
+        .and_then(|value| value.to_str().ok())
+    {
+        // The `_` separating tenant and collection cannot appear in the tenant itself,
+        // or tenant `a` asking for `b_orders` would reach tenant `a_b`'s `orders`
+        Some(tenant)
+            if !tenant.is_empty()
+                && tenant
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-') =>
+        {
+            crate::auth::authorize_tenant(req, data, tenant)?;
+            Ok(format!("{}_", tenant))
+        }
+        Some(_) => Err(AppError::BadRequest(format!("Invalid {} header", header))),
+        None => Err(AppError::BadRequest(format!("Missing {} header", header))),
+    }
+}
+
+/// The write concern a write asked for with `?writeConcern=`, which has to be allowlisted.
+/// Without one the client's (or connection string's) default applies.
+fn write_concern(
+    query: &std::collections::HashMap<String, String>,
+    allowlist: &[String],
+) -> Result<Option<WriteConcern>, AppError> {
+    match query.get("writeConcern") {
+        None => Ok(None),
+        Some(w) if allowlist.contains(w) => Ok(Some(parse_write_concern(w))),
+        Some(w) => Err(AppError::BadRequest(format!(
+            "writeConcern {} is not allowed, expected one of {}",
+            w,
+            allowlist.join(", ")
+        ))),
+    }
+}
+
+/// Whether the request asked to validate a write without performing it
+fn is_dry_run(query: &web::Query<std::collections::HashMap<String, String>>) -> bool {
+    query.get("dryRun").is_some_and(|value| value == "true")
+}
+
+/// Refuses to `action` every document of a collection, as an empty filter would, unless
+/// `confirm=true` asks for exactly that. Dry runs change nothing and are let through.
+fn require_filter(
+    filter: &mongodb::bson::Document,
+    query: &web::Query<std::collections::HashMap<String, String>>,
+    action: &str,
+) -> Result<(), AppError> {
+    if filter.is_empty()
+        && !is_dry_run(query)
+        && query.get("confirm").map(String::as_str) != Some("true")
+    {
+        return Err(AppError::BadRequest(format!(
+            "Refusing to {} every document without a filter; add confirm=true to do so",
+            action
+        )));
+    }
+    Ok(())
+}
+
+/// The transaction counterpart of [`require_filter`]: an operation with an empty filter
+/// has to set `"confirm": true` to touch every document of its collection
+fn confirmed_filter(
+    filter: mongodb::bson::Document,
+    confirm: bool,
+    action: &str,
+) -> Result<mongodb::bson::Document, String> {
+    if filter.is_empty() && !confirm {
+        return Err(format!(
+            "refusing to {} every document without a filter; add \"confirm\": true to do so",
+            action
+        ));
+    }
+    Ok(filter)
+}
+
+/// Reports what a dry-run write would have done
+fn dry_run_response(mut summary: mongodb::bson::Document) -> HttpResponse {
+    summary.insert("dryRun", true);
+    HttpResponse::Ok().json(bson_to_extjson(summary.into(), ExtJsonMode::Relaxed))
+}
+
+/// Counts the documents a write with this filter would touch, tagged with the write's comment
+async fn count_matches(
+    data: &web::Data<AppState>,
+    database: &mongodb::Database,
+    collection_name: &str,
+    filter: mongodb::bson::Document,
+    comment: Bson,
+) -> Result<u64, AppError> {
+    Ok(data
+        .circuit_breaker
+        .call(
+            database
+                .collection::<mongodb::bson::Document>(collection_name)
+                .count_documents(filter)
+                .comment(comment),
+        )
+        .await?)
+}
+
+/// Parses the filter parameters among `query` into a filter. They are read from the raw
+/// query string, where a parameter may repeat, like `_id=gte.100&_id=lt.200`. With
+/// `UNKNOWN_PARAMS=strict` they have to name fields the collection validator declares.
+fn parse_filter(
+    data: &web::Data<AppState>,
+    req: &HttpRequest,
+    collection_name: &str,
+    query: &std::collections::HashMap<String, String>,
+) -> Result<mongodb::bson::Document, AppError> {
+    let pairs = web::Query::<Vec<(String, String)>>::from_query(req.query_string())
+        .map(web::Query::into_inner)
+        .unwrap_or_default();
+    let known_fields = match data.config.unknown_params {
+        UnknownParams::Strict => Some(declared_fields(data, collection_name)),
+        UnknownParams::Lenient => None,
+    };
+    parse_match_query_pairs_with(
+        pairs
+            .iter()
+            .filter(|(query_param, _)| query.contains_key(query_param))
+            .map(|(query_param, field_value)| (query_param.as_str(), field_value.as_str())),
+        ParseOptions {
+            known_fields: known_fields.as_deref(),
+            ..parse_options(data)
+        },
+    )
+    .map_err(|e| AppError::BadRequest(format!("Invalid query parameter: {}", e)))
+}
+
+/// `_id` and the top-level properties of the collection's `$jsonSchema` validator. Without
+/// a validator only `_id` is declared, so strict mode does not quietly turn lenient.
+fn declared_fields(data: &web::Data<AppState>, collection_name: &str) -> Vec<String> {
+    let catalog = crate::catalog::get_cached_collections(data);
+    let properties = catalog
+        .as_ref()
+        .and_then(|catalog| {
+            catalog
+                .collection_specs
+                .iter()
+                .find(|spec| spec.name == collection_name)
+        })
+        .and_then(|spec| spec.options.validator.as_ref())
+        .and_then(|validator| {
+            validator
+                .get_document("$jsonSchema")
+                .unwrap_or(validator)
+                .get_document("properties")
+                .ok()
+        });
+    std::iter::once("_id".to_string())
+        .chain(
+            properties
+                .into_iter()
+                .flat_map(|properties| properties.keys().cloned()),
+        )
+        .collect()
+}
+
+/// The configured limits on filter parameters
+fn parse_options(data: &AppState) -> ParseOptions<'_> {
+    ParseOptions {
+        max_len: data.config.max_query_len,
+        max_params: data.config.max_query_params,
+        repeated: data.config.repeated_params,
+        allowed_operators: data.config.operators_allowlist.as_deref(),
+        known_fields: None,
+        field_case: data.config.field_case,
+    }
+}
+
+/// Rejects a `mask` the find options could not turn into a projection
+fn check_mask(query: &std::collections::HashMap<String, String>) -> Result<(), AppError> {
+    match query.get("mask") {
+        Some(mask) => parse_mask(mask)
+            .map(|_| ())
+            .map_err(|e| AppError::BadRequest(format!("Invalid mask: {}", e))),
+        None => Ok(()),
+    }
+}
+
+/// How many documents `?require=` expects a GET to match
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Require {
+    /// Exactly one
+    One,
+    /// At least one
+    Many,
+}
+
+fn parse_require(
+    query: &std::collections::HashMap<String, String>,
+) -> Result<Option<Require>, AppError> {
+    match query.get("require").map(String::as_str) {
+        None => Ok(None),
+        Some("one") => Ok(Some(Require::One)),
+        Some("many") => Ok(Some(Require::Many)),
+        Some(other) => Err(AppError::BadRequest(format!(
+            "Invalid require: {}, expected one or many",
+            other
+        ))),
+    }
+}
+
+/// Fails a GET whose matches fall short of `require`. An existing collection with no
+/// matching documents is a 404 worded unlike the one for a missing collection.
+fn check_required_matches(
+    require: Option<Require>,
+    matched: usize,
+    collection_name: &str,
+) -> Result<(), AppError> {
+    match require {
+        Some(_) if matched == 0 => Err(AppError::NotFound(format!(
+            "No documents in collection {} match the query",
+            collection_name
+        ))),
+        Some(Require::One) if matched > 1 => Err(AppError::Unprocessable(format!(
+            "Expected one document in collection {} to match the query, found {}",
+            collection_name, matched
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Rejects a `hint` naming an index the collection does not have. The cached catalog answers
+/// for known indexes; anything else is looked up, as the index may be newer than the catalog.
+async fn check_hint(
+    database: &mongodb::Database,
+    collection_name: &str,
+    query: &std::collections::HashMap<String, String>,
+    data: &web::Data<AppState>,
+) -> Result<(), AppError> {
+    let Some(hint) = query.get("hint") else {
+        return Ok(());
+    };
+    let has_index = |indexes: &[mongodb::IndexModel]| {
+        indexes.iter().any(|index| {
+            index
+                .options
+                .as_ref()
+                .and_then(|options| options.name.as_ref())
+                == Some(hint)
+        })
+    };
+    // The catalog only covers the configured database, not `X-Database` overrides
+    if database.name() == data.config.database_name
+        && crate::catalog::get_cached_collections(data).is_some_and(|catalog| {
+            catalog
+                .indexes
+                .get(collection_name)
+                .is_some_and(|indexes| has_index(indexes))
+        })
+    {
+        return Ok(());
+    }
+    let collection = database.collection::<mongodb::bson::Document>(collection_name);
+    let indexes = data
+        .circuit_breaker
+        .call(crate::catalog::fetch_indexes(&collection))
+        .await?;
+    if has_index(&indexes) {
+        Ok(())
+    } else {
+        Err(AppError::BadRequest(format!(
+            "Unknown index {} on {}",
+            hint, collection_name
+        )))
+    }
+}
+
+/// Parses a write body with the configured number policy
+fn parse_document(
+    data: &web::Data<AppState>,
+    value: Value,
+) -> Result<mongodb::bson::Document, AppError> {
+    extjson_to_document(value, data.config.number_policy)
+        .map_err(|e| AppError::BadRequest(format!("Invalid document format: {}", e)))
+}
+
+/// Encodes a document id as it is written in cursors and id lists, the inverse of [`parse_id`]
+fn id_to_cursor(id: &mongodb::bson::Bson) -> Option<String> {
+    match id {
+        mongodb::bson::Bson::ObjectId(oid) => Some(oid.to_hex()),
+        mongodb::bson::Bson::Int32(n) => Some(n.to_string()),
+        mongodb::bson::Bson::Int64(n) => Some(n.to_string()),
+        mongodb::bson::Bson::Double(n) if n.fract() == 0.0 => Some((*n as i64).to_string()),
+        mongodb::bson::Bson::String(s) => Some(s.clone()),
+        _ => None,
+    }
+}
+
+/// Fields excluded from GET responses for this collection unless explicitly selected
+fn excluded_fields<'a>(data: &'a web::Data<AppState>, collection_name: &str) -> &'a [String] {
+    data.config
+        .excluded_fields
+        .get(collection_name)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+/// Rejects updates that would change a document's `_id` with a clear 400. Setting it to the
+/// `_id` the filter pins, e.g. when a whole document is sent back, changes nothing and passes.
+fn check_id_unchanged(
+    update: &mongodb::bson::Document,
+    filter: &mongodb::bson::Document,
+) -> Result<(), AppError> {
+    let pinned = filter
+        .get("_id")
+        .filter(|id| !matches!(id, Bson::Document(_)));
+    for (operator, fields) in update {
+        let Bson::Document(fields) = fields else {
+            continue;
+        };
+        for (field, value) in fields {
+            if field != "_id" && !field.starts_with("_id.") {
+                continue;
+            }
+            let allowed = match (operator.as_str(), pinned) {
+                ("$setOnInsert", _) => true,
+                ("$set", Some(id)) => field == "_id" && same_id(id, value),
+                // Only the server knows the `_id`s an arbitrary filter matches; it refuses
+                // changes with ImmutableField, which maps to 400 as well
+                ("$set", None) => true,
+                _ => false,
+            };
+            if !allowed {
+                return Err(AppError::BadRequest(format!(
+                    "The _id of a document cannot be changed ({} on {})",
+                    operator, field
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Compares ids the way MongoDB matches them, so `1` and `1.0` are the same id
+fn same_id(a: &Bson, b: &Bson) -> bool {
+    let number = |id: &Bson| match id {
+        Bson::Int32(n) => Some(*n as f64),
+        Bson::Int64(n) => Some(*n as f64),
+        Bson::Double(n) => Some(*n),
+        _ => None,
+    };
+    match (number(a), number(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Rejects a body that does not match the OpenAPI schema generated from the collection's
+/// validator, when request validation is enabled
+fn check_schema(
+    data: &web::Data<AppState>,
+    collection_name: &str,
+    body: &Value,
+    partial: bool,
+) -> Result<(), AppError> {
+    if !data.config.validate_request_bodies {
+        return Ok(());
+    }
+    let Some(schema) = crate::catalog::get_cached_collections(data).and_then(|catalog| {
+        catalog
+            .collection_specs
+            .iter()
+            .find(|spec| spec.name == collection_name)
+            .and_then(|spec| spec.options.validator.as_ref())
+            .and_then(|validator| mongo_validator_to_openapi_request_body(validator).ok())
+            .and_then(|request_body| request_body.content.get("application/json")?.schema.clone())
+    }) else {
+        return Ok(());
+    };
+
+    // Bulk bodies are checked document by document, with the index leading each pointer
+    let errors = match body {
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .flat_map(|(i, item)| {
+                crate::schema_validation::validate(&schema, item, partial)
+                    .into_iter()
+                    .map(move |error| match error.strip_prefix("/:") {
+                        Some(rest) => format!("/{}:{}", i, rest),
+                        None => format!("/{}{}", i, error),
+                    })
+            })
+            .collect(),
+        _ => crate::schema_validation::validate(&schema, body, partial),
+    };
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::Validation(errors))
+    }
+}
+
+/// The fields mongor requires in documents written to this collection
+fn required_fields<'a>(data: &'a web::Data<AppState>, collection_name: &str) -> &'a [String] {
+    data.config
+        .required_fields
+        .get(collection_name)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+/// Rejects bodies missing one of the `required` fields (dotted paths reach into embedded
+/// documents), whether or not MongoDB has a validator. Bulk bodies are checked per document.
+fn check_required_fields(required: &[String], body: &Value) -> Result<(), AppError> {
+    let missing = |document: &Value, pointer: &str| -> Vec<String> {
+        required
+            .iter()
+            .filter(|path| {
+                path.split('.')
+                    .try_fold(document, |value, key| value.get(key))
+                    .is_none()
+            })
+            .map(|path| format!("{}/{}: is required", pointer, path.replace('.', "/")))
+            .collect()
+    };
+    let errors: Vec<String> = match body {
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .flat_map(|(i, item)| missing(item, &format!("/{}", i)))
+            .collect(),
+        _ => missing(body, ""),
+    };
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::Validation(errors))
+    }
+}
+
+/// How documents POSTed to this collection without an `_id` get one
+fn id_strategy(data: &web::Data<AppState>, collection_name: &str) -> IdStrategy {
+    data.config
+        .id_strategies
+        .get(collection_name)
+        .copied()
+        .unwrap_or_default()
+}
+
+/// The field `since` and `until` filter on for this collection
+fn timestamp_field<'a>(data: &'a web::Data<AppState>, collection_name: &str) -> &'a str {
+    data.config
+        .timestamp_fields
+        .get(collection_name)
+        .map_or("createdAt", String::as_str)
+}
+
+/// Narrows the filter to the `since`/`until` time range, when either is given
+fn apply_time_range_params(
+    query: &std::collections::HashMap<String, String>,
+    timestamp_field: &str,
+    filter: mongodb::bson::Document,
+) -> Result<mongodb::bson::Document, AppError> {
+    apply_time_range(
+        filter,
+        timestamp_field,
+        query.get("since").map(String::as_str),
+        query.get("until").map(String::as_str),
+    )
+    .map_err(|e| AppError::BadRequest(format!("Invalid query parameter: {}", e)))
+}
+
+/// Fields stripped from every document this collection returns, whatever the projection
+fn redacted_fields<'a>(data: &'a web::Data<AppState>, collection_name: &str) -> &'a [String] {
+    data.config
+        .redacted_fields
+        .get(collection_name)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+async fn ensure_collection_exists(
+    database: &mongodb::Database,
+    collection_name: &str,
+    data: &web::Data<AppState>,
+) -> Result<(), AppError> {
+    let not_found = || AppError::NotFound(format!("Collection {} not found", collection_name));
+    // The catalog only covers the configured database, not `X-Database` overrides
+    let in_catalog = if database.name() == data.config.database_name {
+        crate::catalog::catalog_contains(data, collection_name)
+    } else {
+        Some(false)
+    };
+    match in_catalog {
+        Some(true) => Ok(()),
+        Some(false) => {
+            let names = match data
+                .circuit_breaker
+                .call(database.list_collection_names())
+                .await
+            {
+                Ok(names) => names,
+                Err(BreakerError::Open) => return Err(AppError::Unavailable),
+                // A reconnecting server is worth a retry; other failures stay a generic 500
+                Err(BreakerError::Inner(e)) if AppError::is_transient(&e) => {
+                    return Err(AppError::Database(e));
+                }
+                Err(BreakerError::Inner(_)) => {
+                    return Err(AppError::Internal(
+                        "Failed to check collection existence".to_string(),
+                    ));
+                }
+            };
+            if names.iter().any(|name| name == collection_name) {
+                Ok(())
+            } else {
+                Err(not_found())
+            }
+        }
+        None => Err(AppError::Internal(
+            "Could not access collections catalog".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mongodb::options::Acknowledgment;
+
+    #[test]
+    fn test_batch_delete_id() {
+        let oid = mongodb::bson::oid::ObjectId::new();
+        let id = |value: Value| batch_delete_id(value, NumberPolicy::Preserve);
+        assert_eq!(
+            id(serde_json::json!(oid.to_hex())).unwrap(),
+            Bson::ObjectId(oid)
+        );
+        assert_eq!(
+            id(serde_json::json!({ "$oid": oid.to_hex() })).unwrap(),
+            Bson::ObjectId(oid)
+        );
+        assert_eq!(id(serde_json::json!(7)).unwrap(), Bson::Int32(7));
+        // Unlike path ids, numeric strings stay strings: the body can say 7 instead
+        assert_eq!(
+            id(serde_json::json!("7")).unwrap(),
+            Bson::String("7".into())
+        );
+        assert_eq!(
+            batch_delete_id(serde_json::json!(7), NumberPolicy::Int64).unwrap(),
+            Bson::Int64(7)
+        );
+        for invalid in [serde_json::json!(null), serde_json::json!([1, 2])] {
+            assert!(matches!(id(invalid), Err(AppError::BadRequest(_))));
+        }
+    }
+
+    #[test]
+    fn test_sample_size() {
+        assert_eq!(sample_size(None, 1_000).unwrap(), DEFAULT_SAMPLE_SIZE);
+        assert_eq!(sample_size(Some("25"), 1_000).unwrap(), 25);
+        // Larger samples are capped rather than refused
+        assert_eq!(sample_size(Some("5000"), 1_000).unwrap(), 1_000);
+        assert_eq!(sample_size(None, 3).unwrap(), 3);
+        for invalid in ["0", "-1", "ten"] {
+            assert!(matches!(
+                sample_size(Some(invalid), 1_000),
+                Err(AppError::BadRequest(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_write_concern_param() {
+        let allowlist = ["majority".to_string(), "1".to_string()];
+        let query = |w: &str| {
+            std::collections::HashMap::from([("writeConcern".to_string(), w.to_string())])
+        };
+
+        assert_eq!(
+            write_concern(&Default::default(), &allowlist).unwrap(),
+            None
+        );
+        let majority = write_concern(&query("majority"), &allowlist)
+            .unwrap()
+            .unwrap();
+        assert_eq!(majority.w, Some(Acknowledgment::Majority));
+        let one = write_concern(&query("1"), &allowlist).unwrap().unwrap();
+        assert_eq!(one.w, Some(Acknowledgment::Nodes(1)));
+
+        // Unacknowledged writes have to be allowlisted like any other concern
+        assert!(matches!(
+            write_concern(&query("0"), &allowlist),
+            Err(AppError::BadRequest(_))
+        ));
+    }
+
+    #[test]
+    fn test_find_javascript_operator() {
+        assert_eq!(
+            find_javascript_operator(&doc! { "$where": "this.a == 1" }),
+            Some("$where")
+        );
+        assert_eq!(
+            find_javascript_operator(&doc! { "$or": [{ "a": 1 }, { "$where": "true" }] }),
+            Some("$where")
+        );
+        // Hidden in an array within an array, which `$expr` still evaluates
+        let function = doc! { "$function": { "body": "function() { return true }", "args": [], "lang": "js" } };
+        assert_eq!(
+            find_javascript_operator(&doc! { "$expr": { "$in": [1, [function]] } }),
+            Some("$function")
+        );
+        assert_eq!(
+            find_javascript_operator(&doc! { "tags": { "$in": [["a"], "b"] } }),
+            None
+        );
+    }
+
+    #[test]
+    fn test_check_regexes_in_body_filters() {
+        assert!(check_regexes(&doc! { "name": { "$regex": "^jo", "$options": "i" } }).is_ok());
+        assert!(check_regexes(&doc! { "name": { "$regex": "(a+)+$" } }).is_err());
+        // Regular expression values, nested under logical operators too
+        let regex = mongodb::bson::Regex {
+            pattern: "(x*)*y".to_string(),
+            options: String::new(),
+        };
+        assert!(check_regexes(&doc! { "$or": [{ "a": 1 }, { "name": regex }] }).is_err());
+        // A field that happens to hold such a string is not a pattern
+        assert!(check_regexes(&doc! { "note": "(a+)+" }).is_ok());
+    }
+
+    #[test]
+    fn test_count_method() {
+        // Only an estimate reads the metadata; unfiltered counts stay exact otherwise
+        assert_eq!(count_method(false, &doc! {}).unwrap(), CountMethod::Scan);
+        assert_eq!(count_method(true, &doc! {}).unwrap(), CountMethod::Metadata);
+        assert_eq!(
+            count_method(false, &doc! { "parity": 0.0 }).unwrap(),
+            CountMethod::Scan
+        );
+        assert!(matches!(
+            count_method(true, &doc! { "parity": 0.0 }),
+            Err(AppError::BadRequest(_))
+        ));
     }
 }
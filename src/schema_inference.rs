@@ -0,0 +1,115 @@
+//! Best-effort schemas for collections without a validator, inferred from sampled documents.
+//!
+//! Every field path seen in the sample, embedded documents included as dotted paths
+//! (`address.city`), is reported with how often each BSON type appeared there and the share
+//! of sampled documents carrying it. Array elements are not looked into.
+
+use mongodb::bson::{Bson, Document};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+
+#[derive(Default)]
+struct FieldStats {
+    present: usize,
+    types: BTreeMap<&'static str, usize>,
+}
+
+/// The inferred schema of `documents`, as
+/// `{ "sampled": 2, "fields": { "age": { "types": { "int": 2 }, "frequency": 1.0 } } }`
+pub fn infer_schema(documents: &[Document]) -> Value {
+    let mut fields = BTreeMap::new();
+    for document in documents {
+        collect_fields(&mut fields, "", document);
+    }
+    let fields: serde_json::Map<String, Value> = fields
+        .into_iter()
+        .map(|(path, stats)| {
+            let frequency = stats.present as f64 / documents.len() as f64;
+            (
+                path,
+                json!({ "types": stats.types, "frequency": frequency }),
+            )
+        })
+        .collect();
+    json!({ "sampled": documents.len(), "fields": fields })
+}
+
+fn collect_fields(fields: &mut BTreeMap<String, FieldStats>, prefix: &str, document: &Document) {
+    for (key, value) in document {
+        let path = format!("{}{}", prefix, key);
+        let stats = fields.entry(path.clone()).or_default();
+        stats.present += 1;
+        *stats.types.entry(type_alias(value)).or_default() += 1;
+        if let Bson::Document(embedded) = value {
+            collect_fields(fields, &format!("{}.", path), embedded);
+        }
+    }
+}
+
+/// The `$type` name of a value
+fn type_alias(value: &Bson) -> &'static str {
+    match value {
+        Bson::Double(_) => "double",
+        Bson::String(_) => "string",
+        Bson::Document(_) => "object",
+        Bson::Array(_) => "array",
+        Bson::Binary(_) => "binData",
+        Bson::Undefined => "undefined",
+        Bson::ObjectId(_) => "objectId",
+        Bson::Boolean(_) => "bool",
+        Bson::DateTime(_) => "date",
+        Bson::Null => "null",
+        Bson::RegularExpression(_) => "regex",
+        Bson::DbPointer(_) => "dbPointer",
+        Bson::JavaScriptCode(_) => "javascript",
+        Bson::Symbol(_) => "symbol",
+        Bson::JavaScriptCodeWithScope(_) => "javascriptWithScope",
+        Bson::Int32(_) => "int",
+        Bson::Timestamp(_) => "timestamp",
+        Bson::Int64(_) => "long",
+        Bson::Decimal128(_) => "decimal",
+        Bson::MinKey => "minKey",
+        Bson::MaxKey => "maxKey",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mongodb::bson::doc;
+
+    #[test]
+    fn test_infer_schema_counts_types_and_frequencies() {
+        let schema = infer_schema(&[
+            doc! { "_id": 1, "name": "Ann", "address": { "city": "Oslo" } },
+            doc! { "_id": 2, "name": 7_i64, "tags": ["a"] },
+            doc! { "_id": 3, "name": null, "address": "unknown" },
+            doc! { "_id": 4, "name": "Bo" },
+        ]);
+        assert_eq!(schema["sampled"], 4);
+        let fields = &schema["fields"];
+        assert_eq!(
+            fields["_id"],
+            json!({ "types": { "int": 4 }, "frequency": 1.0 })
+        );
+        assert_eq!(
+            fields["name"]["types"],
+            json!({ "string": 2, "long": 1, "null": 1 })
+        );
+        assert_eq!(
+            fields["address"]["types"],
+            json!({ "object": 1, "string": 1 })
+        );
+        assert_eq!(fields["address"]["frequency"], 0.5);
+        assert_eq!(
+            fields["address.city"],
+            json!({ "types": { "string": 1 }, "frequency": 0.25 })
+        );
+        assert_eq!(fields["tags"]["types"], json!({ "array": 1 }));
+    }
+
+    #[test]
+    fn test_infer_schema_of_nothing() {
+        assert_eq!(infer_schema(&[]), json!({ "sampled": 0, "fields": {} }));
+    }
+}
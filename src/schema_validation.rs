@@ -0,0 +1,235 @@
+//! Validation of JSON request bodies against the OpenAPI schemas generated from
+//! collection validators.
+//!
+//! Only the keywords the validator conversion produces are checked: `type`, `enum`,
+//! `required`, nested `properties`, string lengths and numeric bounds. Extended JSON
+//! wrappers such as `{"$date": ...}` are accepted for any scalar and left to MongoDB.
+
+use serde_json::Value;
+use utoipa::openapi::{RefOr, Schema, Type, schema::SchemaType};
+
+/// Returns one `"{pointer}: {problem}"` message per violation, empty when the value conforms.
+/// With `partial`, missing required properties are allowed, as in `$set` updates.
+pub fn validate(schema: &RefOr<Schema>, value: &Value, partial: bool) -> Vec<String> {
+    let mut errors = Vec::new();
+    validate_at(schema, value, partial, "", &mut errors);
+    errors
+}
+
+fn validate_at(
+    schema: &RefOr<Schema>,
+    value: &Value,
+    partial: bool,
+    pointer: &str,
+    errors: &mut Vec<String>,
+) {
+    let RefOr::T(Schema::Object(object)) = schema else {
+        return;
+    };
+    let location = if pointer.is_empty() { "/" } else { pointer };
+
+    if is_extjson_wrapper(value) && object.schema_type != SchemaType::Type(Type::Object) {
+        return;
+    }
+    if !matches_type(&object.schema_type, value) {
+        errors.push(format!(
+            "{}: expected {}",
+            location,
+            type_name(&object.schema_type)
+        ));
+        return;
+    }
+
+    if let Some(enum_values) = &object.enum_values
+        && !enum_values.contains(value)
+    {
+        errors.push(format!(
+            "{}: value is not one of the allowed values",
+            location
+        ));
+    }
+
+    match value {
+        Value::String(s) => {
+            let length = s.chars().count();
+            if let Some(min_length) = object.min_length
+                && length < min_length
+            {
+                errors.push(format!(
+                    "{}: shorter than {} characters",
+                    location, min_length
+                ));
+            }
+            if let Some(max_length) = object.max_length
+                && length > max_length
+            {
+                errors.push(format!(
+                    "{}: longer than {} characters",
+                    location, max_length
+                ));
+            }
+        }
+        Value::Number(n) => {
+            let n = n.as_f64().unwrap_or_default();
+            if let Some(minimum) = object.minimum.as_ref().map(number_to_f64)
+                && n < minimum
+            {
+                errors.push(format!("{}: less than the minimum {}", location, minimum));
+            }
+            if let Some(maximum) = object.maximum.as_ref().map(number_to_f64)
+                && n > maximum
+            {
+                errors.push(format!(
+                    "{}: greater than the maximum {}",
+                    location, maximum
+                ));
+            }
+        }
+        Value::Object(fields) => {
+            if !partial {
+                for required in &object.required {
+                    if !fields.contains_key(required) {
+                        errors.push(format!("{}/{}: is required", pointer, required));
+                    }
+                }
+            }
+            for (key, property_schema) in &object.properties {
+                if let Some(property) = fields.get(key) {
+                    let property_pointer = format!("{}/{}", pointer, key);
+                    validate_at(
+                        property_schema,
+                        property,
+                        partial,
+                        &property_pointer,
+                        errors,
+                    );
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Whether the value is an Extended JSON type wrapper like `{"$oid": "..."}`
+fn is_extjson_wrapper(value: &Value) -> bool {
+    matches!(value, Value::Object(fields) if fields.len() == 1 && fields.keys().all(|key| key.starts_with('$')))
+}
+
+fn matches_type(schema_type: &SchemaType, value: &Value) -> bool {
+    match schema_type {
+        SchemaType::Type(schema_type) => matches_single_type(schema_type, value),
+        SchemaType::Array(types) => types.iter().any(|t| matches_single_type(t, value)),
+        SchemaType::AnyValue => true,
+    }
+}
+
+fn matches_single_type(schema_type: &Type, value: &Value) -> bool {
+    match schema_type {
+        Type::Object => value.is_object(),
+        Type::String => value.is_string(),
+        Type::Integer => value.is_i64() || value.is_u64(),
+        Type::Number => value.is_number(),
+        Type::Boolean => value.is_boolean(),
+        Type::Array => value.is_array(),
+        Type::Null => value.is_null(),
+    }
+}
+
+fn type_name(schema_type: &SchemaType) -> String {
+    serde_json::to_value(schema_type)
+        .ok()
+        .map(|name| match name {
+            Value::String(name) => name,
+            other => other.to_string(),
+        })
+        .unwrap_or_else(|| "any value".to_string())
+}
+
+fn number_to_f64(number: &utoipa::Number) -> f64 {
+    match number {
+        utoipa::Number::Int(n) => *n as f64,
+        utoipa::Number::UInt(n) => *n as f64,
+        utoipa::Number::Float(n) => *n,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openapi_docs::mongo_validator_to_openapi_request_body;
+    use mongodb::bson::doc;
+    use serde_json::json;
+
+    fn users_schema() -> RefOr<Schema> {
+        let validator = doc! { "$jsonSchema": {
+            "bsonType": "object",
+            "required": ["name", "age"],
+            "properties": {
+                "name": { "bsonType": "string", "minLength": 2_i64 },
+                "age": { "bsonType": "int", "minimum": 0 },
+                "role": { "bsonType": "string", "enum": ["admin", "user"] },
+                "joined": { "bsonType": "date" },
+                "address": {
+                    "bsonType": "object",
+                    "properties": { "city": { "bsonType": "string" } }
+                }
+            }
+        } };
+        mongo_validator_to_openapi_request_body(&validator)
+            .unwrap()
+            .content
+            .get("application/json")
+            .and_then(|content| content.schema.clone())
+            .expect("Expected a request body schema")
+    }
+
+    #[test]
+    fn test_conforming_body() {
+        let body = json!({
+            "name": "Ana",
+            "age": 30,
+            "role": "admin",
+            "joined": { "$date": "2024-01-01T00:00:00Z" },
+            "address": { "city": "Bucharest" },
+            "extra": true
+        });
+        assert!(validate(&users_schema(), &body, false).is_empty());
+    }
+
+    #[test]
+    fn test_violations_report_their_pointer() {
+        let body = json!({
+            "name": "A",
+            "age": -1,
+            "role": "owner",
+            "address": { "city": 42 }
+        });
+        assert_eq!(
+            validate(&users_schema(), &body, false),
+            vec![
+                "/address/city: expected string",
+                "/age: less than the minimum 0",
+                "/name: shorter than 2 characters",
+                "/role: value is not one of the allowed values",
+            ]
+        );
+
+        assert_eq!(
+            validate(&users_schema(), &json!({ "age": "thirty" }), false),
+            vec!["/name: is required", "/age: expected integer"]
+        );
+        assert_eq!(
+            validate(&users_schema(), &json!([]), false),
+            vec!["/: expected object"]
+        );
+    }
+
+    #[test]
+    fn test_partial_bodies_skip_required() {
+        assert!(validate(&users_schema(), &json!({ "age": 31 }), true).is_empty());
+        assert_eq!(
+            validate(&users_schema(), &json!({ "age": 1.5 }), true),
+            vec!["/age: expected integer"]
+        );
+    }
+}
@@ -0,0 +1,182 @@
+//! Per-collection document counts and storage sizes for `/api/stats`.
+//!
+//! Stats come from one `collStats` command per catalog collection, run with bounded
+//! parallelism and cached for a few seconds so dashboards polling the endpoint do not
+//! turn into a stream of commands against every collection.
+
+use futures::{StreamExt, TryStreamExt};
+use mongodb::bson::{Bson, Document, doc};
+use mongodb::results::CollectionType;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use crate::circuit_breaker::BreakerError;
+use crate::shared::AppState;
+
+/// How many `collStats` commands run at the same time
+const MAX_CONCURRENT_STATS: usize = 8;
+
+/// How long a computed set of stats is served before it is recomputed
+const STATS_TTL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionStats {
+    pub name: String,
+    pub count: i64,
+    pub size: i64,
+    pub storage_size: i64,
+    pub total_index_size: i64,
+    pub index_count: i64,
+}
+
+impl CollectionStats {
+    /// Reads the fields we report from a `collStats` reply
+    pub fn from_reply(name: &str, reply: &Document) -> Self {
+        CollectionStats {
+            name: name.to_string(),
+            count: reply_number(reply, "count"),
+            size: reply_number(reply, "size"),
+            storage_size: reply_number(reply, "storageSize"),
+            total_index_size: reply_number(reply, "totalIndexSize"),
+            index_count: reply_number(reply, "nindexes"),
+        }
+    }
+}
+
+/// Replies use int32, int64 or double depending on magnitude and server version
+fn reply_number(reply: &Document, key: &str) -> i64 {
+    match reply.get(key) {
+        Some(Bson::Int32(n)) => *n as i64,
+        Some(Bson::Int64(n)) => *n,
+        Some(Bson::Double(n)) => *n as i64,
+        _ => 0,
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct StatsCache {
+    entry: Mutex<Option<(Instant, Arc<Vec<CollectionStats>>)>>,
+}
+
+impl StatsCache {
+    /// Returns the cached stats, recomputing them once they are older than [`STATS_TTL`].
+    /// Concurrent callers wait for a single recomputation instead of starting their own.
+    pub async fn get(&self, data: &AppState) -> Result<Arc<Vec<CollectionStats>>, BreakerError> {
+        let mut entry = self.entry.lock().await;
+        if let Some((computed_at, stats)) = entry.as_ref()
+            && computed_at.elapsed() < STATS_TTL
+        {
+            return Ok(Arc::clone(stats));
+        }
+
+        let names = match data.collections.read() {
+            Ok(catalog) => catalog
+                .collection_specs
+                .iter()
+                .filter(|spec| spec.collection_type != CollectionType::View)
+                .map(|spec| spec.name.clone())
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        let database = data.db_client.database(&data.config.database_name);
+        let stats = Arc::new(fetch_stats(data, &database, names).await?);
+        *entry = Some((Instant::now(), Arc::clone(&stats)));
+        Ok(stats)
+    }
+}
+
+/// Stats for a database outside the catalog (an `X-Database` override), computed uncached
+pub async fn fetch_database_stats(
+    data: &AppState,
+    database: &mongodb::Database,
+) -> Result<Vec<CollectionStats>, BreakerError> {
+    let specs: Vec<_> = data
+        .circuit_breaker
+        .call(async {
+            database
+                .list_collections()
+                .await?
+                .try_collect::<Vec<_>>()
+                .await
+        })
+        .await?;
+    let names = specs
+        .into_iter()
+        .filter(|spec| {
+            spec.collection_type != CollectionType::View
+                && data.config.is_physical_collection_exposed(&spec.name)
+        })
+        .map(|spec| spec.name)
+        .collect();
+    fetch_stats(data, database, names).await
+}
+
+/// Runs `collStats` for every named collection, skipping collections that
+/// disappeared since they were listed
+async fn fetch_stats(
+    data: &AppState,
+    database: &mongodb::Database,
+    names: Vec<String>,
+) -> Result<Vec<CollectionStats>, BreakerError> {
+    let replies: Vec<_> = futures::stream::iter(names)
+        .map(|name| async move {
+            let reply = data
+                .circuit_breaker
+                .call(database.run_command(doc! { "collStats": name.as_str() }))
+                .await;
+            (name, reply)
+        })
+        .buffer_unordered(MAX_CONCURRENT_STATS)
+        .collect()
+        .await;
+
+    let mut stats = Vec::new();
+    for (name, reply) in replies {
+        match reply {
+            Ok(reply) => stats.push(CollectionStats::from_reply(&name, &reply)),
+            Err(BreakerError::Open) => return Err(BreakerError::Open),
+            Err(BreakerError::Inner(e)) => {
+                eprintln!("Error fetching stats for {}: {}", name, e);
+            }
+        }
+    }
+    stats.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_reply_accepts_any_numeric_type() {
+        let reply = doc! {
+            "count": 3,
+            "size": 120_i64,
+            "storageSize": 4096.0,
+            "totalIndexSize": 4096,
+            "nindexes": 1,
+            "ok": 1.0,
+        };
+        assert_eq!(
+            CollectionStats::from_reply("orders", &reply),
+            CollectionStats {
+                name: "orders".to_string(),
+                count: 3,
+                size: 120,
+                storage_size: 4096,
+                total_index_size: 4096,
+                index_count: 1,
+            }
+        );
+
+        // Missing fields (e.g. on an empty collection) read as zero
+        assert_eq!(
+            CollectionStats::from_reply("empty", &doc! {}).storage_size,
+            0
+        );
+    }
+}
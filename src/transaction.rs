@@ -0,0 +1,240 @@
+//! Ordered multi-collection writes executed inside a single MongoDB transaction.
+//!
+//! Operations take the same shapes as the collection endpoints: `insert` a document,
+//! `update` (a `$set` of the given fields, like PATCH) or `delete` the documents matched
+//! by a `filter` written in query-parameter syntax (`{"status": "eq.draft"}`). Like
+//! `confirm=true` on the endpoints, an empty filter needs `"confirm": true`.
+
+use mongodb::bson::{Bson, Document, doc};
+use mongodb::error::{Error, ErrorKind};
+use mongodb::{Client, ClientSession};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::circuit_breaker::{BreakerError, CircuitBreaker};
+
+#[derive(Debug, Deserialize)]
+pub struct TransactionRequest {
+    pub operations: Vec<TransactionOperation>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum TransactionOperation {
+    Insert {
+        collection: String,
+        document: Value,
+    },
+    Update {
+        collection: String,
+        #[serde(default)]
+        filter: HashMap<String, String>,
+        update: Value,
+        /// Needed to update every document, when the filter is empty
+        #[serde(default)]
+        confirm: bool,
+    },
+    Delete {
+        collection: String,
+        #[serde(default)]
+        filter: HashMap<String, String>,
+        /// Needed to delete every document, when the filter is empty
+        #[serde(default)]
+        confirm: bool,
+    },
+}
+
+impl TransactionOperation {
+    pub fn collection(&self) -> &str {
+        match self {
+            TransactionOperation::Insert { collection, .. }
+            | TransactionOperation::Update { collection, .. }
+            | TransactionOperation::Delete { collection, .. } => collection,
+        }
+    }
+}
+
+/// An operation with its collection resolved and its filter and body parsed
+#[derive(Debug)]
+pub enum PreparedOperation {
+    Insert {
+        collection: String,
+        document: Document,
+    },
+    Update {
+        collection: String,
+        filter: Document,
+        update: Document,
+    },
+    Delete {
+        collection: String,
+        filter: Document,
+    },
+}
+
+#[derive(Debug)]
+pub enum TransactionError {
+    /// The circuit breaker is open and nothing was attempted
+    Open,
+    /// The deployment is a standalone server, which has no transactions
+    Unsupported,
+    /// The session or transaction could not be started or committed
+    Transaction(Error),
+    /// The operation at this index failed and everything before it was rolled back
+    Operation(usize, Error),
+}
+
+impl From<BreakerError> for TransactionError {
+    fn from(e: BreakerError) -> Self {
+        match e {
+            BreakerError::Open => TransactionError::Open,
+            BreakerError::Inner(e) => TransactionError::Transaction(e),
+        }
+    }
+}
+
+/// Runs the operations in order inside one transaction, returning one result per operation
+pub async fn run_transaction(
+    client: &Client,
+    breaker: &CircuitBreaker,
+    database_name: &str,
+    operations: Vec<PreparedOperation>,
+    comment: Bson,
+) -> Result<Vec<Document>, TransactionError> {
+    let mut session = breaker.call(client.start_session()).await?;
+    session
+        .start_transaction()
+        .await
+        .map_err(TransactionError::Transaction)?;
+
+    let mut results = Vec::with_capacity(operations.len());
+    for (i, operation) in operations.into_iter().enumerate() {
+        let comment = comment.clone();
+        match run_operation(
+            client,
+            breaker,
+            database_name,
+            &mut session,
+            operation,
+            comment,
+        )
+        .await
+        {
+            Ok(result) => results.push(result),
+            Err(e) => {
+                let _ = session.abort_transaction().await;
+                return Err(match e {
+                    BreakerError::Open => TransactionError::Open,
+                    BreakerError::Inner(e) if is_transactions_unsupported_error(&e) => {
+                        TransactionError::Unsupported
+                    }
+                    BreakerError::Inner(e) => TransactionError::Operation(i, e),
+                });
+            }
+        }
+    }
+
+    breaker.call(session.commit_transaction()).await?;
+    Ok(results)
+}
+
+async fn run_operation(
+    client: &Client,
+    breaker: &CircuitBreaker,
+    database_name: &str,
+    session: &mut ClientSession,
+    operation: PreparedOperation,
+    comment: Bson,
+) -> Result<Document, BreakerError> {
+    let database = client.database(database_name);
+    match operation {
+        PreparedOperation::Insert {
+            collection,
+            document,
+        } => {
+            let result = breaker
+                .call(
+                    database
+                        .collection::<Document>(&collection)
+                        .insert_one(document)
+                        .comment(comment)
+                        .session(&mut *session),
+                )
+                .await?;
+            Ok(doc! { "insertedId": result.inserted_id })
+        }
+        PreparedOperation::Update {
+            collection,
+            filter,
+            update,
+        } => {
+            let result = breaker
+                .call(
+                    database
+                        .collection::<Document>(&collection)
+                        .update_many(filter, doc! { "$set": update })
+                        .comment(comment)
+                        .session(&mut *session),
+                )
+                .await?;
+            Ok(doc! {
+                "matchedCount": result.matched_count as i64,
+                "modifiedCount": result.modified_count as i64,
+            })
+        }
+        PreparedOperation::Delete { collection, filter } => {
+            let result = breaker
+                .call(
+                    database
+                        .collection::<Document>(&collection)
+                        .delete_many(filter)
+                        .comment(comment)
+                        .session(&mut *session),
+                )
+                .await?;
+            Ok(doc! { "deletedCount": result.deleted_count as i64 })
+        }
+    }
+}
+
+/// Whether the server rejected the transaction because it is not part of a replica set
+fn is_transactions_unsupported_error(e: &Error) -> bool {
+    matches!(e.kind.as_ref(), ErrorKind::Command(command_error) if command_error.code == 20)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operations_deserialize_by_op() {
+        let request: TransactionRequest = serde_json::from_value(serde_json::json!({
+            "operations": [
+                { "op": "insert", "collection": "orders", "document": { "_id": 1 } },
+                { "op": "update", "collection": "stock", "filter": { "sku": "eq.a" }, "update": { "qty": 4 } },
+                { "op": "delete", "collection": "carts" }
+            ]
+        }))
+        .unwrap();
+
+        let collections: Vec<&str> = request
+            .operations
+            .iter()
+            .map(TransactionOperation::collection)
+            .collect();
+        assert_eq!(collections, vec!["orders", "stock", "carts"]);
+        assert!(matches!(
+            &request.operations[1],
+            TransactionOperation::Update { filter, .. } if filter["sku"] == "eq.a"
+        ));
+    }
+
+    #[test]
+    fn test_unknown_operations_are_rejected() {
+        let request = serde_json::from_value::<TransactionRequest>(serde_json::json!({
+            "operations": [{ "op": "drop", "collection": "orders" }]
+        }));
+        assert!(request.is_err());
+    }
+}
@@ -0,0 +1,99 @@
+use jsonwebtoken::{EncodingKey, Header, encode};
+use mongodb::bson::doc;
+use serial_test::serial;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::{TestConfig, TestEnvironment};
+use utils::utils::{make_get_request, make_http_request_with_headers};
+
+// Collection guarded by the token scopes
+static TEST_COLLECTION_NAME: &str = "mongor_auth_test_orders";
+
+// Secret shared by the server and the tokens signed below
+static JWT_SECRET: &str = "mongor-test-secret";
+
+// Helper function to sign a token with the given scopes, expiring relative to now
+fn sign_token(scope: &str, expires_in_secs: i64) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    encode(
+        &Header::default(),
+        &serde_json::json!({ "scope": scope, "exp": now + expires_in_secs }),
+        &EncodingKey::from_secret(JWT_SECRET.as_bytes()),
+    )
+    .expect("Failed to sign token")
+}
+
+// Helper function to send a request with a bearer token and return its status code
+fn request_with_token(method: &str, body: Option<&str>, token: &str) -> u16 {
+    let authorization = format!("Bearer {}", token);
+    let (status_code, _body) = make_http_request_with_headers(
+        &format!("/{}", TEST_COLLECTION_NAME),
+        method,
+        body,
+        &[("Authorization", authorization.as_str())],
+    );
+    status_code
+}
+
+#[test]
+#[serial]
+fn test_auth_all_cases() {
+    let env = TestEnvironment::with_config(TestConfig {
+        app_env: vec![("JWT_SECRET".to_string(), JWT_SECRET.to_string())],
+        ..TestConfig::default()
+    });
+    env.insert_test_data(TEST_COLLECTION_NAME, vec![doc! { "_id": 1 }]);
+
+    // Test case 1: A valid token with the read scope can query the collection
+    {
+        let token = sign_token(&format!("read:{}", TEST_COLLECTION_NAME), 3600);
+        assert_eq!(request_with_token("GET", None, &token), 200);
+    }
+
+    // Test case 2: Missing and expired tokens are rejected with 401
+    {
+        let (status_code, _body) = make_get_request(&format!("/{}", TEST_COLLECTION_NAME));
+        assert_eq!(
+            status_code, 401,
+            "Expected status code 401, got {}",
+            status_code
+        );
+
+        let token = sign_token(&format!("read:{}", TEST_COLLECTION_NAME), -3600);
+        assert_eq!(request_with_token("GET", None, &token), 401);
+    }
+
+    // Test case 3: A token lacking the needed scope is rejected with 403
+    {
+        let token = sign_token("read:some_other_collection", 3600);
+        assert_eq!(request_with_token("GET", None, &token), 403);
+
+        // Reading does not grant writing
+        let token = sign_token(&format!("read:{}", TEST_COLLECTION_NAME), 3600);
+        assert_eq!(
+            request_with_token("POST", Some(r#"{"_id": 2}"#), &token),
+            403
+        );
+
+        let token = sign_token("write:*", 3600);
+        assert_eq!(
+            request_with_token("POST", Some(r#"{"_id": 2}"#), &token),
+            201
+        );
+    }
+
+    // Test case 4: System endpoints stay public
+    {
+        let (status_code, _body) = make_get_request("/api/ping");
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+    }
+}
@@ -0,0 +1,103 @@
+use futures_util::TryStreamExt;
+use mongodb::bson::{Document, doc, oid::ObjectId};
+use serial_test::serial;
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::{TOKIO_RUNTIME, TestEnvironment};
+use utils::utils::make_post_request;
+
+static TEST_COLLECTION_NAME: &str = "mongor_batch_delete_test";
+
+// The `_id`s left in the test collection
+fn remaining_ids(env: &TestEnvironment) -> Vec<String> {
+    TOKIO_RUNTIME.block_on(async {
+        let documents: Vec<Document> = env
+            .mongodb_client
+            .database(&env.config.database_name)
+            .collection::<Document>(TEST_COLLECTION_NAME)
+            .find(doc! {})
+            .await
+            .expect("Failed to query the collection")
+            .try_collect()
+            .await
+            .expect("Failed to read the collection");
+        documents
+            .iter()
+            .map(|document| document.get("_id").unwrap().to_string())
+            .collect()
+    })
+}
+
+#[test]
+#[serial]
+fn test_batch_delete_all_cases() {
+    let env = TestEnvironment::new();
+    let oid = ObjectId::new();
+    let missing_oid = ObjectId::new();
+    let full_request_path = format!("/{}/batch-delete", TEST_COLLECTION_NAME);
+
+    // Test case 1: Existing ids are deleted and missing ones reported, ObjectIds included
+    {
+        env.insert_test_data(
+            TEST_COLLECTION_NAME,
+            vec![
+                doc! { "_id": 1 },
+                doc! { "_id": 2 },
+                doc! { "_id": 3 },
+                doc! { "_id": oid },
+            ],
+        );
+        let (status_code, body) = make_post_request(
+            &full_request_path,
+            &format!(
+                r#"{{"ids": [1, 3, 4, "{}", "{}"]}}"#,
+                oid.to_hex(),
+                missing_oid.to_hex()
+            ),
+        );
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+        let result: serde_json::Value =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        assert_eq!(
+            result,
+            serde_json::json!({
+                "deletedCount": 3,
+                "notFound": [4, { "$oid": missing_oid.to_hex() }],
+            })
+        );
+        assert_eq!(remaining_ids(&env), vec!["2".to_string()]);
+    }
+
+    // Test case 2: A dry run reports the same without deleting
+    {
+        let (status_code, body) = make_post_request(
+            &format!("{}?dryRun=true", full_request_path),
+            r#"{"ids": [2, 5]}"#,
+        );
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+        let result: serde_json::Value =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        assert_eq!(result["deletedCount"], 1);
+        assert_eq!(result["notFound"], serde_json::json!([5]));
+        assert_eq!(remaining_ids(&env), vec!["2".to_string()]);
+    }
+
+    // Test case 3: An empty id list is rejected
+    {
+        let (status_code, _body) = make_post_request(&full_request_path, r#"{"ids": []}"#);
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use mongodb::bson::{Document, doc};
+use serial_test::serial;
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::TestEnvironment;
+use utils::utils::{make_get_request, make_patch_request, make_put_request, send_http_request};
+
+static TEST_COLLECTION_NAME: &str = "mongor_body_filter_test";
+
+// Reads the whole test collection back, in `_id` order
+fn get_documents() -> Vec<Document> {
+    let (status_code, body) = make_get_request(&format!("/{}?sort=_id", TEST_COLLECTION_NAME));
+    assert_eq!(
+        status_code, 200,
+        "Expected status code 200, got {}",
+        status_code
+    );
+    serde_json::from_str(&body).expect("Failed to parse JSON response")
+}
+
+#[test]
+#[serial]
+fn test_body_filter_all_cases() {
+    let env = TestEnvironment::new();
+    let path = format!("/{}", TEST_COLLECTION_NAME);
+    let initial_docs = vec![
+        doc! { "_id": 1, "status": "draft", "priority": 1 },
+        doc! { "_id": 2, "status": "review", "priority": 5 },
+        doc! { "_id": 3, "status": "published", "priority": 9 },
+    ];
+
+    // Test case 1: A body `$or` filter updates every document it matches
+    {
+        env.insert_test_data(TEST_COLLECTION_NAME, initial_docs.clone());
+        let (status_code, body) = make_patch_request(
+            &path,
+            r#"{"filter": {"$or": [{"status": "draft"}, {"priority": {"$gte": 9}}]}, "update": {"flagged": true}}"#,
+        );
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+        let result: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(result["modifiedCount"], 2);
+        let flagged: Vec<i32> = get_documents()
+            .iter()
+            .filter(|doc| doc.get_bool("flagged") == Ok(true))
+            .map(|doc| doc.get_i32("_id").unwrap())
+            .collect();
+        assert_eq!(flagged, vec![1, 3]);
+    }
+
+    // Test case 2: Query-string and body filters both have to match
+    {
+        env.insert_test_data(TEST_COLLECTION_NAME, initial_docs.clone());
+        let (status_code, body) = make_patch_request(
+            &format!("{}?status=published", path),
+            r#"{"filter": {"$or": [{"status": "draft"}, {"priority": {"$gte": 9}}]}, "update": {"flagged": true}}"#,
+        );
+        assert_eq!(status_code, 200, "{}", body);
+        let result: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(result["modifiedCount"], 1);
+    }
+
+    // Test case 3: PUT takes the same wrapper
+    {
+        env.insert_test_data(TEST_COLLECTION_NAME, initial_docs.clone());
+        let (status_code, body) = make_put_request(
+            &path,
+            r#"{"filter": {"priority": {"$lt": 2}}, "update": {"status": "archived"}}"#,
+        );
+        assert_eq!(status_code, 200, "{}", body);
+        assert_eq!(get_documents()[0].get_str("status"), Ok("archived"));
+    }
+
+    // Test case 4: DELETE reads a `{ "filter": ... }` body
+    {
+        env.insert_test_data(TEST_COLLECTION_NAME, initial_docs.clone());
+        let response = send_http_request(
+            &path,
+            "DELETE",
+            Some(r#"{"filter": {"status": {"$in": ["draft", "review"]}}}"#),
+            &[],
+        );
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(get_documents(), initial_docs[2..].to_vec());
+    }
+
+    // Test case 5: Server-side JavaScript is refused
+    {
+        env.insert_test_data(TEST_COLLECTION_NAME, initial_docs.clone());
+        let (status_code, _body) = make_patch_request(
+            &path,
+            r#"{"filter": {"$or": [{"$where": "true"}]}, "update": {"flagged": true}}"#,
+        );
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+        assert_eq!(get_documents(), initial_docs);
+    }
+}
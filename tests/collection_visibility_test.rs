@@ -0,0 +1,90 @@
+use mongodb::bson::doc;
+use serial_test::serial;
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::{TestConfig, TestEnvironment};
+use utils::utils::{
+    make_delete_request, make_get_request, make_patch_request, make_post_request, make_put_request,
+};
+
+// Collections exposed, denied, and left out of the allowlist
+static VISIBLE_COLLECTION_NAME: &str = "mongor_visibility_test_visible";
+static DENIED_COLLECTION_NAME: &str = "mongor_visibility_test_denied";
+static UNLISTED_COLLECTION_NAME: &str = "mongor_visibility_test_unlisted";
+
+#[test]
+#[serial]
+fn test_collection_visibility_all_cases() {
+    let env = TestEnvironment::with_config(TestConfig {
+        app_env: vec![
+            (
+                "COLLECTIONS_ALLOWLIST".to_string(),
+                format!("{},{}", VISIBLE_COLLECTION_NAME, DENIED_COLLECTION_NAME),
+            ),
+            (
+                "COLLECTIONS_DENYLIST".to_string(),
+                DENIED_COLLECTION_NAME.to_string(),
+            ),
+            ("CATALOG_REFRESH_SECS".to_string(), "1".to_string()),
+        ],
+        ..TestConfig::default()
+    });
+
+    for collection_name in [
+        VISIBLE_COLLECTION_NAME,
+        DENIED_COLLECTION_NAME,
+        UNLISTED_COLLECTION_NAME,
+    ] {
+        env.insert_test_data(collection_name, vec![doc! { "_id": 1, "value": 1 }]);
+    }
+    // Let the background task pick the new collections up
+    std::thread::sleep(std::time::Duration::from_secs(2));
+
+    // Test case 1: Exposed collections stay queryable
+    {
+        let (status_code, _body) = make_get_request(&format!("/{}", VISIBLE_COLLECTION_NAME));
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+    }
+
+    // Test case 2: Hidden collections answer 404 on every endpoint
+    {
+        for collection_name in [DENIED_COLLECTION_NAME, UNLISTED_COLLECTION_NAME] {
+            let path = format!("/{}", collection_name);
+            let responses = [
+                make_get_request(&path),
+                make_get_request(&format!("{}/indexes", path)),
+                make_get_request(&format!("{}/explain", path)),
+                make_post_request(&path, r#"{"_id": 2}"#),
+                make_put_request(&format!("{}?_id=1", path), r#"{"value": 2}"#),
+                make_patch_request(&format!("{}?_id=1", path), r#"{"value": 2}"#),
+                make_delete_request(&format!("{}?_id=1", path)),
+            ];
+            for (status_code, body) in responses {
+                assert_eq!(
+                    status_code, 404,
+                    "Expected status code 404 for {}, got {}",
+                    collection_name, status_code
+                );
+                assert!(body.contains("not found"), "Unexpected body: {}", body);
+            }
+        }
+    }
+
+    // Test case 3: Only exposed collections appear in the OpenAPI document
+    {
+        let (status_code, body) = make_get_request("/openapi.json");
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+        assert!(body.contains(VISIBLE_COLLECTION_NAME));
+        assert!(!body.contains(DENIED_COLLECTION_NAME));
+        assert!(!body.contains(UNLISTED_COLLECTION_NAME));
+    }
+}
@@ -0,0 +1,110 @@
+use serial_test::serial;
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::{TestConfig, TestEnvironment};
+use utils::utils::send_http_request;
+
+static ALLOWED_ORIGIN: &str = "https://app.example.com";
+
+#[test]
+#[serial]
+fn test_cors_preflight() {
+    let _env = TestEnvironment::with_config(TestConfig {
+        app_env: vec![
+            (
+                "CORS_ALLOWED_ORIGINS".to_string(),
+                ALLOWED_ORIGIN.to_string(),
+            ),
+            ("CORS_ALLOW_CREDENTIALS".to_string(), "true".to_string()),
+            ("CORS_MAX_AGE_SECS".to_string(), "600".to_string()),
+            ("CORS_EXPOSE_HEADERS".to_string(), "ETag".to_string()),
+        ],
+        ..TestConfig::default()
+    });
+
+    // Test case 1: The preflight carries the configured max-age and credentials
+    {
+        let response = send_http_request(
+            "/mongor_cors_test",
+            "OPTIONS",
+            None,
+            &[
+                ("Origin", ALLOWED_ORIGIN),
+                ("Access-Control-Request-Method", "POST"),
+            ],
+        );
+        assert_eq!(response.status().as_u16(), 200);
+        let headers = response.headers();
+        assert_eq!(headers.get("Access-Control-Max-Age").unwrap(), "600");
+        assert_eq!(
+            headers.get("Access-Control-Allow-Origin").unwrap(),
+            ALLOWED_ORIGIN
+        );
+        assert_eq!(
+            headers.get("Access-Control-Allow-Credentials").unwrap(),
+            "true"
+        );
+    }
+
+    // Test case 2: Responses to allowed origins expose the configured headers
+    {
+        let response = send_http_request("/api/", "GET", None, &[("Origin", ALLOWED_ORIGIN)]);
+        assert_eq!(response.status().as_u16(), 200);
+        let exposed = response
+            .headers()
+            .get("Access-Control-Expose-Headers")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_lowercase();
+        assert!(
+            exposed.contains("etag"),
+            "Unexpected exposed headers {}",
+            exposed
+        );
+    }
+
+    // Test case 3: Other origins get no CORS headers
+    {
+        let response = send_http_request(
+            "/mongor_cors_test",
+            "OPTIONS",
+            None,
+            &[
+                ("Origin", "https://evil.example.com"),
+                ("Access-Control-Request-Method", "POST"),
+            ],
+        );
+        assert!(
+            response
+                .headers()
+                .get("Access-Control-Allow-Origin")
+                .is_none()
+        );
+    }
+}
+
+#[test]
+#[serial]
+fn test_cors_wildcard_with_credentials_is_rejected_at_startup() {
+    // The configuration is checked before connecting, so no MongoDB is needed
+    let output = std::process::Command::new("cargo")
+        .args([
+            "run",
+            "--",
+            "--port",
+            &TestConfig::default().app_port.to_string(),
+        ])
+        .env("CORS_ALLOWED_ORIGINS", "*")
+        .env("CORS_ALLOW_CREDENTIALS", "true")
+        .output()
+        .expect("Failed to run the application");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("CORS_ALLOW_CREDENTIALS requires explicit CORS_ALLOWED_ORIGINS"),
+        "Unexpected output {}",
+        stderr
+    );
+}
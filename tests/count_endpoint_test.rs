@@ -0,0 +1,77 @@
+use mongodb::bson::doc;
+use serial_test::serial;
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::TestEnvironment;
+use utils::utils::make_get_request;
+
+static TEST_COLLECTION_NAME: &str = "mongor_count_endpoint_test";
+
+// Runs a count request expecting 200 and returns the parsed body
+fn get_count(query: &str) -> serde_json::Value {
+    let (status_code, body) =
+        make_get_request(&format!("/{}/count{}", TEST_COLLECTION_NAME, query));
+    assert_eq!(
+        status_code, 200,
+        "Expected status code 200, got {}",
+        status_code
+    );
+    serde_json::from_str(&body).expect("Failed to parse JSON response")
+}
+
+#[test]
+#[serial]
+fn test_count_endpoint_all_cases() {
+    let env = TestEnvironment::new();
+    let docs = (0..1000)
+        .map(|i| doc! { "_id": i, "parity": i % 2 })
+        .collect();
+    env.insert_test_data(TEST_COLLECTION_NAME, docs);
+
+    // Test case 1: Counts are exact by default and take filters
+    {
+        let count = get_count("?parity=0");
+        assert_eq!(
+            count,
+            serde_json::json!({ "count": 500, "estimate": false })
+        );
+
+        let count = get_count("");
+        assert_eq!(
+            count,
+            serde_json::json!({ "count": 1000, "estimate": false })
+        );
+    }
+
+    // Test case 2: Estimates come back quickly, labelled, and close to the real count
+    {
+        let started = std::time::Instant::now();
+        let count = get_count("?estimate=true");
+        assert!(
+            started.elapsed() < std::time::Duration::from_secs(1),
+            "Estimate took {:?}",
+            started.elapsed()
+        );
+        assert_eq!(count["estimate"], true);
+        let estimate = count["count"].as_u64().unwrap();
+        assert!(
+            (900..=1100).contains(&estimate),
+            "Implausible estimate {}",
+            estimate
+        );
+    }
+
+    // Test case 3: Estimates cannot be filtered
+    {
+        let (status_code, _body) = make_get_request(&format!(
+            "/{}/count?estimate=true&parity=0",
+            TEST_COLLECTION_NAME
+        ));
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use mongodb::bson::{Document, doc};
+use serial_test::serial;
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::{TOKIO_RUNTIME, TestConfig, TestEnvironment};
+use utils::utils::{make_get_request, make_http_request_with_headers};
+
+// Collection created in both the configured and the admin database
+static TEST_COLLECTION_NAME: &str = "mongor_database_header_test";
+// Database admin tooling may switch to
+static ADMIN_DATABASE_NAME: &str = "mongor_database_header_test_admin";
+
+#[test]
+#[serial]
+fn test_database_header_all_cases() {
+    let env = TestEnvironment::with_config(TestConfig {
+        app_env: vec![(
+            "DATABASE_ALLOWLIST".to_string(),
+            ADMIN_DATABASE_NAME.to_string(),
+        )],
+        ..TestConfig::default()
+    });
+
+    let default_doc = doc! { "_id": 1, "source": "configured database" };
+    let admin_doc = doc! { "_id": 1, "source": "admin database" };
+    env.insert_test_data(TEST_COLLECTION_NAME, vec![default_doc.clone()]);
+    TOKIO_RUNTIME.block_on(async {
+        let collection = env
+            .mongodb_client
+            .database(ADMIN_DATABASE_NAME)
+            .collection::<Document>(TEST_COLLECTION_NAME);
+        collection.drop().await.ok();
+        collection
+            .insert_one(admin_doc.clone())
+            .await
+            .expect("Failed to insert test data");
+    });
+
+    // Test case 1: Without the header the configured database is used
+    {
+        let (status_code, body) = make_get_request(&format!("/{}", TEST_COLLECTION_NAME));
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+        let docs: Vec<Document> = serde_json::from_str(&body).expect("Failed to parse JSON");
+        assert_eq!(docs, vec![default_doc]);
+    }
+
+    // Test case 2: An allowlisted database is read and written through the header
+    {
+        let (status_code, body) = make_http_request_with_headers(
+            &format!("/{}", TEST_COLLECTION_NAME),
+            "GET",
+            None,
+            &[("X-Database", ADMIN_DATABASE_NAME)],
+        );
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+        let docs: Vec<Document> = serde_json::from_str(&body).expect("Failed to parse JSON");
+        assert_eq!(docs, vec![admin_doc]);
+
+        let (status_code, _body) = make_http_request_with_headers(
+            &format!("/{}", TEST_COLLECTION_NAME),
+            "POST",
+            Some(r#"{"_id": 2, "source": "admin write"}"#),
+            &[("X-Database", ADMIN_DATABASE_NAME)],
+        );
+        assert_eq!(
+            status_code, 201,
+            "Expected status code 201, got {}",
+            status_code
+        );
+        let count = TOKIO_RUNTIME.block_on(async {
+            env.mongodb_client
+                .database(ADMIN_DATABASE_NAME)
+                .collection::<Document>(TEST_COLLECTION_NAME)
+                .count_documents(doc! {})
+                .await
+                .unwrap()
+        });
+        assert_eq!(count, 2);
+    }
+
+    // Test case 3: Databases outside the allowlist are rejected
+    {
+        let (status_code, _body) = make_http_request_with_headers(
+            &format!("/{}", TEST_COLLECTION_NAME),
+            "GET",
+            None,
+            &[("X-Database", "mongor_database_header_test_other")],
+        );
+        assert_eq!(
+            status_code, 403,
+            "Expected status code 403, got {}",
+            status_code
+        );
+    }
+}
@@ -1,5 +1,4 @@
 use mongodb::bson::{Document, doc};
-use serde_json;
 use serial_test::serial;
 
 // Import test environment and utilities from utils module
@@ -181,8 +180,8 @@ fn test_delete_endpoint_all_cases() {
             &env,
             "delete_all_documents",
             initial_docs,
-            "", // Empty query string to delete all documents
-            2,  // Expect 2 documents to be deleted
+            "?confirm=true", // No filter, so deleting everything needs confirming
+            2,               // Expect 2 documents to be deleted
         );
 
         // Verify no documents remain
@@ -193,4 +192,72 @@ fn test_delete_endpoint_all_cases() {
             remaining_docs.len()
         );
     }
+
+    // Test case 4: Delete only the two oldest documents
+    {
+        // Initial documents
+        let initial_docs = vec![
+            doc! { "_id": 1, "created": 30 },
+            doc! { "_id": 2, "created": 10 },
+            doc! { "_id": 3, "created": 40 },
+            doc! { "_id": 4, "created": 20 },
+        ];
+
+        // Run the delete test
+        let remaining_docs = run_delete_test(
+            &env,
+            "delete_oldest_documents",
+            initial_docs,
+            "?sort=created&limit=2",
+            2, // Expect the two oldest documents to be deleted
+        );
+
+        // Verify the newer documents remain
+        assert_eq!(
+            remaining_docs,
+            vec![
+                doc! { "_id": 1, "created": 30 },
+                doc! { "_id": 3, "created": 40 },
+            ]
+        );
+    }
+
+    // Test case 5: Deleting without a filter or confirm=true is refused
+    {
+        let collection_name = unique_collection_name("delete_unconfirmed");
+        env.insert_test_data(&collection_name, vec![doc! { "_id": 1 }]);
+
+        let (status_code, _body) = make_delete_request(&format!("/{}", collection_name));
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+
+        let (_status_code, body) = make_get_request(&format!("/{}", collection_name));
+        let documents: Vec<Document> =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        assert_eq!(documents, vec![doc! { "_id": 1 }]);
+    }
+
+    // Test case 6: A limit only stands in for confirm=true when it is positive
+    {
+        let collection_name = unique_collection_name("delete_zero_limit");
+        env.insert_test_data(&collection_name, vec![doc! { "_id": 1 }]);
+
+        for limit in ["0", "-1", "all"] {
+            let (status_code, _body) =
+                make_delete_request(&format!("/{}?limit={}", collection_name, limit));
+            assert_eq!(
+                status_code, 400,
+                "Expected status code 400, got {}",
+                status_code
+            );
+        }
+
+        let (_status_code, body) = make_get_request(&format!("/{}", collection_name));
+        let documents: Vec<Document> =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        assert_eq!(documents, vec![doc! { "_id": 1 }]);
+    }
 }
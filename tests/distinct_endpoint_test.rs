@@ -0,0 +1,68 @@
+use mongodb::bson::doc;
+use serial_test::serial;
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::TestEnvironment;
+use utils::utils::make_get_request;
+
+static TEST_COLLECTION_NAME: &str = "mongor_distinct_endpoint_test";
+
+// Runs a distinct request expecting 200 and returns the values, sorted for comparison
+fn get_distinct(query: &str) -> Vec<String> {
+    let (status_code, body) =
+        make_get_request(&format!("/{}/distinct{}", TEST_COLLECTION_NAME, query));
+    assert_eq!(
+        status_code, 200,
+        "Expected status code 200, got {}",
+        status_code
+    );
+    let body: serde_json::Value = serde_json::from_str(&body).expect("Failed to parse JSON");
+    let mut values: Vec<String> = body["values"]
+        .as_array()
+        .expect("Expected a values array")
+        .iter()
+        .map(|value| value.as_str().expect("Expected string values").to_string())
+        .collect();
+    values.sort();
+    values
+}
+
+#[test]
+#[serial]
+fn test_distinct_endpoint_all_cases() {
+    let env = TestEnvironment::new();
+    env.insert_test_data(
+        TEST_COLLECTION_NAME,
+        vec![
+            doc! { "_id": 1, "kind": "post", "tags": ["rust", "mongo"], "address": { "city": "Oslo" } },
+            doc! { "_id": 2, "kind": "post", "tags": ["rust"], "address": { "city": "Bergen" } },
+            doc! { "_id": 3, "kind": "page", "tags": ["web"], "address": { "city": "Oslo" } },
+        ],
+    );
+
+    // Test case 1: Array fields contribute their elements, not whole arrays
+    {
+        assert_eq!(get_distinct("?field=tags"), vec!["mongo", "rust", "web"]);
+    }
+
+    // Test case 2: Dotted paths reach into embedded documents
+    {
+        assert_eq!(get_distinct("?field=address.city"), vec!["Bergen", "Oslo"]);
+    }
+
+    // Test case 3: The filter scopes the distinct set
+    {
+        assert_eq!(get_distinct("?field=tags&kind=post"), vec!["mongo", "rust"]);
+    }
+
+    // Test case 4: The field is required
+    {
+        let (status_code, _body) = make_get_request(&format!("/{}/distinct", TEST_COLLECTION_NAME));
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use jsonwebtoken::{EncodingKey, Header, encode};
+use mongodb::bson::doc;
+use serial_test::serial;
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::{TestConfig, TestEnvironment};
+use utils::utils::{make_delete_request, make_http_request_with_headers};
+
+// Collection created and then dropped by the test
+static TEST_COLLECTION_NAME: &str = "mongor_drop_collection_test";
+static JWT_SECRET: &str = "mongor-test-secret";
+
+// Helper function to send a request with a long-lived token reading and writing everything
+fn make_authorized_request(path: &str, method: &str) -> (u16, String) {
+    let token = encode(
+        &Header::default(),
+        &serde_json::json!({ "scope": "read:* write:*", "exp": u32::MAX }),
+        &EncodingKey::from_secret(JWT_SECRET.as_bytes()),
+    )
+    .expect("Failed to sign token");
+    let authorization = format!("Bearer {}", token);
+    make_http_request_with_headers(
+        path,
+        method,
+        None,
+        &[("Authorization", authorization.as_str())],
+    )
+}
+
+#[test]
+#[serial]
+fn test_drop_collection_all_cases() {
+    let env = TestEnvironment::with_config(TestConfig {
+        app_env: vec![
+            ("CATALOG_REFRESH_SECS".to_string(), "1".to_string()),
+            ("JWT_SECRET".to_string(), JWT_SECRET.to_string()),
+        ],
+        ..TestConfig::default()
+    });
+    env.insert_test_data(TEST_COLLECTION_NAME, vec![doc! { "_id": 1 }]);
+    // Let the background task pick the new collection up
+    std::thread::sleep(std::time::Duration::from_secs(2));
+
+    let drop_path = format!("/api/collections/{}", TEST_COLLECTION_NAME);
+
+    // Test case 1: Dropping without confirmation is rejected and keeps the collection
+    {
+        let (status_code, _body) = make_authorized_request(&drop_path, "DELETE");
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+
+        let (status_code, _body) =
+            make_authorized_request(&format!("/{}", TEST_COLLECTION_NAME), "GET");
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+    }
+
+    // Test case 2: A confirmed drop removes the collection from the catalog and queries
+    {
+        let (_status_code, openapi) = make_authorized_request("/openapi.json", "GET");
+        assert!(openapi.contains(TEST_COLLECTION_NAME));
+
+        let (status_code, _body) =
+            make_authorized_request(&format!("{}?confirm=true", drop_path), "DELETE");
+        assert_eq!(
+            status_code, 204,
+            "Expected status code 204, got {}",
+            status_code
+        );
+
+        let (_status_code, openapi) = make_authorized_request("/openapi.json", "GET");
+        assert!(!openapi.contains(TEST_COLLECTION_NAME));
+
+        let (status_code, _body) =
+            make_authorized_request(&format!("/{}", TEST_COLLECTION_NAME), "GET");
+        assert_eq!(
+            status_code, 404,
+            "Expected status code 404, got {}",
+            status_code
+        );
+    }
+
+    // Test case 3: Dropping a missing collection returns 404
+    {
+        let (status_code, _body) =
+            make_authorized_request(&format!("{}?confirm=true", drop_path), "DELETE");
+        assert_eq!(
+            status_code, 404,
+            "Expected status code 404, got {}",
+            status_code
+        );
+    }
+}
+
+#[test]
+#[serial]
+fn test_drop_collection_requires_authentication() {
+    let env = TestEnvironment::new();
+    env.insert_test_data(TEST_COLLECTION_NAME, vec![doc! { "_id": 1 }]);
+
+    // Test case 1: Without JWT_SECRET nobody can drop a collection
+    {
+        let (status_code, _body) = make_delete_request(&format!(
+            "/api/collections/{}?confirm=true",
+            TEST_COLLECTION_NAME
+        ));
+        assert_eq!(
+            status_code, 403,
+            "Expected status code 403, got {}",
+            status_code
+        );
+    }
+}
@@ -0,0 +1,136 @@
+use mongodb::bson::{Document, doc};
+use serial_test::serial;
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::TestEnvironment;
+use utils::utils::{
+    make_delete_request, make_get_request, make_patch_request, make_post_request, make_put_request,
+};
+
+// Base name for test collections
+static TEST_COLLECTION_BASE_NAME: &str = "mongor_dry_run_test";
+
+// Generate a unique collection name for each test
+fn unique_collection_name(test_name: &str) -> String {
+    format!(
+        "{}_{}",
+        TEST_COLLECTION_BASE_NAME,
+        test_name.replace(" ", "_")
+    )
+}
+
+// Helper function to fetch every document of a collection
+fn get_all_documents(collection_name: &str) -> Vec<Document> {
+    let (status_code, body) = make_get_request(&format!("/{}", collection_name));
+    assert_eq!(
+        status_code, 200,
+        "Expected GET status code 200, got {}",
+        status_code
+    );
+    serde_json::from_str(&body).expect("Failed to parse JSON response")
+}
+
+// Helper function to run a dry-run request and check that nothing was written
+fn run_dry_run_test(
+    env: &TestEnvironment,
+    test_name: &str,
+    method: &str,
+    query_params: &str,
+    body: Option<Document>,
+) -> serde_json::Value {
+    let collection_name = unique_collection_name(test_name);
+    let docs = vec![
+        doc! { "_id": 1, "category": "A", "value": 1 },
+        doc! { "_id": 2, "category": "A", "value": 2 },
+        doc! { "_id": 3, "category": "B", "value": 3 },
+    ];
+    env.insert_test_data(&collection_name, docs.clone());
+
+    let path = format!("/{}{}", collection_name, query_params);
+    let json_body = body.map(|body| serde_json::to_string(&body).unwrap());
+    let (status_code, response) = match method {
+        "POST" => make_post_request(&path, json_body.as_deref().unwrap()),
+        "PUT" => make_put_request(&path, json_body.as_deref().unwrap()),
+        "PATCH" => make_patch_request(&path, json_body.as_deref().unwrap()),
+        "DELETE" => make_delete_request(&path),
+        _ => panic!("Unsupported HTTP method: {}", method),
+    };
+    assert_eq!(
+        status_code, 200,
+        "Expected status code 200, got {}",
+        status_code
+    );
+
+    // The collection is untouched
+    assert_eq!(get_all_documents(&collection_name), docs);
+
+    let summary: serde_json::Value =
+        serde_json::from_str(&response).expect("Failed to parse dry-run summary");
+    assert_eq!(summary["dryRun"], true);
+    summary
+}
+
+#[test]
+#[serial]
+fn test_dry_run_all_cases() {
+    // Create a single test environment for all test cases
+    let env = TestEnvironment::new();
+
+    // Test case 1: POST reports the document it would insert
+    {
+        let summary = run_dry_run_test(
+            &env,
+            "post",
+            "POST",
+            "?dryRun=true",
+            Some(doc! { "category": "C", "value": 4 }),
+        );
+        assert_eq!(summary["document"]["category"], "C");
+    }
+
+    // Test case 2: PUT reports a single match and the computed update
+    {
+        let summary = run_dry_run_test(
+            &env,
+            "put",
+            "PUT",
+            "?category=A&dryRun=true",
+            Some(doc! { "value": 10 }),
+        );
+        assert_eq!(summary["matchedCount"], 1);
+        assert_eq!(summary["upsert"], false);
+        assert_eq!(summary["update"]["$set"]["value"], 10);
+    }
+
+    // Test case 3: PUT without matches reports an upsert
+    {
+        let summary = run_dry_run_test(
+            &env,
+            "put_upsert",
+            "PUT",
+            "?category=Z&dryRun=true",
+            Some(doc! { "value": 10 }),
+        );
+        assert_eq!(summary["matchedCount"], 0);
+        assert_eq!(summary["upsert"], true);
+    }
+
+    // Test case 4: PATCH reports every matching document
+    {
+        let summary = run_dry_run_test(
+            &env,
+            "patch",
+            "PATCH",
+            "?category=A&dryRun=true",
+            Some(doc! { "value": 10 }),
+        );
+        assert_eq!(summary["matchedCount"], 2);
+    }
+
+    // Test case 5: DELETE reports how many documents would be deleted
+    {
+        let summary = run_dry_run_test(&env, "delete", "DELETE", "?value=gt.1&dryRun=true", None);
+        assert_eq!(summary["deletedCount"], 2);
+    }
+}
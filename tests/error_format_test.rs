@@ -0,0 +1,80 @@
+use mongodb::bson::doc;
+use serial_test::serial;
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::{TestConfig, TestEnvironment};
+use utils::utils::{make_get_request, make_http_request_with_headers, send_http_request};
+
+// Collection queried with an invalid filter
+static TEST_COLLECTION_NAME: &str = "mongor_error_format_test";
+
+#[test]
+#[serial]
+fn test_error_format_all_cases() {
+    let env = TestEnvironment::with_config(TestConfig {
+        app_env: vec![("ERROR_FORMAT".to_string(), "problem".to_string())],
+        ..TestConfig::default()
+    });
+    env.insert_test_data(TEST_COLLECTION_NAME, vec![doc! { "_id": 1 }]);
+
+    // Test case 1: A missing collection is reported as a problem document
+    {
+        let response = send_http_request("/mongor_error_format_missing", "GET", None, &[]);
+        assert_eq!(response.status().as_u16(), 404);
+        assert_eq!(
+            response.headers()["Content-Type"],
+            "application/problem+json"
+        );
+
+        let body = response.text().expect("Failed to read HTTP response");
+        let problem: serde_json::Value =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        assert_eq!(problem["type"], "about:blank");
+        assert_eq!(problem["title"], "Not Found");
+        assert_eq!(problem["status"], 404);
+        assert!(problem["detail"].is_string());
+    }
+
+    // Test case 2: An invalid query parameter is reported as a problem document
+    {
+        let (status_code, body) =
+            make_get_request(&format!("/{}?age=between.1", TEST_COLLECTION_NAME));
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+
+        let problem: serde_json::Value =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        assert_eq!(problem["title"], "Bad Request");
+        assert_eq!(problem["status"], 400);
+        assert!(
+            problem["detail"]
+                .as_str()
+                .unwrap()
+                .starts_with("Invalid query parameter")
+        );
+    }
+
+    // Test case 3: Plain text is kept for clients asking for it
+    {
+        let (status_code, body) = make_http_request_with_headers(
+            &format!("/{}?age=between.1", TEST_COLLECTION_NAME),
+            "GET",
+            None,
+            &[("Accept", "text/plain")],
+        );
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+        assert!(
+            body.starts_with("Invalid query parameter"),
+            "Unexpected body: {}",
+            body
+        );
+    }
+}
@@ -0,0 +1,69 @@
+use mongodb::bson::doc;
+use serial_test::serial;
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::TestEnvironment;
+use utils::utils::make_get_request;
+
+// Base name for test collections
+static TEST_COLLECTION_BASE_NAME: &str = "mongor_explain_endpoint_test";
+
+// Generate a unique collection name for each test
+fn unique_collection_name(test_name: &str) -> String {
+    format!(
+        "{}_{}",
+        TEST_COLLECTION_BASE_NAME,
+        test_name.replace(" ", "_")
+    )
+}
+
+#[test]
+#[serial]
+fn test_explain_endpoint_all_cases() {
+    // Create a single test environment for all test cases
+    let env = TestEnvironment::new();
+
+    // Test case 1: Explain a filtered query
+    {
+        let collection_name = unique_collection_name("filtered_query");
+        env.insert_test_data(
+            &collection_name,
+            vec![doc! { "_id": 1, "age": 25 }, doc! { "_id": 2, "age": 35 }],
+        );
+
+        let (status_code, body) =
+            make_get_request(&format!("/{}/explain?age=gt.30&limit=5", collection_name));
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+
+        let plan: serde_json::Value =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        assert!(
+            plan["queryPlanner"].is_object(),
+            "Expected a queryPlanner section, got {}",
+            body
+        );
+        assert_eq!(
+            plan["queryPlanner"]["parsedQuery"],
+            serde_json::json!({ "age": { "$gt": 30.0 } })
+        );
+    }
+
+    // Test case 2: Invalid filter returns 400
+    {
+        let collection_name = unique_collection_name("invalid_filter");
+        env.insert_test_data(&collection_name, vec![doc! { "_id": 1 }]);
+
+        let (status_code, _body) =
+            make_get_request(&format!("/{}/explain?age=gt.", collection_name));
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use futures_util::TryStreamExt;
+use mongodb::bson::{Document, doc};
+use serial_test::serial;
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::{TOKIO_RUNTIME, TestConfig, TestEnvironment};
+use utils::utils::{
+    make_get_request, make_http_request_with_headers, make_patch_request, make_post_request,
+};
+
+static TEST_COLLECTION_NAME: &str = "mongor_field_case_test";
+
+// Reads the stored documents directly, bypassing the renaming
+fn stored_documents(env: &TestEnvironment) -> Vec<Document> {
+    TOKIO_RUNTIME.block_on(async {
+        env.mongodb_client
+            .database(&env.config.database_name)
+            .collection::<Document>(TEST_COLLECTION_NAME)
+            .find(doc! {})
+            .sort(doc! { "_id": 1 })
+            .await
+            .expect("Failed to read the stored documents")
+            .try_collect()
+            .await
+            .expect("Failed to read the stored documents")
+    })
+}
+
+fn get_documents(query: &str) -> Vec<Document> {
+    let (status_code, body) = make_get_request(&format!("/{}{}", TEST_COLLECTION_NAME, query));
+    assert_eq!(
+        status_code, 200,
+        "Expected status code 200, got {}",
+        status_code
+    );
+    serde_json::from_str(&body).expect("Failed to parse JSON response")
+}
+
+#[test]
+#[serial]
+fn test_field_case_all_cases() {
+    let env = TestEnvironment::with_config(TestConfig {
+        app_env: vec![("FIELD_CASE".to_string(), "camel".to_string())],
+        ..TestConfig::default()
+    });
+    env.insert_test_data(
+        TEST_COLLECTION_NAME,
+        vec![doc! { "_id": 1, "first_name": "Ann", "home_address": { "zip_code": "0150" } }],
+    );
+
+    // Test case 1: Stored snake_case names are returned as camelCase
+    {
+        assert_eq!(
+            get_documents("?_id=1"),
+            vec![doc! { "_id": 1, "firstName": "Ann", "homeAddress": { "zipCode": "0150" } }]
+        );
+    }
+
+    // Test case 2: Written camelCase names are stored as snake_case and read back unchanged
+    {
+        let (status_code, _body) = make_post_request(
+            &format!("/{}", TEST_COLLECTION_NAME),
+            r#"{"_id": 2, "firstName": "Bo", "pastJobs": [{"jobTitle": "clerk"}]}"#,
+        );
+        assert_eq!(
+            status_code, 201,
+            "Expected status code 201, got {}",
+            status_code
+        );
+        assert_eq!(
+            stored_documents(&env)[1],
+            doc! { "_id": 2, "first_name": "Bo", "past_jobs": [{ "job_title": "clerk" }] }
+        );
+        assert_eq!(
+            get_documents("?_id=2"),
+            vec![doc! { "_id": 2, "firstName": "Bo", "pastJobs": [{ "jobTitle": "clerk" }] }]
+        );
+    }
+
+    // Test case 3: Updates and filters are renamed too
+    {
+        let (status_code, _body) = make_patch_request(
+            &format!("/{}?firstName=Ann", TEST_COLLECTION_NAME),
+            r#"{"lastName": "Lee"}"#,
+        );
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+        assert_eq!(stored_documents(&env)[0].get_str("last_name"), Ok("Lee"));
+        assert_eq!(get_documents("?_id=1")[0].get_str("lastName"), Ok("Lee"));
+    }
+
+    // Test case 4: `sort` and `select` name fields as responses do
+    {
+        assert_eq!(
+            get_documents("?select=firstName&sort=firstName.desc"),
+            vec![
+                doc! { "_id": 2, "firstName": "Bo" },
+                doc! { "_id": 1, "firstName": "Ann" },
+            ]
+        );
+    }
+
+    // Test case 5: JSON Patch paths are renamed like bodies
+    {
+        let (status_code, _body) = make_http_request_with_headers(
+            &format!("/{}/1", TEST_COLLECTION_NAME),
+            "PATCH",
+            Some(r#"[{"op": "replace", "path": "/homeAddress/zipCode", "value": "0160"}]"#),
+            &[("Content-Type", "application/json-patch+json")],
+        );
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+        assert_eq!(
+            stored_documents(&env)[0].get_document("home_address"),
+            Ok(&doc! { "zip_code": "0160" })
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use mongodb::bson::{Binary, doc, spec::BinarySubtype};
+use serial_test::serial;
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::TestEnvironment;
+use utils::utils::send_http_request;
+
+static TEST_COLLECTION_NAME: &str = "mongor_field_download_test";
+
+#[test]
+#[serial]
+fn test_field_download_all_cases() {
+    let env = TestEnvironment::new();
+    let payload: Vec<u8> = (0..32).collect();
+    env.insert_test_data(
+        TEST_COLLECTION_NAME,
+        vec![doc! {
+            "_id": 1,
+            "name": "blob",
+            "file": { "data": Binary { subtype: BinarySubtype::Generic, bytes: payload.clone() } },
+        }],
+    );
+    let field_path = format!("/{}/1/field/file.data", TEST_COLLECTION_NAME);
+
+    // Test case 1: Without a Range the whole field is sent, advertising range support
+    {
+        let response = send_http_request(&field_path, "GET", None, &[]);
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(response.headers().get("Accept-Ranges").unwrap(), "bytes");
+        assert_eq!(response.bytes().unwrap().to_vec(), payload);
+    }
+
+    // Test case 2: A byte range comes back as 206 with its Content-Range
+    {
+        let response = send_http_request(&field_path, "GET", None, &[("Range", "bytes=2-5")]);
+        assert_eq!(response.status().as_u16(), 206);
+        assert_eq!(
+            response.headers().get("Content-Range").unwrap(),
+            "bytes 2-5/32"
+        );
+        assert_eq!(response.bytes().unwrap().to_vec(), payload[2..=5].to_vec());
+
+        let response = send_http_request(&field_path, "GET", None, &[("Range", "bytes=-4")]);
+        assert_eq!(response.status().as_u16(), 206);
+        assert_eq!(
+            response.headers().get("Content-Range").unwrap(),
+            "bytes 28-31/32"
+        );
+        assert_eq!(response.bytes().unwrap().to_vec(), payload[28..].to_vec());
+    }
+
+    // Test case 3: Ranges past the end are not satisfiable
+    {
+        let response = send_http_request(&field_path, "GET", None, &[("Range", "bytes=64-")]);
+        assert_eq!(response.status().as_u16(), 416);
+        assert_eq!(
+            response.headers().get("Content-Range").unwrap(),
+            "bytes */32"
+        );
+    }
+
+    // Test case 4: Only binary fields can be downloaded, and they have to exist
+    {
+        let path = format!("/{}/1/field/name", TEST_COLLECTION_NAME);
+        let response = send_http_request(&path, "GET", None, &[]);
+        assert_eq!(response.status().as_u16(), 422);
+
+        let path = format!("/{}/1/field/missing", TEST_COLLECTION_NAME);
+        let response = send_http_request(&path, "GET", None, &[]);
+        assert_eq!(response.status().as_u16(), 404);
+
+        let path = format!("/{}/2/field/file.data", TEST_COLLECTION_NAME);
+        let response = send_http_request(&path, "GET", None, &[]);
+        assert_eq!(response.status().as_u16(), 404);
+    }
+}
@@ -0,0 +1,65 @@
+use mongodb::bson::{Document, doc};
+use serial_test::serial;
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::TestEnvironment;
+use utils::utils::make_post_request;
+
+static TEST_COLLECTION_NAME: &str = "mongor_find_endpoint_test";
+
+#[test]
+#[serial]
+fn test_find_endpoint_all_cases() {
+    let env = TestEnvironment::new();
+    let docs = vec![
+        doc! { "_id": 1, "status": "open", "items": [{ "sku": "a", "qty": 5 }] },
+        doc! { "_id": 2, "status": "open", "items": [{ "sku": "a", "qty": 1 }, { "sku": "b", "qty": 9 }] },
+        doc! { "_id": 3, "status": "closed", "items": [{ "sku": "a", "qty": 7 }] },
+    ];
+    env.insert_test_data(TEST_COLLECTION_NAME, docs.clone());
+    let path = format!("/{}/find", TEST_COLLECTION_NAME);
+
+    // Test case 1: A raw `$and` with `$elemMatch` runs as written
+    {
+        let (status_code, body) = make_post_request(
+            &format!("{}?sort=_id", path),
+            r#"{"$and": [{"status": "open"}, {"items": {"$elemMatch": {"sku": "a", "qty": {"$gte": 5}}}}]}"#,
+        );
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+        let documents: Vec<Document> =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        assert_eq!(documents, docs[..1].to_vec());
+    }
+
+    // Test case 2: Query-string options still apply
+    {
+        let (status_code, body) = make_post_request(
+            &format!("{}?sort=_id.desc&limit=1&select=status", path),
+            "{}",
+        );
+        assert_eq!(status_code, 200, "{}", body);
+        let documents: Vec<Document> =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        assert_eq!(documents, vec![doc! { "_id": 3, "status": "closed" }]);
+    }
+
+    // Test case 3: `$where` is rejected, however deeply it is nested
+    {
+        for body in [
+            r#"{"$where": "this.status == 'open'"}"#,
+            r#"{"$or": [{"status": "open"}, {"$where": "true"}]}"#,
+        ] {
+            let (status_code, _body) = make_post_request(&path, body);
+            assert_eq!(
+                status_code, 400,
+                "Expected status code 400, got {}",
+                status_code
+            );
+        }
+    }
+}
@@ -1,11 +1,13 @@
-use mongodb::bson::{Document, doc};
-use serde_json;
+use mongodb::IndexModel;
+use mongodb::bson::{Bson, DateTime, Document, doc, oid::ObjectId};
 use serial_test::serial;
 
 // Import test environment and utilities from utils module
 mod utils;
-use utils::test_environment::TestEnvironment;
-use utils::utils::make_get_request;
+use utils::test_environment::{TOKIO_RUNTIME, TestConfig, TestEnvironment};
+use utils::utils::{
+    make_get_request, make_http_request_with_headers, make_post_request, send_http_request,
+};
 
 // Base name for test collections
 static TEST_COLLECTION_BASE_NAME: &str = "mongor_get_endpoint_test";
@@ -287,4 +289,1298 @@ fn test_query_parser_all_cases() {
             vec![docs[2].clone(), docs[3].clone()],
         );
     }
+
+    // Test case 5: Modulo query selecting every fifth document
+    {
+        // Test documents with sequential counters
+        let docs: Vec<Document> = (1..=12)
+            .map(|i| {
+                doc! {
+                    "_id": i,
+                    "counter": i
+                }
+            })
+            .collect();
+
+        // counter % 5 == 0 should match documents 5 and 10
+        run_get_test(
+            &env,
+            "modulo_query",
+            docs.clone(),
+            "?counter=mod.(5,0)",
+            vec![docs[4].clone(), docs[9].clone()],
+        );
+    }
+
+    // Test case 6: Array contains all of the listed values
+    {
+        // Test documents with varying tag sets
+        let docs = vec![
+            doc! {
+                "_id": 1,
+                "tags": ["rust", "mongodb", "web"]
+            },
+            doc! {
+                "_id": 2,
+                "tags": ["rust"]
+            },
+            doc! {
+                "_id": 3,
+                "tags": ["mongodb", "rust"]
+            },
+            doc! {
+                "_id": 4,
+                "tags": ["mongodb", "python"]
+            },
+        ];
+
+        // Only documents tagged with both rust and mongodb match
+        run_get_test(
+            &env,
+            "all_query",
+            docs.clone(),
+            "?tags=all.(rust,mongodb)",
+            vec![docs[0].clone(), docs[2].clone()],
+        );
+    }
+
+    // Test case 7: Filter by BSON type on a field holding mixed types
+    {
+        // Test documents with string and numeric values for the same field
+        let docs = vec![
+            doc! {
+                "_id": 1,
+                "value": "forty-two"
+            },
+            doc! {
+                "_id": 2,
+                "value": 42
+            },
+            doc! {
+                "_id": 3,
+                "value": 4.2
+            },
+            doc! {
+                "_id": 4,
+                "value": "seven"
+            },
+        ];
+
+        run_get_test(
+            &env,
+            "type_string_query",
+            docs.clone(),
+            "?value=type.string",
+            vec![docs[0].clone(), docs[3].clone()],
+        );
+        run_get_test(
+            &env,
+            "type_number_query",
+            docs.clone(),
+            "?value=type.number",
+            vec![docs[1].clone(), docs[2].clone()],
+        );
+    }
+
+    // Test case 8: Top-level and group containing a nested or group
+    {
+        // Test documents
+        let docs = vec![
+            doc! {
+                "_id": 1,
+                "category": "A",
+                "status": "active"
+            },
+            doc! {
+                "_id": 2,
+                "category": "B",
+                "status": "active"
+            },
+            doc! {
+                "_id": 3,
+                "category": "C",
+                "status": "active"
+            },
+            doc! {
+                "_id": 4,
+                "category": "A",
+                "status": "inactive"
+            },
+        ];
+
+        // (category=A OR category=C) AND status=active matches documents 1 and 3
+        run_get_test(
+            &env,
+            "nested_and_or_query",
+            docs.clone(),
+            "?and=(or=(category.A,category.C),status.active)",
+            vec![docs[0].clone(), docs[2].clone()],
+        );
+    }
+}
+
+#[test]
+#[serial]
+fn test_get_endpoint_projection_cases() {
+    // Configure a default exclusion for the heavy field of one collection
+    let collection_name = unique_collection_name("default_exclusion");
+    let env = TestEnvironment::with_config(TestConfig {
+        app_env: vec![(
+            "COLLECTION_EXCLUDED_FIELDS".to_string(),
+            format!("{}:rawPayload", collection_name),
+        )],
+        ..TestConfig::default()
+    });
+
+    let test_doc = doc! {
+        "_id": 1,
+        "name": "test document",
+        "rawPayload": "a very large payload"
+    };
+
+    // Test case 1: Excluded field is absent by default
+    run_get_test(
+        &env,
+        "default_exclusion",
+        vec![test_doc.clone()],
+        "",
+        vec![doc! { "_id": 1, "name": "test document" }],
+    );
+
+    // Test case 2: Excluded field is present when explicitly selected
+    run_get_test(
+        &env,
+        "default_exclusion",
+        vec![test_doc.clone()],
+        "?select=name,rawPayload",
+        vec![test_doc],
+    );
+}
+
+#[test]
+#[serial]
+fn test_get_endpoint_ejson_cases() {
+    // Create a single test environment for all test cases
+    let env = TestEnvironment::new();
+
+    // Test case 1: Canonical EJSON round-trips an ObjectId and a DateTime
+    {
+        let collection_name = unique_collection_name("ejson_round_trip");
+        let test_doc = doc! {
+            "_id": ObjectId::new(),
+            "createdAt": DateTime::from_millis(1_700_000_000_000),
+            "count": 3
+        };
+        env.insert_test_data(&collection_name, vec![test_doc.clone()]);
+
+        let (status_code, body) = make_http_request_with_headers(
+            &format!("/{}", collection_name),
+            "GET",
+            None,
+            &[("Accept", "application/ejson")],
+        );
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+
+        // Parse the body as Extended JSON back into BSON
+        let json: serde_json::Value =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        let documents = match Bson::try_from(json).expect("Failed to parse Extended JSON") {
+            Bson::Array(values) => values,
+            other => panic!("Expected an array, got {}", other),
+        };
+        assert_eq!(documents, vec![Bson::Document(test_doc)]);
+    }
+}
+
+#[test]
+#[serial]
+fn test_get_endpoint_cursor_pagination() {
+    // Create a single test environment for all test cases
+    let env = TestEnvironment::new();
+
+    // Test case 1: Paging with cursors visits every document exactly once, in _id order
+    {
+        let collection_name = unique_collection_name("cursor_pagination");
+        let docs: Vec<Document> = (1..=7)
+            .rev()
+            .map(|i| doc! { "_id": i, "counter": i })
+            .collect();
+        env.insert_test_data(&collection_name, docs);
+
+        let mut seen = Vec::new();
+        let mut after = String::new();
+        loop {
+            let response = send_http_request(
+                &format!("/{}?after={}&limit=3", collection_name, after),
+                "GET",
+                None,
+                &[],
+            );
+            assert_eq!(response.status().as_u16(), 200);
+            let next_cursor = response
+                .headers()
+                .get("X-Next-Cursor")
+                .map(|cursor| cursor.to_str().unwrap().to_string());
+            let page: Vec<Document> =
+                serde_json::from_str(&response.text().unwrap()).expect("Failed to parse JSON");
+            seen.extend(page.iter().map(|doc| doc.get_i32("_id").unwrap()));
+
+            match next_cursor {
+                Some(cursor) => after = cursor,
+                None => break,
+            }
+        }
+
+        assert_eq!(seen, (1..=7).collect::<Vec<i32>>());
+    }
+
+    // Test case 2: ObjectId cursors compare by ObjectId order
+    {
+        let collection_name = unique_collection_name("cursor_pagination_object_id");
+        let ids: Vec<ObjectId> = (0..4).map(|_| ObjectId::new()).collect();
+        env.insert_test_data(
+            &collection_name,
+            ids.iter().map(|id| doc! { "_id": *id }).collect(),
+        );
+
+        let (status_code, body) = make_get_request(&format!(
+            "/{}?after={}&limit=10",
+            collection_name,
+            ids[1].to_hex()
+        ));
+        assert_eq!(status_code, 200);
+        let response: serde_json::Value =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        let documents = match Bson::try_from(response).expect("Failed to parse Extended JSON") {
+            Bson::Array(values) => values,
+            other => panic!("Expected an array, got {}", other),
+        };
+        assert_eq!(
+            documents,
+            vec![
+                Bson::Document(doc! { "_id": ids[2] }),
+                Bson::Document(doc! { "_id": ids[3] }),
+            ]
+        );
+    }
+}
+
+#[test]
+#[serial]
+fn test_get_endpoint_batch_ids() {
+    // Create a single test environment for all test cases
+    let env = TestEnvironment::new();
+
+    // Test case 1: A mix of existing and missing ids returns the existing subset
+    {
+        let collection_name = unique_collection_name("batch_ids");
+        let docs: Vec<Document> = (1..=5).map(|i| doc! { "_id": i, "counter": i }).collect();
+        env.insert_test_data(&collection_name, docs.clone());
+
+        let response = send_http_request(
+            &format!("/{}?ids=2,4,9,11", collection_name),
+            "GET",
+            None,
+            &[],
+        );
+        assert_eq!(response.status().as_u16(), 200);
+        let missing_ids = response
+            .headers()
+            .get("X-Missing-Ids")
+            .map(|ids| ids.to_str().unwrap().to_string());
+        let documents: Vec<Document> =
+            serde_json::from_str(&response.text().unwrap()).expect("Failed to parse JSON");
+
+        assert_eq!(documents, vec![docs[1].clone(), docs[3].clone()]);
+        assert_eq!(missing_ids.as_deref(), Some("9,11"));
+    }
+
+    // Test case 2: ObjectId ids are matched as ObjectIds
+    {
+        let collection_name = unique_collection_name("batch_object_ids");
+        let ids: Vec<ObjectId> = (0..3).map(|_| ObjectId::new()).collect();
+        env.insert_test_data(
+            &collection_name,
+            ids.iter().map(|id| doc! { "_id": *id }).collect(),
+        );
+
+        let response = send_http_request(
+            &format!(
+                "/{}?ids={},{}",
+                collection_name,
+                ids[0].to_hex(),
+                ids[2].to_hex()
+            ),
+            "GET",
+            None,
+            &[],
+        );
+        assert_eq!(response.status().as_u16(), 200);
+        assert!(response.headers().get("X-Missing-Ids").is_none());
+        let json: serde_json::Value =
+            serde_json::from_str(&response.text().unwrap()).expect("Failed to parse JSON");
+        assert_eq!(
+            Bson::try_from(json).expect("Failed to parse Extended JSON"),
+            Bson::Array(vec![
+                Bson::Document(doc! { "_id": ids[0] }),
+                Bson::Document(doc! { "_id": ids[2] }),
+            ])
+        );
+    }
+}
+
+#[test]
+#[serial]
+fn test_get_endpoint_response_byte_budget() {
+    // Allow roughly two of the large documents below per response
+    let env = TestEnvironment::with_config(TestConfig {
+        app_env: vec![("MAX_RESPONSE_BYTES".to_string(), "10000".to_string())],
+        ..TestConfig::default()
+    });
+
+    let collection_name = unique_collection_name("response_byte_budget");
+    let docs: Vec<Document> = (1..=3)
+        .map(|i| doc! { "_id": i, "payload": "x".repeat(4000) })
+        .collect();
+    env.insert_test_data(&collection_name, docs.clone());
+
+    // Test case 1: The full result set exceeds the budget
+    {
+        let (status_code, _body) = make_get_request(&format!("/{}", collection_name));
+        assert_eq!(
+            status_code, 413,
+            "Expected status code 413, got {}",
+            status_code
+        );
+    }
+
+    // Test case 2: A smaller page fits within the budget
+    run_get_test(
+        &env,
+        "response_byte_budget",
+        docs.clone(),
+        "?limit=2",
+        vec![docs[0].clone(), docs[1].clone()],
+    );
+}
+
+#[test]
+#[serial]
+fn test_get_endpoint_meta_envelope() {
+    // Create a single test environment for all test cases
+    let env = TestEnvironment::new();
+
+    // Test case 1: The envelope echoes the parsed filter alongside the results
+    {
+        let collection_name = unique_collection_name("meta_envelope");
+        let docs = vec![
+            doc! { "_id": 1, "age": 25, "status": "inactive" },
+            doc! { "_id": 2, "age": 35, "status": "inactive" },
+            doc! { "_id": 3, "age": 20, "status": "active" },
+        ];
+        env.insert_test_data(&collection_name, docs.clone());
+
+        let (status_code, body) = make_get_request(&format!(
+            "/{}?or=(age.gt.30,status.active)&meta=true",
+            collection_name
+        ));
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+
+        let envelope: serde_json::Value =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        let data: Vec<Document> =
+            serde_json::from_value(envelope["data"].clone()).expect("Failed to parse data");
+        assert_eq!(data, vec![docs[1].clone(), docs[2].clone()]);
+
+        let meta = &envelope["meta"];
+        assert_eq!(
+            meta["filter"],
+            serde_json::json!({ "$or": [{ "age": { "$gt": 30.0 } }, { "status": "active" }] })
+        );
+        assert_eq!(meta["count"], 2);
+        assert!(meta["took_ms"].is_u64());
+    }
+}
+
+#[test]
+#[serial]
+fn test_get_endpoint_redacted_fields() {
+    // Configure sensitive fields that must never leave the server
+    let collection_name = unique_collection_name("redacted_fields");
+    let env = TestEnvironment::with_config(TestConfig {
+        app_env: vec![(
+            "COLLECTION_REDACTED_FIELDS".to_string(),
+            format!("{}:password,profile.ssn", collection_name),
+        )],
+        ..TestConfig::default()
+    });
+
+    let test_doc = doc! {
+        "_id": 1,
+        "name": "test user",
+        "password": "hunter2",
+        "profile": { "ssn": "123-45-6789", "city": "Bucharest" }
+    };
+    let redacted_doc = doc! {
+        "_id": 1,
+        "name": "test user",
+        "profile": { "city": "Bucharest" }
+    };
+
+    // Test case 1: Redacted fields are absent by default
+    run_get_test(
+        &env,
+        "redacted_fields",
+        vec![test_doc.clone()],
+        "",
+        vec![redacted_doc.clone()],
+    );
+
+    // Test case 2: Redacted fields stay absent even when explicitly selected
+    run_get_test(
+        &env,
+        "redacted_fields",
+        vec![test_doc.clone()],
+        "?select=name,password,profile.ssn",
+        vec![doc! { "_id": 1, "name": "test user", "profile": {} }],
+    );
+
+    // Test case 3: The meta envelope carries the redacted documents too
+    {
+        let (status_code, body) = make_get_request(&format!("/{}?meta=true", collection_name));
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+        assert!(!body.contains("hunter2"), "Unexpected body: {}", body);
+        assert!(!body.contains("123-45-6789"), "Unexpected body: {}", body);
+    }
+}
+
+#[test]
+#[serial]
+fn test_get_endpoint_sort_tiebreaker() {
+    // Create a single test environment for all test cases
+    let env = TestEnvironment::new();
+
+    // Ties on the sort field are inserted out of `_id` order
+    let docs = vec![
+        doc! { "_id": 4, "category": "B" },
+        doc! { "_id": 2, "category": "A" },
+        doc! { "_id": 5, "category": "A" },
+        doc! { "_id": 1, "category": "B" },
+        doc! { "_id": 3, "category": "A" },
+    ];
+
+    // Test case 1: Ties are broken by ascending `_id`
+    run_get_test(
+        &env,
+        "sort_tiebreaker",
+        docs.clone(),
+        "?sort=category",
+        vec![
+            doc! { "_id": 2, "category": "A" },
+            doc! { "_id": 3, "category": "A" },
+            doc! { "_id": 5, "category": "A" },
+            doc! { "_id": 1, "category": "B" },
+            doc! { "_id": 4, "category": "B" },
+        ],
+    );
+
+    // Test case 2: Pages over the ties are repeatable and do not overlap
+    {
+        let collection_name = unique_collection_name("sort_tiebreaker");
+        let get_page = |skip: usize| {
+            let (status_code, body) = make_get_request(&format!(
+                "/{}?sort=category.desc&limit=2&skip={}",
+                collection_name, skip
+            ));
+            assert_eq!(
+                status_code, 200,
+                "Expected status code 200, got {}",
+                status_code
+            );
+            serde_json::from_str::<Vec<Document>>(&body).expect("Failed to parse JSON response")
+        };
+
+        let first_pass: Vec<Document> = (0..3).flat_map(|page| get_page(page * 2)).collect();
+        let second_pass: Vec<Document> = (0..3).flat_map(|page| get_page(page * 2)).collect();
+        assert_eq!(first_pass, second_pass);
+        assert_eq!(
+            first_pass
+                .iter()
+                .map(|doc| doc.get_i32("_id").unwrap())
+                .collect::<Vec<_>>(),
+            vec![1, 4, 2, 3, 5]
+        );
+    }
+}
+
+#[test]
+#[serial]
+fn test_get_endpoint_conditional_get() {
+    // Create a single test environment for all test cases
+    let env = TestEnvironment::new();
+    let collection_name = unique_collection_name("conditional_get");
+    env.insert_test_data(
+        &collection_name,
+        (1..=3).map(|i| doc! { "_id": i, "counter": i }).collect(),
+    );
+
+    // Test case 1: Query responses carry an ETag and a Cache-Control header
+    let response = send_http_request(&format!("/{}", collection_name), "GET", None, &[]);
+    assert_eq!(response.status().as_u16(), 200);
+    let etag = response
+        .headers()
+        .get("ETag")
+        .expect("Expected an ETag header")
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert_eq!(response.headers().get("Cache-Control").unwrap(), "no-cache");
+
+    // Test case 2: Revalidating with the ETag returns 304 without a body
+    let response = send_http_request(
+        &format!("/{}", collection_name),
+        "GET",
+        None,
+        &[("If-None-Match", &etag)],
+    );
+    assert_eq!(response.status().as_u16(), 304);
+    assert!(response.text().unwrap().is_empty());
+
+    // Test case 3: A changed result gets a new ETag
+    make_post_request(
+        &format!("/{}", collection_name),
+        r#"{"_id": 4, "counter": 4}"#,
+    );
+    let response = send_http_request(
+        &format!("/{}", collection_name),
+        "GET",
+        None,
+        &[("If-None-Match", &etag)],
+    );
+    assert_eq!(response.status().as_u16(), 200);
+    assert_ne!(response.headers().get("ETag").unwrap(), etag.as_str());
+}
+
+#[test]
+#[serial]
+fn test_get_endpoint_flatten() {
+    // Create a single test environment for all test cases
+    let env = TestEnvironment::new();
+    let nested_doc = doc! {
+        "_id": 1,
+        "name": "warehouse",
+        "address": { "city": "Bucharest", "geo": { "lat": 44.4, "lng": 26.1 } },
+        "tags": ["north", "cold"],
+    };
+
+    // Test case 1: Nested keys become dotted paths and arrays are indexed
+    run_get_test(
+        &env,
+        "flatten",
+        vec![nested_doc.clone()],
+        "?flatten=true",
+        vec![doc! {
+            "_id": 1,
+            "name": "warehouse",
+            "address.city": "Bucharest",
+            "address.geo.lat": 44.4,
+            "address.geo.lng": 26.1,
+            "tags.0": "north",
+            "tags.1": "cold",
+        }],
+    );
+
+    // Test case 2: Documents keep their shape without the option
+    run_get_test(
+        &env,
+        "flatten",
+        vec![nested_doc.clone()],
+        "",
+        vec![nested_doc],
+    );
+}
+
+#[test]
+#[serial]
+fn test_get_endpoint_field_mask() {
+    // Create a single test environment for all test cases
+    let env = TestEnvironment::new();
+    let test_doc = doc! {
+        "_id": 1,
+        "name": "warehouse",
+        "address": { "city": "Bucharest", "zip": "010011", "street": "Lipscani" },
+        "capacity": 500,
+    };
+
+    // Test case 1: Braces select nested subfields
+    run_get_test(
+        &env,
+        "field_mask",
+        vec![test_doc.clone()],
+        "?mask=name,address{city,zip}",
+        vec![doc! {
+            "_id": 1,
+            "name": "warehouse",
+            "address": { "city": "Bucharest", "zip": "010011" },
+        }],
+    );
+
+    // Test case 2: A mask without braces behaves like select
+    run_get_test(
+        &env,
+        "field_mask",
+        vec![test_doc.clone()],
+        "?mask=capacity",
+        vec![doc! { "_id": 1, "capacity": 500 }],
+    );
+
+    // Test case 3: Unbalanced braces are rejected
+    {
+        let collection_name = unique_collection_name("field_mask");
+        let (status_code, _body) =
+            make_get_request(&format!("/{}?mask=address{{city", collection_name));
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+    }
+}
+
+#[test]
+#[serial]
+fn test_get_endpoint_time_range() {
+    // One collection filters on a custom timestamp field
+    let collection_name = unique_collection_name("time_range");
+    let custom_collection = unique_collection_name("time_range_custom");
+    let env = TestEnvironment::with_config(TestConfig {
+        app_env: vec![(
+            "TIMESTAMP_FIELDS".to_string(),
+            format!("{}:occurredAt", custom_collection),
+        )],
+        ..TestConfig::default()
+    });
+
+    let at = |timestamp: &str| DateTime::parse_rfc3339_str(timestamp).unwrap();
+    env.insert_test_data(
+        &collection_name,
+        vec![
+            doc! { "_id": 1, "createdAt": at("2022-12-31T23:59:59Z") },
+            doc! { "_id": 2, "createdAt": at("2023-01-01T00:00:00Z") },
+            doc! { "_id": 3, "createdAt": at("2023-01-15T08:00:00Z") },
+            doc! { "_id": 4, "createdAt": at("2023-02-01T00:00:00Z") },
+        ],
+    );
+    env.insert_test_data(
+        &custom_collection,
+        vec![
+            doc! { "_id": 1, "occurredAt": at("2023-01-10T00:00:00Z") },
+            doc! { "_id": 2, "occurredAt": at("2023-03-10T00:00:00Z") },
+        ],
+    );
+
+    // Helper returning the ids a GET matches
+    let get_ids = |path: &str| -> Vec<i64> {
+        let (status_code, body) = make_get_request(path);
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+        let documents: Vec<serde_json::Value> =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        documents
+            .iter()
+            .map(|document| document["_id"].as_i64().unwrap())
+            .collect()
+    };
+
+    // Test case 1: `since` is inclusive and `until` exclusive
+    assert_eq!(
+        get_ids(&format!(
+            "/{}?since=2023-01-01&until=2023-02-01",
+            collection_name
+        )),
+        vec![2, 3]
+    );
+
+    // Test case 2: Either bound works alone and combines with other filters
+    assert_eq!(
+        get_ids(&format!(
+            "/{}?since=2023-01-15T08:00:00Z&_id=lt.4",
+            collection_name
+        )),
+        vec![3]
+    );
+
+    // Test case 3: The configured timestamp field is used instead of `createdAt`
+    assert_eq!(
+        get_ids(&format!("/{}?until=2023-02-01", custom_collection)),
+        vec![1]
+    );
+
+    // Test case 4: Unparseable timestamps are rejected
+    {
+        let (status_code, _body) =
+            make_get_request(&format!("/{}?since=yesterday", collection_name));
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+    }
+}
+
+#[test]
+#[serial]
+fn test_get_endpoint_geo_within() {
+    // Create a single test environment for all test cases
+    let env = TestEnvironment::new();
+    let collection_name = unique_collection_name("geo_within");
+    let places = vec![
+        doc! { "_id": 1, "name": "Times Square", "location": [-73.985, 40.758] },
+        doc! { "_id": 2, "name": "Central Park", "location": [-73.965, 40.782] },
+        doc! { "_id": 3, "name": "Brooklyn Bridge", "location": [-73.996, 40.706] },
+        doc! { "_id": 4, "name": "Boston Common", "location": [-71.066, 42.355] },
+    ];
+    env.insert_test_data(&collection_name, places.clone());
+    TOKIO_RUNTIME.block_on(async {
+        env.mongodb_client
+            .database(&env.config.database_name)
+            .collection::<Document>(&collection_name)
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "location": "2d" })
+                    .build(),
+            )
+            .await
+            .expect("Failed to create index");
+    });
+
+    // Test case 1: A box selects the points inside it
+    {
+        let (status_code, body) = make_get_request(&format!(
+            "/{}?location=within.box.(-74.0,40.75,-73.9,40.8)",
+            collection_name
+        ));
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+        let documents: Vec<Document> =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        assert_eq!(documents, places[..2].to_vec());
+    }
+
+    // Test case 2: A polygon around Manhattan's south tip
+    {
+        let (status_code, body) = make_get_request(&format!(
+            "/{}?location=within.polygon.(-74.02,40.70,-73.97,40.70,-73.99,40.73)",
+            collection_name
+        ));
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+        let documents: Vec<Document> =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        assert_eq!(documents, vec![places[2].clone()]);
+    }
+
+    // Test case 3: A box needs exactly two corners
+    {
+        let (status_code, _body) = make_get_request(&format!(
+            "/{}?location=within.box.(-74.0,40.75,-73.9)",
+            collection_name
+        ));
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+    }
+}
+
+#[test]
+#[serial]
+fn test_get_endpoint_id_range() {
+    // Create a single test environment for all test cases
+    let env = TestEnvironment::new();
+    let docs: Vec<Document> = (1..=10).map(|i| doc! { "_id": i, "n": i * 10 }).collect();
+
+    // Test case 1: Both bounds on `_id` apply
+    run_get_test(
+        &env,
+        "id_range",
+        docs.clone(),
+        "?_id=gte.3&_id=lt.6",
+        docs[2..5].to_vec(),
+    );
+
+    // Test case 2: A range on `_id` combines with conditions on other fields
+    run_get_test(
+        &env,
+        "id_range",
+        docs.clone(),
+        "?_id=gt.2&n=lte.50&_id=lte.9",
+        docs[2..5].to_vec(),
+    );
+}
+
+#[test]
+#[serial]
+fn test_get_endpoint_collation() {
+    // Create a single test environment for all test cases
+    let env = TestEnvironment::new();
+    let docs = vec![
+        doc! { "_id": 1, "name": "John" },
+        doc! { "_id": 2, "name": "Jane" },
+    ];
+
+    // Test case 1: Equality is case-sensitive by default
+    run_get_test(&env, "collation", docs.clone(), "?name=john", Vec::new());
+
+    // Test case 2: A case-insensitive collation matches regardless of case
+    run_get_test(
+        &env,
+        "collation",
+        docs.clone(),
+        "?name=john&collation=ci",
+        docs[..1].to_vec(),
+    );
+
+    // Test case 3: A locale with `.ci` does the same
+    run_get_test(
+        &env,
+        "collation",
+        docs.clone(),
+        "?name=JOHN&collation=en.ci",
+        docs[..1].to_vec(),
+    );
+}
+
+#[test]
+#[serial]
+fn test_get_endpoint_ilike() {
+    // Create a single test environment for all test cases
+    let env = TestEnvironment::new();
+    let docs = vec![
+        doc! { "_id": 1, "name": "John" },
+        doc! { "_id": 2, "name": "Joan" },
+        doc! { "_id": 3, "name": "Johnny" },
+        doc! { "_id": 4, "name": "50% off" },
+    ];
+
+    // Test case 1: `%` matches any run of characters, anchored at both ends and ignoring case
+    run_get_test(
+        &env,
+        "ilike",
+        docs.clone(),
+        "?name=ilike.jo%25n",
+        docs[..2].to_vec(),
+    );
+
+    // Test case 2: `_` matches a single character
+    run_get_test(
+        &env,
+        "ilike",
+        docs.clone(),
+        "?name=ilike.J_HN",
+        docs[..1].to_vec(),
+    );
+
+    // Test case 3: An escaped `%` is literal
+    run_get_test(
+        &env,
+        "ilike",
+        docs.clone(),
+        "?name=ilike.50%5C%25%25",
+        docs[3..].to_vec(),
+    );
+}
+
+#[test]
+#[serial]
+fn test_get_endpoint_hint() {
+    // Create a single test environment for all test cases
+    let env = TestEnvironment::new();
+    let collection_name = unique_collection_name("hint");
+    let docs = vec![
+        doc! { "_id": 1, "status": "active" },
+        doc! { "_id": 2, "status": "archived" },
+    ];
+    env.insert_test_data(&collection_name, docs.clone());
+    TOKIO_RUNTIME.block_on(async {
+        env.mongodb_client
+            .database(&env.config.database_name)
+            .collection::<Document>(&collection_name)
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "status": 1 })
+                    .options(
+                        mongodb::options::IndexOptions::builder()
+                            .name("status_1".to_string())
+                            .build(),
+                    )
+                    .build(),
+            )
+            .await
+            .expect("Failed to create index");
+    });
+
+    // Test case 1: A hint naming an existing index runs the query through it
+    {
+        let (status_code, body) =
+            make_get_request(&format!("/{}?status=active&hint=status_1", collection_name));
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+        let documents: Vec<Document> =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        assert_eq!(documents, docs[..1].to_vec());
+    }
+
+    // Test case 2: Unknown indexes are rejected
+    {
+        let (status_code, _body) =
+            make_get_request(&format!("/{}?hint=no_such_index", collection_name));
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+    }
+}
+
+#[test]
+#[serial]
+fn test_get_endpoint_require() {
+    // Create a single test environment for all test cases
+    let env = TestEnvironment::new();
+    let collection_name = unique_collection_name("require");
+    let docs = vec![
+        doc! { "_id": 1, "status": "active" },
+        doc! { "_id": 2, "status": "active" },
+        doc! { "_id": 3, "status": "archived" },
+    ];
+    env.insert_test_data(&collection_name, docs.clone());
+
+    // Test case 1: Without `require`, no matches is an empty page
+    run_get_test(&env, "require", docs.clone(), "?status=gone", Vec::new());
+
+    // Test case 2: With `require`, no matches is a 404 naming the query rather than the collection
+    let (status_code, no_match_body) =
+        make_get_request(&format!("/{}?status=gone&require=many", collection_name));
+    assert_eq!(
+        status_code, 404,
+        "Expected status code 404, got {}",
+        status_code
+    );
+    assert!(
+        no_match_body.contains("match the query"),
+        "{}",
+        no_match_body
+    );
+
+    // Test case 3: A missing collection is a 404 with a different body
+    let (status_code, missing_body) =
+        make_get_request("/mongor_get_endpoint_test_absent?require=one");
+    assert_eq!(
+        status_code, 404,
+        "Expected status code 404, got {}",
+        status_code
+    );
+    assert_ne!(missing_body, no_match_body);
+    assert!(missing_body.contains("not found"), "{}", missing_body);
+
+    // Test case 4: `require=one` accepts a single match and rejects several
+    run_get_test(
+        &env,
+        "require",
+        docs.clone(),
+        "?status=archived&require=one",
+        docs[2..].to_vec(),
+    );
+    let (status_code, _body) =
+        make_get_request(&format!("/{}?status=active&require=one", collection_name));
+    assert_eq!(
+        status_code, 422,
+        "Expected status code 422, got {}",
+        status_code
+    );
+
+    // Test case 5: Other values are rejected
+    let (status_code, _body) = make_get_request(&format!("/{}?require=all", collection_name));
+    assert_eq!(
+        status_code, 400,
+        "Expected status code 400, got {}",
+        status_code
+    );
+}
+
+#[test]
+#[serial]
+fn test_get_endpoint_with_count() {
+    // Create a single test environment for all test cases
+    let env = TestEnvironment::new();
+    let collection_name = unique_collection_name("with_count");
+    let docs: Vec<Document> = (1..=30)
+        .map(|i| doc! { "_id": i, "parity": i % 2 })
+        .collect();
+    env.insert_test_data(&collection_name, docs.clone());
+
+    // Runs a withCount query, returning the page and the reported total
+    let get_with_count = |query: &str| {
+        let (status_code, body) =
+            make_get_request(&format!("/{}?withCount=true&{}", collection_name, query));
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+        let body: serde_json::Value = serde_json::from_str(&body).expect("Failed to parse JSON");
+        let page: Vec<Document> =
+            serde_json::from_value(body["data"].clone()).expect("Expected a data array");
+        (page, body["total"].as_u64().expect("Expected a total"))
+    };
+
+    // Test case 1: The total counts every match, not just the page
+    {
+        let (page, total) = get_with_count("parity=0&sort=_id&limit=5&skip=2");
+        assert_eq!(total, 15);
+        let ids: Vec<i32> = page.iter().map(|doc| doc.get_i32("_id").unwrap()).collect();
+        assert_eq!(ids, vec![6, 8, 10, 12, 14]);
+    }
+
+    // Test case 2: With a large limit the page holds every match
+    {
+        let (page, total) = get_with_count("parity=1&limit=1000");
+        assert_eq!(total, 15);
+        assert_eq!(page.len() as u64, total);
+    }
+
+    // Test case 3: No matches is an empty page and a zero total
+    {
+        let (page, total) = get_with_count("parity=2");
+        assert!(page.is_empty());
+        assert_eq!(total, 0);
+    }
+}
+
+#[test]
+#[serial]
+fn test_get_endpoint_repeated_params() {
+    let docs = vec![
+        doc! { "_id": 1, "tags": ["a", "b"], "age": 10 },
+        doc! { "_id": 2, "tags": ["a"], "age": 40 },
+        doc! { "_id": 3, "tags": ["c"], "age": 70 },
+    ];
+
+    // Test case 1: By default every value of a repeated parameter has to match
+    {
+        let env = TestEnvironment::new();
+        run_get_test(
+            &env,
+            "repeated_params_all",
+            docs.clone(),
+            "?tags=a&tags=b",
+            vec![docs[0].clone()],
+        );
+    }
+
+    // Test case 2: With REPEATED_PARAMS=any, any value or condition may match
+    {
+        let env = TestEnvironment::with_config(TestConfig {
+            app_env: vec![("REPEATED_PARAMS".to_string(), "any".to_string())],
+            ..TestConfig::default()
+        });
+        run_get_test(
+            &env,
+            "repeated_params_any",
+            docs.clone(),
+            "?tags=a&tags=b",
+            vec![docs[0].clone(), docs[1].clone()],
+        );
+        run_get_test(
+            &env,
+            "repeated_params_any_operators",
+            docs.clone(),
+            "?age=lt.18&age=gte.65",
+            vec![docs[0].clone(), docs[2].clone()],
+        );
+    }
+}
+
+#[test]
+#[serial]
+fn test_get_endpoint_ids_only() {
+    // Create a single test environment for all test cases
+    let env = TestEnvironment::new();
+    let collection_name = unique_collection_name("ids_only");
+    let oid = ObjectId::new();
+    env.insert_test_data(
+        &collection_name,
+        vec![
+            doc! { "_id": 1, "status": "active", "name": "Ann" },
+            doc! { "_id": 2, "status": "inactive", "name": "Bo" },
+            doc! { "_id": oid, "status": "active", "name": "Cy" },
+        ],
+    );
+
+    // Runs an idsOnly query, returning the parsed response
+    let get_ids = |query: &str| {
+        let (status_code, body) =
+            make_get_request(&format!("/{}?idsOnly=true&{}", collection_name, query));
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+        serde_json::from_str::<serde_json::Value>(&body).expect("Failed to parse JSON")
+    };
+
+    // Test case 1: The matching ids come back as a flat array, formatted like documents' ids
+    {
+        assert_eq!(
+            get_ids("status=active&sort=name"),
+            serde_json::json!([1, { "$oid": oid.to_hex() }])
+        );
+    }
+
+    // Test case 2: Find options still apply
+    {
+        assert_eq!(
+            get_ids("sort=name.desc&limit=2"),
+            serde_json::json!([{ "$oid": oid.to_hex() }, 2])
+        );
+    }
+
+    // Test case 3: No matches is an empty array
+    {
+        assert_eq!(get_ids("status=deleted"), serde_json::json!([]));
+    }
+}
+
+#[test]
+#[serial]
+fn test_get_endpoint_unknown_params() {
+    // Create a single test environment for all test cases
+    let env = TestEnvironment::with_config(TestConfig {
+        app_env: vec![
+            ("UNKNOWN_PARAMS".to_string(), "strict".to_string()),
+            ("CATALOG_REFRESH_SECS".to_string(), "1".to_string()),
+        ],
+        ..TestConfig::default()
+    });
+    let collection_name = unique_collection_name("unknown_params");
+    TOKIO_RUNTIME.block_on(async {
+        let database = env.mongodb_client.database(&env.config.database_name);
+        database
+            .collection::<Document>(&collection_name)
+            .drop()
+            .await
+            .ok();
+        database
+            .create_collection(&collection_name)
+            .validator(doc! { "$jsonSchema": {
+                "bsonType": "object",
+                "properties": {
+                    "name": { "bsonType": "string" },
+                    "address": { "bsonType": "object" }
+                }
+            } })
+            .await
+            .expect("Failed to create validated collection");
+        database
+            .collection::<Document>(&collection_name)
+            .insert_many(vec![
+                doc! { "_id": 1, "name": "Ann", "address": { "city": "Oslo" } },
+                doc! { "_id": 2, "name": "Bo", "address": { "city": "Rome" } },
+            ])
+            .await
+            .expect("Failed to insert test data");
+    });
+    // Let the background task pick the validator up
+    std::thread::sleep(std::time::Duration::from_secs(2));
+
+    // Test case 1: A misspelled reserved parameter is rejected, naming it
+    {
+        let (status_code, body) = make_get_request(&format!("/{}?limt=10", collection_name));
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+        assert!(
+            body.contains("limt"),
+            "Expected the offending key in {}",
+            body
+        );
+    }
+
+    // Test case 2: Reserved parameters and declared fields, dotted or not, still work
+    {
+        let (status_code, body) = make_get_request(&format!(
+            "/{}?limit=10&address.city=Oslo&name=Ann&_id=gte.1",
+            collection_name
+        ));
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+        let documents: Vec<Document> =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        assert_eq!(
+            documents,
+            vec![doc! { "_id": 1, "name": "Ann", "address": { "city": "Oslo" } }]
+        );
+    }
+
+    // Test case 3: Fields inside `or` groups are checked too
+    {
+        let (status_code, _body) =
+            make_get_request(&format!("/{}?or=(limt.eq.10)", collection_name));
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+    }
+
+    // Test case 4: Collections without a validator only accept `_id`
+    {
+        let unvalidated = unique_collection_name("unknown_params_unvalidated");
+        env.insert_test_data(&unvalidated, vec![doc! { "_id": 1, "name": "Ann" }]);
+        std::thread::sleep(std::time::Duration::from_secs(2));
+
+        let (status_code, _body) = make_get_request(&format!("/{}?limt=10", unvalidated));
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+        let (status_code, _body) = make_get_request(&format!("/{}?_id=1", unvalidated));
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+    }
 }
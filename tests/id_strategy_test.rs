@@ -0,0 +1,104 @@
+use mongodb::bson::{Document, doc};
+use serial_test::serial;
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::{TOKIO_RUNTIME, TestConfig, TestEnvironment};
+use utils::utils::{make_get_request, make_post_request};
+
+// Collections configured with each id strategy
+static UUID_COLLECTION_NAME: &str = "mongor_id_strategy_test_uuid";
+static SEQUENCE_COLLECTION_NAME: &str = "mongor_id_strategy_test_sequence";
+
+// Whether a string is a hyphenated UUID like 67e55044-10b1-426f-9247-bb680e5fe0c8
+fn is_uuid(id: &str) -> bool {
+    id.len() == 36
+        && id.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+#[test]
+#[serial]
+fn test_id_strategy_all_cases() {
+    let env = TestEnvironment::with_config(TestConfig {
+        app_env: vec![(
+            "ID_STRATEGY".to_string(),
+            format!(
+                "{}:uuid;{}:sequence",
+                UUID_COLLECTION_NAME, SEQUENCE_COLLECTION_NAME
+            ),
+        )],
+        ..TestConfig::default()
+    });
+    env.insert_test_data(UUID_COLLECTION_NAME, vec![doc! { "_id": "seed" }]);
+    env.insert_test_data(SEQUENCE_COLLECTION_NAME, vec![doc! { "_id": 0 }]);
+    // Start the sequence from scratch
+    TOKIO_RUNTIME.block_on(async {
+        env.mongodb_client
+            .database(&env.config.database_name)
+            .collection::<Document>("mongor_counters")
+            .delete_one(doc! { "_id": SEQUENCE_COLLECTION_NAME })
+            .await
+            .expect("Failed to reset the sequence");
+    });
+
+    // Test case 1: The uuid strategy generates and returns a UUID string
+    {
+        let (status_code, body) =
+            make_post_request(&format!("/{}", UUID_COLLECTION_NAME), r#"{"name": "a"}"#);
+        assert_eq!(
+            status_code, 201,
+            "Expected status code 201, got {}",
+            status_code
+        );
+        let id: serde_json::Value =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        let id = id.as_str().expect("Expected a string id");
+        assert!(is_uuid(id), "Unexpected id: {}", id);
+
+        let (_status_code, body) =
+            make_get_request(&format!("/{}?ids={}", UUID_COLLECTION_NAME, id));
+        let documents: Vec<Document> =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        assert_eq!(documents, vec![doc! { "_id": id, "name": "a" }]);
+    }
+
+    // Test case 2: The sequence strategy numbers single and bulk inserts in order
+    {
+        let (status_code, body) = make_post_request(
+            &format!("/{}", SEQUENCE_COLLECTION_NAME),
+            r#"{"name": "a"}"#,
+        );
+        assert_eq!(
+            status_code, 201,
+            "Expected status code 201, got {}",
+            status_code
+        );
+        assert_eq!(body, "1");
+
+        let (status_code, body) = make_post_request(
+            &format!("/{}", SEQUENCE_COLLECTION_NAME),
+            r#"[{"name": "b"}, {"_id": 100, "name": "c"}, {"name": "d"}]"#,
+        );
+        assert_eq!(
+            status_code, 201,
+            "Expected status code 201, got {}",
+            status_code
+        );
+        let response: serde_json::Value =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        assert_eq!(response["insertedIds"], serde_json::json!([2, 100, 3]));
+    }
+
+    // Test case 3: The counters collection is not exposed
+    {
+        let (status_code, _body) = make_get_request("/mongor_counters");
+        assert_eq!(
+            status_code, 404,
+            "Expected status code 404, got {}",
+            status_code
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use mongodb::bson::doc;
+use serial_test::serial;
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::{TestConfig, TestEnvironment};
+use utils::utils::{make_get_request, make_post_request};
+
+// Base name for test collections
+static TEST_COLLECTION_BASE_NAME: &str = "mongor_increment_endpoint_test";
+
+// Generate a unique collection name for each test
+fn unique_collection_name(test_name: &str) -> String {
+    format!(
+        "{}_{}",
+        TEST_COLLECTION_BASE_NAME,
+        test_name.replace(" ", "_")
+    )
+}
+
+#[test]
+#[serial]
+fn test_increment_endpoint_all_cases() {
+    // Create a single test environment for all test cases
+    let env = TestEnvironment::new();
+
+    let collection_name = unique_collection_name("counters");
+    env.insert_test_data(
+        &collection_name,
+        vec![doc! { "_id": "home", "views": 10, "title": "Home" }],
+    );
+    let increment_path = format!("/{}/home/increment", collection_name);
+
+    // Test case 1: The response carries the incremented value
+    {
+        let (status_code, body) =
+            make_post_request(&increment_path, r#"{"field": "views", "by": 5}"#);
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+        let response: serde_json::Value =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        assert_eq!(
+            response,
+            serde_json::json!({ "field": "views", "value": 15 })
+        );
+    }
+
+    // Test case 2: Concurrent increments are all applied and each sees a distinct value
+    {
+        let handles: Vec<_> = (0..10)
+            .map(|_| {
+                let increment_path = increment_path.clone();
+                std::thread::spawn(move || {
+                    let (status_code, body) =
+                        make_post_request(&increment_path, r#"{"field": "views", "by": 1}"#);
+                    assert_eq!(status_code, 200, "Unexpected body: {}", body);
+                    let response: serde_json::Value =
+                        serde_json::from_str(&body).expect("Failed to parse JSON response");
+                    response["value"].as_i64().unwrap()
+                })
+            })
+            .collect();
+        let mut values: Vec<i64> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        values.sort();
+        assert_eq!(values, (16..=25).collect::<Vec<i64>>());
+
+        let (_status_code, body) = make_get_request(&format!("/{}?_id=home", collection_name));
+        let documents: serde_json::Value =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        assert_eq!(documents[0]["views"], 25);
+    }
+
+    // Test case 3: Non-numeric increments and non-numeric fields are rejected
+    {
+        let (status_code, _body) =
+            make_post_request(&increment_path, r#"{"field": "views", "by": "one"}"#);
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+
+        let (status_code, _body) =
+            make_post_request(&increment_path, r#"{"field": "title", "by": 1}"#);
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+    }
+
+    // Test case 4: Missing documents return 404
+    {
+        let (status_code, _body) = make_post_request(
+            &format!("/{}/missing/increment", collection_name),
+            r#"{"field": "views", "by": 1}"#,
+        );
+        assert_eq!(
+            status_code, 404,
+            "Expected status code 404, got {}",
+            status_code
+        );
+    }
+}
+
+#[test]
+#[serial]
+fn test_increment_endpoint_redacted_fields() {
+    let collection_name = unique_collection_name("redacted");
+    let env = TestEnvironment::with_config(TestConfig {
+        app_env: vec![(
+            "COLLECTION_REDACTED_FIELDS".to_string(),
+            format!("{}:secretScore,profile.balance", collection_name),
+        )],
+        ..TestConfig::default()
+    });
+    env.insert_test_data(
+        &collection_name,
+        vec![doc! { "_id": "home", "secretScore": 42, "profile": { "balance": 100 } }],
+    );
+    let increment_path = format!("/{}/home/increment", collection_name);
+
+    // Test case 1: Redacted fields are incremented without their value being returned
+    {
+        for field in ["secretScore", "profile.balance"] {
+            let (status_code, body) = make_post_request(
+                &increment_path,
+                &format!(r#"{{"field": "{}", "by": 0}}"#, field),
+            );
+            assert_eq!(
+                status_code, 200,
+                "Expected status code 200, got {}",
+                status_code
+            );
+            let response: serde_json::Value =
+                serde_json::from_str(&body).expect("Failed to parse JSON response");
+            assert_eq!(response["value"], serde_json::Value::Null);
+        }
+    }
+}
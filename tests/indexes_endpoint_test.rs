@@ -0,0 +1,98 @@
+use mongodb::{
+    IndexModel,
+    bson::{Document, doc},
+};
+use serial_test::serial;
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::{TOKIO_RUNTIME, TestConfig, TestEnvironment};
+use utils::utils::make_get_request;
+
+// Base name for test collections
+static TEST_COLLECTION_BASE_NAME: &str = "mongor_indexes_endpoint_test";
+
+// Generate a unique collection name for each test
+fn unique_collection_name(test_name: &str) -> String {
+    format!(
+        "{}_{}",
+        TEST_COLLECTION_BASE_NAME,
+        test_name.replace(" ", "_")
+    )
+}
+
+// Create an index on the given collection directly through the MongoDB client
+fn create_index(env: &TestEnvironment, collection_name: &str, keys: Document) {
+    TOKIO_RUNTIME.block_on(async {
+        env.mongodb_client
+            .database(&env.config.database_name)
+            .collection::<Document>(collection_name)
+            .create_index(IndexModel::builder().keys(keys).build())
+            .await
+            .expect("Failed to create index");
+    });
+}
+
+#[test]
+#[serial]
+fn test_indexes_endpoint_all_cases() {
+    // Refresh the catalog every second so the new index is picked up quickly
+    let env = TestEnvironment::with_config(TestConfig {
+        app_env: vec![("CATALOG_REFRESH_SECS".to_string(), "1".to_string())],
+        ..TestConfig::default()
+    });
+
+    // Test case 1: Created index is listed by the endpoint and in the catalog
+    {
+        let collection_name = unique_collection_name("created_index");
+        env.insert_test_data(&collection_name, vec![doc! { "_id": 1, "category": "A" }]);
+        create_index(&env, &collection_name, doc! { "category": 1 });
+
+        // The endpoint queries MongoDB directly
+        let (status_code, body) = make_get_request(&format!("/{}/indexes", collection_name));
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+        let indexes: Vec<serde_json::Value> =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        assert!(
+            indexes.iter().any(|index| index["key"]["category"] == 1),
+            "Expected a category index, got {}",
+            body
+        );
+
+        // Wait for the background refresh, then check the OpenAPI doc built from the catalog
+        std::thread::sleep(std::time::Duration::from_secs(2));
+        let (status_code, body) = make_get_request("/openapi.json");
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+        let openapi: serde_json::Value =
+            serde_json::from_str(&body).expect("Failed to parse OpenAPI document");
+        let description =
+            openapi["paths"][format!("/collections/{}", collection_name)]["get"]["description"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string();
+        assert!(
+            description.contains("category"),
+            "Expected catalog to note the category index, got {}",
+            description
+        );
+    }
+
+    // Test case 2: Missing collection returns 404
+    {
+        let (status_code, _body) =
+            make_get_request(&format!("/{}/indexes", unique_collection_name("missing")));
+        assert_eq!(
+            status_code, 404,
+            "Expected status code 404, got {}",
+            status_code
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use mongodb::bson::{Document, doc};
+use serial_test::serial;
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::{TestConfig, TestEnvironment};
+use utils::utils::make_get_request;
+
+// Base name for test collections
+static TEST_COLLECTION_BASE_NAME: &str = "mongor_join_endpoint_test";
+
+// Generate a unique collection name for each test
+fn unique_collection_name(test_name: &str) -> String {
+    format!(
+        "{}_{}",
+        TEST_COLLECTION_BASE_NAME,
+        test_name.replace(" ", "_")
+    )
+}
+
+#[test]
+#[serial]
+fn test_join_endpoint_all_cases() {
+    // The audit collection is denied and may not be joined in
+    let audit_collection = unique_collection_name("audit");
+    let env = TestEnvironment::with_config(TestConfig {
+        app_env: vec![("COLLECTIONS_DENYLIST".to_string(), audit_collection.clone())],
+        ..TestConfig::default()
+    });
+
+    let orders_collection = unique_collection_name("orders");
+    let customers_collection = unique_collection_name("customers");
+    env.insert_test_data(
+        &orders_collection,
+        vec![
+            doc! { "_id": 1, "customerId": 10, "total": 5 },
+            doc! { "_id": 2, "customerId": 20, "total": 50 },
+            doc! { "_id": 3, "customerId": 10, "total": 75 },
+        ],
+    );
+    env.insert_test_data(
+        &customers_collection,
+        vec![
+            doc! { "_id": 10, "name": "Ana" },
+            doc! { "_id": 20, "name": "Bogdan" },
+        ],
+    );
+    env.insert_test_data(&audit_collection, vec![doc! { "_id": 1, "customerId": 10 }]);
+
+    // Test case 1: Filtered base documents get the matching foreign documents embedded
+    {
+        let (status_code, body) = make_get_request(&format!(
+            "/{}/join?foreign={}&localField=customerId&foreignField=_id&as=customer&total=gt.10&sort=total",
+            orders_collection, customers_collection
+        ));
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+
+        let docs: Vec<Document> =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        assert_eq!(
+            docs,
+            vec![
+                doc! {
+                    "_id": 2,
+                    "customerId": 20,
+                    "total": 50,
+                    "customer": [{ "_id": 20, "name": "Bogdan" }],
+                },
+                doc! {
+                    "_id": 3,
+                    "customerId": 10,
+                    "total": 75,
+                    "customer": [{ "_id": 10, "name": "Ana" }],
+                },
+            ]
+        );
+    }
+
+    // Test case 2: The limit applies to the base collection
+    {
+        let (status_code, body) = make_get_request(&format!(
+            "/{}/join?foreign={}&localField=customerId&foreignField=_id&limit=1",
+            orders_collection, customers_collection
+        ));
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+
+        let docs: Vec<Document> =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        assert_eq!(docs.len(), 1);
+        assert_eq!(
+            docs[0].get_array(&customers_collection).unwrap().len(),
+            1,
+            "Expected the joined array under the foreign collection name"
+        );
+    }
+
+    // Test case 3: Denied collections cannot be joined in
+    {
+        let (status_code, _body) = make_get_request(&format!(
+            "/{}/join?foreign={}&localField=customerId&foreignField=customerId",
+            customers_collection, audit_collection
+        ));
+        assert_eq!(
+            status_code, 404,
+            "Expected status code 404, got {}",
+            status_code
+        );
+    }
+
+    // Test case 4: The join fields are required
+    {
+        let (status_code, _body) = make_get_request(&format!(
+            "/{}/join?foreign={}",
+            orders_collection, customers_collection
+        ));
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+    }
+}
@@ -0,0 +1,162 @@
+use mongodb::bson::{Document, doc};
+use serde_json::json;
+use serial_test::serial;
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::TestEnvironment;
+use utils::utils::{make_get_request, make_http_request_with_headers};
+
+// Base name for test collections
+static TEST_COLLECTION_BASE_NAME: &str = "mongor_json_patch_endpoint_test";
+
+// Generate a unique collection name for each test
+fn unique_collection_name(test_name: &str) -> String {
+    format!(
+        "{}_{}",
+        TEST_COLLECTION_BASE_NAME,
+        test_name.replace(" ", "_")
+    )
+}
+
+// Helper function to send a JSON Patch to a single document and return the collection contents
+fn run_json_patch_test(
+    env: &TestEnvironment,
+    test_name: &str,
+    initial_docs: Vec<Document>,
+    id: &str,
+    patch: serde_json::Value,
+    expected_status: u16,
+) -> Vec<Document> {
+    // Generate a unique collection name for this test
+    let collection_name = unique_collection_name(test_name);
+
+    // Insert initial test data
+    env.insert_test_data(&collection_name, initial_docs);
+
+    // Send the patch with the JSON Patch content type
+    let (status_code, _body) = make_http_request_with_headers(
+        &format!("/{}/{}", collection_name, id),
+        "PATCH",
+        Some(&patch.to_string()),
+        &[("Content-Type", "application/json-patch+json")],
+    );
+    assert_eq!(
+        status_code, expected_status,
+        "Expected status code {}, got {}",
+        expected_status, status_code
+    );
+
+    // Fetch the documents to verify the result
+    let (get_status_code, get_body) = make_get_request(&format!("/{}", collection_name));
+    assert_eq!(
+        get_status_code, 200,
+        "Expected GET status code 200, got {}",
+        get_status_code
+    );
+
+    serde_json::from_str(&get_body).expect("Failed to parse JSON response")
+}
+
+#[test]
+#[serial]
+fn test_json_patch_endpoint_all_cases() {
+    // Create a single test environment for all test cases
+    let env = TestEnvironment::new();
+
+    let initial_doc = doc! {
+        "_id": 1,
+        "name": "old name",
+        "status": "draft",
+        "tags": ["rust"]
+    };
+
+    // Test case 1: replace, add and remove are applied to the targeted document
+    {
+        let documents = run_json_patch_test(
+            &env,
+            "replace_add_remove",
+            vec![initial_doc.clone(), doc! { "_id": 2, "name": "untouched" }],
+            "1",
+            json!([
+                { "op": "replace", "path": "/name", "value": "new name" },
+                { "op": "add", "path": "/address", "value": { "city": "Bucharest" } },
+                { "op": "add", "path": "/tags/-", "value": "mongodb" },
+                { "op": "remove", "path": "/status" }
+            ]),
+            200,
+        );
+
+        assert_eq!(
+            documents,
+            vec![
+                doc! {
+                    "_id": 1,
+                    "name": "new name",
+                    "tags": ["rust", "mongodb"],
+                    "address": { "city": "Bucharest" }
+                },
+                doc! { "_id": 2, "name": "untouched" },
+            ]
+        );
+    }
+
+    // Test case 2: Unsupported operations are rejected and nothing changes
+    {
+        let documents = run_json_patch_test(
+            &env,
+            "unsupported_operation",
+            vec![initial_doc.clone()],
+            "1",
+            json!([{ "op": "move", "from": "/tags/0", "path": "/tags/1" }]),
+            422,
+        );
+
+        assert_eq!(documents, vec![initial_doc.clone()]);
+    }
+
+    // Test case 3: Patching a missing document returns 404
+    {
+        let documents = run_json_patch_test(
+            &env,
+            "missing_document",
+            vec![initial_doc.clone()],
+            "999",
+            json!([{ "op": "replace", "path": "/name", "value": "new name" }]),
+            404,
+        );
+
+        assert_eq!(documents, vec![initial_doc.clone()]);
+    }
+
+    // Test case 4: Array indices, conflicting paths and field names MongoDB cannot
+    // address are rejected and nothing changes
+    {
+        for (test_case, patch) in [
+            (
+                "array_index",
+                json!([{ "op": "remove", "path": "/tags/0" }]),
+            ),
+            (
+                "dotted_token",
+                json!([{ "op": "replace", "path": "/a.b", "value": 1 }]),
+            ),
+            (
+                "operator_token",
+                json!([{ "op": "add", "path": "/$where", "value": "1" }]),
+            ),
+            (
+                "conflicting_paths",
+                json!([
+                    { "op": "add", "path": "/name", "value": "new name" },
+                    { "op": "remove", "path": "/name" }
+                ]),
+            ),
+        ] {
+            let documents =
+                run_json_patch_test(&env, test_case, vec![initial_doc.clone()], "1", patch, 422);
+
+            assert_eq!(documents, vec![initial_doc.clone()]);
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use jsonwebtoken::{EncodingKey, Header, encode};
+use mongodb::bson::{Document, doc};
+use serial_test::serial;
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::{TestConfig, TestEnvironment};
+use utils::utils::{make_http_request_with_headers, make_post_request, send_http_request};
+
+static TEST_COLLECTION_NAME: &str = "mongor_maintenance_test";
+static JWT_SECRET: &str = "mongor-test-secret";
+
+// Helper function to sign a long-lived token for the operator switching modes
+fn admin_authorization() -> String {
+    let token = encode(
+        &Header::default(),
+        &serde_json::json!({
+            "scope": "admin:maintenance read:* write:*",
+            "exp": u32::MAX,
+        }),
+        &EncodingKey::from_secret(JWT_SECRET.as_bytes()),
+    )
+    .expect("Failed to sign token");
+    format!("Bearer {}", token)
+}
+
+// Helper function to send a request with the operator's token
+fn make_admin_request(path: &str, method: &str, json_body: Option<&str>) -> (u16, String) {
+    let authorization = admin_authorization();
+    make_http_request_with_headers(
+        path,
+        method,
+        json_body,
+        &[("Authorization", authorization.as_str())],
+    )
+}
+
+// Switches maintenance mode and checks the endpoint reports it back
+fn set_mode(mode: &str) {
+    let (status_code, body) = make_admin_request(
+        "/api/maintenance",
+        "POST",
+        Some(&format!(r#"{{"mode": "{}"}}"#, mode)),
+    );
+    assert_eq!(
+        status_code, 200,
+        "Expected status code 200, got {}",
+        status_code
+    );
+    assert_eq!(body, format!(r#"{{"mode":"{}"}}"#, mode));
+}
+
+#[test]
+#[serial]
+fn test_maintenance_all_cases() {
+    let env = TestEnvironment::with_config(TestConfig {
+        app_env: vec![("JWT_SECRET".to_string(), JWT_SECRET.to_string())],
+        ..TestConfig::default()
+    });
+    env.insert_test_data(TEST_COLLECTION_NAME, vec![doc! { "_id": 1 }]);
+    let collection_path = format!("/{}", TEST_COLLECTION_NAME);
+
+    // Test case 1: The server starts out of maintenance
+    {
+        let (status_code, body) = make_admin_request("/api/maintenance", "GET", None);
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+        assert_eq!(body, r#"{"mode":"off"}"#);
+    }
+
+    // Test case 2: Read-only mode rejects writes but keeps serving reads
+    {
+        set_mode("readonly");
+
+        let authorization = admin_authorization();
+        let response = send_http_request(
+            &collection_path,
+            "POST",
+            Some(r#"{"_id": 2}"#),
+            &[("Authorization", authorization.as_str())],
+        );
+        assert_eq!(response.status().as_u16(), 503);
+        assert!(response.headers().get("Retry-After").is_some());
+
+        let (status_code, body) = make_admin_request(&collection_path, "GET", None);
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+        let docs: Vec<Document> = serde_json::from_str(&body).expect("Failed to parse JSON");
+        assert_eq!(docs, vec![doc! { "_id": 1 }]);
+    }
+
+    // Test case 3: Full maintenance rejects reads too, but not the maintenance endpoint
+    {
+        set_mode("full");
+
+        let (status_code, _body) = make_admin_request(&collection_path, "GET", None);
+        assert_eq!(
+            status_code, 503,
+            "Expected status code 503, got {}",
+            status_code
+        );
+        let (status_code, _body) = make_admin_request("/api/maintenance", "GET", None);
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+    }
+
+    // Test case 4: Switching maintenance off accepts writes again
+    {
+        set_mode("off");
+
+        let (status_code, _body) =
+            make_admin_request(&collection_path, "POST", Some(r#"{"_id": 2}"#));
+        assert_eq!(
+            status_code, 201,
+            "Expected status code 201, got {}",
+            status_code
+        );
+    }
+
+    // Test case 5: Unknown modes are rejected
+    {
+        let (status_code, _body) =
+            make_admin_request("/api/maintenance", "POST", Some(r#"{"mode": "drain"}"#));
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+    }
+}
+
+#[test]
+#[serial]
+fn test_maintenance_requires_authentication() {
+    let _env = TestEnvironment::new();
+
+    // Test case 1: Without JWT_SECRET nobody can switch maintenance mode
+    {
+        let (status_code, _body) = make_post_request("/api/maintenance", r#"{"mode": "full"}"#);
+        assert_eq!(
+            status_code, 403,
+            "Expected status code 403, got {}",
+            status_code
+        );
+    }
+}
@@ -0,0 +1,68 @@
+use mongodb::bson::{Document, doc};
+use serial_test::serial;
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::{TOKIO_RUNTIME, TestConfig, TestEnvironment};
+use utils::utils::{make_get_request, send_http_request};
+
+// Collection created while the server runs, changing the catalog
+static TEST_COLLECTION_NAME: &str = "mongor_openapi_version_test";
+
+// The ETag a HEAD request reports for the OpenAPI document
+fn openapi_etag() -> String {
+    let response = send_http_request("/openapi.json", "HEAD", None, &[]);
+    assert_eq!(response.status().as_u16(), 200);
+    response
+        .headers()
+        .get("ETag")
+        .expect("Expected an ETag header")
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+#[test]
+#[serial]
+fn test_openapi_version_all_cases() {
+    // Refresh the catalog every second so the new collection is picked up quickly
+    let env = TestEnvironment::with_config(TestConfig {
+        app_env: vec![("CATALOG_REFRESH_SECS".to_string(), "1".to_string())],
+        ..TestConfig::default()
+    });
+    // Left over from an earlier run, the collection would already be in the catalog
+    TOKIO_RUNTIME.block_on(async {
+        env.mongodb_client
+            .database(&env.config.database_name)
+            .collection::<Document>(TEST_COLLECTION_NAME)
+            .drop()
+            .await
+            .ok();
+    });
+    std::thread::sleep(std::time::Duration::from_secs(2));
+    let (_status_code, openapi) = make_get_request("/openapi.json");
+    let openapi: serde_json::Value = serde_json::from_str(&openapi).expect("Failed to parse JSON");
+    let version = openapi["info"]["version"].as_str().unwrap().to_string();
+    let etag = openapi_etag();
+
+    // Test case 1: Refreshes that find nothing new keep the ETag
+    {
+        std::thread::sleep(std::time::Duration::from_secs(2));
+        assert_eq!(openapi_etag(), etag);
+
+        let response = send_http_request("/openapi.json", "GET", None, &[("If-None-Match", &etag)]);
+        assert_eq!(response.status().as_u16(), 304);
+    }
+
+    // Test case 2: A new collection changes the ETag and the document version
+    {
+        env.insert_test_data(TEST_COLLECTION_NAME, vec![doc! { "_id": 1 }]);
+        std::thread::sleep(std::time::Duration::from_secs(2));
+        assert_ne!(openapi_etag(), etag);
+
+        let (_status_code, openapi) = make_get_request("/openapi.json");
+        let openapi: serde_json::Value =
+            serde_json::from_str(&openapi).expect("Failed to parse JSON");
+        assert_ne!(openapi["info"]["version"].as_str().unwrap(), version);
+    }
+}
@@ -0,0 +1,41 @@
+use serial_test::serial;
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::TestEnvironment;
+use utils::utils::{make_get_request, send_http_request};
+
+#[test]
+#[serial]
+fn test_ping_all_cases() {
+    let _env = TestEnvironment::new();
+
+    // Test case 1: The default answer is JSON with the round-trip latency
+    {
+        let (status_code, body) = make_get_request("/api/ping");
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+        let body: serde_json::Value = serde_json::from_str(&body).expect("Failed to parse JSON");
+        assert_eq!(body["ok"], true);
+        assert_eq!(body["ping"]["ok"], 1.0);
+        let latency = body["latencyMs"]
+            .as_f64()
+            .expect("Expected a numeric latency");
+        assert!(
+            (0.0..10_000.0).contains(&latency),
+            "Implausible latency {}",
+            latency
+        );
+    }
+
+    // Test case 2: Plain-text clients keep the text answer
+    {
+        let response = send_http_request("/api/ping", "GET", None, &[("Accept", "text/plain")]);
+        assert_eq!(response.status().as_u16(), 200);
+        let body = response.text().expect("Failed to read the body");
+        assert!(body.starts_with("Pong! "), "{}", body);
+    }
+}
@@ -1,10 +1,11 @@
-use mongodb::bson::{Document, doc};
-use serde_json;
+use flate2::{Compression, write::GzEncoder};
+use mongodb::bson::{Bson, DateTime, Document, doc, oid::ObjectId};
 use serial_test::serial;
+use std::io::Write;
 
 // Import test environment and utilities from utils module
 mod utils;
-use utils::test_environment::TestEnvironment;
+use utils::test_environment::{TOKIO_RUNTIME, TestConfig, TestEnvironment};
 use utils::utils::{make_get_request, make_post_request};
 
 // Base name for test collections
@@ -98,3 +99,292 @@ fn test_post_endpoint_all_cases() {
         run_post_test(&env, "complex_document", test_doc);
     }
 }
+
+#[test]
+#[serial]
+fn test_post_endpoint_ejson_round_trip() {
+    // Create a single test environment for all test cases
+    let env = TestEnvironment::new();
+
+    // Test case 1: POST a document with an ObjectId and DateTime and GET it back unchanged
+    {
+        let collection_name = unique_collection_name("ejson_round_trip");
+        env.insert_test_data(&collection_name, Vec::new());
+
+        let test_doc = doc! {
+            "_id": ObjectId::new(),
+            "createdAt": DateTime::from_millis(1_700_000_000_000),
+            "name": "typed document",
+            "count": 7
+        };
+        let json_body = Bson::Document(test_doc.clone())
+            .into_relaxed_extjson()
+            .to_string();
+
+        let full_request_path = format!("/{}", collection_name);
+        let (status_code, _body) = make_post_request(&full_request_path, &json_body);
+        assert_eq!(
+            status_code, 201,
+            "Expected status code 201, got {}",
+            status_code
+        );
+
+        let (get_status_code, get_body) = make_get_request(&full_request_path);
+        assert_eq!(
+            get_status_code, 200,
+            "Expected GET status code 200, got {}",
+            get_status_code
+        );
+
+        // The GET response is relaxed Extended JSON, so it parses back into the same BSON
+        let json: serde_json::Value =
+            serde_json::from_str(&get_body).expect("Failed to parse JSON response");
+        assert_eq!(
+            Bson::try_from(json).expect("Failed to parse Extended JSON"),
+            Bson::Array(vec![Bson::Document(test_doc)])
+        );
+    }
+
+    // Test case 2: Hand-written `$date` and `$oid` wrappers are stored as typed BSON values
+    {
+        let collection_name = unique_collection_name("ejson_wrappers");
+        env.insert_test_data(&collection_name, Vec::new());
+
+        let owner = ObjectId::new();
+        let json_body = format!(
+            r#"{{"_id": 1, "createdAt": {{"$date": "2024-01-01T00:00:00Z"}}, "owner": {{"$oid": "{}"}}}}"#,
+            owner.to_hex()
+        );
+        let (status_code, _body) = make_post_request(&format!("/{}", collection_name), &json_body);
+        assert_eq!(
+            status_code, 201,
+            "Expected status code 201, got {}",
+            status_code
+        );
+
+        // Read the stored document directly to see the BSON types
+        let stored = TOKIO_RUNTIME.block_on(async {
+            env.mongodb_client
+                .database(&env.config.database_name)
+                .collection::<Document>(&collection_name)
+                .find_one(doc! {})
+                .await
+                .expect("Failed to read the stored document")
+                .expect("Expected a stored document")
+        });
+        assert_eq!(
+            stored.get("createdAt"),
+            Some(&Bson::DateTime(DateTime::from_millis(1_704_067_200_000)))
+        );
+        assert_eq!(stored.get("owner"), Some(&Bson::ObjectId(owner)));
+    }
+}
+
+#[test]
+#[serial]
+fn test_post_endpoint_bulk_insert() {
+    // Create a single test environment for all test cases
+    let env = TestEnvironment::new();
+    let docs: Vec<Document> = (1..=50)
+        .map(|i| doc! { "_id": i, "name": format!("document {}", i) })
+        .collect();
+    let json_body = serde_json::to_string(&docs).expect("Failed to convert documents to JSON");
+
+    // Test case 1: A JSON array inserts every document and reports the ids in order
+    {
+        let collection_name = unique_collection_name("bulk_insert");
+        env.insert_test_data(&collection_name, Vec::new());
+
+        let full_request_path = format!("/{}", collection_name);
+        let (status_code, body) = make_post_request(&full_request_path, &json_body);
+        assert_eq!(
+            status_code, 201,
+            "Expected status code 201, got {}",
+            status_code
+        );
+        let result: serde_json::Value =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        assert_eq!(
+            result["insertedIds"],
+            serde_json::json!((1..=50).collect::<Vec<_>>())
+        );
+
+        let (_status_code, get_body) = make_get_request(&full_request_path);
+        let documents: Vec<Document> =
+            serde_json::from_str(&get_body).expect("Failed to parse JSON response");
+        assert_eq!(documents, docs);
+    }
+
+    // Test case 2: A gzip-compressed array is decompressed before parsing
+    {
+        let collection_name = unique_collection_name("gzip_bulk_insert");
+        env.insert_test_data(&collection_name, Vec::new());
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(json_body.as_bytes())
+            .expect("Failed to compress body");
+        let compressed = encoder.finish().expect("Failed to compress body");
+
+        let full_request_path = format!("/{}", collection_name);
+        let response = reqwest::blocking::Client::new()
+            .post(format!(
+                "http://127.0.0.1:{}{}",
+                TestConfig::default().app_port,
+                full_request_path
+            ))
+            .header("Content-Type", "application/json")
+            .header("Content-Encoding", "gzip")
+            .body(compressed)
+            .send()
+            .expect("Failed to send POST request");
+        assert_eq!(
+            response.status().as_u16(),
+            201,
+            "Expected status code 201, got {}",
+            response.status()
+        );
+
+        let (_status_code, get_body) = make_get_request(&full_request_path);
+        let documents: Vec<Document> =
+            serde_json::from_str(&get_body).expect("Failed to parse JSON response");
+        assert_eq!(documents, docs);
+    }
+
+    // Test case 3: An empty array is rejected
+    {
+        let collection_name = unique_collection_name("empty_bulk_insert");
+        env.insert_test_data(&collection_name, Vec::new());
+
+        let (status_code, _body) = make_post_request(&format!("/{}", collection_name), "[]");
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+    }
+}
+
+#[test]
+#[serial]
+fn test_post_endpoint_int64_number_policy() {
+    let env = TestEnvironment::with_config(TestConfig {
+        app_env: vec![("NUMBER_POLICY".to_string(), "int64".to_string())],
+        ..TestConfig::default()
+    });
+
+    // Test case 1: Whole numbers are stored as int64, fractions stay doubles
+    {
+        let collection_name = unique_collection_name("int64_number_policy");
+        env.insert_test_data(&collection_name, Vec::new());
+
+        let (status_code, _body) = make_post_request(
+            &format!("/{}", collection_name),
+            r#"{"_id": 1, "count": 42, "whole": 7.0, "ratio": 0.5}"#,
+        );
+        assert_eq!(
+            status_code, 201,
+            "Expected status code 201, got {}",
+            status_code
+        );
+
+        // Read the stored document directly to see the BSON types
+        let stored = TOKIO_RUNTIME.block_on(async {
+            env.mongodb_client
+                .database(&env.config.database_name)
+                .collection::<Document>(&collection_name)
+                .find_one(doc! {})
+                .await
+                .expect("Failed to read the stored document")
+                .expect("Expected a stored document")
+        });
+        assert_eq!(stored.get("count"), Some(&Bson::Int64(42)));
+        assert_eq!(stored.get("whole"), Some(&Bson::Int64(7)));
+        assert_eq!(stored.get("ratio"), Some(&Bson::Double(0.5)));
+    }
+}
+
+#[test]
+#[serial]
+fn test_post_endpoint_unordered_bulk_insert() {
+    // Create a single test environment for all test cases
+    let env = TestEnvironment::new();
+    let json_body = r#"[{"_id": 1}, {"_id": 2, "name": "duplicate"}, {"_id": 3}]"#;
+    let stored_ids = |collection_name: &str| -> Vec<Bson> {
+        let (_status_code, get_body) = make_get_request(&format!("/{}", collection_name));
+        let documents: Vec<Document> =
+            serde_json::from_str(&get_body).expect("Failed to parse JSON response");
+        documents
+            .iter()
+            .map(|document| document.get("_id").cloned().unwrap())
+            .collect()
+    };
+
+    // Test case 1: By default the batch stops at the duplicate
+    {
+        let collection_name = unique_collection_name("ordered_bulk_insert");
+        env.insert_test_data(&collection_name, vec![doc! { "_id": 2 }]);
+
+        let (status_code, _body) = make_post_request(&format!("/{}", collection_name), json_body);
+        assert_eq!(
+            status_code, 409,
+            "Expected status code 409, got {}",
+            status_code
+        );
+        assert_eq!(
+            stored_ids(&collection_name),
+            vec![Bson::Int32(2), Bson::Int32(1)]
+        );
+    }
+
+    // Test case 2: With `ordered=false` the rest still go in, and each outcome is reported
+    {
+        let collection_name = unique_collection_name("unordered_bulk_insert");
+        env.insert_test_data(&collection_name, vec![doc! { "_id": 2 }]);
+
+        let (status_code, body) =
+            make_post_request(&format!("/{}?ordered=false", collection_name), json_body);
+        assert_eq!(
+            status_code, 207,
+            "Expected status code 207, got {}",
+            status_code
+        );
+        let result: serde_json::Value =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        assert_eq!(result["insertedIds"], serde_json::json!([1, 3]));
+        assert_eq!(
+            result["results"][0],
+            serde_json::json!({ "index": 0, "insertedId": 1 })
+        );
+        assert_eq!(result["results"][1]["index"], 1);
+        assert_eq!(result["results"][1]["error"]["code"], 11000);
+        assert_eq!(
+            result["results"][2],
+            serde_json::json!({ "index": 2, "insertedId": 3 })
+        );
+
+        let mut ids = stored_ids(&collection_name);
+        ids.sort_by_key(|id| id.as_i32());
+        assert_eq!(ids, vec![Bson::Int32(1), Bson::Int32(2), Bson::Int32(3)]);
+    }
+
+    // Test case 3: A fully successful unordered batch is a plain 201, generating missing ids
+    {
+        let collection_name = unique_collection_name("unordered_bulk_insert_ok");
+        env.insert_test_data(&collection_name, Vec::new());
+
+        let (status_code, body) = make_post_request(
+            &format!("/{}?ordered=false", collection_name),
+            r#"[{"name": "a"}, {"name": "b"}]"#,
+        );
+        assert_eq!(
+            status_code, 201,
+            "Expected status code 201, got {}",
+            status_code
+        );
+        let result: serde_json::Value =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        assert_eq!(result["insertedIds"].as_array().map(Vec::len), Some(2));
+        assert_eq!(stored_ids(&collection_name).len(), 2);
+    }
+}
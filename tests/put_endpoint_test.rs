@@ -1,5 +1,4 @@
 use mongodb::bson::{Document, doc};
-use serde_json;
 use serial_test::serial;
 
 // Import test environment and utilities from utils module
@@ -312,4 +311,199 @@ fn test_put_endpoint_all_cases() {
             "Document value not correct"
         );
     }
+
+    // Test case 4: PATCH with arrayFilters updates only the matching array elements
+    {
+        let initial_docs = vec![doc! {
+            "_id": 1,
+            "items": [
+                { "sku": "apple", "qty": 1 },
+                { "sku": "pear", "qty": 2 },
+                { "sku": "apple", "qty": 3 },
+            ]
+        }];
+
+        // The wrapper carries the update and the filters its `$[identifier]` refers to
+        let update_doc = doc! {
+            "update": { "items.$[item].qty": 0 },
+            "arrayFilters": [{ "item.sku": "apple" }],
+        };
+
+        let documents = run_patch_test(
+            &env,
+            "array_filters",
+            initial_docs,
+            "?_id=1",
+            update_doc,
+            1, // Expected matched count
+            1, // Expected modified count
+        );
+
+        let quantities: Vec<(String, i32)> = documents[0]
+            .get_array("items")
+            .unwrap()
+            .iter()
+            .map(|item| {
+                let item = item.as_document().unwrap();
+                (
+                    item.get_str("sku").unwrap().to_string(),
+                    item.get_i32("qty").unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            quantities,
+            vec![
+                ("apple".to_string(), 0),
+                ("pear".to_string(), 2),
+                ("apple".to_string(), 0),
+            ]
+        );
+    }
+
+    // Test case 5: PATCH with upsert=true creates a document when nothing matches
+    {
+        let initial_docs = vec![doc! { "_id": 1, "name": "existing" }];
+        let (update_result, documents) = run_update_test(
+            &env,
+            "patch_upsert",
+            initial_docs,
+            "?name=missing&upsert=true",
+            doc! { "value": 300 },
+            "PATCH",
+            201, // Created, as for a PUT upsert
+        );
+        assert_eq!(update_result["matchedCount"].as_u64().unwrap(), 0);
+        assert!(
+            update_result["upsertedId"].is_object(),
+            "Expected upsertedId for a new document"
+        );
+
+        // The new document combines the filter and the update
+        assert_eq!(
+            documents.len(),
+            2,
+            "Expected 2 documents, got {}",
+            documents.len()
+        );
+        let created = documents
+            .iter()
+            .find(|doc| doc.get_str("name") == Ok("missing"))
+            .expect("Expected the upserted document");
+        assert_eq!(created.get_i32("value"), Ok(300));
+    }
+
+    // Test case 6: PATCH without a filter needs confirm=true to touch every document
+    {
+        let collection_name = unique_collection_name("patch_without_filter");
+        env.insert_test_data(
+            &collection_name,
+            vec![doc! { "_id": 1, "value": 1 }, doc! { "_id": 2, "value": 2 }],
+        );
+
+        let (status_code, _body) =
+            make_patch_request(&format!("/{}", collection_name), r#"{"value": 0}"#);
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+
+        let documents = run_patch_test(
+            &env,
+            "patch_without_filter",
+            vec![doc! { "_id": 1, "value": 1 }, doc! { "_id": 2, "value": 2 }],
+            "?confirm=true",
+            doc! { "value": 0 },
+            2, // Expected matched count
+            2, // Expected modified count
+        );
+        assert!(documents.iter().all(|doc| doc.get_i32("value") == Ok(0)));
+    }
+
+    // Test case 7: PATCH with multi=false updates only one of the matching documents
+    {
+        let documents = run_patch_test(
+            &env,
+            "patch_single",
+            vec![
+                doc! { "_id": 1, "status": "pending" },
+                doc! { "_id": 2, "status": "pending" },
+            ],
+            "?status=pending&multi=false",
+            doc! { "status": "done" },
+            1, // Expected matched count
+            1, // Expected modified count
+        );
+        let done = documents
+            .iter()
+            .filter(|doc| doc.get_str("status") == Ok("done"))
+            .count();
+        assert_eq!(done, 1, "Expected one updated document, got {}", done);
+    }
+}
+
+#[test]
+#[serial]
+fn test_update_rejects_id_changes() {
+    let env = TestEnvironment::new();
+    let collection_name = unique_collection_name("id_changes");
+    let collection_path = format!("/{}", collection_name);
+    let initial_docs = vec![doc! { "_id": 1, "name": "first" }];
+
+    // Test case 1: PUT with a different `_id` than the filter pins is refused
+    {
+        env.insert_test_data(&collection_name, initial_docs.clone());
+        let (status_code, body) = make_put_request(
+            &format!("{}?_id=1", collection_path),
+            r#"{"_id": 2, "name": "changed"}"#,
+        );
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+        assert!(body.contains("_id"), "Unexpected error {}", body);
+    }
+
+    // Test case 2: PATCH by id with a different `_id` is refused
+    {
+        let (status_code, _body) =
+            make_patch_request(&format!("{}/1", collection_path), r#"{"_id": 2}"#);
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+    }
+
+    // Test case 3: PATCH by filter that matches a document with another `_id` is refused
+    {
+        let (status_code, _body) =
+            make_patch_request(&format!("{}?name=first", collection_path), r#"{"_id": 3}"#);
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+
+        // None of the attempts changed the document
+        let (_status_code, body) = make_get_request(&collection_path);
+        let documents: Vec<Document> =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        assert_eq!(documents, initial_docs);
+    }
+
+    // Test case 4: Sending the document's own `_id` back is fine
+    {
+        let (status_code, _body) = make_patch_request(
+            &format!("{}/1", collection_path),
+            r#"{"_id": 1, "name": "renamed"}"#,
+        );
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+    }
 }
@@ -0,0 +1,72 @@
+use mongodb::bson::{Document, doc};
+use serial_test::serial;
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::{TOKIO_RUNTIME, TestConfig, TestEnvironment};
+use utils::utils::{make_get_request, make_post_request};
+
+static TEST_COLLECTION_NAME: &str = "mongor_query_cache_test";
+
+fn get_documents(query: &str) -> Vec<Document> {
+    let (status_code, body) = make_get_request(&format!("/{}?{}", TEST_COLLECTION_NAME, query));
+    assert_eq!(
+        status_code, 200,
+        "Expected status code 200, got {}",
+        status_code
+    );
+    serde_json::from_str(&body).expect("Failed to parse JSON")
+}
+
+#[test]
+#[serial]
+fn test_query_cache_all_cases() {
+    let env = TestEnvironment::with_config(TestConfig {
+        app_env: vec![
+            ("QUERY_CACHE_SIZE".to_string(), "16".to_string()),
+            ("QUERY_CACHE_TTL_MS".to_string(), "60000".to_string()),
+        ],
+        ..TestConfig::default()
+    });
+
+    let first = doc! { "_id": 1, "status": "open" };
+    env.insert_test_data(TEST_COLLECTION_NAME, vec![first.clone()]);
+
+    // Test case 1: A repeated query is served from the cache
+    {
+        assert_eq!(get_documents("status=open"), vec![first.clone()]);
+
+        // Written behind the API's back, so only a cache miss would see it
+        TOKIO_RUNTIME.block_on(async {
+            env.mongodb_client
+                .database(&env.config.database_name)
+                .collection::<Document>(TEST_COLLECTION_NAME)
+                .insert_one(doc! { "_id": 2, "status": "open" })
+                .await
+                .expect("Failed to insert test data");
+        });
+        assert_eq!(get_documents("status=open"), vec![first.clone()]);
+
+        // A different query is not a hit
+        assert_eq!(get_documents("status=open&limit=10").len(), 2);
+    }
+
+    // Test case 2: A write through the API invalidates the collection's cached queries
+    {
+        let (status_code, _body) = make_post_request(
+            &format!("/{}", TEST_COLLECTION_NAME),
+            r#"{"_id": 3, "status": "open"}"#,
+        );
+        assert_eq!(
+            status_code, 201,
+            "Expected status code 201, got {}",
+            status_code
+        );
+
+        let ids: Vec<i32> = get_documents("status=open")
+            .iter()
+            .map(|doc| doc.get_i32("_id").unwrap())
+            .collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+    }
+}
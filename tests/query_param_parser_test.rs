@@ -26,14 +26,14 @@ mod tests {
     #[test]
     fn test_tokenize_empty() {
         let mut lexer = Lexer::new("");
-        assert_eq!(lexer.tokenize(), vec![]);
+        assert_eq!(lexer.tokenize().unwrap(), vec![]);
     }
 
     #[test]
     fn test_tokenize_punctuation() {
         let mut lexer = Lexer::new(".,()");
         assert_eq!(
-            lexer.tokenize(),
+            lexer.tokenize().unwrap(),
             vec![
                 LexItem::SpecialChar('.'),
                 LexItem::SpecialChar(','),
@@ -101,13 +101,13 @@ mod tests {
         ];
         for (input, expected) in test_cases {
             let mut lexer = Lexer::new(input);
-            assert_eq!(lexer.tokenize(), expected);
+            assert_eq!(lexer.tokenize().unwrap(), expected);
         }
     }
 
     #[test]
     fn test_simple_top_level_expr() {
-        let input_key = "test".as_ref();
+        let input_key = "test";
 
         let test_cases = [
             (
@@ -127,7 +127,7 @@ mod tests {
         ];
         for (input, expected) in test_cases {
             let mut lexer = Lexer::new(input);
-            let mut parser = Parser::new(lexer.tokenize());
+            let mut parser = Parser::new(lexer.tokenize().unwrap());
             assert_eq!(parser.parse(input_key).expect("Not good"), bson!(expected));
         }
     }
@@ -191,7 +191,7 @@ mod tests {
 
         for (input, expected) in test_cases {
             let mut lexer = Lexer::new(input);
-            let mut parser = Parser::new(lexer.tokenize());
+            let mut parser = Parser::new(lexer.tokenize().unwrap());
             assert_eq!(parser.parse("or").unwrap(), bson!(expected));
         }
     }
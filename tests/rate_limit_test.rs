@@ -0,0 +1,147 @@
+use jsonwebtoken::{EncodingKey, Header, encode};
+use serial_test::serial;
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::{TestConfig, TestEnvironment};
+use utils::utils::send_http_request;
+
+// Secret shared by the server and the tokens signed below
+static JWT_SECRET: &str = "mongor-test-secret";
+
+// Helper function to sign a long-lived token for `subject`
+fn sign_token(subject: &str) -> String {
+    encode(
+        &Header::default(),
+        &serde_json::json!({ "sub": subject, "exp": u32::MAX }),
+        &EncodingKey::from_secret(JWT_SECRET.as_bytes()),
+    )
+    .expect("Failed to sign token")
+}
+
+#[test]
+#[serial]
+fn test_rate_limit_all_cases() {
+    // Allow five requests per minute per client
+    let _env = TestEnvironment::with_config(TestConfig {
+        app_env: vec![("RATE_LIMIT_PER_MIN".to_string(), "5".to_string())],
+        ..TestConfig::default()
+    });
+
+    // Test case 1: Requests over the threshold get 429 with Retry-After
+    {
+        let headers = [("X-API-Key", "rate-limited-client")];
+        for attempt in 1..=5 {
+            let response = send_http_request("/api/", "GET", None, &headers);
+            assert_eq!(
+                response.status().as_u16(),
+                200,
+                "Expected request {} to be allowed",
+                attempt
+            );
+        }
+
+        let response = send_http_request("/api/", "GET", None, &headers);
+        assert_eq!(
+            response.status().as_u16(),
+            429,
+            "Expected status code 429 after the limit"
+        );
+        let retry_after: u64 = response
+            .headers()
+            .get("Retry-After")
+            .expect("Expected a Retry-After header")
+            .to_str()
+            .unwrap()
+            .parse()
+            .expect("Expected Retry-After in seconds");
+        assert!((1..=60).contains(&retry_after));
+    }
+
+    // Test case 2: Unverified API keys do not pick the bucket, so rotating them does not help
+    {
+        for attempt in 1..=3 {
+            let api_key = format!("rotated-client-{}", attempt);
+            let response =
+                send_http_request("/api/", "GET", None, &[("X-API-Key", api_key.as_str())]);
+            assert_eq!(
+                response.status().as_u16(),
+                429,
+                "Expected a rotated API key to stay limited"
+            );
+        }
+    }
+
+    // Test case 3: Forwarding headers from untrusted peers do not pick the bucket either
+    {
+        for attempt in 1..=3 {
+            let forwarded_for = format!("203.0.113.{}", attempt);
+            let response = send_http_request(
+                "/api/",
+                "GET",
+                None,
+                &[
+                    ("X-Forwarded-For", forwarded_for.as_str()),
+                    ("Forwarded", &format!("for={}", forwarded_for)),
+                ],
+            );
+            assert_eq!(
+                response.status().as_u16(),
+                429,
+                "Expected a rotated X-Forwarded-For to stay limited"
+            );
+        }
+    }
+}
+
+#[test]
+#[serial]
+fn test_rate_limit_authenticated_subjects() {
+    // Allow two requests per minute per client
+    let _env = TestEnvironment::with_config(TestConfig {
+        app_env: vec![
+            ("RATE_LIMIT_PER_MIN".to_string(), "2".to_string()),
+            ("JWT_SECRET".to_string(), JWT_SECRET.to_string()),
+        ],
+        ..TestConfig::default()
+    });
+    let get_as = |subject: &str| {
+        let authorization = format!("Bearer {}", sign_token(subject));
+        send_http_request(
+            "/api/",
+            "GET",
+            None,
+            &[("Authorization", authorization.as_str())],
+        )
+        .status()
+        .as_u16()
+    };
+
+    // Test case 1: Each verified subject has its own budget
+    {
+        assert_eq!(get_as("alice"), 200);
+        assert_eq!(get_as("alice"), 200);
+        assert_eq!(get_as("alice"), 429);
+        assert_eq!(get_as("bob"), 200);
+    }
+
+    // Test case 2: A token signed with another secret falls back to the client IP
+    {
+        let forged = encode(
+            &Header::default(),
+            &serde_json::json!({ "sub": "mallory", "exp": u32::MAX }),
+            &EncodingKey::from_secret(b"not-the-secret"),
+        )
+        .unwrap();
+        let authorization = format!("Bearer {}", forged);
+        let headers = [("Authorization", authorization.as_str())];
+        let statuses: Vec<u16> = (0..3)
+            .map(|_| {
+                send_http_request("/api/", "GET", None, &headers)
+                    .status()
+                    .as_u16()
+            })
+            .collect();
+        assert_eq!(statuses, vec![200, 200, 429]);
+    }
+}
@@ -0,0 +1,65 @@
+use mongodb::bson::doc;
+use serial_test::serial;
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::TestEnvironment;
+use utils::utils::send_http_request;
+
+static TEST_COLLECTION_NAME: &str = "mongor_request_id_test";
+
+// Sends a GET and returns the request id the response carries
+fn response_request_id(headers: &[(&str, &str)]) -> String {
+    let response = send_http_request(&format!("/{}", TEST_COLLECTION_NAME), "GET", None, headers);
+    assert_eq!(response.status().as_u16(), 200);
+    response
+        .headers()
+        .get("X-Request-ID")
+        .expect("Missing X-Request-ID header")
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+#[test]
+#[serial]
+fn test_request_id_all_cases() {
+    let env = TestEnvironment::new();
+    env.insert_test_data(TEST_COLLECTION_NAME, vec![doc! { "_id": 1 }]);
+
+    // Test case 1: A provided request id is echoed back
+    {
+        let id = response_request_id(&[("X-Request-ID", "trace-abc-123")]);
+        assert_eq!(id, "trace-abc-123");
+    }
+
+    // Test case 2: Requests without one get a fresh id each
+    {
+        let first = response_request_id(&[]);
+        let second = response_request_id(&[]);
+        assert!(!first.is_empty());
+        assert_ne!(first, second);
+    }
+
+    // Test case 3: Malformed ids are replaced rather than echoed
+    {
+        let id = response_request_id(&[("X-Request-ID", "not a token")]);
+        assert_ne!(id, "not a token");
+        assert!(!id.is_empty());
+    }
+
+    // Test case 4: Errors carry the id too
+    {
+        let response = send_http_request(
+            "/mongor_request_id_missing",
+            "GET",
+            None,
+            &[("X-Request-ID", "trace-missing")],
+        );
+        assert_eq!(response.status().as_u16(), 404);
+        assert_eq!(
+            response.headers().get("X-Request-ID").unwrap(),
+            "trace-missing"
+        );
+    }
+}
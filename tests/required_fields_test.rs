@@ -0,0 +1,74 @@
+use serial_test::serial;
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::{TestConfig, TestEnvironment};
+use utils::utils::{make_post_request, make_put_request};
+
+static TEST_COLLECTION_NAME: &str = "mongor_required_fields_test";
+
+#[test]
+#[serial]
+fn test_required_fields_all_cases() {
+    let env = TestEnvironment::with_config(TestConfig {
+        app_env: vec![(
+            "COLLECTION_REQUIRED_FIELDS".to_string(),
+            format!("{}:email,address.city", TEST_COLLECTION_NAME),
+        )],
+        ..TestConfig::default()
+    });
+    // The collection has no MongoDB validator, so only mongor enforces the fields
+    env.insert_test_data(TEST_COLLECTION_NAME, Vec::new());
+    let path = format!("/{}", TEST_COLLECTION_NAME);
+
+    // Test case 1: A POST missing a required field is rejected, naming the field
+    {
+        let (status_code, body) =
+            make_post_request(&path, r#"{"email": "a@example.com", "address": {}}"#);
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+        assert!(body.contains("/address/city"), "{}", body);
+    }
+
+    // Test case 2: A POST carrying every required field is inserted
+    {
+        let (status_code, body) = make_post_request(
+            &path,
+            r#"{"_id": 1, "email": "a@example.com", "address": {"city": "Oslo"}}"#,
+        );
+        assert_eq!(status_code, 201, "{}", body);
+    }
+
+    // Test case 3: Bulk inserts check every document
+    {
+        let (status_code, body) = make_post_request(
+            &path,
+            r#"[{"email": "b@example.com", "address": {"city": "Bergen"}}, {"address": {"city": "Troms"}}]"#,
+        );
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+        assert!(body.contains("/1/email"), "{}", body);
+    }
+
+    // Test case 4: PUT is checked as well, as it may create the document
+    {
+        let (status_code, _body) =
+            make_put_request(&format!("{}?_id=2", path), r#"{"email": "c@example.com"}"#);
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+        let (status_code, body) = make_put_request(
+            &format!("{}?_id=2", path),
+            r#"{"email": "c@example.com", "address": {"city": "Oslo"}}"#,
+        );
+        assert_eq!(status_code, 201, "{}", body);
+    }
+}
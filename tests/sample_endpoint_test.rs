@@ -0,0 +1,105 @@
+use mongodb::bson::{Document, doc};
+use serial_test::serial;
+use std::collections::HashSet;
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::{TestConfig, TestEnvironment};
+use utils::utils::make_get_request;
+
+static TEST_COLLECTION_NAME: &str = "mongor_sample_endpoint_test";
+
+// Runs a sample request expecting 200 and returns the sampled documents
+fn get_sample(query: &str) -> Vec<Document> {
+    let (status_code, body) =
+        make_get_request(&format!("/{}/sample{}", TEST_COLLECTION_NAME, query));
+    assert_eq!(
+        status_code, 200,
+        "Expected status code 200, got {}",
+        status_code
+    );
+    serde_json::from_str(&body).expect("Failed to parse JSON response")
+}
+
+fn sampled_ids(documents: &[Document]) -> Vec<i32> {
+    let mut ids: Vec<i32> = documents
+        .iter()
+        .map(|document| document.get_i32("_id").expect("Expected an int _id"))
+        .collect();
+    ids.sort();
+    ids
+}
+
+fn test_documents() -> Vec<Document> {
+    (1..=100)
+        .map(|i| doc! { "_id": i, "parity": if i % 2 == 0 { "even" } else { "odd" } })
+        .collect()
+}
+
+#[test]
+#[serial]
+fn test_sample_endpoint_all_cases() {
+    let env = TestEnvironment::new();
+    env.insert_test_data(TEST_COLLECTION_NAME, test_documents());
+
+    // Test case 1: As many distinct documents as asked for come back
+    {
+        let ids = sampled_ids(&get_sample("?size=5"));
+        assert_eq!(ids.len(), 5);
+        assert_eq!(ids.iter().collect::<HashSet<_>>().len(), 5);
+    }
+
+    // Test case 2: Samples differ across calls
+    {
+        let samples: HashSet<Vec<i32>> = (0..5)
+            .map(|_| sampled_ids(&get_sample("?size=5")))
+            .collect();
+        assert!(
+            samples.len() > 1,
+            "Expected varying samples, got {:?}",
+            samples
+        );
+    }
+
+    // Test case 3: The filter applies before sampling
+    {
+        let documents = get_sample("?size=10&parity=even");
+        assert_eq!(documents.len(), 10);
+        assert!(
+            documents
+                .iter()
+                .all(|document| document.get_str("parity") == Ok("even"))
+        );
+    }
+
+    // Test case 4: A size beyond the matches returns every match
+    {
+        assert_eq!(get_sample("?size=500").len(), 100);
+    }
+
+    // Test case 5: The size has to be a positive integer
+    {
+        let (status_code, _body) =
+            make_get_request(&format!("/{}/sample?size=0", TEST_COLLECTION_NAME));
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+    }
+}
+
+#[test]
+#[serial]
+fn test_sample_endpoint_max_size() {
+    let env = TestEnvironment::with_config(TestConfig {
+        app_env: vec![("MAX_SAMPLE_SIZE".to_string(), "3".to_string())],
+        ..TestConfig::default()
+    });
+    env.insert_test_data(TEST_COLLECTION_NAME, test_documents());
+
+    // Test case 1: Sizes beyond MAX_SAMPLE_SIZE are capped
+    {
+        assert_eq!(get_sample("?size=50").len(), 3);
+    }
+}
@@ -0,0 +1,81 @@
+use mongodb::bson::{DateTime, doc};
+use serial_test::serial;
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::TestEnvironment;
+use utils::utils::make_get_request;
+
+static TEST_COLLECTION_NAME: &str = "mongor_schema_endpoint_test";
+
+// Runs a schema request expecting 200 and returns the inferred schema
+fn get_schema(query: &str) -> serde_json::Value {
+    let (status_code, body) =
+        make_get_request(&format!("/{}/schema{}", TEST_COLLECTION_NAME, query));
+    assert_eq!(
+        status_code, 200,
+        "Expected status code 200, got {}",
+        status_code
+    );
+    serde_json::from_str(&body).expect("Failed to parse JSON")
+}
+
+#[test]
+#[serial]
+fn test_schema_endpoint_all_cases() {
+    let env = TestEnvironment::new();
+    env.insert_test_data(
+        TEST_COLLECTION_NAME,
+        vec![
+            doc! { "_id": 1, "kind": "user", "name": "Ann", "age": 31, "address": { "city": "Oslo" } },
+            doc! { "_id": 2, "kind": "user", "name": "Bo", "age": 42_i64, "joined": DateTime::from_millis(0) },
+            doc! { "_id": 3, "kind": "user", "name": "Cy", "age": "unknown", "tags": ["a"] },
+            doc! { "_id": 4, "kind": "bot", "name": null },
+        ],
+    );
+
+    // Test case 1: Common fields report every type they hold and how often they appear
+    {
+        let schema = get_schema("");
+        assert_eq!(schema["sampled"], 4);
+        let fields = &schema["fields"];
+        assert_eq!(fields["_id"]["types"], serde_json::json!({ "int": 4 }));
+        assert_eq!(fields["_id"]["frequency"], 1.0);
+        assert_eq!(
+            fields["name"]["types"],
+            serde_json::json!({ "string": 3, "null": 1 })
+        );
+        assert_eq!(
+            fields["age"]["types"],
+            serde_json::json!({ "int": 1, "long": 1, "string": 1 })
+        );
+        assert_eq!(fields["age"]["frequency"], 0.75);
+        assert_eq!(fields["joined"]["types"], serde_json::json!({ "date": 1 }));
+        assert_eq!(fields["tags"]["types"], serde_json::json!({ "array": 1 }));
+        assert_eq!(
+            fields["address.city"]["types"],
+            serde_json::json!({ "string": 1 })
+        );
+    }
+
+    // Test case 2: The filter scopes the sample
+    {
+        let schema = get_schema("?kind=bot");
+        assert_eq!(schema["sampled"], 1);
+        assert_eq!(
+            schema["fields"]["name"]["types"],
+            serde_json::json!({ "null": 1 })
+        );
+        assert!(schema["fields"].get("age").is_none());
+    }
+
+    // Test case 3: Missing collections are 404
+    {
+        let (status_code, _body) = make_get_request("/mongor_schema_endpoint_missing/schema");
+        assert_eq!(
+            status_code, 404,
+            "Expected status code 404, got {}",
+            status_code
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use mongodb::bson::{Document, doc};
+use serial_test::serial;
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::{TOKIO_RUNTIME, TestConfig, TestEnvironment};
+use utils::utils::{make_get_request, make_post_request, make_put_request};
+
+// Collection created with a $jsonSchema validator
+static TEST_COLLECTION_NAME: &str = "mongor_schema_validation_test_users";
+
+// Helper function to create the validated collection
+fn create_validated_collection(env: &TestEnvironment) {
+    TOKIO_RUNTIME.block_on(async {
+        let database = env.mongodb_client.database(&env.config.database_name);
+        database
+            .collection::<Document>(TEST_COLLECTION_NAME)
+            .drop()
+            .await
+            .ok();
+        database
+            .create_collection(TEST_COLLECTION_NAME)
+            .validator(doc! { "$jsonSchema": {
+                "bsonType": "object",
+                "required": ["name", "age"],
+                "properties": {
+                    "name": { "bsonType": "string" },
+                    "age": { "bsonType": "int", "minimum": 0 }
+                }
+            } })
+            .await
+            .expect("Failed to create validated collection");
+    });
+    // Let the background task pick the validator up
+    std::thread::sleep(std::time::Duration::from_secs(2));
+}
+
+#[test]
+#[serial]
+fn test_schema_validation_all_cases() {
+    let env = TestEnvironment::with_config(TestConfig {
+        app_env: vec![
+            ("VALIDATE_REQUEST_BODIES".to_string(), "true".to_string()),
+            ("CATALOG_REFRESH_SECS".to_string(), "1".to_string()),
+        ],
+        ..TestConfig::default()
+    });
+    create_validated_collection(&env);
+    let full_request_path = format!("/{}", TEST_COLLECTION_NAME);
+
+    // Test case 1: A conforming body is inserted
+    {
+        let (status_code, _body) = make_post_request(
+            &full_request_path,
+            r#"{"_id": 1, "name": "Ana", "age": 30}"#,
+        );
+        assert_eq!(
+            status_code, 201,
+            "Expected status code 201, got {}",
+            status_code
+        );
+    }
+
+    // Test case 2: A non-conforming body is rejected with the offending schema paths
+    {
+        let (status_code, body) =
+            make_post_request(&full_request_path, r#"{"_id": 2, "age": "thirty"}"#);
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+        let errors: serde_json::Value =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        assert_eq!(
+            errors,
+            serde_json::json!({ "errors": ["/name: is required", "/age: expected integer"] })
+        );
+    }
+
+    // Test case 3: Partial updates are checked without requiring every field
+    {
+        let (status_code, _body) =
+            make_put_request(&format!("{}?_id=1", full_request_path), r#"{"age": -5}"#);
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+
+        let (status_code, _body) =
+            make_put_request(&format!("{}?_id=1", full_request_path), r#"{"age": 31}"#);
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+    }
+
+    // Test case 4: Only the conforming writes reached the collection
+    {
+        let (status_code, body) = make_get_request(&full_request_path);
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+        let documents: Vec<Document> =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        assert_eq!(documents, vec![doc! { "_id": 1, "name": "Ana", "age": 31 }]);
+    }
+}
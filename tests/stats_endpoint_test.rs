@@ -0,0 +1,71 @@
+use mongodb::bson::doc;
+use serial_test::serial;
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::{TestConfig, TestEnvironment};
+use utils::utils::make_get_request;
+
+// Base name for test collections
+static TEST_COLLECTION_BASE_NAME: &str = "mongor_stats_endpoint_test";
+
+// Generate a unique collection name for each test
+fn unique_collection_name(test_name: &str) -> String {
+    format!(
+        "{}_{}",
+        TEST_COLLECTION_BASE_NAME,
+        test_name.replace(" ", "_")
+    )
+}
+
+#[test]
+#[serial]
+fn test_stats_endpoint_all_cases() {
+    let env = TestEnvironment::with_config(TestConfig {
+        app_env: vec![("CATALOG_REFRESH_SECS".to_string(), "1".to_string())],
+        ..TestConfig::default()
+    });
+
+    let small_collection = unique_collection_name("small");
+    let large_collection = unique_collection_name("large");
+    env.insert_test_data(&small_collection, vec![doc! { "_id": 1 }]);
+    env.insert_test_data(
+        &large_collection,
+        (1..=25).map(|i| doc! { "_id": i, "value": i }).collect(),
+    );
+    // Let the background task pick the new collections up
+    std::thread::sleep(std::time::Duration::from_secs(2));
+
+    // Test case 1: Every test collection is reported with its document count
+    {
+        let (status_code, body) = make_get_request("/api/stats");
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+
+        let stats: serde_json::Value =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        let collection_stats = |name: &str| {
+            stats["collections"]
+                .as_array()
+                .expect("Expected a collections array")
+                .iter()
+                .find(|collection| collection["name"] == name)
+                .cloned()
+                .unwrap_or_else(|| panic!("Missing stats for {}", name))
+        };
+
+        let small = collection_stats(&small_collection);
+        assert_eq!(small["count"], 1);
+        assert_eq!(small["indexCount"], 1);
+
+        let large = collection_stats(&large_collection);
+        assert_eq!(large["count"], 25);
+        assert!(large["size"].as_i64().unwrap() > small["size"].as_i64().unwrap());
+
+        // Totals cover at least the test collections
+        assert!(stats["totals"]["count"].as_i64().unwrap() >= 26);
+    }
+}
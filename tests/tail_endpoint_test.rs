@@ -0,0 +1,95 @@
+use mongodb::bson::doc;
+use serial_test::serial;
+use std::io::{BufRead, BufReader};
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::{TOKIO_RUNTIME, TestEnvironment};
+use utils::utils::{make_get_request, make_post_request, send_http_request};
+
+// Base name for test collections
+static TEST_COLLECTION_BASE_NAME: &str = "mongor_tail_endpoint_test";
+
+// Generate a unique collection name for each test
+fn unique_collection_name(test_name: &str) -> String {
+    format!(
+        "{}_{}",
+        TEST_COLLECTION_BASE_NAME,
+        test_name.replace(" ", "_")
+    )
+}
+
+// Helper function to create an empty capped collection
+fn create_capped_collection(env: &TestEnvironment, collection_name: &str) {
+    TOKIO_RUNTIME.block_on(async {
+        let database = env.mongodb_client.database(&env.config.database_name);
+        database
+            .collection::<mongodb::bson::Document>(collection_name)
+            .drop()
+            .await
+            .ok();
+        database
+            .create_collection(collection_name)
+            .capped(true)
+            .size(1024 * 1024)
+            .await
+            .expect("Failed to create capped collection");
+    });
+    std::thread::sleep(std::time::Duration::from_millis(500));
+}
+
+#[test]
+#[serial]
+fn test_tail_endpoint_all_cases() {
+    // Create a single test environment for all test cases
+    let env = TestEnvironment::new();
+
+    // Test case 1: A subscriber receives new matching documents as SSE events
+    {
+        let collection_name = unique_collection_name("capped");
+        create_capped_collection(&env, &collection_name);
+
+        let response = send_http_request(
+            &format!("/{}/tail?level=error", collection_name),
+            "GET",
+            None,
+            &[],
+        );
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(
+            response.headers().get("Content-Type").unwrap(),
+            "text/event-stream"
+        );
+
+        let full_request_path = format!("/{}", collection_name);
+        let (status_code, _body) =
+            make_post_request(&full_request_path, r#"{"_id": 1, "level": "info"}"#);
+        assert_eq!(status_code, 201);
+        let (status_code, _body) =
+            make_post_request(&full_request_path, r#"{"_id": 2, "level": "error"}"#);
+        assert_eq!(status_code, 201);
+
+        // The first event is the error log; the info log is filtered out
+        let data_line = BufReader::new(response)
+            .lines()
+            .map(|line| line.expect("Failed to read SSE stream"))
+            .find(|line| line.starts_with("data: "))
+            .expect("Expected an SSE event");
+        let event: serde_json::Value =
+            serde_json::from_str(&data_line["data: ".len()..]).expect("Failed to parse event");
+        assert_eq!(event, serde_json::json!({ "_id": 2, "level": "error" }));
+    }
+
+    // Test case 2: Tailing a collection that is not capped is rejected
+    {
+        let collection_name = unique_collection_name("not_capped");
+        env.insert_test_data(&collection_name, vec![doc! { "_id": 1 }]);
+
+        let (status_code, _body) = make_get_request(&format!("/{}/tail", collection_name));
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+    }
+}
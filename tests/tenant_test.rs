@@ -0,0 +1,256 @@
+use jsonwebtoken::{EncodingKey, Header, encode};
+use mongodb::bson::{Document, doc};
+use serial_test::serial;
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::{TestConfig, TestEnvironment};
+use utils::utils::{make_get_request, make_http_request_with_headers};
+
+// Logical collection name shared by both tenants
+static TEST_COLLECTION_NAME: &str = "mongor_tenant_test_orders";
+
+// Secret the tenant tokens are signed with
+static JWT_SECRET: &str = "mongor-test-secret";
+
+// Helper function to sign a long-lived token reading the collection for `tenant`
+fn sign_token(tenant: &str) -> String {
+    encode(
+        &Header::default(),
+        &serde_json::json!({
+            "scope": format!("read:{}", TEST_COLLECTION_NAME),
+            "tenant": tenant,
+            "exp": u32::MAX,
+        }),
+        &EncodingKey::from_secret(JWT_SECRET.as_bytes()),
+    )
+    .expect("Failed to sign token")
+}
+
+// Helper function to read the collection as a given tenant
+fn get_as_tenant(tenant: &str) -> Vec<Document> {
+    let (status_code, body) = make_http_request_with_headers(
+        &format!("/{}", TEST_COLLECTION_NAME),
+        "GET",
+        None,
+        &[("X-Tenant-ID", tenant)],
+    );
+    assert_eq!(
+        status_code, 200,
+        "Expected status code 200, got {}",
+        status_code
+    );
+    serde_json::from_str(&body).expect("Failed to parse JSON response")
+}
+
+#[test]
+#[serial]
+fn test_tenant_all_cases() {
+    let env = TestEnvironment::with_config(TestConfig {
+        app_env: vec![("TENANT_HEADER".to_string(), "X-Tenant-ID".to_string())],
+        ..TestConfig::default()
+    });
+
+    let acme_doc = doc! { "_id": 1, "customer": "acme customer" };
+    let globex_doc = doc! { "_id": 1, "customer": "globex customer" };
+    env.insert_test_data(
+        &format!("acme_{}", TEST_COLLECTION_NAME),
+        vec![acme_doc.clone()],
+    );
+    env.insert_test_data(
+        &format!("globex_{}", TEST_COLLECTION_NAME),
+        vec![globex_doc.clone()],
+    );
+
+    // Test case 1: Each tenant only sees its own documents
+    {
+        assert_eq!(get_as_tenant("acme"), vec![acme_doc.clone()]);
+        assert_eq!(get_as_tenant("globex"), vec![globex_doc.clone()]);
+    }
+
+    // Test case 2: Writes land in the writing tenant's collection
+    {
+        let (status_code, _body) = make_http_request_with_headers(
+            &format!("/{}", TEST_COLLECTION_NAME),
+            "POST",
+            Some(r#"{"_id": 2, "customer": "second acme customer"}"#),
+            &[("X-Tenant-ID", "acme")],
+        );
+        assert_eq!(
+            status_code, 201,
+            "Expected status code 201, got {}",
+            status_code
+        );
+
+        assert_eq!(get_as_tenant("acme").len(), 2);
+        assert_eq!(get_as_tenant("globex"), vec![globex_doc]);
+    }
+
+    // Test case 3: Requests without the tenant header are rejected
+    {
+        let (status_code, _body) = make_get_request(&format!("/{}", TEST_COLLECTION_NAME));
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+    }
+
+    // Test case 4: Tenants cannot contain the `_` separating them from the collection
+    {
+        let (status_code, _body) = make_http_request_with_headers(
+            &format!("/{}", TEST_COLLECTION_NAME),
+            "GET",
+            None,
+            &[("X-Tenant-ID", "acme_x")],
+        );
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+    }
+}
+
+#[test]
+#[serial]
+fn test_tenant_collection_visibility() {
+    let hidden_collection_name = "mongor_tenant_test_secrets";
+    let env = TestEnvironment::with_config(TestConfig {
+        app_env: vec![
+            ("TENANT_HEADER".to_string(), "X-Tenant-ID".to_string()),
+            (
+                "COLLECTIONS_ALLOWLIST".to_string(),
+                format!("{},{}", TEST_COLLECTION_NAME, hidden_collection_name),
+            ),
+            (
+                "COLLECTIONS_DENYLIST".to_string(),
+                hidden_collection_name.to_string(),
+            ),
+            ("CATALOG_REFRESH_SECS".to_string(), "1".to_string()),
+        ],
+        ..TestConfig::default()
+    });
+
+    let acme_doc = doc! { "_id": 1, "customer": "acme customer" };
+    env.insert_test_data(
+        &format!("acme_{}", TEST_COLLECTION_NAME),
+        vec![acme_doc.clone()],
+    );
+    env.insert_test_data(
+        &format!("acme_{}", hidden_collection_name),
+        vec![doc! { "_id": 1, "secret": "acme secret" }],
+    );
+    // Let the background task pick the new collections up
+    std::thread::sleep(std::time::Duration::from_secs(2));
+
+    // Test case 1: The allowlist names logical collections, whatever the tenant
+    {
+        assert_eq!(get_as_tenant("acme"), vec![acme_doc]);
+    }
+
+    // Test case 2: A denied logical collection stays hidden from every tenant
+    {
+        let (status_code, _body) = make_http_request_with_headers(
+            &format!("/{}", hidden_collection_name),
+            "GET",
+            None,
+            &[("X-Tenant-ID", "acme")],
+        );
+        assert_eq!(
+            status_code, 404,
+            "Expected status code 404, got {}",
+            status_code
+        );
+    }
+
+    // Test case 3: Physical names do not slip past the lists
+    {
+        let (status_code, _body) = make_http_request_with_headers(
+            &format!("/acme_{}", hidden_collection_name),
+            "GET",
+            None,
+            &[("X-Tenant-ID", "acme")],
+        );
+        assert_eq!(
+            status_code, 404,
+            "Expected status code 404, got {}",
+            status_code
+        );
+    }
+
+    // Test case 4: The OpenAPI document only covers the tenant's exposed collections
+    {
+        let (status_code, body) = make_http_request_with_headers(
+            "/openapi.json",
+            "GET",
+            None,
+            &[("X-Tenant-ID", "acme")],
+        );
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+        assert!(body.contains(&format!("/collections/{}", TEST_COLLECTION_NAME)));
+        assert!(!body.contains(hidden_collection_name));
+        assert!(!body.contains("acme_"));
+    }
+}
+
+#[test]
+#[serial]
+fn test_tenant_bound_to_token() {
+    let env = TestEnvironment::with_config(TestConfig {
+        app_env: vec![
+            ("TENANT_HEADER".to_string(), "X-Tenant-ID".to_string()),
+            ("JWT_SECRET".to_string(), JWT_SECRET.to_string()),
+        ],
+        ..TestConfig::default()
+    });
+
+    let acme_doc = doc! { "_id": 1, "customer": "acme customer" };
+    env.insert_test_data(
+        &format!("acme_{}", TEST_COLLECTION_NAME),
+        vec![acme_doc.clone()],
+    );
+    env.insert_test_data(
+        &format!("globex_{}", TEST_COLLECTION_NAME),
+        vec![doc! { "_id": 1, "customer": "globex customer" }],
+    );
+    let get_as = |token_tenant: &str, header_tenant: &str| {
+        let authorization = format!("Bearer {}", sign_token(token_tenant));
+        make_http_request_with_headers(
+            &format!("/{}", TEST_COLLECTION_NAME),
+            "GET",
+            None,
+            &[
+                ("Authorization", authorization.as_str()),
+                ("X-Tenant-ID", header_tenant),
+            ],
+        )
+    };
+
+    // Test case 1: A token reads its own tenant's collection
+    {
+        let (status_code, body) = get_as("acme", "acme");
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+        let documents: Vec<Document> =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        assert_eq!(documents, vec![acme_doc]);
+    }
+
+    // Test case 2: Switching the tenant header does not reach another tenant
+    {
+        let (status_code, _body) = get_as("acme", "globex");
+        assert_eq!(
+            status_code, 403,
+            "Expected status code 403, got {}",
+            status_code
+        );
+    }
+}
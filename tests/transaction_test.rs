@@ -0,0 +1,146 @@
+use mongodb::bson::{Document, doc};
+use serial_test::serial;
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::{TOKIO_RUNTIME, TestConfig, TestEnvironment};
+use utils::utils::{make_get_request, make_post_request};
+
+// Base name for test collections
+static TEST_COLLECTION_BASE_NAME: &str = "mongor_transaction_test";
+
+// Generate a unique collection name for each test
+fn unique_collection_name(test_name: &str) -> String {
+    format!(
+        "{}_{}",
+        TEST_COLLECTION_BASE_NAME,
+        test_name.replace(" ", "_")
+    )
+}
+
+// Transactions only work on replica sets, which the test MongoDB may not be
+fn is_replica_set(env: &TestEnvironment) -> bool {
+    TOKIO_RUNTIME.block_on(async {
+        env.mongodb_client
+            .database("admin")
+            .run_command(doc! { "hello": 1 })
+            .await
+            .map(|hello| hello.contains_key("setName"))
+            .unwrap_or(false)
+    })
+}
+
+#[test]
+#[serial]
+fn test_transaction_endpoint_all_cases() {
+    let env = TestEnvironment::with_config(TestConfig {
+        app_env: vec![("CATALOG_REFRESH_SECS".to_string(), "1".to_string())],
+        ..TestConfig::default()
+    });
+
+    let orders = unique_collection_name("orders");
+    let stock = unique_collection_name("stock");
+    env.insert_test_data(&orders, vec![doc! { "_id": 1, "sku": "a" }]);
+    env.insert_test_data(&stock, vec![doc! { "_id": "a", "qty": 5 }]);
+    // Let the background task pick the new collections up
+    std::thread::sleep(std::time::Duration::from_secs(2));
+
+    if !is_replica_set(&env) {
+        // Test case 1: Standalone servers get an informative error
+        let body = format!(
+            r#"{{"operations": [{{"op": "insert", "collection": "{}", "document": {{"_id": 2}}}}]}}"#,
+            orders
+        );
+        let (status_code, body) = make_post_request("/api/transaction", &body);
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+        assert!(body.contains("replica set"), "Unexpected body: {}", body);
+        return;
+    }
+
+    // Test case 2: All operations are applied and reported in order
+    {
+        let body = format!(
+            r#"{{"operations": [
+                {{"op": "insert", "collection": "{}", "document": {{"_id": 2, "sku": "a"}}}},
+                {{"op": "update", "collection": "{}", "filter": {{"_id": "eq.a"}}, "update": {{"qty": 4}}}}
+            ]}}"#,
+            orders, stock
+        );
+        let (status_code, body) = make_post_request("/api/transaction", &body);
+        assert_eq!(
+            status_code, 200,
+            "Expected status code 200, got {}",
+            status_code
+        );
+        let response: serde_json::Value =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        assert_eq!(
+            response,
+            serde_json::json!({ "results": [
+                { "insertedId": 2 },
+                { "matchedCount": 1, "modifiedCount": 1 }
+            ] })
+        );
+    }
+
+    // Test case 3: A failing operation rolls back the ones before it
+    {
+        let body = format!(
+            r#"{{"operations": [
+                {{"op": "update", "collection": "{}", "filter": {{"_id": "eq.a"}}, "update": {{"qty": 3}}}},
+                {{"op": "insert", "collection": "{}", "document": {{"_id": 1}}}}
+            ]}}"#,
+            stock, orders
+        );
+        let (status_code, body) = make_post_request("/api/transaction", &body);
+        assert_eq!(
+            status_code, 409,
+            "Expected status code 409, got {}",
+            status_code
+        );
+        let response: serde_json::Value =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        assert_eq!(response["failedOperation"], 1);
+        assert_eq!(response["rolledBack"], true);
+
+        let (_status_code, body) = make_get_request(&format!("/{}", stock));
+        let documents: Vec<Document> =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        assert_eq!(documents, vec![doc! { "_id": "a", "qty": 4 }]);
+    }
+
+    // Test case 4: Unknown collections are rejected before anything runs
+    {
+        let body =
+            r#"{"operations": [{"op": "delete", "collection": "mongor_transaction_missing"}]}"#;
+        let (status_code, _body) = make_post_request("/api/transaction", body);
+        assert_eq!(
+            status_code, 404,
+            "Expected status code 404, got {}",
+            status_code
+        );
+    }
+
+    // Test case 5: Operations without a filter need confirming
+    {
+        let body = format!(
+            r#"{{"operations": [{{"op": "delete", "collection": "{}"}}]}}"#,
+            stock
+        );
+        let (status_code, _body) = make_post_request("/api/transaction", &body);
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+
+        let (_status_code, body) = make_get_request(&format!("/{}", stock));
+        let documents: Vec<Document> =
+            serde_json::from_str(&body).expect("Failed to parse JSON response");
+        assert_eq!(documents.len(), 1);
+    }
+}
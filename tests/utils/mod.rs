@@ -1,3 +1,4 @@
 // Export the test environment and utilities
 pub mod test_environment;
+#[allow(clippy::module_inception)]
 pub mod utils;
@@ -1,3 +1,5 @@
+#![allow(dead_code)]
+
 use mongodb::{Client, bson::Document, options::ClientOptions};
 use std::process::Child;
 use tokio::runtime::Runtime;
@@ -17,6 +19,8 @@ pub struct TestConfig {
     pub mongodb_log_path: String,
     pub app_port: u16,
     pub database_name: String,
+    // Extra environment variables passed to the application process
+    pub app_env: Vec<(String, String)>,
 }
 
 // Use fixed ports for tests since they run serially
@@ -31,6 +35,7 @@ impl Default for TestConfig {
             mongodb_log_path: format!("./test-dbpath/mongodb_test_{}.log", MONGODB_PORT),
             app_port: APP_PORT,
             database_name: "test".to_string(),
+            app_env: Vec::new(),
         }
     }
 }
@@ -69,6 +74,7 @@ impl TestEnvironment {
             .args(["run", "--", "--port", &config.app_port.to_string()])
             .env("DATABASE_CONN_URL", &mongodb_uri)
             .env("DATABASE_NAME", &config.database_name)
+            .envs(config.app_env.iter().cloned())
             .spawn()
             .expect("Failed to start application server");
 
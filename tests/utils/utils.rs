@@ -122,6 +122,31 @@ pub fn make_get_request(path: &str) -> (u16, String) {
 
 // Make HTTP request with specified method and optional JSON body
 pub fn make_http_request(path: &str, method: &str, json_body: Option<&str>) -> (u16, String) {
+    make_http_request_with_headers(path, method, json_body, &[])
+}
+
+// Make HTTP request with specified method, optional JSON body and extra headers
+pub fn make_http_request_with_headers(
+    path: &str,
+    method: &str,
+    json_body: Option<&str>,
+    headers: &[(&str, &str)],
+) -> (u16, String) {
+    let response = send_http_request(path, method, json_body, headers);
+
+    let status_code = response.status().as_u16();
+    let body = response.text().expect("Failed to read HTTP response");
+
+    (status_code, body)
+}
+
+// Send an HTTP request and return the raw response, for tests inspecting headers
+pub fn send_http_request(
+    path: &str,
+    method: &str,
+    json_body: Option<&str>,
+    headers: &[(&str, &str)],
+) -> reqwest::blocking::Response {
     // Get the test config
     let config = TestConfig::default();
     let port = config.app_port;
@@ -131,28 +156,33 @@ pub fn make_http_request(path: &str, method: &str, json_body: Option<&str>) -> (
 
     let mut request = match method {
         "GET" => client.get(&url),
+        "HEAD" => client.head(&url),
         "POST" => client.post(&url),
         "PUT" => client.put(&url),
         "PATCH" => client.patch(&url),
         "DELETE" => client.delete(&url),
+        "OPTIONS" => client.request(reqwest::Method::OPTIONS, &url),
         _ => panic!("Unsupported HTTP method: {}", method),
     };
 
-    // Add JSON body if provided
+    // Add JSON body if provided, unless the caller overrides the content type
     if let Some(body) = json_body {
-        request = request
-            .header("Content-Type", "application/json")
-            .body(body.to_string());
+        if !headers
+            .iter()
+            .any(|(name, _)| name.eq_ignore_ascii_case("Content-Type"))
+        {
+            request = request.header("Content-Type", "application/json");
+        }
+        request = request.body(body.to_string());
     }
 
-    let response = request
-        .send()
-        .expect(&format!("Failed to send {} request", method));
-
-    let status_code = response.status().as_u16();
-    let body = response.text().expect("Failed to read HTTP response");
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
 
-    (status_code, body)
+    request
+        .send()
+        .unwrap_or_else(|_| panic!("Failed to send {} request", method))
 }
 
 // Make HTTP POST request with JSON body
@@ -0,0 +1,80 @@
+use mongodb::bson::doc;
+use serial_test::serial;
+
+// Import test environment and utilities from utils module
+mod utils;
+use utils::test_environment::{TOKIO_RUNTIME, TestConfig, TestEnvironment};
+use utils::utils::{make_get_request, make_post_request};
+
+// Base name for test collections
+static TEST_COLLECTION_BASE_NAME: &str = "mongor_watch_endpoint_test";
+
+// Generate a unique collection name for each test
+fn unique_collection_name(test_name: &str) -> String {
+    format!(
+        "{}_{}",
+        TEST_COLLECTION_BASE_NAME,
+        test_name.replace(" ", "_")
+    )
+}
+
+// Change streams only work on replica sets, which the test MongoDB may not be
+fn is_replica_set(env: &TestEnvironment) -> bool {
+    TOKIO_RUNTIME.block_on(async {
+        env.mongodb_client
+            .database("admin")
+            .run_command(doc! { "hello": 1 })
+            .await
+            .map(|hello| hello.contains_key("setName"))
+            .unwrap_or(false)
+    })
+}
+
+#[test]
+#[serial]
+fn test_watch_endpoint_all_cases() {
+    // Create a single test environment for all test cases
+    let env = TestEnvironment::new();
+    let collection_name = unique_collection_name("watch");
+    env.insert_test_data(&collection_name, Vec::new());
+
+    if !is_replica_set(&env) {
+        // Test case 1: Standalone servers get an informative error instead of an upgrade
+        let (status_code, body) = make_get_request(&format!("/{}/watch", collection_name));
+        assert_eq!(
+            status_code, 400,
+            "Expected status code 400, got {}",
+            status_code
+        );
+        assert!(body.contains("replica set"), "Unexpected body: {}", body);
+        return;
+    }
+
+    // Test case 2: A subscriber receives the insert event for a matching document
+    {
+        let url = format!(
+            "ws://127.0.0.1:{}/{}/watch?status=active",
+            TestConfig::default().app_port,
+            collection_name
+        );
+        let (mut socket, _response) = tungstenite::connect(url).expect("Failed to connect");
+
+        let full_request_path = format!("/{}", collection_name);
+        let (status_code, _body) =
+            make_post_request(&full_request_path, r#"{"_id": 1, "status": "inactive"}"#);
+        assert_eq!(status_code, 201);
+        let (status_code, _body) =
+            make_post_request(&full_request_path, r#"{"_id": 2, "status": "active"}"#);
+        assert_eq!(status_code, 201);
+
+        // Only the active document passes the filter
+        let message = socket.read().expect("Failed to read change event");
+        let event: serde_json::Value =
+            serde_json::from_str(message.to_text().unwrap()).expect("Failed to parse event");
+        assert_eq!(event["operationType"], "insert");
+        assert_eq!(event["fullDocument"]["_id"], 2);
+        assert_eq!(event["fullDocument"]["status"], "active");
+
+        socket.close(None).ok();
+    }
+}